@@ -0,0 +1,91 @@
+//! Benchmark of the resolution and pool assembly on recorded fixtures, without network calls.
+//!
+//! Record the fixtures of a chain with `ans-scraper --network <chain_id> record-fixtures --out
+//! <file>`, then run:
+//!
+//! ```text
+//! cargo bench --bench pipeline -- <file> [iterations]
+//! ```
+//!
+//! Reports pairs/sec and the allocations per run as JSON.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use ans_scraper_rs::fixtures::{run_pipeline, Fixtures};
+use ans_scraper_rs::ChainRegistry;
+use serde_json::json;
+
+/// Default number of runs measured after the warm up.
+const DEFAULT_ITERATIONS: u32 = 100;
+
+/// Counts the allocations of the bench so they can be reported per run.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() -> anyhow::Result<()> {
+    // `cargo bench` passes `--bench` to every bench target
+    let mut args = std::env::args().skip(1).filter(|arg| arg != "--bench");
+    let path = args
+        .next()
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("usage: pipeline <fixtures file> [iterations]"))?;
+    let iterations = match args.next() {
+        Some(iterations) => iterations.parse::<u32>()?.max(1),
+        None => DEFAULT_ITERATIONS,
+    };
+
+    let fixtures = Fixtures::load(&path)?;
+    let chain_registry = ChainRegistry::from_asset_lists(fixtures.asset_lists.clone());
+
+    // warm up, and make sure the fixtures actually produce something
+    let output = run_pipeline(&fixtures, &chain_registry);
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(run_pipeline(&fixtures, &chain_registry));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let allocated_bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - allocated_bytes;
+
+    let pairs = fixtures.pairs.len() as f64 * iterations as f64;
+    println!(
+        "{}",
+        json!({
+            "chain_id": fixtures.chain_id,
+            "pairs": fixtures.pairs.len(),
+            "assets": output.assets.len(),
+            "pools": output.pools.len(),
+            "not_found_assets": output.not_found_assets.len(),
+            "skipped_pools": output.skipped_pools.len(),
+            "iterations": iterations,
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "pairs_per_sec": (pairs / elapsed.as_secs_f64()).round(),
+            "allocations_per_run": allocations / iterations as usize,
+            "allocated_bytes_per_run": allocated_bytes / iterations as usize,
+        })
+    );
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! The AnsHost versions the registrar writes messages for.
+
+use std::fmt;
+
+use abstract_core::ans_host::ExecuteMsg;
+use abstract_core::objects::PoolType;
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use serde_json::Value;
+
+use crate::helpers::clients::ChainClients;
+use crate::helpers::retry::retry;
+
+/// Storage key of the cw2 contract version.
+const CONTRACT_INFO_KEY: &[u8] = b"contract_info";
+
+/// Version of an AnsHost contract, the messages and pool metadata it accepts depend on it.
+///
+/// The messages are built with the types of the abstract-core version of the scraper
+/// ([`Self::LATEST`]) and adapted to the target by [`Self::adapt_msg`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum TargetVersion {
+    /// AnsHost 0.13, without concentrated liquidity pools
+    #[value(name = "0.13")]
+    V0_13,
+    #[value(name = "0.14")]
+    V0_14,
+}
+
+/// A cw2 version of an AnsHost contract the scraper has no messages for.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "AnsHost {ans_host} is {contract} {version}, supported versions are 0.13 and 0.14, pass \
+     --ans-version to write the messages of one of those anyway"
+)]
+pub struct UnsupportedVersion {
+    pub ans_host: String,
+    pub contract: String,
+    pub version: String,
+}
+
+impl fmt::Display for TargetVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetVersion::V0_13 => write!(f, "0.13"),
+            TargetVersion::V0_14 => write!(f, "0.14"),
+        }
+    }
+}
+
+impl TargetVersion {
+    /// The version of the abstract-core types the scraper is built with.
+    pub const LATEST: Self = TargetVersion::V0_14;
+
+    /// The version of a cw2 `major.minor.patch` version, `None` if it isn't supported.
+    pub fn from_contract_version(version: &str) -> Option<Self> {
+        let version = semver::Version::parse(version).ok()?;
+        match (version.major, version.minor) {
+            (0, 13) => Some(TargetVersion::V0_13),
+            (0, 14) => Some(TargetVersion::V0_14),
+            _ => None,
+        }
+    }
+
+    /// Read the cw2 version of `ans_host` and pick the matching target version.
+    pub async fn detect(clients: &ChainClients, ans_host: &Addr) -> anyhow::Result<Self> {
+        let response = retry(format!("contract version of {}", ans_host), || async {
+            clients
+                .wasm
+                .contract_raw_state(ans_host.to_string(), CONTRACT_INFO_KEY.to_vec())
+                .await
+                .map_err(Into::into)
+        })
+        .await
+        .with_context(|| format!("querying the contract version of AnsHost {}", ans_host))?;
+        let contract_version: cw2::ContractVersion = serde_json::from_slice(&response.data)
+            .with_context(|| format!("parsing the contract version of AnsHost {}", ans_host))?;
+
+        let version = Self::from_contract_version(&contract_version.version).ok_or_else(|| {
+            UnsupportedVersion {
+                ans_host: ans_host.to_string(),
+                contract: contract_version.contract.clone(),
+                version: contract_version.version.clone(),
+            }
+        })?;
+        log::info!(
+            "AnsHost {} is {} {}, writing messages for {}",
+            ans_host,
+            contract_version.contract,
+            contract_version.version,
+            version
+        );
+        Ok(version)
+    }
+
+    /// Name of `pool_type` in the pool metadata of this version, `None` if it has no such type.
+    pub fn pool_type_name(self, pool_type: &PoolType) -> Option<&'static str> {
+        match (self, pool_type) {
+            (_, PoolType::ConstantProduct) => Some("constant_product"),
+            (_, PoolType::Stable) => Some("stable"),
+            (_, PoolType::Weighted) => Some("weighted"),
+            (_, PoolType::LiquidityBootstrap) => Some("liquidity_bootstrap"),
+            (TargetVersion::V0_13, PoolType::ConcentratedLiquidity) => None,
+            (TargetVersion::V0_14, PoolType::ConcentratedLiquidity) => {
+                Some("concentrated_liquidity")
+            }
+        }
+    }
+
+    /// Whether AnsHost of this version can register pools of `pool_type`.
+    pub fn supports_pool_type(self, pool_type: &PoolType) -> bool {
+        self.pool_type_name(pool_type).is_some()
+    }
+
+    /// The JSON of `msg` as AnsHost of this version expects it.
+    ///
+    /// Fails on a pool this version has no pool type for, those are left out by the registrar.
+    pub fn adapt_msg(self, msg: &ExecuteMsg) -> anyhow::Result<Value> {
+        let mut json = serde_json::to_value(msg)?;
+        if let ExecuteMsg::UpdatePools { to_add, .. } = msg {
+            let pools = json
+                .pointer_mut("/update_pools/to_add")
+                .and_then(Value::as_array_mut)
+                .context("update_pools message without to_add")?;
+            for ((pool, metadata), pool_json) in to_add.iter().zip(pools) {
+                let name = self.pool_type_name(&metadata.pool_type).with_context(|| {
+                    format!(
+                        "AnsHost {} has no pool type {:?} for pool {:?}",
+                        self, metadata.pool_type, pool
+                    )
+                })?;
+                // `[pool address, metadata]`
+                pool_json[1]["pool_type"] = Value::String(name.to_string());
+            }
+        }
+        Ok(json)
+    }
+}
@@ -0,0 +1,856 @@
+//! The scraper CLI: scrape the dexes of one or more networks, diff the result against AnsHost or
+//! register it.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::{Args, Parser, Subcommand};
+
+use abstract_boot::AnsHost;
+use abstract_core::ANS_HOST;
+use cosmwasm_std::Addr;
+use cw_orch::{
+    networks::ChainInfo,
+    queriers::{Bank, DaemonQuerier},
+    ContractInstance, Daemon, TxHandler,
+};
+
+use ans_scraper_rs::ans_version::TargetVersion;
+use ans_scraper_rs::cassette::CassetteRecorder;
+use ans_scraper_rs::dexes::astroport::{pair_asset_infos, AstroportScraper, PairSourceKind};
+use ans_scraper_rs::diff::{AnsDiff, AnsHostEntries, DEFAULT_MAX_HEIGHT_GAP};
+use ans_scraper_rs::export::{ExportFormat, ScrapeExport};
+use ans_scraper_rs::fixtures::Fixtures;
+use ans_scraper_rs::logging::{init_logger, log_event, LogFormat};
+use ans_scraper_rs::pipeline::{ConflictPolicy, ScrapeOutput};
+use ans_scraper_rs::registrar::{AnsRegistrar, DEFAULT_BATCH_SIZE};
+use ans_scraper_rs::report::summary_table;
+use ans_scraper_rs::scrape::{scrape_network, scrape_networks, Dex, ScrapeOptions};
+use ans_scraper_rs::verification::verify_entries;
+use ans_scraper_rs::{
+    grpc_url, parse_network, require_signer, set_offline, with_grpc_url, ChainClients,
+    ChainRegistry, ChainRegistryOptions, LazyChainRegistry, ResolutionCache, ScraperConfig,
+};
+use cw_asset::AssetInfo;
+use log::Level;
+use serde_json::json;
+use tokio::runtime::Runtime;
+
+/// Outcome of a single environment check.
+struct CheckResult {
+    name: &'static str,
+    passed: bool,
+    details: String,
+}
+
+impl CheckResult {
+    fn new(name: &'static str, result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(details) => Self {
+                name,
+                passed: true,
+                details,
+            },
+            Err(err) => Self {
+                name,
+                passed: false,
+                details: format!("{:#}", err),
+            },
+        }
+    }
+}
+
+/// Verify that the environment, endpoints and credentials work before a long run.
+///
+/// Returns whether all the checks passed.
+fn check(rt: &Runtime, network: ChainInfo, grpc_url: Option<&str>, config: &ScraperConfig) -> bool {
+    let mut results = vec![];
+
+    let clients = query_clients(rt, &network, grpc_url, config);
+    let clients = match clients {
+        Ok(clients) => {
+            results.push(CheckResult::new(
+                "query client",
+                Ok(format!("connected to {}", clients.chain_id)),
+            ));
+            Some(clients)
+        }
+        Err(err) => {
+            results.push(CheckResult::new("query client", Err(err)));
+            None
+        }
+    };
+
+    if let Some(clients) = &clients {
+        results.push(CheckResult::new(
+            "block height",
+            block_on(rt, config, clients.node.block_height())
+                .map(|height| format!("latest block {}", height))
+                .map_err(Into::into),
+        ));
+
+        // Without a wallet the network can still be scraped and diffed
+        match require_signer("update") {
+            Ok(()) => results.extend(signer_checks(rt, network.clone(), grpc_url, clients)),
+            Err(err) => results.push(CheckResult::new(
+                "sender",
+                Ok(format!("query only, {}", err)),
+            )),
+        }
+
+        results.push(CheckResult::new(
+            "chain registry",
+            registry_needed(&clients.chain_id, config).map(|assets| match assets {
+                None => {
+                    "needed to resolve the assets of the pairs, none are cached yet".to_string()
+                }
+                Some(0) => "not needed, there are no astroport pairs".to_string(),
+                Some(n) => format!("needed to resolve {} assets", n),
+            }),
+        ));
+    }
+
+    results.push(CheckResult::new(
+        "astroport deployment",
+        block_on(
+            rt,
+            config,
+            AstroportScraper::fetch_deployment_address(
+                config,
+                &network.chain_id,
+                "factory_address",
+            ),
+        )
+        .map(|addr| format!("factory at {}", addr)),
+    ));
+
+    results.push(CheckResult::new(
+        "cache directory",
+        check_cache_dir(&config.cache_dir),
+    ));
+
+    for result in &results {
+        log_event(
+            if result.passed {
+                Level::Info
+            } else {
+                Level::Error
+            },
+            "check",
+            json!({
+                "check": result.name,
+                "passed": result.passed,
+                "details": result.details,
+            }),
+        );
+    }
+
+    results.iter().all(|r| r.passed)
+}
+
+/// The checks of the wallet `update` signs with: the daemon, its AnsHost and the sender balance.
+fn signer_checks(
+    rt: &Runtime,
+    network: ChainInfo,
+    grpc_url: Option<&str>,
+    clients: &ChainClients,
+) -> Vec<CheckResult> {
+    let chain = with_grpc_url(network, grpc_url).and_then(|network| {
+        Ok(Daemon::builder()
+            .chain(network)
+            .handle(rt.handle())
+            .build()?)
+    });
+    let chain = match chain {
+        Ok(chain) => chain,
+        Err(err) => return vec![CheckResult::new("signer", Err(err))],
+    };
+
+    let ans_host = AnsHost::new(ANS_HOST, chain.clone());
+    let sender = chain.sender();
+    let bank = Bank::new(clients.channel.clone());
+    vec![
+        CheckResult::new(
+            "ans host",
+            ans_host
+                .address()
+                .map(|addr| addr.to_string())
+                .map_err(Into::into),
+        ),
+        CheckResult::new(
+            "sender",
+            rt.block_on(bank.balance(sender.as_str(), None))
+                .map(|balance| format!("{} with balance {:?}", sender, balance))
+                .map_err(Into::into),
+        ),
+    ]
+}
+
+/// Count the assets of the cached astroport pairs, the chain registry is only fetched to name
+/// those. `None` without cached pairs, the check doesn't query the factory nor write the cache.
+fn registry_needed(chain_id: &str, config: &ScraperConfig) -> anyhow::Result<Option<usize>> {
+    let Some(pairs) = AstroportScraper::pairs_in_cache(&config.cache_dir, chain_id)? else {
+        return Ok(None);
+    };
+    let assets = pair_asset_infos(&pairs)
+        .iter()
+        .filter(|info| matches!(info, AssetInfo::Native(_) | AssetInfo::Cw20(_)))
+        .count();
+    Ok(Some(assets))
+}
+
+/// Make sure we can write to the cache directory.
+fn check_cache_dir(cache_dir: &Path) -> anyhow::Result<String> {
+    std::fs::create_dir_all(cache_dir)?;
+    let probe = cache_dir.join(".write_check");
+    std::fs::write(&probe, b"ok")?;
+    std::fs::remove_file(&probe)?;
+    Ok(format!("{} is writable", cache_dir.display()))
+}
+
+/// Record the chain data of the astroport pipeline to a fixtures file.
+fn record_fixtures(
+    rt: &Runtime,
+    clients: Arc<ChainClients>,
+    path: &Path,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let chain_registry = block_on(rt, config, ChainRegistry::new(config))?;
+
+    let fixtures = block_on(
+        rt,
+        config,
+        Fixtures::record(clients, chain_registry, config),
+    )?;
+    fixtures.save(path)?;
+    log::info!(
+        "Recorded {} pairs, {} token infos and {} denom traces of {} to {}",
+        fixtures.pairs.len(),
+        fixtures.token_infos.len(),
+        fixtures.denom_traces.len(),
+        fixtures.chain_id,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Scrape the dexes of the network with the library pipeline.
+fn scrape(
+    rt: &Runtime,
+    clients: Arc<ChainClients>,
+    args: &ScrapeArgs,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<ScrapeOutput> {
+    let chain_id = &clients.chain_id;
+    // Only the asset lists of the chain (and the source chains of its IBC denoms) are fetched
+    let chain_registry = LazyChainRegistry::for_chains(
+        &[config.ans_prefix(chain_id)?],
+        args.registry_options(config),
+    );
+    Ok(rt.block_on(scrape_network(
+        clients.clone(),
+        chain_registry,
+        Arc::new(ResolutionCache::new()),
+        config,
+        &args.scrape_options(shared),
+    ))?)
+}
+
+/// Scrape the networks, up to `parallel_networks` at the same time, and write the output files,
+/// the reports and optionally the ANS files of every network, then the summary of the run.
+///
+/// Fails if any network failed, once the others are scraped.
+fn scrape_to_files(
+    rt: &Runtime,
+    args: &ScrapeArgs,
+    parallel_networks: usize,
+    ans_dir: Option<&Path>,
+    page_limit: Option<u32>,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    if shared.network.is_empty() {
+        anyhow::bail!("--network is required for this command");
+    }
+    let options = ScrapeOptions {
+        page_limit,
+        ..args.scrape_options(shared)
+    };
+    let run_summary = rt.block_on(scrape_networks(
+        &shared.network,
+        parallel_networks,
+        shared.grpc_url.as_deref(),
+        args.registry_options(config),
+        config,
+        &options,
+        ans_dir,
+    ))?;
+    if !run_summary.failed_networks.is_empty() {
+        anyhow::bail!(
+            "{} of {} networks failed",
+            run_summary.failed_networks.len(),
+            shared.network.len()
+        );
+    }
+    Ok(())
+}
+
+/// Scrape the network through a [`CassetteRecorder`] and save its queries to `path`, also when
+/// the scrape fails.
+fn record_cassette_of_scrape(
+    rt: &Runtime,
+    args: &ScrapeArgs,
+    ans_dir: Option<&Path>,
+    page_limit: Option<u32>,
+    path: &Path,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let network = network(shared)?;
+    let upstream = query_clients(rt, &network, shared.grpc_url.as_deref(), config)?;
+    let recorder = {
+        let _runtime = rt.enter();
+        CassetteRecorder::start(&network.chain_id, upstream.channel.clone())?
+    };
+    let options = ScrapeOptions {
+        page_limit,
+        ..args.scrape_options(shared)
+    };
+    let run_summary = rt.block_on(scrape_networks(
+        &shared.network,
+        1,
+        Some(&recorder.url()),
+        args.registry_options(config),
+        config,
+        &options,
+        ans_dir,
+    ));
+    let cassette = recorder.cassette();
+    cassette.save(path)?;
+    log::info!(
+        "{}: recorded {} queries to {}",
+        network.chain_id,
+        cassette.interactions.len(),
+        path.display()
+    );
+    if !run_summary?.failed_networks.is_empty() {
+        anyhow::bail!("{}: the recorded scrape failed", network.chain_id);
+    }
+    Ok(())
+}
+
+/// Export the output files in `from`, or the scrape of the network, to the output directory.
+fn export(
+    rt: &Runtime,
+    args: &ScrapeArgs,
+    format: ExportFormat,
+    from: Option<&Path>,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let export = match from {
+        Some(chain_dir) => ScrapeExport::load(chain_dir)?,
+        None => ScrapeExport::from_output(&scrape(
+            rt,
+            clients(rt, shared, config)?,
+            args,
+            shared,
+            config,
+        )?),
+    };
+    for path in export.write(&config.out_dir, format)? {
+        log::info!("{}: exported to {}", export.meta.chain_id, path.display());
+    }
+    Ok(())
+}
+
+/// Scrape the network and log how the result differs from the entries of AnsHost.
+fn diff(
+    rt: &Runtime,
+    clients: Arc<ChainClients>,
+    args: &ScrapeArgs,
+    ans_host: &Addr,
+    max_height_gap: u64,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let output = scrape(rt, clients.clone(), args, shared, config)?;
+    let registered = block_on(rt, config, AnsHostEntries::query(&clients, ans_host))?;
+    AnsDiff::new(&registered, &output, max_height_gap)?.log_summary();
+    Ok(())
+}
+
+/// Scrape the network and register what AnsHost is missing with `registrar`.
+fn update(
+    rt: &Runtime,
+    clients: Arc<ChainClients>,
+    args: &ScrapeArgs,
+    registrar: &AnsRegistrar,
+    dry_run: bool,
+    shared: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let chain_id = clients.chain_id.clone();
+    let output = scrape(rt, clients, args, shared, config)?;
+    let batches = registrar.register(&output)?;
+    log::info!(
+        "{}: {} update messages {}",
+        chain_id,
+        batches.len(),
+        if dry_run { "logged" } else { "executed" }
+    );
+    Ok(())
+}
+
+/// Check the entries of AnsHost against the chain and write the verification report.
+fn verify(
+    rt: &Runtime,
+    clients: Arc<ChainClients>,
+    ans_host: &Addr,
+    dex: Option<&str>,
+    config: &ScraperConfig,
+) -> anyhow::Result<()> {
+    let report = block_on(
+        rt,
+        config,
+        verify_entries(&clients, ans_host, dex, config.query_concurrency),
+    )?;
+    report.log_summary();
+    let path = report.write(&config.out_dir)?;
+    log::info!(
+        "{}: verification report written to {}",
+        clients.chain_id,
+        path.display()
+    );
+    Ok(())
+}
+
+/// What to scrape, shared by `scrape`, `diff` and `update`.
+#[derive(Args, Debug)]
+struct ScrapeArgs {
+    /// Dexes to scrape, comma separated
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "astroport")]
+    dex: Vec<Dex>,
+    /// Number of cw20 token info and denom trace queries to run concurrently [default: 10]
+    #[arg(long)]
+    query_concurrency: Option<usize>,
+    /// Directory of the per-chain configuration, e.g. `<config_dir>/<chain_id>/verified_cw20s.json`
+    /// [default: config]
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
+    /// Also register cw20s that are neither allow-listed nor in the chain registry
+    #[arg(long)]
+    include_unverified: bool,
+    /// Drop asset names that map to different assets (and their pools) instead of failing
+    #[arg(long)]
+    drop_asset_conflicts: bool,
+    /// Also name IBC assets that travelled through more than one channel
+    #[arg(long)]
+    allow_multihop: bool,
+    /// Drop the astroport pools with a reserve below this amount (in the smallest unit of the
+    /// asset), per-asset minimums go in `<config_dir>/<chain_id>/min_reserves.json`
+    #[arg(long)]
+    min_liquidity: Option<u128>,
+    /// List the astroport pairs by paginating the factory, or with the pairs API of
+    /// `astroport_api_urls` in the config, cross-checked with the factory
+    #[arg(long, value_enum, default_value = "onchain")]
+    pair_source: PairSourceKind,
+    /// Continue an interrupted scrape of the same dexes from its last completed phase
+    #[arg(long)]
+    resume: bool,
+}
+
+impl ScrapeArgs {
+    /// What and how to scrape on every network.
+    fn scrape_options(&self, shared: &Arguments) -> ScrapeOptions {
+        ScrapeOptions {
+            dexes: self.dex.clone(),
+            refresh: shared.refresh,
+            conflict_policy: if self.drop_asset_conflicts {
+                ConflictPolicy::Drop
+            } else {
+                ConflictPolicy::Fail
+            },
+            include_unverified: self.include_unverified,
+            min_liquidity: self.min_liquidity,
+            pair_source: self.pair_source,
+            resume: self.resume,
+            page_limit: None,
+        }
+    }
+
+    /// How the chain registry asset lists are fetched and matched.
+    fn registry_options(&self, config: &ScraperConfig) -> ChainRegistryOptions {
+        ChainRegistryOptions {
+            allow_multihop: self.allow_multihop,
+            ..config.chain_registry_options()
+        }
+    }
+
+    /// Override the settings of the config file with the flags that are set.
+    fn override_config(&self, config: &mut ScraperConfig) {
+        if let Some(query_concurrency) = self.query_concurrency {
+            config.query_concurrency = query_concurrency;
+        }
+        if let Some(config_dir) = &self.config_dir {
+            config.config_dir = config_dir.clone();
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Scrape the dexes of the networks and write the results to the output directory, one
+    /// subdirectory per chain
+    Scrape {
+        #[command(flatten)]
+        scrape: ScrapeArgs,
+        /// Number of networks to scrape at the same time
+        #[arg(long, default_value_t = 1)]
+        parallel_networks: usize,
+        /// Directory to write the output files to [default: out]
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Also merge the results into the assets/contracts/pools.json files of abstract in this
+        /// directory
+        #[arg(long)]
+        ans_dir: Option<PathBuf>,
+        /// Only use the cached pairs, token infos, denom traces and asset lists of earlier runs,
+        /// fail on anything that isn't cached instead of querying (astroport only)
+        #[arg(long, conflicts_with_all = ["refresh", "min_liquidity"])]
+        offline: bool,
+        /// Stream the astroport pairs through the scraper in pages of this size instead of
+        /// loading them all, without verifying the cw20s or resolving decimals and channels, the
+        /// pools of conflicting names are dropped
+        #[arg(
+            long,
+            conflicts_with_all = ["ans_dir", "offline", "resume"],
+            requires_all = ["drop_asset_conflicts", "include_unverified"]
+        )]
+        page_limit: Option<u32>,
+        /// Record the gRPC queries of the scrape and their responses to this file, to replay the
+        /// run in tests (single network, needs `--refresh` so nothing comes from the caches)
+        #[arg(long, conflicts_with = "offline", requires = "refresh")]
+        record_cassette: Option<PathBuf>,
+    },
+    /// Scrape the dexes of the network and compare the results to the entries of AnsHost
+    Diff {
+        #[command(flatten)]
+        scrape: ScrapeArgs,
+        /// Address of the AnsHost contract
+        #[arg(long)]
+        ans_host: String,
+        /// Warn when the scrape and the AnsHost entries are more than this many blocks apart
+        #[arg(long, default_value_t = DEFAULT_MAX_HEIGHT_GAP)]
+        max_height_gap: u64,
+    },
+    /// Scrape the dexes of the network and register the missing entries on AnsHost, needs the
+    /// mnemonic of a wallet
+    Update {
+        #[command(flatten)]
+        scrape: ScrapeArgs,
+        /// Address of the AnsHost contract
+        #[arg(long)]
+        ans_host: String,
+        /// Number of entries per AnsHost update message
+        #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+        /// Log the AnsHost update messages instead of broadcasting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Also remove the registered entries of the scraped dexes that the scrape no longer finds
+        #[arg(long)]
+        prune: bool,
+        /// Write the messages for this AnsHost version, defaults to the version of the contract
+        #[arg(long, value_enum)]
+        ans_version: Option<TargetVersion>,
+    },
+    /// Check the pools and cw20s registered on AnsHost against the chain: dead contracts,
+    /// pools holding other assets than registered and cw20s that no longer exist
+    Verify {
+        /// Address of the AnsHost contract
+        #[arg(long)]
+        ans_host: String,
+        /// Only check the pools of this dex (ANS name) and the cw20s they use
+        #[arg(long)]
+        dex: Option<String>,
+        /// Directory to write the verification report to [default: out]
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Export the assets, pools and contracts of a scrape for review in a spreadsheet
+    Export {
+        #[command(flatten)]
+        scrape: ScrapeArgs,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ExportFormat,
+        /// Directory to write the exported files to [default: out]
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Export the output files of an earlier scrape in this directory (`<out>/<chain_id>`)
+        /// instead of scraping the network
+        #[arg(long)]
+        from: Option<PathBuf>,
+    },
+    /// Check the environment, endpoints and credentials for the network
+    Check,
+    /// Record the chain data of the astroport pipeline to replay it with `benches/pipeline.rs`
+    RecordFixtures {
+        /// File to write the fixtures to
+        #[arg(short, long)]
+        out: PathBuf,
+        /// Number of queries to run concurrently [default: 10]
+        #[arg(long)]
+        query_concurrency: Option<usize>,
+    },
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Arguments {
+    #[command(subcommand)]
+    command: Command,
+    /// Network Id to run the command on, `scrape` takes several (comma separated or repeated)
+    #[arg(short, long, global = true, value_delimiter = ',')]
+    network: Vec<String>,
+    /// gRPC endpoint to query, defaults to `GRPC_URL` or the endpoint known by cw-orch
+    #[arg(long, global = true)]
+    grpc_url: Option<String>,
+    /// Settings file of the scraper (TOML), the flags below take precedence over it
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Directory of the pairs, names and chain registry caches [default: `ANS_SCRAPER_CACHE_DIR`,
+    /// or the cache directory of the platform]
+    #[arg(long, global = true)]
+    cache_dir: Option<PathBuf>,
+    /// Fetch everything again instead of starting from the cached data
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Log level, `RUST_LOG` is used when not set
+    #[arg(long, global = true)]
+    log_level: Option<log::LevelFilter>,
+    /// Only log warnings and errors
+    #[arg(long, global = true, conflicts_with = "log_level")]
+    quiet: bool,
+    /// Write the logs as text, or as one JSON object per line with the fields of the events
+    #[arg(long, global = true, value_enum, default_value = "text")]
+    log_format: LogFormat,
+    /// Number of times a failed query or download is retried [default: 3]
+    #[arg(long, global = true)]
+    max_retries: Option<u32>,
+    /// Delay before the first retry in milliseconds, doubled for every following one
+    /// [default: 500]
+    #[arg(long, global = true)]
+    retry_base_delay_ms: Option<u64>,
+}
+
+fn main() {
+    dotenv().ok();
+
+    use dotenv::dotenv;
+
+    let args = Arguments::parse();
+    init_logger(args.log_level, args.quiet, args.log_format);
+
+    let config = match scraper_config(&args) {
+        Ok(config) => config,
+        Err(err) => {
+            log::error!("{:#}", err);
+            ::std::process::exit(1);
+        }
+    };
+
+    // The one runtime of the binary, every daemon and async call runs on it
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(err) => {
+            log::error!("Failed to start the tokio runtime: {}", err);
+            ::std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = run(&rt, &args, &config) {
+        log::error!("{:#}", err);
+        ::std::process::exit(1);
+    }
+}
+
+/// The settings of `--config`, overridden by the flags that are set.
+fn scraper_config(args: &Arguments) -> anyhow::Result<ScraperConfig> {
+    let mut config = ScraperConfig::load_or_default(args.config.as_deref())?;
+    if let Some(cache_dir) = &args.cache_dir {
+        config.cache_dir = cache_dir.clone();
+    }
+    if let Some(max_retries) = args.max_retries {
+        config.max_retries = max_retries;
+    }
+    if let Some(retry_base_delay_ms) = args.retry_base_delay_ms {
+        config.retry_base_delay_ms = retry_base_delay_ms;
+    }
+
+    match &args.command {
+        Command::Scrape { scrape, out, .. } | Command::Export { scrape, out, .. } => {
+            scrape.override_config(&mut config);
+            if let Some(out) = out {
+                config.out_dir = out.clone();
+            }
+        }
+        Command::Diff { scrape, .. } | Command::Update { scrape, .. } => {
+            scrape.override_config(&mut config)
+        }
+        Command::Verify { out: Some(out), .. } => config.out_dir = out.clone(),
+        Command::RecordFixtures {
+            query_concurrency: Some(query_concurrency),
+            ..
+        } => config.query_concurrency = *query_concurrency,
+        _ => {}
+    }
+    Ok(config)
+}
+
+fn run(rt: &Runtime, args: &Arguments, config: &ScraperConfig) -> anyhow::Result<()> {
+    match &args.command {
+        Command::Scrape {
+            scrape,
+            parallel_networks,
+            ans_dir,
+            offline,
+            page_limit,
+            record_cassette,
+            ..
+        } => {
+            set_offline(*offline);
+            match record_cassette {
+                Some(cassette) => record_cassette_of_scrape(
+                    rt,
+                    scrape,
+                    ans_dir.as_deref(),
+                    *page_limit,
+                    cassette,
+                    args,
+                    config,
+                ),
+                None => scrape_to_files(
+                    rt,
+                    scrape,
+                    *parallel_networks,
+                    ans_dir.as_deref(),
+                    *page_limit,
+                    args,
+                    config,
+                ),
+            }
+        }
+        Command::Diff {
+            scrape,
+            ans_host,
+            max_height_gap,
+        } => {
+            let clients = clients(rt, args, config)?;
+            diff(
+                rt,
+                clients,
+                scrape,
+                &Addr::unchecked(ans_host),
+                *max_height_gap,
+                args,
+                config,
+            )
+        }
+        Command::Update {
+            scrape,
+            ans_host,
+            batch_size,
+            dry_run,
+            prune,
+            ans_version,
+        } => {
+            // A dry run only queries the chain, it needs no wallet
+            let clients = clients(rt, args, config)?;
+            let mut registrar = AnsRegistrar::new(
+                rt.handle().clone(),
+                clients.clone(),
+                Addr::unchecked(ans_host),
+            )
+            .with_batch_size(*batch_size)
+            .with_prune(*prune)
+            .with_target_version(*ans_version)
+            .with_retry_policy(config.retry_policy());
+            if !*dry_run {
+                require_signer("update")?;
+                registrar = registrar.with_sender(daemon(rt, args)?);
+            }
+            update(rt, clients, scrape, &registrar, *dry_run, args, config)
+        }
+        Command::Verify { ans_host, dex, .. } => verify(
+            rt,
+            clients(rt, args, config)?,
+            &Addr::unchecked(ans_host),
+            dex.as_deref(),
+            config,
+        ),
+        Command::Export {
+            scrape,
+            format,
+            from,
+            ..
+        } => export(rt, scrape, *format, from.as_deref(), args, config),
+        Command::Check => {
+            if !check(rt, network(args)?, args.grpc_url.as_deref(), config) {
+                anyhow::bail!("some checks failed");
+            }
+            Ok(())
+        }
+        Command::RecordFixtures { out, .. } => {
+            record_fixtures(rt, clients(rt, args, config)?, out, config)
+        }
+    }
+}
+
+/// The network of `--network`, required by the commands that talk to a single chain.
+fn network(args: &Arguments) -> anyhow::Result<ChainInfo> {
+    match &args.network[..] {
+        [] => anyhow::bail!("--network is required for this command"),
+        [network_id] => Ok(parse_network(network_id)?),
+        _ => anyhow::bail!("only scrape takes several networks"),
+    }
+}
+
+/// Query-only clients of the network, no wallet needed.
+fn clients(
+    rt: &Runtime,
+    args: &Arguments,
+    config: &ScraperConfig,
+) -> anyhow::Result<Arc<ChainClients>> {
+    query_clients(rt, &network(args)?, args.grpc_url.as_deref(), config)
+}
+
+fn query_clients(
+    rt: &Runtime,
+    network: &ChainInfo,
+    grpc_url: Option<&str>,
+    config: &ScraperConfig,
+) -> anyhow::Result<Arc<ChainClients>> {
+    let grpc_url = self::grpc_url(network, grpc_url)?;
+    block_on(
+        rt,
+        config,
+        ChainClients::connect(&network.chain_id, &grpc_url),
+    )
+}
+
+/// Run `fut` on the runtime of the binary, retrying its failed queries and downloads with the
+/// retry policy of `config`.
+fn block_on<F: Future>(rt: &Runtime, config: &ScraperConfig, fut: F) -> F::Output {
+    rt.block_on(config.retry_policy().scope(fut))
+}
+
+/// The daemon of the network, which signs with the wallet of the mnemonic in the environment.
+fn daemon(rt: &Runtime, args: &Arguments) -> anyhow::Result<Daemon> {
+    Ok(Daemon::builder()
+        .chain(with_grpc_url(network(args)?, args.grpc_url.as_deref())?)
+        .handle(rt.handle())
+        .build()?)
+}
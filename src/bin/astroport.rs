@@ -1,164 +1,177 @@
-use std::collections::HashMap;
+//! Scrapes astroport on one or more networks, kept for one release next to `ans-scraper scrape`.
+//!
+//! Only translates its flags, the networks are scraped by the same library code as
+//! `ans-scraper scrape --dex astroport`. Registering the results is `ans-scraper update`.
 
-use clap::Parser;
-use cosmwasm_std::Addr;
-use cw20::{Cw20QueryMsg, TokenInfoResponse};
-use cw_asset::AssetInfo;
+use std::path::PathBuf;
 
-use cw_orch::{
-    networks::parse_network, networks::ChainInfo, queriers::DaemonQuerier, Contract, CwEnv, Daemon,
-};
+use clap::Parser;
 
-use ans_scraper_rs::ChainRegistry;
+use ans_scraper_rs::dexes::astroport::PairSourceKind;
+use ans_scraper_rs::logging::{init_logger, LogFormat};
+use ans_scraper_rs::pipeline::ConflictPolicy;
+use ans_scraper_rs::scrape::{scrape_networks, Dex, ScrapeOptions};
+use ans_scraper_rs::{set_offline, ChainRegistryOptions, ScraperConfig};
 use tokio::runtime::Runtime;
 
-use ans_scraper_rs::dexes::astroport::AstroportScraper;
-use ans_scraper_rs::traits::dex::{AssetSource, DexScraper};
-
-pub struct Scraper<Chain: CwEnv> {
-    chain: Chain,
-    chain_registry: ChainRegistry,
-    ans_prefix: String,
-    dex_scrapers: Vec<Box<dyn DexScraper>>,
-    // TODO: use bimap (not possible because of stupid AssetInfo)
-    assets: HashMap<String, AssetInfo>,
+#[derive(Parser, Default, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Arguments {
+    /// Network Ids to scrape, comma separated
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    network_id: Vec<String>,
+    /// Settings file of the scraper (TOML), the flags take precedence over it
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// Directory to write the output files to [default: out]
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+    /// Number of cw20 token info and denom trace queries to run concurrently [default: 10]
+    #[arg(long)]
+    query_concurrency: Option<usize>,
+    /// Also merge the results into the assets/contracts/pools.json files of abstract in this
+    /// directory
+    #[arg(long)]
+    ans_dir: Option<PathBuf>,
+    /// gRPC endpoint to query (single network runs only), defaults to `GRPC_URL` or the endpoint
+    /// known by cw-orch
+    #[arg(long)]
+    grpc_url: Option<String>,
+    /// Fetch all the astroport pairs, token infos and denom traces again instead of using the
+    /// cached ones
+    #[arg(long)]
+    refresh: bool,
+    /// Only use the cached pairs, token infos, denom traces and asset lists of earlier runs, fail
+    /// on anything that isn't cached instead of querying
+    #[arg(long, conflicts_with_all = ["refresh", "min_liquidity"])]
+    offline: bool,
+    /// Also name IBC assets that travelled through more than one channel
+    #[arg(long)]
+    allow_multihop: bool,
+    /// Drop asset names that map to different assets (and their pools) instead of failing
+    #[arg(long)]
+    drop_asset_conflicts: bool,
+    /// Directory of the per-chain configuration, e.g. `<config_dir>/<chain_id>/verified_cw20s.json`
+    /// [default: config]
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
+    /// Also register cw20s that are neither allow-listed nor in the chain registry
+    #[arg(long)]
+    include_unverified: bool,
+    /// Drop the pools with a reserve below this amount (in the smallest unit of the asset),
+    /// per-asset minimums go in `<config_dir>/<chain_id>/min_reserves.json`
+    #[arg(long)]
+    min_liquidity: Option<u128>,
+    /// List the pairs by paginating the factory, or with the pairs API of `astroport_api_urls` in
+    /// the config, cross-checked with the factory
+    #[arg(long, value_enum, default_value = "onchain")]
+    pair_source: PairSourceKind,
+    /// Continue an interrupted scrape from its last completed phase
+    #[arg(long)]
+    resume: bool,
+    /// Stream the pairs through the scraper in pages of this size instead of loading them all,
+    /// without verifying the cw20s or resolving decimals and channels, the pools of conflicting
+    /// names are dropped
+    #[arg(
+        long,
+        conflicts_with_all = ["ans_dir", "offline", "resume"],
+        requires_all = ["drop_asset_conflicts", "include_unverified"]
+    )]
+    page_limit: Option<u32>,
+    /// Number of times a failed query or download is retried [default: 3]
+    #[arg(long)]
+    max_retries: Option<u32>,
+    /// Delay before the first retry in milliseconds, doubled for every following one
+    /// [default: 500]
+    #[arg(long)]
+    retry_base_delay_ms: Option<u64>,
+    /// Only log warnings and errors
+    #[arg(long)]
+    quiet: bool,
+    /// Write the logs as text, or as one JSON object per line with the fields of the events
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
 }
 
-impl Scraper<Daemon> {
-    pub async fn new(chain: Daemon, chain_registry: ChainRegistry) -> Self {
-        Self {
-            chain: chain.clone(),
-            chain_registry,
-            // TODO!!!
-            ans_prefix: "terra2".to_string(),
-            dex_scrapers: vec![Box::new(AstroportScraper::new(chain.clone()).await)],
-            assets: Default::default(),
+impl Arguments {
+    /// The settings of `--config`, overridden by the flags that are set.
+    fn scraper_config(&self) -> anyhow::Result<ScraperConfig> {
+        let mut config = ScraperConfig::load_or_default(self.config.as_deref())?;
+        if let Some(out_dir) = &self.out_dir {
+            config.out_dir = out_dir.clone();
         }
+        if let Some(query_concurrency) = self.query_concurrency {
+            config.query_concurrency = query_concurrency;
+        }
+        if let Some(config_dir) = &self.config_dir {
+            config.config_dir = config_dir.clone();
+        }
+        if let Some(max_retries) = self.max_retries {
+            config.max_retries = max_retries;
+        }
+        if let Some(retry_base_delay_ms) = self.retry_base_delay_ms {
+            config.retry_base_delay_ms = retry_base_delay_ms;
+        }
+        Ok(config)
     }
 
-    pub fn scrape(&mut self) -> anyhow::Result<()> {
-        self.scrape_assets();
-
-        Ok(())
-    }
-
-    fn scrape_assets(&mut self) -> anyhow::Result<()> {
-        let mut not_found_assets = vec![];
-
-        // Scrape assets, contracts, and pools from DEX sources
-        for dex_scraper in self.dex_scrapers.iter_mut() {
-            let _dex_id = dex_scraper.dex_id();
-            let asset_infos = dex_scraper.fetch_asset_infos()?;
-
-            for asset_info in asset_infos {
-                let (asset_name, _unchecked_info) = match &asset_info {
-                    // TODO: check for pre-existence USING THE BIMAP (not possible because of stupid AssetInfo)
-                    AssetInfo::Cw20(contract_addr) => {
-                        if let Ok(entry) = cw20_asset_entry(
-                            self.chain.clone(),
-                            self.ans_prefix.as_str(),
-                            contract_addr,
-                        ) {
-                            (entry, asset_info.clone())
-                        } else {
-                            not_found_assets.push(asset_info.clone());
-                            continue;
-                        }
-                    }
-                    AssetInfo::Native(denom) => {
-                        if let Some(entry) = self.chain.rt_handle.block_on(
-                            self.chain_registry
-                                .resolve_native_asset(self.chain.clone(), denom.clone()),
-                        ) {
-                            (entry, AssetInfo::native(denom.clone()))
-                        } else {
-                            not_found_assets.push(asset_info.clone());
-                            continue;
-                        }
-                    }
-                    _ => {
-                        log::warn!("AssetInfo not supported: {:?}", asset_info.clone());
-                        not_found_assets.push(asset_info.clone());
-                        continue;
-                    }
-                };
-                self.assets.insert(asset_name, asset_info);
-            }
+    /// The `ans-scraper scrape --dex astroport` options of the flags.
+    fn scrape_options(&self) -> ScrapeOptions {
+        ScrapeOptions {
+            dexes: vec![Dex::Astroport],
+            refresh: self.refresh,
+            conflict_policy: if self.drop_asset_conflicts {
+                ConflictPolicy::Drop
+            } else {
+                ConflictPolicy::Fail
+            },
+            include_unverified: self.include_unverified,
+            min_liquidity: self.min_liquidity,
+            pair_source: self.pair_source,
+            resume: self.resume,
+            page_limit: self.page_limit,
         }
-        Ok(())
     }
 }
 
-/// Fetch a given cw20 asset entry for the chain.
-/// TODO: move somewhere
-fn cw20_asset_entry(
-    chain: Daemon,
-    chain_ans_prefix: &str,
-    contract_addr: &Addr,
-) -> anyhow::Result<String> {
-    let cw20 =
-        Contract::new(contract_addr.clone().as_str(), chain).with_address(Some(contract_addr));
-
-    // get the name
-    let info: TokenInfoResponse = cw20.query(&Cw20QueryMsg::TokenInfo {})?;
-
-    let name = info.symbol.to_ascii_lowercase();
-    Ok(format!("{}>{}", chain_ans_prefix, name))
-}
-
-/// Script that registers the first Account in abstract (our Account)
-pub fn astroport_ans(network: ChainInfo) -> anyhow::Result<()> {
-    // let network = LOCAL_JUNO;
-    let rt = Runtime::new()?;
-
-    let chain = Daemon::builder()
-        .chain(network.clone())
-        .handle(rt.handle())
-        .build()
-        .unwrap();
-
-    // let mut astroport = rt.block_on(AstroportScraper::new(chain, "terra2"));
-
-    let chain_registry = rt.block_on(ChainRegistry::new())?;
-
-    let mut scraper = rt.block_on(Scraper::new(chain, chain_registry));
-    scraper.scrape()?;
-
-    // println!("{:?}", test);
-
-    Ok(())
-}
-
-#[derive(Parser, Default, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Arguments {
-    /// Network Id to deploy on
-    #[arg(short, long)]
-    network_id: String,
-}
-
 fn main() {
     dotenv().ok();
-    env_logger::init();
 
     use dotenv::dotenv;
 
     let args = Arguments::parse();
+    init_logger(None, args.quiet, args.log_format);
 
-    let network = parse_network(&args.network_id);
-
-    if let Err(ref err) = astroport_ans(network) {
-        log::error!("{}", err);
-        err.chain()
-            .skip(1)
-            .for_each(|cause| log::error!("because: {}", cause));
+    if let Err(err) = run(&args) {
+        log::error!("{:#}", err);
+        ::std::process::exit(1);
+    }
+}
 
-        // The backtrace is not always generated. Try to run this example
-        // with `$env:RUST_BACKTRACE=1`.
-        //    if let Some(backtrace) = e.backtrace() {
-        //        log::debug!("backtrace: {:?}", backtrace);
-        //    }
+fn run(args: &Arguments) -> anyhow::Result<()> {
+    let config = args.scraper_config()?;
+    set_offline(args.offline);
 
-        ::std::process::exit(1);
+    // The one runtime of the binary, every async call runs on it
+    let rt = Runtime::new()?;
+    let run_summary = rt.block_on(scrape_networks(
+        &args.network_id,
+        1,
+        args.grpc_url.as_deref(),
+        ChainRegistryOptions {
+            allow_multihop: args.allow_multihop,
+            ..config.chain_registry_options()
+        },
+        &config,
+        &args.scrape_options(),
+        args.ans_dir.as_deref(),
+    ))?;
+    if !run_summary.failed_networks.is_empty() {
+        anyhow::bail!(
+            "{} of {} networks failed",
+            run_summary.failed_networks.len(),
+            args.network_id.len()
+        );
     }
+    Ok(())
 }
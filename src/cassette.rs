@@ -0,0 +1,433 @@
+//! Recorded gRPC traffic of a chain, to run the whole pipeline offline and deterministically.
+//!
+//! A [`CassetteRecorder`] is a gRPC proxy on localhost that forwards the queries of a scrape to
+//! the node and keeps every request with its response, `ans-scraper scrape --record-cassette`
+//! saves them as a [`Cassette`]. A [`CassettePlayer`] serves the recorded responses again. Both
+//! are plain gRPC endpoints, the [`ChainClients`](crate::ChainClients) connect to them like to
+//! any node.
+//!
+//! Smart queries and their responses are stored as JSON so the cassettes stay readable and can
+//! be edited by hand, all other messages are stored as base64 protobuf.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use cosmos_sdk_proto::cosmwasm::wasm::v1::{
+    QuerySmartContractStateRequest, QuerySmartContractStateResponse,
+};
+use cosmwasm_std::Binary;
+use futures::future::BoxFuture;
+use hyper::body::{Body, Bytes};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{HeaderMap, Response, Server};
+use prost::bytes::{Buf, BufMut};
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+use tonic::{Code, Status};
+
+use crate::helpers::cache::write_json_atomic;
+
+/// gRPC method of the smart queries of contracts.
+pub const SMART_QUERY_METHOD: &str = "/cosmwasm.wasm.v1.Query/SmartContractState";
+
+/// Length of the header of a gRPC message: compression flag and message length.
+const GRPC_HEADER_LEN: usize = 5;
+
+/// The gRPC queries of a run with their responses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub chain_id: String,
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        write_json_atomic(path, self)
+    }
+}
+
+/// One query and what the node answered, either a response or an error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Full gRPC method path
+    pub method: String,
+    pub request: Payload,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Payload>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<GrpcError>,
+}
+
+/// A gRPC message of an [`Interaction`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Payload {
+    SmartQuery { contract: String, msg: Value },
+    SmartResponse(Value),
+    Protobuf(Binary),
+}
+
+impl Payload {
+    fn request(method: &str, message: &[u8]) -> Self {
+        if method == SMART_QUERY_METHOD {
+            if let Ok(request) = QuerySmartContractStateRequest::decode(message) {
+                if let Ok(msg) = serde_json::from_slice(&request.query_data) {
+                    return Payload::SmartQuery {
+                        contract: request.address,
+                        msg,
+                    };
+                }
+            }
+        }
+        Payload::Protobuf(Binary::from(message))
+    }
+
+    fn response(method: &str, message: &[u8]) -> Self {
+        if method == SMART_QUERY_METHOD {
+            if let Ok(response) = QuerySmartContractStateResponse::decode(message) {
+                if let Ok(data) = serde_json::from_slice(&response.data) {
+                    return Payload::SmartResponse(data);
+                }
+            }
+        }
+        Payload::Protobuf(Binary::from(message))
+    }
+
+    /// The protobuf message of the payload.
+    fn encode(&self) -> Bytes {
+        match self {
+            Payload::SmartQuery { contract, msg } => QuerySmartContractStateRequest {
+                address: contract.clone(),
+                query_data: serde_json::to_vec(msg).expect("json values serialize"),
+            }
+            .encode_to_vec()
+            .into(),
+            Payload::SmartResponse(data) => QuerySmartContractStateResponse {
+                data: serde_json::to_vec(data).expect("json values serialize"),
+            }
+            .encode_to_vec()
+            .into(),
+            Payload::Protobuf(message) => Bytes::copy_from_slice(message.as_slice()),
+        }
+    }
+
+    /// Key of the request, independent of the order of the keys of smart queries.
+    fn key(&self) -> String {
+        serde_json::to_string(self).expect("payloads serialize")
+    }
+}
+
+/// Status of a query that failed on the node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GrpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl From<&Status> for GrpcError {
+    fn from(status: &Status) -> Self {
+        Self {
+            code: status.code() as i32,
+            message: status.message().to_string(),
+        }
+    }
+}
+
+impl From<&GrpcError> for Status {
+    fn from(error: &GrpcError) -> Self {
+        Status::new(Code::from_i32(error.code), error.message.clone())
+    }
+}
+
+type InteractionKey = (String, String);
+
+/// Forwards the queries of the clients to a node and records them.
+pub struct CassetteRecorder {
+    chain_id: String,
+    interactions: Arc<Mutex<BTreeMap<InteractionKey, Interaction>>>,
+    server: LoopbackServer,
+}
+
+impl CassetteRecorder {
+    /// Start forwarding to `upstream`, must be called from a Tokio runtime.
+    pub fn start(chain_id: &str, upstream: Channel) -> anyhow::Result<Self> {
+        let interactions = Arc::new(Mutex::new(BTreeMap::new()));
+        let recorded = interactions.clone();
+        let server = LoopbackServer::start(Arc::new(
+            move |method: String, message: Bytes| -> HandlerFuture {
+                let upstream = upstream.clone();
+                let recorded = recorded.clone();
+                Box::pin(async move {
+                    let result = forward(upstream, &method, message.clone()).await;
+                    let request = Payload::request(&method, &message);
+                    let key = (method.clone(), request.key());
+                    let interaction = Interaction {
+                        response: result
+                            .as_ref()
+                            .ok()
+                            .map(|response| Payload::response(&method, response)),
+                        error: result.as_ref().err().map(GrpcError::from),
+                        method,
+                        request,
+                    };
+                    let mut recorded = recorded.lock().unwrap();
+                    // a retried query that went through replaces its failures
+                    match recorded.get(&key) {
+                        Some(Interaction { error: None, .. }) => {}
+                        Some(_) if interaction.error.is_some() => {}
+                        _ => {
+                            recorded.insert(key, interaction);
+                        }
+                    }
+                    result
+                })
+            },
+        ))?;
+        Ok(Self {
+            chain_id: chain_id.to_string(),
+            interactions,
+            server,
+        })
+    }
+
+    /// gRPC url to connect the clients to.
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+
+    /// The queries recorded so far, sorted by method and request.
+    pub fn cassette(&self) -> Cassette {
+        Cassette {
+            chain_id: self.chain_id.clone(),
+            interactions: self
+                .interactions
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Serves the responses of a cassette.
+pub struct CassettePlayer {
+    misses: Arc<Mutex<Vec<String>>>,
+    server: LoopbackServer,
+}
+
+impl CassettePlayer {
+    /// Start serving the cassette, must be called from a Tokio runtime.
+    ///
+    /// Queries that aren't on the cassette fail as unimplemented, which isn't retried.
+    pub fn start(cassette: &Cassette) -> anyhow::Result<Self> {
+        let interactions: Arc<BTreeMap<InteractionKey, Interaction>> = Arc::new(
+            cassette
+                .interactions
+                .iter()
+                .map(|interaction| {
+                    let key = (interaction.method.clone(), interaction.request.key());
+                    (key, interaction.clone())
+                })
+                .collect(),
+        );
+        let misses = Arc::new(Mutex::new(vec![]));
+        let missed = misses.clone();
+        let server = LoopbackServer::start(Arc::new(
+            move |method: String, message: Bytes| -> HandlerFuture {
+                let request = Payload::request(&method, &message);
+                let result = match interactions.get(&(method.clone(), request.key())) {
+                    Some(Interaction {
+                        response: Some(response),
+                        ..
+                    }) => Ok(response.encode()),
+                    Some(Interaction {
+                        error: Some(error), ..
+                    }) => Err(Status::from(error)),
+                    _ => {
+                        let miss = format!("{} {}", method, request.key());
+                        missed.lock().unwrap().push(miss.clone());
+                        Err(Status::unimplemented(format!(
+                            "not on the cassette: {}",
+                            miss
+                        )))
+                    }
+                };
+                Box::pin(async move { result })
+            },
+        ))?;
+        Ok(Self { misses, server })
+    }
+
+    /// gRPC url to connect the clients to.
+    pub fn url(&self) -> String {
+        self.server.url()
+    }
+
+    /// The queries that weren't on the cassette, as method and request.
+    pub fn misses(&self) -> Vec<String> {
+        self.misses.lock().unwrap().clone()
+    }
+}
+
+/// Send the raw `message` to `method` of the node.
+async fn forward(upstream: Channel, method: &str, message: Bytes) -> Result<Bytes, Status> {
+    let path = PathAndQuery::try_from(method)
+        .map_err(|_| Status::invalid_argument(format!("invalid method {}", method)))?;
+    let mut client = tonic::client::Grpc::new(upstream);
+    client
+        .ready()
+        .await
+        .map_err(|err| Status::unavailable(err.to_string()))?;
+    let response = client
+        .unary(tonic::Request::new(message), path, RawCodec)
+        .await?;
+    Ok(response.into_inner())
+}
+
+/// Passes the encoded protobuf messages through untouched.
+#[derive(Debug, Clone, Copy, Default)]
+struct RawCodec;
+
+impl Codec for RawCodec {
+    type Encode = Bytes;
+    type Decode = Bytes;
+    type Encoder = RawCodec;
+    type Decoder = RawCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        RawCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        RawCodec
+    }
+}
+
+impl Encoder for RawCodec {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn encode(&mut self, item: Bytes, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        dst.put(item);
+        Ok(())
+    }
+}
+
+impl Decoder for RawCodec {
+    type Item = Bytes;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Bytes>, Status> {
+        Ok(Some(src.copy_to_bytes(src.remaining())))
+    }
+}
+
+/// Answers a unary query: method path and request message to the response message.
+type Handler = Arc<dyn Fn(String, Bytes) -> HandlerFuture + Send + Sync>;
+
+type HandlerFuture = BoxFuture<'static, Result<Bytes, Status>>;
+
+/// HTTP/2 gRPC server on a free localhost port, shut down when dropped.
+struct LoopbackServer {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl LoopbackServer {
+    fn start(handler: Handler) -> anyhow::Result<Self> {
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    serve_unary(handler.clone(), request)
+                }))
+            }
+        });
+        let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .context("binding the gRPC loopback server")?
+            .http2_only(true)
+            .serve(make_service);
+        let addr = server.local_addr();
+        let (shutdown, signal) = oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async {
+            signal.await.ok();
+        }));
+        Ok(Self {
+            addr,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).ok();
+        }
+    }
+}
+
+async fn serve_unary(
+    handler: Handler,
+    request: hyper::Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let method = request.uri().path().to_string();
+    let result = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) if body.len() >= GRPC_HEADER_LEN && body[0] == 0 => {
+            handler(method, body.slice(GRPC_HEADER_LEN..)).await
+        }
+        Ok(_) => Err(Status::invalid_argument(
+            "expected one uncompressed gRPC message",
+        )),
+        Err(err) => Err(Status::internal(err.to_string())),
+    };
+
+    let (message, status) = match result {
+        Ok(message) => (Some(message), Status::new(Code::Ok, "")),
+        Err(status) => (None, status),
+    };
+    let mut trailers = HeaderMap::new();
+    if status.add_header(&mut trailers).is_err() {
+        trailers = HeaderMap::new();
+        Status::internal("invalid status")
+            .add_header(&mut trailers)
+            .ok();
+    }
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        if let Some(message) = message {
+            let mut frame = Vec::with_capacity(GRPC_HEADER_LEN + message.len());
+            frame.put_u8(0);
+            frame.put_u32(message.len() as u32);
+            frame.put_slice(&message);
+            if sender.send_data(frame.into()).await.is_err() {
+                return;
+            }
+        }
+        sender.send_trailers(trailers).await.ok();
+    });
+    Ok(Response::builder()
+        .header("content-type", "application/grpc")
+        .body(body)
+        .expect("static response headers are valid"))
+}
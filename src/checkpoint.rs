@@ -0,0 +1,141 @@
+//! Intermediate results of a scrape, so an interrupted one can be resumed.
+
+use std::path::{Path, PathBuf};
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, UncheckedContractEntry};
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::cache::{read_json_or_invalidate, write_json_atomic};
+use crate::pipeline::{ScrapeMeta, ABSTRACT_VERSION};
+use crate::report::DexReport;
+
+/// Checkpoint of the scrape of a chain, in its cache directory.
+pub const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// Phases of the scrape of a dex, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrapePhase {
+    /// The pairs were fetched and their assets resolved
+    AssetsResolved,
+    /// The pools were mapped to ANS pool metadata
+    PoolsMapped,
+    /// The staking contracts of the LP tokens were fetched
+    StakingFetched,
+    /// The contract entries were fetched and the report taken, nothing of the dex is left
+    Done,
+}
+
+/// What the scrape of a dex found up to its last completed phase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DexCheckpoint {
+    pub dex: String,
+    /// Last completed phase, `None` if none was
+    pub phase: Option<ScrapePhase>,
+    pub assets: Vec<(String, AssetInfo)>,
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    /// Staking contracts, followed by the contract entries once the dex is done
+    pub contracts: Vec<(UncheckedContractEntry, String)>,
+    /// Report of the scraper once its pools are mapped
+    pub report: Option<DexReport>,
+}
+
+impl DexCheckpoint {
+    pub fn new(dex: impl Into<String>) -> Self {
+        Self {
+            dex: dex.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `phase` was completed.
+    pub fn completed(&self, phase: ScrapePhase) -> bool {
+        self.phase >= Some(phase)
+    }
+}
+
+/// The progress of a scrape, written to `<cache_dir>/<chain_id>/checkpoint.json` after every
+/// phase of every dex.
+///
+/// A checkpoint only resumes the scrape it was written by: one of another chain, another set of
+/// dexes or another [`ABSTRACT_VERSION`] is discarded when loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeCheckpoint {
+    /// Chain, starting height and scraper version of the interrupted scrape
+    pub meta: ScrapeMeta,
+    /// Dexes of the scrape, in the order they are scraped
+    pub dexes: Vec<String>,
+    pub progress: Vec<DexCheckpoint>,
+}
+
+impl ScrapeCheckpoint {
+    pub fn new(meta: ScrapeMeta, dexes: Vec<String>) -> Self {
+        Self {
+            meta,
+            dexes,
+            progress: vec![],
+        }
+    }
+
+    /// `<cache_dir>/<chain_id>/checkpoint.json`
+    pub fn path(cache_dir: &Path, chain_id: &str) -> PathBuf {
+        cache_dir.join(chain_id).join(CHECKPOINT_FILE)
+    }
+
+    /// Load the checkpoint at `path` if it was written by a scrape of `dexes` on `chain_id` with
+    /// this version of the scraper.
+    pub fn load(path: &Path, chain_id: &str, dexes: &[String]) -> anyhow::Result<Option<Self>> {
+        let Some(checkpoint) = read_json_or_invalidate::<Self>(path)? else {
+            return Ok(None);
+        };
+        let stale = if checkpoint.meta.chain_id != chain_id {
+            Some(format!("it is of chain {}", checkpoint.meta.chain_id))
+        } else if checkpoint.dexes != dexes {
+            Some(format!("it is of dexes {}", checkpoint.dexes.join(",")))
+        } else if checkpoint.meta.scraper_version != ABSTRACT_VERSION {
+            Some(format!(
+                "it was written by scraper {}",
+                checkpoint.meta.scraper_version
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = stale {
+            log::info!("Discarding checkpoint {}, {}", path.display(), reason);
+            std::fs::remove_file(path)?;
+            return Ok(None);
+        }
+        Ok(Some(checkpoint))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        write_json_atomic(path, self)
+    }
+
+    /// Remove the checkpoint at `path` after the scrape completed.
+    pub fn remove(path: &Path) -> anyhow::Result<()> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// The progress of `dex`, empty if it wasn't started.
+    pub fn dex(&self, dex: &str) -> DexCheckpoint {
+        self.progress
+            .iter()
+            .find(|progress| progress.dex == dex)
+            .cloned()
+            .unwrap_or_else(|| DexCheckpoint::new(dex))
+    }
+
+    /// Record the progress of its dex.
+    pub fn update(&mut self, progress: DexCheckpoint) {
+        match self.progress.iter_mut().find(|p| p.dex == progress.dex) {
+            Some(recorded) => *recorded = progress,
+            None => self.progress.push(progress),
+        }
+    }
+}
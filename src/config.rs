@@ -0,0 +1,233 @@
+//! Settings of a scrape run, loaded from a TOML file and overridden by the command line flags.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::dexes::astroport::{
+    DEFAULT_API_SAMPLE_SIZE, DEFAULT_MAX_PAIR_PAGES, DEFAULT_PAIRS_CACHE_TTL,
+};
+use crate::dexes::kujira::KujiraDeployment;
+use crate::helpers::cache::default_cache_dir;
+use crate::helpers::chain_registry::{ChainRegistryOptions, DEFAULT_ASSET_LIST_FETCH_CONCURRENCY};
+use crate::helpers::cw20::DEFAULT_QUERY_CONCURRENCY;
+use crate::helpers::deployments::{known_deployments, DeploymentRegistry};
+use crate::helpers::networks::{self, NetworkError};
+use crate::helpers::retry::{RetryPolicy, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BASE_DELAY};
+use crate::helpers::verify::DEFAULT_CONFIG_DIR;
+
+/// Directory the output files are written to.
+pub const DEFAULT_OUT_DIR: &str = "out";
+
+/// Where the scraper keeps its files, where it finds the deployments and how hard it queries.
+///
+/// Every field is optional in the file, missing ones keep their default:
+///
+/// ```toml
+/// cache_dir = "/var/cache/ans-scraper"
+/// query_concurrency = 20
+/// max_retries = 5
+/// pairs_cache_ttl_secs = 3600
+///
+/// [ans_prefixes]
+/// "phoenix-2" = "terra2"
+///
+/// [deployment_urls.astroport]
+/// "phoenix-2" = "https://example.com/core_phoenix.json"
+///
+/// [deployment_addresses.astroport."phoenix-2"]
+/// factory_address = "terra1..."
+///
+/// [deployment_addresses.whitewhale."phoenix-1"]
+/// pool_factory_address = "terra1..."
+/// incentive_factory_address = "terra1..."
+///
+/// [kujira_deployments."kaiyo-1"]
+/// fin_code_ids = [56]
+/// bow_code_ids = [54]
+/// fin_pool_type = "constant_product"
+///
+/// [astroport_api_urls]
+/// "phoenix-1" = "https://example.com/phoenix-1/pairs"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScraperConfig {
+    /// Directory of the pairs, names, deployments and chain registry caches, defaults to
+    /// `ANS_SCRAPER_CACHE_DIR` or the cache directory of the platform
+    pub cache_dir: PathBuf,
+    /// Directory of the per-chain configuration, e.g. the verified cw20s
+    pub config_dir: PathBuf,
+    /// Directory the output files are written to
+    pub out_dir: PathBuf,
+    /// Number of cw20 token info, denom trace and reserve queries run concurrently
+    pub query_concurrency: usize,
+    /// Number of chain registry asset lists fetched concurrently
+    pub asset_list_concurrency: usize,
+    /// Number of times a failed query or download is retried
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for every following one
+    pub retry_base_delay_ms: u64,
+    /// Chain id -> ANS prefix, on top of (or instead of) the ones of [`networks::NETWORKS`]
+    pub ans_prefixes: BTreeMap<String, String>,
+    /// Protocol -> chain id -> url of the deployment file, on top of the known ones
+    pub deployment_urls: BTreeMap<String, BTreeMap<String, String>>,
+    /// Protocol -> chain id -> contract key -> address, used instead of the deployment file
+    pub deployment_addresses: BTreeMap<String, BTreeMap<String, BTreeMap<String, String>>>,
+    /// Chain id -> url of the astroport pairs API, for `--pair-source api`
+    pub astroport_api_urls: BTreeMap<String, String>,
+    /// Number of the pairs of the API that are checked against the factory
+    pub astroport_api_sample_size: usize,
+    /// Number of pages of pairs queried from a factory before its pagination is given up, in
+    /// case the node keeps answering the same page
+    pub max_pair_pages: usize,
+    /// Age in seconds after which the cached astroport pairs are all fetched again
+    pub pairs_cache_ttl_secs: u64,
+    /// Chain id -> code ids of the FIN and BOW contracts, kujira has no deployment file
+    pub kujira_deployments: BTreeMap<String, KujiraDeployment>,
+}
+
+impl Default for ScraperConfig {
+    fn default() -> Self {
+        Self {
+            cache_dir: default_cache_dir(),
+            config_dir: PathBuf::from(DEFAULT_CONFIG_DIR),
+            out_dir: PathBuf::from(DEFAULT_OUT_DIR),
+            query_concurrency: DEFAULT_QUERY_CONCURRENCY,
+            asset_list_concurrency: DEFAULT_ASSET_LIST_FETCH_CONCURRENCY,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_base_delay_ms: DEFAULT_RETRY_BASE_DELAY.as_millis() as u64,
+            ans_prefixes: BTreeMap::new(),
+            deployment_urls: BTreeMap::new(),
+            deployment_addresses: BTreeMap::new(),
+            astroport_api_urls: BTreeMap::new(),
+            astroport_api_sample_size: DEFAULT_API_SAMPLE_SIZE,
+            max_pair_pages: DEFAULT_MAX_PAIR_PAGES,
+            pairs_cache_ttl_secs: DEFAULT_PAIRS_CACHE_TTL.as_secs(),
+            kujira_deployments: BTreeMap::new(),
+        }
+    }
+}
+
+impl ScraperConfig {
+    /// The built-in settings of the chain, spelled out: its ANS prefix and the deployment files
+    /// published for it.
+    pub fn default_for(chain_id: &str) -> Self {
+        let mut config = Self::default();
+        if let Ok(prefix) = networks::ans_prefix(chain_id) {
+            config
+                .ans_prefixes
+                .insert(chain_id.to_string(), prefix.to_string());
+        }
+        for (protocol, url) in known_deployments(chain_id) {
+            config
+                .deployment_urls
+                .entry(protocol.to_string())
+                .or_default()
+                .insert(chain_id.to_string(), url.to_string());
+        }
+        config
+    }
+
+    /// Load the settings of a TOML file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("reading scraper config from {}", path.display()))?;
+        toml::from_str(&toml)
+            .with_context(|| format!("parsing scraper config from {}", path.display()))
+    }
+
+    /// Load the settings of `path`, or the defaults without a file.
+    pub fn load_or_default(path: Option<&Path>) -> anyhow::Result<Self> {
+        path.map_or_else(|| Ok(Self::default()), Self::load)
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(&self, chain_id: &str) -> Result<&str, NetworkError> {
+        match self.ans_prefixes.get(chain_id) {
+            Some(prefix) => Ok(prefix.as_str()),
+            None => networks::ans_prefix(chain_id),
+        }
+    }
+
+    /// ANS prefix of the chain for `dex`, which is deployed on the networks in `chain_ids` and on
+    /// those it has a configured deployment on.
+    pub fn dex_ans_prefix(
+        &self,
+        dex: &str,
+        chain_ids: &[&str],
+        chain_id: &str,
+    ) -> Result<&str, NetworkError> {
+        let prefix = self.ans_prefix(chain_id)?;
+        if !chain_ids.contains(&chain_id) && !self.has_deployment(dex, chain_id) {
+            return Err(NetworkError::UnsupportedDex {
+                dex: dex.to_string(),
+                chain_id: chain_id.to_string(),
+            });
+        }
+        Ok(prefix)
+    }
+
+    /// The configured addresses of the deployment of `protocol` on `chain_id`, by contract key.
+    pub fn configured_addresses(
+        &self,
+        protocol: &str,
+        chain_id: &str,
+    ) -> Option<&BTreeMap<String, String>> {
+        self.deployment_addresses.get(protocol)?.get(chain_id)
+    }
+
+    /// Whether a deployment file or addresses of `protocol` on `chain_id` are configured.
+    fn has_deployment(&self, protocol: &str, chain_id: &str) -> bool {
+        self.deployment_urls
+            .get(protocol)
+            .map_or(false, |urls| urls.contains_key(chain_id))
+            || self
+                .deployment_addresses
+                .get(protocol)
+                .map_or(false, |addresses| addresses.contains_key(chain_id))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_base_delay_ms),
+        }
+    }
+
+    /// Age after which the cached astroport pairs are all fetched again.
+    pub fn pairs_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.pairs_cache_ttl_secs)
+    }
+
+    /// The known deployments with the configured ones, cached in `<cache_dir>/deployments`.
+    pub fn deployment_registry(&self) -> DeploymentRegistry {
+        let mut registry =
+            DeploymentRegistry::new().with_cache_dir(self.cache_dir.join("deployments"));
+        for (protocol, urls) in &self.deployment_urls {
+            for (chain_id, url) in urls {
+                registry = registry.with_url(protocol, chain_id, url);
+            }
+        }
+        for (protocol, chains) in &self.deployment_addresses {
+            for (chain_id, addresses) in chains {
+                for (key, address) in addresses {
+                    registry = registry.with_address(protocol, chain_id, key, address);
+                }
+            }
+        }
+        registry
+    }
+
+    /// Asset lists cached in `<cache_dir>/asset_lists`, fetched with the asset list concurrency.
+    pub fn chain_registry_options(&self) -> ChainRegistryOptions {
+        ChainRegistryOptions {
+            cache_dir: self.cache_dir.join("asset_lists"),
+            fetch_concurrency: self.asset_list_concurrency,
+            ..Default::default()
+        }
+    }
+}
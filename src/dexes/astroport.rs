@@ -1,220 +1,2614 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use abstract_core::objects::pool_id::UncheckedPoolAddress;
-use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use abstract_core::objects::{AssetEntry, ContractEntry, PoolMetadata, PoolType};
+use anyhow::Context;
 use astroport::asset::{AssetInfo as AstroportAssetInfo, PairInfo};
-use astroport::factory::{AstroportFactory, PairType, PairsResponse, QueryMsgFns};
+use astroport::factory::{PairType, PairsResponse, QueryMsg as FactoryQueryMsg};
+use astroport::pair::{PoolResponse, QueryMsg as PairQueryMsg};
+use async_trait::async_trait;
 use cosmwasm_std::Addr;
+use rand::seq::SliceRandom;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
 use cw_asset::AssetInfo;
-use cw_orch::{queriers::DaemonQuerier, ContractInstance, CwEnv, Daemon};
-use reqwest::Error;
 
-use crate::traits::dex::{AssetSource, DexId, DexScraper};
-
-const ASTROPORT_PHOENIX_ADDRS: &str = "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/terra-2/phoenix-1/core_phoenix.json";
-const ASTROPORT_PISCO_ADDRS: &str = "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/terra-2/pisco-1/core_pisco.json";
+use crate::config::ScraperConfig;
+use crate::error::ScraperError;
+use crate::helpers::cache::{read_json_or_invalidate, unix_now, write_json_atomic};
+use crate::helpers::clients::ChainClients;
+use crate::helpers::deployments::DeploymentRegistry;
+use crate::helpers::http::fetch_with_cache;
+use crate::helpers::liquidity::LiquidityFilter;
+use crate::helpers::names::lp_token_name;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::pagination::{FactoryPair, PairPages};
+use crate::helpers::query_cache::MissingCacheEntries;
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::helpers::resolve::AssetResolver;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::report::{DexReport, PoolReserve, SkippedPool, UnresolvedAsset, UnresolvedReason};
+use crate::traits::deployment::DeploymentSource;
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::traits::pairs::{PairSource, StaticPairs};
+use crate::traits::resolve::DenomResolver;
+use crate::LazyChainRegistry;
 
 const ASTROPORT_DEX: &str = "astroport";
 
-pub struct AstroportScraper<Chain: CwEnv> {
-    chain: Chain,
-    factory: AstroportFactory<Chain>,
+/// Networks with an astroport deployment.
+const ASTROPORT_NETWORKS: [&str; 5] =
+    ["phoenix-1", "pisco-1", "neutron-1", "pion-1", "injective-1"];
+
+/// Changelog key of the factory, the only contract the scraper can't do without.
+pub(crate) const FACTORY_KEY: &str = "factory_address";
+
+/// Changelog keys of the contract LP tokens are staked in, the generator was replaced by the
+/// incentives contract on newer deployments.
+const GENERATOR_KEYS: [&str; 2] = ["generator_address", "incentives_address"];
+
+/// ANS contract name and changelog key of the protocol contracts adapters look up, besides the
+/// generator.
+const PROTOCOL_CONTRACTS: [(&str, &str); 4] = [
+    ("router", "router_address"),
+    ("maker", "maker_address"),
+    ("staking", "staking_address"),
+    ("voting_escrow", "voting_escrow_address"),
+];
+
+/// Changelog key of the ASTRO cw20, absent where ASTRO is a native denom.
+pub(crate) const ASTRO_TOKEN_KEY: &str = "astro_token_address";
+
+/// The deployments published in the astroport changelog repository, or any other astroport
+/// deployment file of the registry.
+#[derive(Debug, Clone, Default)]
+pub struct AstroportChangelog {
+    registry: DeploymentRegistry,
+}
+
+impl AstroportChangelog {
+    pub fn new(registry: DeploymentRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl DeploymentSource for AstroportChangelog {
+    async fn address(&self, chain_id: &str, key: &str) -> anyhow::Result<Option<String>> {
+        let address = self.registry.address(ASTROPORT_DEX, chain_id, key).await?;
+        Ok(address.map(String::from))
+    }
+
+    async fn keys(&self, chain_id: &str) -> anyhow::Result<Vec<String>> {
+        self.registry.keys(ASTROPORT_DEX, chain_id).await
+    }
+}
+
+/// File in `<cache_dir>/<chain_id>/` with the pairs of the last run.
+const PAIRS_CACHE_FILE: &str = "astroport_pairs.json";
+
+/// Age after which the cached pairs are refetched from scratch instead of only being extended.
+pub const DEFAULT_PAIRS_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default number of pages of pairs queried from the factory before the pagination is given up.
+pub const DEFAULT_MAX_PAIR_PAGES: usize = 1_000;
+
+/// File in `<cache_dir>/<chain_id>/` with the last response of the pairs API.
+const API_PAIRS_CACHE_FILE: &str = "astroport_api_pairs.json";
+
+/// Default number of the pairs of the API that are checked against the factory.
+pub const DEFAULT_API_SAMPLE_SIZE: usize = 10;
+
+/// Where [`AstroportScraper::load_pairs`] gets the pairs of the factory from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PairSourceKind {
+    /// Paginate the factory, see [`FactoryPairs`]
+    #[default]
+    Onchain,
+    /// List them with the pairs API of the chain, see [`ApiPairs`]
+    Api,
+}
+
+/// The pairs of the factory, paginated through its `pairs` query.
+#[derive(Clone)]
+pub struct FactoryPairs {
+    clients: Arc<ChainClients>,
+    factory: Addr,
+    max_pages: usize,
+}
+
+impl FactoryPairs {
+    pub fn new(clients: Arc<ChainClients>, factory: Addr) -> Self {
+        Self {
+            clients,
+            factory,
+            max_pages: DEFAULT_MAX_PAIR_PAGES,
+        }
+    }
+
+    /// Stop paginating the factory after this many pages.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Query a single page of pairs from the factory.
+    pub async fn fetch_page(
+        &self,
+        limit: Option<u32>,
+        start_after_pair: Option<Vec<AstroportAssetInfo>>,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        let query = FactoryQueryMsg::Pairs {
+            start_after: start_after_pair.clone(),
+            limit,
+        };
+        let PairsResponse { pairs } = self
+            .query(&query)
+            .await
+            .with_context(|| format!("querying pairs after {:?}", start_after_pair))?;
+        Ok(pairs)
+    }
+
+    /// The pair of the factory with these assets.
+    pub async fn pair(&self, asset_infos: Vec<AstroportAssetInfo>) -> anyhow::Result<PairInfo> {
+        self.query(&FactoryQueryMsg::Pair { asset_infos }).await
+    }
+
+    async fn query<T: DeserializeOwned>(&self, query: &FactoryQueryMsg) -> anyhow::Result<T> {
+        let factory = &self.factory;
+        let response = WasmQueryBatcher::new(&self.clients.wasm, 1)
+            .query(factory, query)
+            .await
+            .with_context(|| format!("querying astroport factory {}", factory))?;
+        serde_json::from_slice(&response)
+            .with_context(|| format!("parsing response of astroport factory {}", factory))
+    }
+}
+
+#[async_trait]
+impl PairSource for FactoryPairs {
+    async fn pairs_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        let mut pages = PairPages::new(
+            ASTROPORT_DEX,
+            &self.clients.chain_id,
+            start_after,
+            self.max_pages,
+        );
+        let mut pairs = vec![];
+        while let Some(start_after) = pages.cursor() {
+            let page = self.fetch_page(None, start_after).await?;
+            pairs.extend(pages.advance(page));
+        }
+        Ok(pairs)
+    }
+
+    async fn page_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        self.fetch_page(Some(limit), start_after).await
+    }
+}
+
+impl FactoryPair for PairInfo {
+    type AssetInfo = AstroportAssetInfo;
+
+    fn contract_addr(&self) -> &Addr {
+        &self.contract_addr
+    }
+
+    fn asset_infos(&self) -> &[AstroportAssetInfo] {
+        &self.asset_infos
+    }
+}
+
+/// The pairs of the factory listed by an indexer API, in one request instead of paginating the
+/// factory through the gRPC node.
+///
+/// The API returns the pairs like the factory's `pairs` query does, as `{"pairs": [...]}` or a
+/// bare array. An indexer can lag behind the chain, so a random sample of the pairs is looked up
+/// in the factory and any pair the factory doesn't have (or has another contract for) fails the
+/// fetch.
+pub struct ApiPairs {
+    factory: FactoryPairs,
+    url: String,
+    cache_path: PathBuf,
+    sample_size: usize,
+}
+
+/// Pairs of the API the factory doesn't agree with.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "pairs API {url} is stale, {} of {sampled} sampled pairs differ from the factory: {}; use \
+     --pair-source onchain",
+    stale.len(),
+    stale.join(", ")
+)]
+pub struct StaleApiPairs {
+    pub url: String,
+    pub sampled: usize,
+    pub stale: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ApiPairsResponse {
+    Pairs { pairs: Vec<PairInfo> },
+    List(Vec<PairInfo>),
+}
+
+impl ApiPairs {
+    pub fn new(
+        factory: FactoryPairs,
+        url: impl Into<String>,
+        cache_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            factory,
+            url: url.into(),
+            cache_path: cache_path.into(),
+            sample_size: DEFAULT_API_SAMPLE_SIZE,
+        }
+    }
+
+    /// Check this many pairs against the factory, 0 to trust the API.
+    pub fn with_sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+
+    /// Look a random sample of `pairs` up in the factory.
+    async fn check_sample(&self, pairs: &[PairInfo]) -> Result<(), StaleApiPairs> {
+        if self.factory.clients.offline || self.sample_size == 0 {
+            return Ok(());
+        }
+        let sample: Vec<&PairInfo> = pairs
+            .choose_multiple(&mut rand::thread_rng(), self.sample_size)
+            .collect();
+
+        let mut stale = vec![];
+        for pair in &sample {
+            match self.factory.pair(pair.asset_infos.to_vec()).await {
+                Ok(factory_pair) if factory_pair.contract_addr == pair.contract_addr => {}
+                Ok(factory_pair) => stale.push(format!(
+                    "{} is {} in the factory",
+                    pair.contract_addr, factory_pair.contract_addr
+                )),
+                Err(err) => stale.push(format!("{} ({:#})", pair.contract_addr, err)),
+            }
+        }
+        if stale.is_empty() {
+            log::info!(
+                "{}: {} sampled pairs of {} match the factory",
+                self.factory.clients.chain_id,
+                sample.len(),
+                self.url
+            );
+            return Ok(());
+        }
+        Err(StaleApiPairs {
+            url: self.url.clone(),
+            sampled: sample.len(),
+            stale,
+        })
+    }
+}
+
+#[async_trait]
+impl PairSource for ApiPairs {
+    /// All the pairs, the API can't be paginated.
+    async fn pairs_after(
+        &self,
+        _start_after: Option<Vec<AstroportAssetInfo>>,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        let body = fetch_with_cache(&self.url, &self.cache_path, Some(Duration::ZERO))
+            .await
+            .with_context(|| format!("fetching astroport pairs from {}", self.url))?
+            .body;
+        let pairs = match serde_json::from_str(&body)
+            .with_context(|| format!("parsing astroport pairs of {}", self.url))?
+        {
+            ApiPairsResponse::Pairs { pairs } | ApiPairsResponse::List(pairs) => pairs,
+        };
+        self.check_sample(&pairs).await?;
+        Ok(pairs)
+    }
+
+    fn is_incremental(&self) -> bool {
+        false
+    }
+}
+
+/// Pairs of the factory as of `block_height`.
+#[derive(Serialize, Deserialize)]
+struct PairsCache {
+    factory: String,
+    block_height: u64,
+    /// Unix timestamp of the last time all the pairs were fetched, 0 for caches from before it
+    /// was recorded
+    #[serde(default)]
+    fetched_at: u64,
+    pairs: Vec<PairInfo>,
+}
+
+/// The staking contract of the deployment with the LP tokens registered with it.
+#[derive(Debug, Clone)]
+struct Generator {
+    address: Addr,
+    lp_tokens: Vec<Addr>,
+    /// Token the stakers are rewarded with (ASTRO)
+    reward_token: Option<AstroportAssetInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum GeneratorQueryMsg {
+    ActivePools {},
+    Config {},
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum StakingQueryMsg {
+    Config {},
+}
+
+/// Where the pairs of the last [`AstroportScraper::load_pairs`] call came from.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PairsCacheStats {
+    /// Pairs taken from the pairs cache
+    pub cached: usize,
+    /// Pairs queried from the factory
+    pub fetched: usize,
+}
+
+pub struct AstroportScraper {
+    chain_id: String,
+    /// `None` when the pairs and names only come from injected sources
+    clients: Option<Arc<ChainClients>>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    ans_prefix: String,
+    factory: Addr,
+    /// Lists the pairs of the factory, paginating the factory by default
+    pair_source: Box<dyn PairSource>,
+    generator_address: Option<Addr>,
+    /// Router, generator, maker, staking and voting escrow contracts of the deployment
+    protocol_contracts: Vec<(ContractEntry, Addr)>,
+    /// ASTRO cw20 of the deployment
+    astro_token: Option<Addr>,
+    loaded_generator: Option<Generator>,
     loaded_pairs: Vec<PairInfo>,
     asset_info_to_name: HashMap<AstroportAssetInfo, String>,
+    not_found_assets: Vec<UnresolvedAsset>,
+    skipped_pools: Vec<SkippedPool>,
+    query_concurrency: usize,
+    pairs_cache_stats: PairsCacheStats,
+    pairs_cache_ttl: Duration,
+    /// Ignore the cached pairs and fetch them all again
+    refresh: bool,
+    cache_dir: PathBuf,
+    /// custom pair type -> ANS pool type
+    custom_pair_types: HashMap<String, PoolType>,
+    unknown_pair_types: BTreeMap<String, Vec<UncheckedPoolAddress>>,
+    /// Names the assets instead of the chain registry and cw20 queries
+    denom_resolver: Option<Box<dyn DenomResolver + Send + Sync>>,
+    /// Number of pages of pairs queried from the factory before the pagination is given up
+    max_pair_pages: usize,
+    /// Drops the pairs whose reserves are below the minimum
+    liquidity_filter: Option<LiquidityFilter>,
+    /// Reserves of the loaded pairs that are below the minimum, by pair address
+    illiquid_pairs: Option<BTreeMap<Addr, Vec<PoolReserve>>>,
+    /// Loaded pairs that were created again, with the pair that replaced them
+    duplicate_pairs: Option<BTreeMap<Addr, Addr>>,
 }
 
-impl<T: cw_orch::TxHandler> DexId for AstroportScraper<T> {
+impl DexId for AstroportScraper {
     fn dex_id(&self) -> &'static str {
         ASTROPORT_DEX
     }
 }
 
-impl AstroportScraper<Daemon> {
-    pub async fn new(chain: Daemon) -> Self {
-        let factory_address =
-            Self::fetch_deployment_address(chain.state.chain_id.as_str(), "factory_address")
-                .await
-                .unwrap();
+impl AstroportScraper {
+    /// Create the scraper for the chain with the astroport deployment of `config`, by default the
+    /// one of the astroport changelog. The chain registry is only fetched once a native denom has
+    /// to be resolved.
+    pub async fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let deployment = AstroportChangelog::new(config.deployment_registry());
+        Self::from_deployment(clients, chain_registry, &deployment, config).await
+    }
 
-        let mut factory =
-            astroport::factory::AstroportFactory::new("astroport:factory", chain.clone());
-        factory
-            .as_instance_mut()
-            .set_address(&Addr::unchecked(factory_address));
+    /// Create the scraper for the chain with the contracts of `deployment`.
+    pub async fn from_deployment(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        deployment: &dyn DeploymentSource,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let mut scraper = Self::build(&clients.chain_id, deployment, config).await?;
+        scraper.pair_source = Box::new(
+            FactoryPairs::new(clients.clone(), scraper.factory.clone())
+                .with_max_pages(config.max_pair_pages),
+        );
+        scraper.clients = Some(clients);
+        scraper.chain_registry = chain_registry.into();
+        Ok(scraper)
+    }
 
-        Self {
-            chain,
-            factory,
+    /// Create the scraper for `chain_id` without querying the chain: the contracts come from
+    /// `deployment`, the pairs from `pair_source` and the asset names from `denom_resolver`.
+    ///
+    /// Nothing is cached and the generator, xASTRO and pool reserves are left out.
+    pub async fn from_sources(
+        chain_id: &str,
+        deployment: &dyn DeploymentSource,
+        pair_source: impl PairSource + 'static,
+        denom_resolver: impl DenomResolver + Send + Sync + 'static,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        Ok(Self::build(chain_id, deployment, config)
+            .await?
+            .with_pair_source(pair_source)
+            .with_denom_resolver(denom_resolver))
+    }
+
+    /// The scraper of the contracts of `deployment`, without clients or pairs.
+    async fn build(
+        chain_id: &str,
+        deployment: &dyn DeploymentSource,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let ans_prefix = config.dex_ans_prefix(ASTROPORT_DEX, &ASTROPORT_NETWORKS, chain_id)?;
+        let Some(factory_address) = deployment
+            .address(chain_id, FACTORY_KEY)
+            .await
+            .with_context(|| format!("fetching astroport factory address for {}", chain_id))?
+        else {
+            return Err(ScraperError::DeploymentKeyMissing {
+                protocol: ASTROPORT_DEX.to_string(),
+                key: FACTORY_KEY.to_string(),
+                available: deployment.keys(chain_id).await.unwrap_or_default(),
+            });
+        };
+        let generator_address = generator_address(deployment, chain_id)
+            .await
+            .with_context(|| format!("fetching astroport generator address for {}", chain_id))?;
+        let protocol_contracts =
+            protocol_contracts(deployment, chain_id, generator_address.as_deref())
+                .await
+                .with_context(|| format!("fetching astroport contracts for {}", chain_id))?;
+        let astro_token = deployment
+            .address(chain_id, ASTRO_TOKEN_KEY)
+            .await
+            .with_context(|| format!("fetching astroport ASTRO token for {}", chain_id))?;
+
+        Ok(Self {
+            chain_id: chain_id.to_string(),
+            clients: None,
+            chain_registry: LazyChainRegistry::default(),
+            resolution_cache: Default::default(),
+            ans_prefix: ans_prefix.to_string(),
+            factory: Addr::unchecked(factory_address),
+            pair_source: Box::new(StaticPairs::default()),
+            generator_address: generator_address.map(Addr::unchecked),
+            protocol_contracts,
+            astro_token: astro_token.map(Addr::unchecked),
+            loaded_generator: None,
             loaded_pairs: vec![],
             asset_info_to_name: HashMap::new(),
+            not_found_assets: vec![],
+            skipped_pools: vec![],
+            query_concurrency: config.query_concurrency,
+            pairs_cache_stats: Default::default(),
+            pairs_cache_ttl: config.pairs_cache_ttl(),
+            refresh: false,
+            cache_dir: config.cache_dir.clone(),
+            custom_pair_types: HashMap::new(),
+            unknown_pair_types: BTreeMap::new(),
+            denom_resolver: None,
+            max_pair_pages: config.max_pair_pages,
+            liquidity_filter: None,
+            illiquid_pairs: None,
+            duplicate_pairs: None,
+        })
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(ASTROPORT_DEX, &ASTROPORT_NETWORKS, chain_id)
+    }
+
+    /// The clients of the chain, `None` offline or without clients.
+    fn online_clients(&self) -> Option<&Arc<ChainClients>> {
+        self.clients.as_ref().filter(|clients| !clients.offline)
+    }
+
+    /// Resolve the ANS names of all the assets in the loaded pairs, plus the LP tokens of the
+    /// pairs whose assets all resolved and the ASTRO and xASTRO cw20s of the deployment.
+    ///
+    /// Resolved names are remembered so [`DexScraper::fetch_dex_pools`] can build the pool metadata,
+    /// assets that can't be named are available through [`Self::not_found_assets`].
+    pub async fn resolve_assets(&mut self) -> anyhow::Result<Vec<(String, AssetInfo)>> {
+        self.not_found_assets.clear();
+        self.asset_info_to_name.clear();
+        let pairs = self.kept_pairs().await?;
+        let mut asset_infos = pair_asset_infos(&pairs);
+        // The staking reward token, when no pair has it
+        if let Some(reward_token) = self.load_generator().await?.and_then(|g| g.reward_token) {
+            let reward_token = from_astroport_info(&reward_token);
+            if !asset_infos.contains(&reward_token) {
+                asset_infos.push(reward_token);
+            }
         }
+        for token in self
+            .astro_token
+            .clone()
+            .into_iter()
+            .chain(self.xastro_token().await)
+        {
+            let token = AssetInfo::cw20(token);
+            if !asset_infos.contains(&token) {
+                asset_infos.push(token);
+            }
+        }
+        let mut ans_assets_to_add = self.resolve_asset_infos(asset_infos).await?;
+        ans_assets_to_add.extend(pair_lp_tokens(&pairs, &self.asset_info_to_name));
+        Ok(ans_assets_to_add)
     }
 
-    fn load_pairs(&mut self) -> anyhow::Result<Vec<PairInfo>> {
-        // Fetch pairs if not already done
-        if self.loaded_pairs.is_empty() {
-            let mut all_pairs = vec![];
-            let mut start_after_pair = None;
-            loop {
-                let PairsResponse { mut pairs } = self.factory.pairs(None, start_after_pair)?;
-                if pairs.is_empty() {
-                    break;
+    /// Resolve the ANS names of the given assets, skipping the ones this scraper already named.
+    async fn resolve_asset_infos(
+        &mut self,
+        asset_infos: Vec<AssetInfo>,
+    ) -> anyhow::Result<Vec<(String, AssetInfo)>> {
+        let asset_infos = asset_infos
+            .into_iter()
+            .filter(|info| {
+                !self.not_found_assets.iter().any(|a| a.info == *info)
+                    && to_astroport_info(info)
+                        .map_or(true, |info| !self.asset_info_to_name.contains_key(&info))
+            })
+            .collect::<Vec<_>>();
+
+        let resolved = match (&self.denom_resolver, &self.clients) {
+            (Some(denom_resolver), _) => denom_resolver.resolve(asset_infos).await?,
+            (None, None) => anyhow::bail!(
+                "{}: the assets can't be named without clients or denom resolver",
+                self.chain_id
+            ),
+            (None, Some(clients)) => {
+                AssetResolver {
+                    clients,
+                    chain_registry: &self.chain_registry,
+                    resolution_cache: &self.resolution_cache,
+                    ans_prefix: &self.ans_prefix,
+                    concurrency: self.query_concurrency,
                 }
-                all_pairs.append(&mut pairs);
-                start_after_pair = all_pairs.last().map(|p| p.asset_infos.to_vec());
+                .resolve(asset_infos)
+                .await?
             }
+        };
+
+        let mut names = Vec::with_capacity(resolved.names.len());
+        for (name, asset_info) in resolved.names {
+            // A denom resolver may name assets that can't be in an astroport pair
+            let Some(astroport_info) = to_astroport_info(&asset_info) else {
+                log::warn!(
+                    "Skipping asset {} named {}: not in astroport pairs",
+                    asset_info,
+                    name
+                );
+                let reason = UnresolvedReason::Unsupported(format!("{:?}", asset_info));
+                self.not_found_assets
+                    .push(UnresolvedAsset::new(asset_info, reason));
+                continue;
+            };
+            self.asset_info_to_name.insert(astroport_info, name.clone());
+            names.push((name, asset_info));
+        }
+        self.not_found_assets.extend(resolved.not_found);
+        Ok(names)
+    }
+
+    /// Scrape the pairs of the pair source one page at a time.
+    ///
+    /// Every page's new assets are resolved and handed to `sink` together with the page's pools,
+    /// so only the resolved names are kept in memory instead of all the pairs. Pairs that were
+    /// created again can't be told apart from their replacement across pages and are all kept.
+    pub async fn scrape_paged(
+        &mut self,
+        page_limit: u32,
+        mut sink: impl FnMut(
+            &[(String, AssetInfo)],
+            &[(UncheckedPoolAddress, PoolMetadata)],
+        ) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.not_found_assets.clear();
+        self.skipped_pools.clear();
+        self.unknown_pair_types.clear();
+
+        let mut pages = PairPages::new(ASTROPORT_DEX, &self.chain_id, None, self.max_pair_pages);
+        while let Some(start_after_pair) = pages.cursor() {
+            let pairs = pages.advance(
+                self.pair_source
+                    .page_after(start_after_pair, page_limit)
+                    .await?,
+            );
+            if pairs.is_empty() {
+                continue;
+            }
+
+            let illiquid_pairs = self.query_illiquid_pairs(&pairs).await;
+            let pairs = pairs
+                .into_iter()
+                .filter(|pair| !illiquid_pairs.contains_key(&pair.contract_addr))
+                .collect::<Vec<_>>();
+            let mut assets = self.resolve_asset_infos(pair_asset_infos(&pairs)).await?;
+            assets.extend(pair_lp_tokens(&pairs, &self.asset_info_to_name));
+            let pools = self.pairs_to_pools(pairs);
+            self.skipped_pools
+                .extend(low_liquidity_pools(&illiquid_pairs));
+            sink(&assets, &pools)?;
+        }
+        self.log_unknown_pair_types();
+        Ok(())
+    }
+
+    /// All the pairs of the factory, queried once and kept for the lifetime of the scraper.
+    ///
+    /// The pairs are persisted together with the block height they were captured at. The next run
+    /// only asks the factory for the pairs after the last cached one, and refetches everything if
+    /// that pair was deregistered in the meantime, the cache is older than the TTL or a refresh
+    /// was requested. Offline, the cached pairs are used whatever their age. Without clients, all
+    /// the pairs of the pair source are listed and nothing is cached.
+    pub async fn load_pairs(&mut self) -> anyhow::Result<Vec<PairInfo>> {
+        let Some(clients) = self.clients.clone() else {
+            if self.loaded_pairs.is_empty() {
+                let mut pairs = self.pair_source.pairs_after(None).await?;
+                let mut seen = HashSet::new();
+                pairs.retain(|pair| seen.insert(pair.contract_addr.clone()));
+                self.pairs_cache_stats = PairsCacheStats {
+                    cached: 0,
+                    fetched: pairs.len(),
+                };
+                self.loaded_pairs = pairs;
+            }
+            return Ok(self.loaded_pairs.clone());
+        };
+        if self.loaded_pairs.is_empty() && clients.offline {
+            self.loaded_pairs = self.cached_pairs()?;
+            self.pairs_cache_stats = PairsCacheStats {
+                cached: self.loaded_pairs.len(),
+                fetched: 0,
+            };
+        }
+        // Fetch pairs if not already done
+        if self.loaded_pairs.is_empty() && !clients.offline {
+            let cache_path = self.pairs_cache_path();
+            let block_height = clients.node.block_height().await?;
+
+            let cached: Option<PairsCache> = if self.refresh || !self.pair_source.is_incremental() {
+                None
+            } else {
+                read_json_or_invalidate(&cache_path)?
+            };
+            let factory = self.factory.to_string();
+            let ttl = self.pairs_cache_ttl.as_secs();
+            let mut fetched_at = unix_now();
+            let cache_is_fresh = match &cached {
+                Some(cached) => {
+                    cached.factory == factory
+                        && fetched_at.saturating_sub(cached.fetched_at) < ttl
+                        && self.cursor_exists(&cached.pairs).await
+                }
+                None => false,
+            };
+            let mut all_pairs = match cached {
+                Some(cached) if cache_is_fresh => {
+                    log::info!(
+                        "{}: {} pairs cached at height {}, fetching the pairs added since",
+                        self.chain_id,
+                        cached.pairs.len(),
+                        cached.block_height
+                    );
+                    fetched_at = cached.fetched_at;
+                    cached.pairs
+                }
+                Some(_) => {
+                    log::info!(
+                        "{}: pairs cache is stale, refetching all pairs",
+                        self.chain_id
+                    );
+                    vec![]
+                }
+                None => vec![],
+            };
+            let cached_pairs = all_pairs.len();
+
+            let start_after_pair = all_pairs.last().map(|p| p.asset_infos.to_vec());
+            all_pairs.append(&mut self.pair_source.pairs_after(start_after_pair).await?);
+            // A source may repeat a pair, within its pages or from the cached ones
+            let mut seen = HashSet::new();
+            all_pairs.retain(|pair| seen.insert(pair.contract_addr.clone()));
+
+            self.pairs_cache_stats = PairsCacheStats {
+                cached: cached_pairs,
+                fetched: all_pairs.len().saturating_sub(cached_pairs),
+            };
+            log::info!(
+                "{}: {} pairs from cache, {} fetched",
+                self.chain_id,
+                self.pairs_cache_stats.cached,
+                self.pairs_cache_stats.fetched
+            );
+
+            write_json_atomic(
+                &cache_path,
+                &PairsCache {
+                    factory,
+                    block_height,
+                    fetched_at,
+                    pairs: all_pairs.clone(),
+                },
+            )?;
             self.loaded_pairs = all_pairs;
         }
 
         Ok(self.loaded_pairs.clone())
     }
 
-    /// Get a deployment address for astroprt given its chainId and key
-    async fn fetch_deployment_address(chain_id: &str, key: &str) -> Result<String, Error> {
-        let url = match chain_id {
-            "phoenix-1" => ASTROPORT_PHOENIX_ADDRS,
-            "pisco-1" => ASTROPORT_PISCO_ADDRS,
-            _ => panic!("Network not supported"),
-        };
+    /// The loaded pairs without the duplicates and the ones below the minimum liquidity, which are
+    /// looked up once and kept for the lifetime of the scraper.
+    async fn kept_pairs(&mut self) -> anyhow::Result<Vec<PairInfo>> {
+        let mut pairs = self.load_pairs().await?;
+        if self.duplicate_pairs.is_none() {
+            self.duplicate_pairs = Some(self.find_duplicate_pairs(&pairs).await);
+        }
+        let duplicate_pairs = self
+            .duplicate_pairs
+            .as_ref()
+            .expect("duplicate pairs are looked up");
+        pairs.retain(|pair| !duplicate_pairs.contains_key(&pair.contract_addr));
+
+        if self.illiquid_pairs.is_none() {
+            self.illiquid_pairs = Some(self.query_illiquid_pairs(&pairs).await);
+        }
+        let illiquid_pairs = self
+            .illiquid_pairs
+            .as_ref()
+            .expect("illiquid pairs are queried");
+        Ok(pairs
+            .into_iter()
+            .filter(|pair| !illiquid_pairs.contains_key(&pair.contract_addr))
+            .collect())
+    }
 
-        let response_text = reqwest::get(url).await?.text().await?;
+    /// Pairs with the same assets and pair type as another pair, a pair that was deregistered and
+    /// created again, mapped to the pair that is kept: the most recently instantiated one.
+    async fn find_duplicate_pairs(&self, pairs: &[PairInfo]) -> BTreeMap<Addr, Addr> {
+        let mut created = HashMap::new();
+        if let Some(clients) = &self.clients {
+            for pair in pair_groups(pairs).into_iter().flatten() {
+                if let Some(height) = clients.contract_created(pair.contract_addr.as_str()).await {
+                    created.insert(pair.contract_addr.clone(), height);
+                }
+            }
+        }
 
-        let lines = response_text.lines().collect::<Vec<_>>();
-        let mut json_map = HashMap::new();
+        let duplicate_pairs = duplicate_pairs(pairs, &created);
+        for (pair, kept) in &duplicate_pairs {
+            log::warn!(
+                "{}: dropping pair {}, it was created again as {}",
+                self.chain_id,
+                pair,
+                kept
+            );
+        }
+        duplicate_pairs
+    }
 
-        // We parse the json manually because the astroport team does not ensure that their json is incorrect 🙃
-        for line in lines {
-            if line.trim().is_empty()
-                || line.trim().starts_with('{')
-                || line.trim().starts_with('}')
-            {
-                continue;
+    /// Reserves of the pairs that are below the minimum liquidity, by pair address. Empty without
+    /// a liquidity filter.
+    ///
+    /// A pair whose reserves can't be queried is kept, with a warning.
+    async fn query_illiquid_pairs(&self, pairs: &[PairInfo]) -> BTreeMap<Addr, Vec<PoolReserve>> {
+        let mut illiquid_pairs = BTreeMap::new();
+        let Some(liquidity_filter) = &self.liquidity_filter else {
+            return illiquid_pairs;
+        };
+        let Some(clients) = self.online_clients() else {
+            log::warn!(
+                "{}: pool reserves are not queried offline, no pairs are dropped for low liquidity",
+                self.chain_id
+            );
+            return illiquid_pairs;
+        };
+
+        let responses = WasmQueryBatcher::new(&clients.wasm, self.query_concurrency)
+            .query_each(
+                pairs.iter().map(|pair| pair.contract_addr.clone()),
+                &PairQueryMsg::Pool {},
+            )
+            .await;
+        for (pair, response) in responses {
+            let response = response.and_then(|response| {
+                serde_json::from_slice::<PoolResponse>(&response).map_err(anyhow::Error::from)
+            });
+            match response {
+                Ok(PoolResponse { assets, .. }) => {
+                    let reserves = assets
+                        .iter()
+                        .map(|asset| PoolReserve {
+                            info: from_astroport_info(&asset.info),
+                            amount: asset.amount,
+                        })
+                        .collect::<Vec<_>>();
+                    if !liquidity_filter.is_liquid(&reserves) {
+                        illiquid_pairs.insert(pair, reserves);
+                    }
+                }
+                Err(err) => log::warn!(
+                    "{}: keeping pair {}, its reserves could not be queried: {:#}",
+                    self.chain_id,
+                    pair,
+                    err
+                ),
             }
+        }
+        if !illiquid_pairs.is_empty() {
+            log::info!(
+                "{}: dropped {} of {} pairs below the minimum liquidity",
+                self.chain_id,
+                illiquid_pairs.len(),
+                pairs.len()
+            );
+        }
+        illiquid_pairs
+    }
+
+    /// The pairs of the last run, for offline runs.
+    fn cached_pairs(&self) -> anyhow::Result<Vec<PairInfo>> {
+        let cache_path = self.pairs_cache_path();
+        let cached: Option<PairsCache> = read_json_or_invalidate(&cache_path)?;
+        let cached = cached
+            .filter(|cached| cached.factory == self.factory.as_str())
+            .ok_or_else(|| MissingCacheEntries {
+                chain_id: self.chain_id.clone(),
+                missing: vec![format!(
+                    "pairs of astroport factory {} ({})",
+                    self.factory,
+                    cache_path.display()
+                )],
+            })?;
+        log::info!(
+            "{}: {} pairs cached at height {}, offline",
+            self.chain_id,
+            cached.pairs.len(),
+            cached.block_height
+        );
+        Ok(cached.pairs)
+    }
+
+    /// The generator with its registered LP tokens, queried once and kept for the lifetime of the
+    /// scraper. `None` if the deployment has no generator, or offline.
+    async fn load_generator(&mut self) -> anyhow::Result<Option<Generator>> {
+        let Some(address) = self.generator_address.clone() else {
+            return Ok(None);
+        };
+        let Some(clients) = self.online_clients().cloned() else {
+            log::warn!(
+                "{}: generator {} is not queried offline, its staking contracts are left out",
+                self.chain_id,
+                address
+            );
+            return Ok(None);
+        };
+        if self.loaded_generator.is_none() {
+            let batcher = WasmQueryBatcher::new(&clients.wasm, 1);
+            let response = batcher
+                .query(&address, &GeneratorQueryMsg::ActivePools {})
+                .await
+                .with_context(|| format!("querying active pools of generator {}", address))?;
+            // (lp token, alloc points), only the lp token is needed
+            let active_pools: Vec<(Addr, serde_json::Value)> = serde_json::from_slice(&response)
+                .with_context(|| format!("parsing active pools of generator {}", address))?;
+
+            let response = batcher
+                .query(&address, &GeneratorQueryMsg::Config {})
+                .await
+                .with_context(|| format!("querying config of generator {}", address))?;
+            let config: serde_json::Value = serde_json::from_slice(&response)
+                .with_context(|| format!("parsing config of generator {}", address))?;
+            // A plain address on older generators, an asset info on newer ones
+            let reward_token = match config.get("astro_token") {
+                Some(serde_json::Value::String(contract_addr)) => Some(AstroportAssetInfo::Token {
+                    contract_addr: Addr::unchecked(contract_addr),
+                }),
+                Some(info) => serde_json::from_value(info.clone()).ok(),
+                None => None,
+            };
+
+            self.loaded_generator = Some(Generator {
+                address,
+                lp_tokens: active_pools
+                    .into_iter()
+                    .map(|(lp_token, _)| lp_token)
+                    .collect(),
+                reward_token,
+            });
+        }
+
+        Ok(self.loaded_generator.clone())
+    }
 
-            let parts = line.split(':').collect::<Vec<_>>();
-            if parts.len() == 2 {
-                let key = parts[0].trim().trim_matches('"').to_string();
-                let value = parts[1]
-                    .trim()
-                    .trim_matches(',')
-                    .trim_matches('"')
-                    .to_string();
-                json_map.insert(key, value);
-            }
-        }
-
-        let key_address = json_map
-            .get(key)
-            .unwrap_or_else(|| panic!("{} not found in JSON", key));
-
-        Ok(key_address.to_string())
-    }
-}
-
-impl AssetSource for AstroportScraper<Daemon> {
-    // fn fetch_asset_infos(&mut self) -> anyhow::Result<Vec<(String, AssetInfo)>> {
-    //     let mut not_found_assets = vec![];
-    //
-    //     let mut ans_assets_to_add = Vec::<(String, AssetInfo)>::new();
-    //
-    //     for asset_info in self.all_asset_infos()? {
-    //         let (name, unchecked_info) = match &asset_info {
-    //             AssetInfo::Token { contract_addr } => {
-    //                 if let Ok(entry) = cw20_asset_entry(
-    //                     self.chain.clone(),
-    //                     self.chain_ans_prefix.as_str(),
-    //                     contract_addr,
-    //                 ) {
-    //                     (entry, AssetInfo::cw20(contract_addr.clone()))
-    //                 } else {
-    //                     not_found_assets.push(asset_info.clone());
-    //                     continue;
-    //                 }
-    //             }
-    //             AssetInfo::NativeToken { denom } => {
-    //                 if let Some(entry) = self.chain.rt_handle.block_on(
-    //                     self.chain_registry
-    //                         .resolve_native_asset(self.chain.clone(), denom.clone()),
-    //                 ) {
-    //                     (entry, AssetInfo::native(denom.clone()))
-    //                 } else {
-    //                     not_found_assets.push(asset_info.clone());
-    //                     continue;
-    //                 }
-    //             }
-    //         };
-    //
-    //         self.asset_info_to_name
-    //             .insert(asset_info.clone(), name.clone());
-    //         ans_assets_to_add.push((name, unchecked_info));
-    //     }
-    //
-    //     Ok(ans_assets_to_add)
-    // }
-
-    fn fetch_asset_infos(&mut self) -> anyhow::Result<Vec<AssetInfo>> {
-        return self
-            .load_pairs()?
+    /// The xASTRO cw20, the share token of the staking contract. `None` without staking contract,
+    /// offline or if its config can't be queried.
+    async fn xastro_token(&self) -> Option<Addr> {
+        let (_, staking) = self
+            .protocol_contracts
             .iter()
-            .flat_map(|p| p.asset_infos.to_vec())
-            .map(|asset_info| {
-                // we don't use unchecked because these are coming from on-chain data
-                Ok(match &asset_info {
-                    AstroportAssetInfo::Token { contract_addr } => {
-                        AssetInfo::cw20(contract_addr.clone())
-                    }
-                    AstroportAssetInfo::NativeToken { denom } => AssetInfo::native(denom.clone()),
-                })
-            })
-            .collect::<anyhow::Result<Vec<_>>>();
+            .find(|(entry, _)| entry.contract == "staking")?;
+        let Some(clients) = self.online_clients() else {
+            log::info!(
+                "{}: staking contract {} is not queried offline, xASTRO is left out",
+                self.chain_id,
+                staking
+            );
+            return None;
+        };
+
+        let config = WasmQueryBatcher::new(&clients.wasm, 1)
+            .query(staking, &StakingQueryMsg::Config {})
+            .await
+            .and_then(|response| {
+                serde_json::from_slice::<serde_json::Value>(&response).map_err(Into::into)
+            });
+        match config.map(|config| config.get("share_token_addr").cloned()) {
+            Ok(Some(serde_json::Value::String(share_token))) => Some(Addr::unchecked(share_token)),
+            Ok(_) => {
+                log::warn!("Config of staking contract {} has no share token", staking);
+                None
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to query the config of staking contract {}: {:#}",
+                    staking,
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    /// Whether the last cached pair is still registered, so it can be used as pagination cursor.
+    async fn cursor_exists(&self, pairs: &[PairInfo]) -> bool {
+        let Some(last_pair) = pairs.last() else {
+            return false;
+        };
+        let pair = async {
+            self.factory_pairs()?
+                .pair(last_pair.asset_infos.to_vec())
+                .await
+        };
+        match pair.await {
+            Ok(pair) => pair.contract_addr == last_pair.contract_addr,
+            Err(err) => {
+                log::debug!(
+                    "Cached pair cursor {} is gone: {:#}",
+                    last_pair.contract_addr,
+                    err
+                );
+                false
+            }
+        }
+    }
+
+    /// The pagination of the factory, whatever the pair source. Fails without clients.
+    fn factory_pairs(&self) -> anyhow::Result<FactoryPairs> {
+        let clients = self.clients.clone().with_context(|| {
+            format!(
+                "{}: the astroport factory can't be queried without clients",
+                self.chain_id
+            )
+        })?;
+        Ok(FactoryPairs::new(clients, self.factory.clone()).with_max_pages(self.max_pair_pages))
+    }
+
+    /// The pairs API of the chain in `config`, cross-checked with the factory.
+    pub fn api_pairs(&self, config: &ScraperConfig) -> anyhow::Result<ApiPairs> {
+        let chain_id = &self.chain_id;
+        let url = config.astroport_api_urls.get(chain_id).with_context(|| {
+            format!(
+                "no astroport pairs API for {}, set astroport_api_urls.\"{}\" in the config",
+                chain_id, chain_id
+            )
+        })?;
+        let cache_path = self.cache_dir.join(chain_id).join(API_PAIRS_CACHE_FILE);
+        Ok(ApiPairs::new(self.factory_pairs()?, url, cache_path)
+            .with_sample_size(config.astroport_api_sample_size))
+    }
+
+    fn pairs_cache_path(&self) -> PathBuf {
+        self.cache_dir.join(&self.chain_id).join(PAIRS_CACHE_FILE)
+    }
+
+    /// The pairs in the pairs cache of `chain_id`, `None` if there is none. Nothing is queried and
+    /// the cache is left as is, even when it can't be parsed.
+    pub fn pairs_in_cache(
+        cache_dir: &Path,
+        chain_id: &str,
+    ) -> anyhow::Result<Option<Vec<PairInfo>>> {
+        let path = cache_dir.join(chain_id).join(PAIRS_CACHE_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        let cache: PairsCache = serde_json::from_slice(&json)
+            .with_context(|| format!("parsing pairs cache {}", path.display()))?;
+        Ok(Some(cache.pairs))
+    }
+
+    /// Address of the generator (or incentives contract) of the deployment, `None` if the
+    /// deployment has neither.
+    pub async fn fetch_generator_address(
+        config: &ScraperConfig,
+        chain_id: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let deployment = AstroportChangelog::new(config.deployment_registry());
+        generator_address(&deployment, chain_id).await
+    }
+
+    /// Get a deployment address for astroport given its chainId and key
+    pub async fn fetch_deployment_address(
+        config: &ScraperConfig,
+        chain_id: &str,
+        key: &str,
+    ) -> anyhow::Result<String> {
+        let deployment = config
+            .deployment_registry()
+            .deployment(ASTROPORT_DEX, chain_id)
+            .await?;
+        Ok(deployment.get_address(key)?.to_string())
     }
 }
 
-impl DexScraper for AstroportScraper<Daemon> {
-    fn fetch_staking_contracts(&mut self) -> anyhow::Result<Vec<(String, Addr)>> {
-        Ok(vec![])
+impl AstroportScraper {
+    /// List the pairs with `pair_source` instead of paginating the factory. Paged scrapes always
+    /// paginate the factory.
+    pub fn with_pair_source(mut self, pair_source: impl PairSource + 'static) -> Self {
+        self.pair_source = Box::new(pair_source);
+        self
     }
 
-    fn fetch_dex_pools(&mut self) -> anyhow::Result<Vec<(UncheckedPoolAddress, PoolMetadata)>> {
-        let mut ans_pools_to_add = Vec::<(UncheckedPoolAddress, PoolMetadata)>::new();
-        let mut skipped_ans_pools = vec![];
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.resolution_cache = resolution_cache;
+        self
+    }
 
-        for pair in self.load_pairs()? {
-            let pool_id = UncheckedPoolAddress::contract(pair.contract_addr);
+    /// Name the assets with `denom_resolver` instead of querying the chain and the chain registry.
+    pub fn with_denom_resolver(
+        mut self,
+        denom_resolver: impl DenomResolver + Send + Sync + 'static,
+    ) -> Self {
+        self.denom_resolver = Some(Box::new(denom_resolver));
+        self
+    }
 
-            let pool_type = match pair.pair_type {
-                PairType::Stable {} => PoolType::Stable,
-                PairType::Xyk {} => PoolType::ConstantProduct,
-                PairType::Concentrated {} => PoolType::Weighted,
-                PairType::Custom(_) => panic!("Custom pair type not supported"),
-            };
+    /// Set the number of cw20 `TokenInfo` and denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.query_concurrency = concurrency;
+        self
+    }
 
-            let mut assets = vec![];
-            let mut missing_asset = false;
+    /// Set the age after which the cached pairs are all fetched again, instead of the one of the
+    /// config.
+    pub fn with_pairs_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.pairs_cache_ttl = ttl;
+        self
+    }
+
+    /// Fetch all the pairs instead of starting from the cached ones.
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    /// Keep the pairs cache in `cache_dir` instead of the one of the config.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// Drop the pairs (with their LP tokens and staking contracts) whose reserves are below the
+    /// minimums of `liquidity_filter`. Their reserves are queried with the query concurrency.
+    pub fn with_liquidity_filter(mut self, liquidity_filter: LiquidityFilter) -> Self {
+        self.liquidity_filter = Some(liquidity_filter);
+        self
+    }
 
-            for asset_info in &pair.asset_infos {
-                if let Some(name) = self.asset_info_to_name.get(asset_info) {
-                    assets.push(AssetEntry::from(name.clone()));
-                } else {
-                    missing_asset = true;
-                    break;
+    /// Register the ANS pool type of a custom astroport pair type, e.g. `concentrated_duality`.
+    ///
+    /// Pairs with a custom type that isn't registered are skipped.
+    pub fn with_custom_pair_type(
+        mut self,
+        pair_type: impl Into<String>,
+        pool_type: PoolType,
+    ) -> Self {
+        self.custom_pair_types.insert(pair_type.into(), pool_type);
+        self
+    }
+
+    /// Custom pair types the last [`DexScraper::fetch_dex_pools`] or [`Self::scrape_paged`] call
+    /// found no pool type for, with the skipped pools.
+    pub fn unknown_pair_types(&self) -> &BTreeMap<String, Vec<UncheckedPoolAddress>> {
+        &self.unknown_pair_types
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] or [`Self::scrape_paged`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        &self.not_found_assets
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] or [`Self::scrape_paged`] call because an
+    /// asset wasn't resolved, the pair type is not supported by ANS or the reserves are too low.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        &self.skipped_pools
+    }
+
+    /// Add pools that were dropped after they were scraped, e.g. by the conflict checks of
+    /// [`crate::scrape::write_paged_scrape`], to the skipped pools of the report.
+    pub fn skip_pools(&mut self, pools: impl IntoIterator<Item = SkippedPool>) {
+        self.skipped_pools.extend(pools);
+    }
+
+    /// How many of the loaded pairs came from the pairs cache.
+    pub fn pairs_cache_stats(&self) -> PairsCacheStats {
+        self.pairs_cache_stats
+    }
+
+    /// Map pairs to ANS pools using the resolved asset names.
+    ///
+    /// Pairs with an unresolved asset or unknown custom pair type are added to
+    /// [`Self::skipped_pools`].
+    fn pairs_to_pools(
+        &mut self,
+        pairs: Vec<PairInfo>,
+    ) -> Vec<(UncheckedPoolAddress, PoolMetadata)> {
+        let mut assembled =
+            pairs_to_pools(pairs, &self.asset_info_to_name, &self.custom_pair_types);
+        self.skipped_pools.append(&mut assembled.skipped_pools);
+        for (pair_type, mut pools) in assembled.unknown_pair_types {
+            self.unknown_pair_types
+                .entry(pair_type)
+                .or_default()
+                .append(&mut pools);
+        }
+        assembled.pools
+    }
+
+    /// Log every custom pair type that had no pool type registered.
+    fn log_unknown_pair_types(&self) {
+        for (pair_type, pools) in &self.unknown_pair_types {
+            log::warn!(
+                "{}: skipped {} pools with unknown custom pair type {}",
+                self.chain_id,
+                pools.len(),
+                pair_type
+            );
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for AstroportScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(self
+            .resolve_assets()
+            .await?
+            .into_iter()
+            .map(|(entry, info)| AnsAsset::new(entry, info))
+            .collect())
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for AstroportScraper {
+    /// The generator for every pair whose LP token is registered with it, named after the LP
+    /// token.
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        let Some(generator) = self.load_generator().await? else {
+            return Ok(vec![]);
+        };
+        let pairs = self
+            .kept_pairs()
+            .await?
+            .into_iter()
+            .filter(|pair| generator.lp_tokens.contains(&pair.liquidity_token))
+            .collect::<Vec<_>>();
+
+        Ok(pair_lp_tokens(&pairs, &self.asset_info_to_name)
+            .into_iter()
+            .map(|(name, _)| (name, generator.address.clone()))
+            .collect())
+    }
+
+    async fn fetch_contract_entries(&mut self) -> Result<Vec<(ContractEntry, Addr)>, ScraperError> {
+        Ok(self.protocol_contracts.clone())
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.skipped_pools.clear();
+        self.unknown_pair_types.clear();
+        let pairs = self.kept_pairs().await?;
+        let pools = self.pairs_to_pools(pairs);
+        if let Some(duplicate_pairs) = &self.duplicate_pairs {
+            self.skipped_pools
+                .extend(duplicate_pairs.iter().map(|(pair, kept)| {
+                    SkippedPool::duplicate_of(
+                        UncheckedPoolAddress::contract(pair),
+                        UncheckedPoolAddress::contract(kept),
+                    )
+                }));
+        }
+        if let Some(illiquid_pairs) = &self.illiquid_pairs {
+            self.skipped_pools
+                .extend(low_liquidity_pools(illiquid_pairs));
+        }
+        self.log_unknown_pair_types();
+        Ok(pools)
+    }
+
+    fn report(&self) -> DexReport {
+        DexReport::new(
+            ASTROPORT_DEX,
+            self.not_found_assets.clone(),
+            self.skipped_pools.clone(),
+        )
+    }
+}
+
+/// Address of the generator (or incentives contract) of the deployment, `None` if it has neither.
+async fn generator_address(
+    deployment: &dyn DeploymentSource,
+    chain_id: &str,
+) -> anyhow::Result<Option<String>> {
+    for key in GENERATOR_KEYS {
+        if let Some(address) = deployment.address(chain_id, key).await? {
+            return Ok(Some(address));
+        }
+    }
+    log::info!("{}: no astroport generator in the deployment", chain_id);
+    Ok(None)
+}
+
+/// The [`PROTOCOL_CONTRACTS`] and the generator the deployment has, as `astroport:<contract>`
+/// entries.
+async fn protocol_contracts(
+    deployment: &dyn DeploymentSource,
+    chain_id: &str,
+    generator_address: Option<&str>,
+) -> anyhow::Result<Vec<(ContractEntry, Addr)>> {
+    let mut contracts = vec![];
+    for (contract, key) in PROTOCOL_CONTRACTS {
+        match deployment.address(chain_id, key).await? {
+            Some(address) => {
+                contracts.push((astroport_contract(contract), Addr::unchecked(address)))
+            }
+            None => log::info!("{}: no astroport {} in the deployment", chain_id, contract),
+        }
+    }
+    if let Some(address) = generator_address {
+        contracts.push((astroport_contract("generator"), Addr::unchecked(address)));
+    }
+    contracts.sort_by(|(a, _), (b, _)| a.contract.cmp(&b.contract));
+    Ok(contracts)
+}
+
+fn astroport_contract(contract: &str) -> ContractEntry {
+    ContractEntry {
+        protocol: ASTROPORT_DEX.to_string(),
+        contract: contract.to_string(),
+    }
+}
+
+/// The pairs dropped for low liquidity as skipped pools, with their reserves.
+fn low_liquidity_pools(
+    illiquid_pairs: &BTreeMap<Addr, Vec<PoolReserve>>,
+) -> impl Iterator<Item = SkippedPool> + '_ {
+    illiquid_pairs.iter().map(|(pair, reserves)| {
+        SkippedPool::low_liquidity(UncheckedPoolAddress::contract(pair), reserves.clone())
+    })
+}
+
+/// Groups of more than one pair with the same pair type and assets, whatever their order.
+fn pair_groups(pairs: &[PairInfo]) -> Vec<Vec<&PairInfo>> {
+    // pair type and sorted assets -> pairs
+    let mut groups = BTreeMap::<(String, Vec<String>), Vec<&PairInfo>>::new();
+    for pair in pairs {
+        let mut assets = pair
+            .asset_infos
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assets.sort();
+        groups
+            .entry((pair.pair_type.to_string(), assets))
+            .or_default()
+            .push(pair);
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Pairs with the same assets and pair type as another pair, mapped to the pair that is kept.
+///
+/// The kept pair is the most recently instantiated one by `created`, the `(block height, tx
+/// index)` of the instantiation of the pairs. Without creation heights the factory's last pair
+/// is kept.
+pub fn duplicate_pairs(
+    pairs: &[PairInfo],
+    created: &HashMap<Addr, (u64, u64)>,
+) -> BTreeMap<Addr, Addr> {
+    let mut duplicate_pairs = BTreeMap::new();
+    for group in pair_groups(pairs) {
+        let mut newest = *group.last().expect("groups are not empty");
+        let mut newest_created = None;
+        for pair in &group {
+            let created = created.get(&pair.contract_addr);
+            if created.is_some() && created >= newest_created {
+                newest = pair;
+                newest_created = created;
+            }
+        }
+        for pair in group {
+            if pair.contract_addr != newest.contract_addr {
+                duplicate_pairs.insert(pair.contract_addr.clone(), newest.contract_addr.clone());
+            }
+        }
+    }
+    duplicate_pairs
+}
+
+/// Pools assembled from factory pairs.
+#[derive(Debug, Default)]
+pub struct AssembledPools {
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    /// Pairs with an asset without a name or an unknown custom pair type
+    pub skipped_pools: Vec<SkippedPool>,
+    /// Custom pair types without a registered pool type, with the pairs that have them
+    pub unknown_pair_types: BTreeMap<String, Vec<UncheckedPoolAddress>>,
+}
+
+/// Map pairs to ANS pools using the resolved asset names.
+///
+/// Custom pair types are looked up in `custom_pair_types`, pairs with a custom type that isn't
+/// in there are skipped.
+pub fn pairs_to_pools(
+    pairs: Vec<PairInfo>,
+    asset_info_to_name: &HashMap<AstroportAssetInfo, String>,
+    custom_pair_types: &HashMap<String, PoolType>,
+) -> AssembledPools {
+    let mut assembled = AssembledPools::default();
+
+    for pair in pairs {
+        let pool_id = UncheckedPoolAddress::contract(pair.contract_addr);
+
+        let pool_type = match pair.pair_type {
+            PairType::Stable {} => PoolType::Stable,
+            PairType::Xyk {} => PoolType::ConstantProduct,
+            PairType::Concentrated {} => PoolType::ConcentratedLiquidity,
+            PairType::Custom(pair_type) => match custom_pair_types.get(&pair_type) {
+                Some(pool_type) => pool_type.clone(),
+                None => {
+                    log::debug!(
+                        "Skipping pool {:?} with custom pair type {}",
+                        pool_id,
+                        pair_type
+                    );
+                    assembled
+                        .skipped_pools
+                        .push(SkippedPool::unsupported_pair_type(
+                            pool_id.clone(),
+                            &pair_type,
+                        ));
+                    assembled
+                        .unknown_pair_types
+                        .entry(pair_type)
+                        .or_default()
+                        .push(pool_id);
+                    continue;
                 }
+            },
+        };
+
+        // Forked factories may answer with more assets than the pair type can have, which would
+        // make invalid pairings
+        if let Err(details) = check_asset_count(&pool_type, pair.asset_infos.len()) {
+            log::warn!("Skipping malformed pair {:?}: {}", pool_id, details);
+            assembled
+                .skipped_pools
+                .push(SkippedPool::malformed_pair(pool_id, details));
+            continue;
+        }
+
+        let mut assets = vec![];
+        let mut missing_assets = vec![];
+
+        for asset_info in &pair.asset_infos {
+            match asset_info_to_name.get(asset_info) {
+                Some(name) => assets.push(AssetEntry::from(name.clone())),
+                None => missing_assets.push(from_astroport_info(asset_info)),
             }
+        }
 
-            if missing_asset {
-                skipped_ans_pools.push(pool_id.clone());
-                continue;
+        if !missing_assets.is_empty() {
+            assembled
+                .skipped_pools
+                .push(SkippedPool::missing_assets(pool_id, missing_assets));
+            continue;
+        }
+
+        // ANS pairings don't depend on the order, only sorted assets make runs comparable
+        assets.sort_by_key(|asset| asset.to_string());
+        let pool_metadata = PoolMetadata {
+            dex: ASTROPORT_DEX.to_string(),
+            pool_type,
+            assets,
+        };
+        assembled.pools.push((pool_id, pool_metadata));
+    }
+
+    assembled
+}
+
+/// Check that a pool of `pool_type` can have `count` assets: exactly two for a constant product
+/// pool, at least two for the others.
+fn check_asset_count(pool_type: &PoolType, count: usize) -> Result<(), String> {
+    match pool_type {
+        PoolType::ConstantProduct if count != 2 => Err(format!(
+            "{} assets for a constant product pair, it has 2",
+            count
+        )),
+        _ if count < 2 => Err(format!("{} assets for a {:?} pair", count, pool_type)),
+        _ => Ok(()),
+    }
+}
+
+/// The LP token entries of the pairs whose assets all have a name, e.g.
+/// `astroport/terra2>astro,terra2>luna`.
+pub fn pair_lp_tokens(
+    pairs: &[PairInfo],
+    asset_info_to_name: &HashMap<AstroportAssetInfo, String>,
+) -> Vec<(String, AssetInfo)> {
+    pairs
+        .iter()
+        .filter_map(|pair| {
+            let names = pair
+                .asset_infos
+                .iter()
+                .map(|info| asset_info_to_name.get(info).cloned())
+                .collect::<Option<Vec<_>>>()?;
+            Some((
+                lp_token_name(ASTROPORT_DEX, &names),
+                AssetInfo::cw20(pair.liquidity_token.clone()),
+            ))
+        })
+        .collect()
+}
+
+/// The unique assets of the pairs, in order of appearance.
+pub fn pair_asset_infos(pairs: &[PairInfo]) -> Vec<AssetInfo> {
+    // the same asset shows up in many pairs
+    let mut seen = HashSet::new();
+    pairs
+        .iter()
+        .flat_map(|p| p.asset_infos.iter())
+        .filter(|asset_info| seen.insert(*asset_info))
+        // we don't use unchecked because these are coming from on-chain data
+        .map(from_astroport_info)
+        .collect()
+}
+
+pub fn from_astroport_info(asset_info: &AstroportAssetInfo) -> AssetInfo {
+    match asset_info {
+        AstroportAssetInfo::Token { contract_addr } => AssetInfo::cw20(contract_addr.clone()),
+        AstroportAssetInfo::NativeToken { denom } => AssetInfo::native(denom.clone()),
+    }
+}
+
+pub fn to_astroport_info(asset_info: &AssetInfo) -> Option<AstroportAssetInfo> {
+    match asset_info {
+        AssetInfo::Cw20(contract_addr) => Some(AstroportAssetInfo::Token {
+            contract_addr: contract_addr.clone(),
+        }),
+        AssetInfo::Native(denom) => Some(AstroportAssetInfo::NativeToken {
+            denom: denom.clone(),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::{DexAssetPairing, UncheckedContractEntry, UniquePoolId};
+
+    use abstract_core::ans_host::ExecuteMsg;
+    use cw_asset::AssetInfoUnchecked;
+
+    use super::*;
+    use crate::ans_version::TargetVersion;
+    use crate::diff::{AnsDiff, AnsHostEntries, DEFAULT_MAX_HEIGHT_GAP};
+    use crate::export::ScrapeExport;
+    use crate::helpers::names::LP_TOKEN_SEPARATOR;
+    use crate::helpers::resolve::ResolvedAssets;
+    use crate::output::{write_contracts, write_output, ScrapeSummary};
+    use crate::pipeline::{
+        staking_contract_entry, ConflictPolicy, ScrapeMeta, ScrapeOutput, ScrapePipeline,
+    };
+    use crate::registrar::{plan_batches, DEFAULT_BATCH_SIZE};
+    use crate::report::SkipReason;
+    use crate::test_utils::astroport::{
+        assemble, names, native, pair, scraper_naming, scraper_of, static_scraper, token,
+    };
+    use crate::test_utils::{registry_from_lists, AssetListBuilder, DenomTraceFixture};
+    use crate::traits::deployment::StaticDeployment;
+    use crate::traits::resolve::KnownNames;
+
+    #[test]
+    fn astroport_networks_have_a_prefix_and_a_deployment() {
+        let registry = DeploymentRegistry::new();
+        for (chain_id, prefix, changelog_dir) in [
+            ("phoenix-1", "terra2", "terra-2/phoenix-1"),
+            ("pisco-1", "terra2", "terra-2/pisco-1"),
+            ("neutron-1", "neutron", "neutron/neutron-1"),
+            ("pion-1", "neutron", "neutron/pion-1"),
+            ("injective-1", "injective", "injective/injective-1"),
+        ] {
+            assert_eq!(AstroportScraper::ans_prefix(chain_id).unwrap(), prefix);
+            let url = registry.url(ASTROPORT_DEX, chain_id).unwrap();
+            assert!(url.contains(changelog_dir), "{}: {}", chain_id, url);
+        }
+    }
+
+    #[test]
+    fn other_networks_are_errors() {
+        assert!(matches!(
+            AstroportScraper::ans_prefix("osmosis-1"),
+            Err(NetworkError::UnsupportedDex { .. })
+        ));
+        assert!(matches!(
+            AstroportScraper::ans_prefix("made-up-1"),
+            Err(NetworkError::Unknown { .. })
+        ));
+        assert!(DeploymentRegistry::new()
+            .url(ASTROPORT_DEX, "osmosis-1")
+            .is_err());
+    }
+
+    #[test]
+    fn pagination_stops_when_the_factory_repeats_a_page() {
+        let page = vec![
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![native("uluna"), token("other_token")]),
+        ];
+        let mut pages = PairPages::new(ASTROPORT_DEX, "pisco-1", None, DEFAULT_MAX_PAIR_PAGES);
+
+        assert_eq!(pages.cursor(), Some(None));
+        assert_eq!(pages.advance(page.clone()).len(), 2);
+        assert_eq!(
+            pages.cursor(),
+            Some(Some(vec![native("uluna"), token("other_token")]))
+        );
+
+        // the node ignores the cursor and answers the first page again
+        assert!(pages.advance(page).is_empty());
+        assert_eq!(pages.cursor(), None);
+    }
+
+    #[test]
+    fn pagination_drops_the_pairs_of_previous_pages() {
+        let mut pages = PairPages::new(ASTROPORT_DEX, "pisco-1", None, DEFAULT_MAX_PAIR_PAGES);
+        pages.advance(vec![pair(
+            "first",
+            vec![native("uluna"), token("astro_token")],
+        )]);
+
+        let new_pairs = pages.advance(vec![
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![native("uluna"), token("other_token")]),
+        ]);
+        assert_eq!(new_pairs.len(), 1);
+        assert_eq!(new_pairs[0].contract_addr, Addr::unchecked("second"));
+        assert!(pages.cursor().is_some());
+    }
+
+    #[test]
+    fn pagination_stops_after_the_max_pages() {
+        let mut pages = PairPages::new(ASTROPORT_DEX, "pisco-1", None, 2);
+        pages.advance(vec![pair(
+            "first",
+            vec![native("uluna"), token("astro_token")],
+        )]);
+        assert!(pages.cursor().is_some());
+
+        pages.advance(vec![pair(
+            "second",
+            vec![native("uluna"), token("other_token")],
+        )]);
+        assert_eq!(pages.cursor(), None);
+    }
+
+    #[test]
+    fn pagination_stops_on_an_empty_page() {
+        let mut pages = PairPages::new(ASTROPORT_DEX, "pisco-1", None, DEFAULT_MAX_PAIR_PAGES);
+        assert!(pages.advance(vec![]).is_empty());
+        assert_eq!(pages.cursor(), None);
+    }
+
+    #[test]
+    fn pair_asset_infos_are_unique_in_order_of_appearance() {
+        let pairs = vec![
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![token("astro_token"), native("uusdc")]),
+        ];
+
+        assert_eq!(
+            pair_asset_infos(&pairs),
+            vec![
+                AssetInfo::native("uluna"),
+                AssetInfo::cw20(Addr::unchecked("astro_token")),
+                AssetInfo::native("uusdc"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pairs_cache_round_trips_the_pairs() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("ans-scraper-pairs-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        assert!(AstroportScraper::pairs_in_cache(&cache_dir, "pisco-1")
+            .unwrap()
+            .is_none());
+
+        let mut pairs = vec![
+            pair("xyk", vec![native("uluna"), token("astro_token")]),
+            pair("stable", vec![native("uluna"), native("ibc/usdc")]),
+            pair(
+                "pcl",
+                vec![native("uluna"), token("astro_token"), native("ibc/usdc")],
+            ),
+            pair("custom", vec![native("uluna"), token("astro_token")]),
+        ];
+        pairs[1].pair_type = PairType::Stable {};
+        pairs[2].pair_type = PairType::Concentrated {};
+        pairs[3].pair_type = PairType::Custom("custom-concentrated".to_string());
+        write_json_atomic(
+            cache_dir.join("pisco-1").join(PAIRS_CACHE_FILE),
+            &PairsCache {
+                factory: "terra1factory".to_string(),
+                block_height: 4242,
+                fetched_at: unix_now(),
+                pairs: pairs.clone(),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            AstroportScraper::pairs_in_cache(&cache_dir, "pisco-1").unwrap(),
+            Some(pairs)
+        );
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+
+    #[test]
+    fn pairs_caches_from_before_the_fetch_time_are_stale() {
+        let cache: PairsCache = serde_json::from_value(serde_json::json!({
+            "factory": "terra1factory",
+            "block_height": 4242,
+            "pairs": [],
+        }))
+        .unwrap();
+        assert_eq!(cache.fetched_at, 0);
+    }
+
+    #[test]
+    fn asset_counts_are_checked_per_pool_type() {
+        assert!(check_asset_count(&PoolType::ConstantProduct, 2).is_ok());
+        assert!(check_asset_count(&PoolType::ConstantProduct, 3).is_err());
+        assert!(check_asset_count(&PoolType::ConstantProduct, 1).is_err());
+        assert!(check_asset_count(&PoolType::Stable, 3).is_ok());
+        assert!(check_asset_count(&PoolType::ConcentratedLiquidity, 3).is_ok());
+        assert!(check_asset_count(&PoolType::Stable, 1).is_err());
+        assert!(check_asset_count(&PoolType::Stable, 0).is_err());
+    }
+
+    #[test]
+    fn malformed_pairs_are_skipped() {
+        let xyk = pair(
+            "xyk3",
+            vec![native("uluna"), token("astro_token"), native("uluna")],
+        );
+        let mut stable = pair("stable1", vec![native("uluna")]);
+        stable.pair_type = PairType::Stable {};
+
+        let assembled = assemble(vec![xyk, stable]);
+
+        assert!(assembled.pools.is_empty());
+        let skipped: Vec<_> = assembled
+            .skipped_pools
+            .iter()
+            .map(|skipped| (skipped.pool.clone(), skipped.reason.clone()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                (
+                    UncheckedPoolAddress::contract("xyk3"),
+                    SkipReason::MalformedPair(
+                        "3 assets for a constant product pair, it has 2".to_string()
+                    )
+                ),
+                (
+                    UncheckedPoolAddress::contract("stable1"),
+                    SkipReason::MalformedPair("1 assets for a Stable pair".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn lp_tokens_are_named_after_the_sorted_assets() {
+        let mut names = names();
+        names.insert(native("uusdc"), "terra2>usdc".to_string());
+        let pairs = vec![
+            pair("xyk", vec![token("astro_token"), native("uluna")]),
+            pair(
+                "pcl",
+                vec![native("uusdc"), native("uluna"), token("astro_token")],
+            ),
+            pair("unnamed", vec![native("uluna"), token("unknown_token")]),
+        ];
+
+        assert_eq!(
+            pair_lp_tokens(&pairs, &names),
+            [
+                (
+                    "astroport/terra2>astro,terra2>luna".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("xyk_lp"))
+                ),
+                (
+                    "astroport/terra2>astro,terra2>luna,terra2>usdc".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("pcl_lp"))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pair_types_are_mapped_to_pool_types() {
+        let assets = vec![native("uluna"), token("astro_token")];
+        let typed = |contract_addr: &str, pair_type: PairType| PairInfo {
+            pair_type,
+            ..pair(contract_addr, assets.clone())
+        };
+        let custom_pair_types = HashMap::from([("weighted".to_string(), PoolType::Weighted)]);
+
+        let assembled = pairs_to_pools(
+            vec![
+                typed("xyk", PairType::Xyk {}),
+                typed("stable", PairType::Stable {}),
+                typed("pcl", PairType::Concentrated {}),
+                typed("weighted", PairType::Custom("weighted".to_string())),
+                typed(
+                    "custom",
+                    PairType::Custom("custom-concentrated".to_string()),
+                ),
+            ],
+            &names(),
+            &custom_pair_types,
+        );
+
+        let pool_types: Vec<(UncheckedPoolAddress, PoolType)> = assembled
+            .pools
+            .iter()
+            .map(|(address, metadata)| (address.clone(), metadata.pool_type.clone()))
+            .collect();
+        assert_eq!(
+            pool_types,
+            [
+                (
+                    UncheckedPoolAddress::contract("xyk"),
+                    PoolType::ConstantProduct
+                ),
+                (UncheckedPoolAddress::contract("stable"), PoolType::Stable),
+                (
+                    UncheckedPoolAddress::contract("pcl"),
+                    PoolType::ConcentratedLiquidity
+                ),
+                (
+                    UncheckedPoolAddress::contract("weighted"),
+                    PoolType::Weighted
+                ),
+            ]
+        );
+        let [skipped] = &assembled.skipped_pools[..] else {
+            panic!("{:?}", assembled.skipped_pools);
+        };
+        assert_eq!(skipped.pool, UncheckedPoolAddress::contract("custom"));
+        assert!(matches!(
+            &skipped.reason,
+            SkipReason::UnsupportedPairType(pair_type) if pair_type == "custom-concentrated"
+        ));
+        assert_eq!(
+            assembled.unknown_pair_types["custom-concentrated"],
+            [UncheckedPoolAddress::contract("custom")]
+        );
+    }
+
+    #[test]
+    fn pool_assets_are_sorted() {
+        let assembled = assemble(vec![pair(
+            "pair",
+            vec![native("uluna"), token("astro_token")],
+        )]);
+
+        let (_, metadata) = &assembled.pools[0];
+        let assets: Vec<String> = metadata.assets.iter().map(ToString::to_string).collect();
+        assert_eq!(assets, ["terra2>astro", "terra2>luna"]);
+    }
+
+    #[test]
+    fn pools_are_identical_whatever_the_asset_order() {
+        let forward = assemble(vec![pair(
+            "pair",
+            vec![native("uluna"), token("astro_token")],
+        )]);
+        let reversed = assemble(vec![pair(
+            "pair",
+            vec![token("astro_token"), native("uluna")],
+        )]);
+
+        assert_eq!(
+            serde_json::to_vec(&forward.pools).unwrap(),
+            serde_json::to_vec(&reversed.pools).unwrap()
+        );
+    }
+
+    #[test]
+    fn assembled_pairs_are_pools_unless_an_asset_is_unnamed() {
+        let mut stable = pair("stable", vec![native("uluna"), token("astro_token")]);
+        stable.pair_type = PairType::Stable {};
+        let pairs = vec![
+            pair("xyk", vec![native("uluna"), token("astro_token")]),
+            stable,
+            pair("unnamed", vec![native("uluna"), token("unknown_token")]),
+        ];
+
+        let assembled = assemble(pairs);
+
+        // the assets are sorted by name
+        let assets = vec![
+            AssetEntry::new("terra2>astro"),
+            AssetEntry::new("terra2>luna"),
+        ];
+        assert_eq!(
+            assembled.pools,
+            [
+                (
+                    UncheckedPoolAddress::contract("xyk"),
+                    PoolMetadata::new(ASTROPORT_DEX, PoolType::ConstantProduct, assets.clone())
+                ),
+                (
+                    UncheckedPoolAddress::contract("stable"),
+                    PoolMetadata::new(ASTROPORT_DEX, PoolType::Stable, assets)
+                ),
+            ]
+        );
+        // the pair of the cw20 without a name is skipped, not registered
+        assert_eq!(
+            assembled.skipped_pools,
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("unnamed"),
+                vec![AssetInfo::cw20(Addr::unchecked("unknown_token"))]
+            )]
+        );
+    }
+
+    #[test]
+    fn recreated_pair_is_dropped_for_the_newest() {
+        let pairs = vec![
+            pair("old", vec![native("uluna"), token("astro_token")]),
+            pair("new", vec![token("astro_token"), native("uluna")]),
+            pair("other", vec![native("uluna"), token("other_token")]),
+        ];
+        let created = HashMap::from([
+            (Addr::unchecked("old"), (200, 0)),
+            (Addr::unchecked("new"), (300, 1)),
+        ]);
+
+        let duplicates = duplicate_pairs(&pairs, &created);
+        assert_eq!(
+            duplicates,
+            BTreeMap::from([(Addr::unchecked("old"), Addr::unchecked("new"))])
+        );
+
+        let kept = pairs
+            .into_iter()
+            .filter(|pair| !duplicates.contains_key(&pair.contract_addr))
+            .collect();
+        let assembled = assemble(kept);
+        assert_eq!(assembled.pools.len(), 1);
+        assert_eq!(assembled.pools[0].0, UncheckedPoolAddress::contract("new"));
+        // the pair with an unnamed asset is skipped, not dropped as a duplicate
+        assert_eq!(assembled.skipped_pools.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_without_creation_heights_keeps_the_last_pair() {
+        let pairs = vec![
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![native("uluna"), token("astro_token")]),
+        ];
+
+        assert_eq!(
+            duplicate_pairs(&pairs, &HashMap::new()),
+            BTreeMap::from([(Addr::unchecked("first"), Addr::unchecked("second"))])
+        );
+    }
+
+    #[test]
+    fn pairs_of_other_types_are_not_duplicates() {
+        let mut stable = pair("stable", vec![native("uluna"), token("astro_token")]);
+        stable.pair_type = PairType::Stable {};
+        let pairs = vec![
+            pair("xyk", vec![native("uluna"), token("astro_token")]),
+            stable,
+        ];
+
+        assert!(duplicate_pairs(&pairs, &HashMap::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn pairs_cache_ttl_comes_from_the_config() {
+        let deployment = StaticDeployment::new([(FACTORY_KEY, "factory")]);
+        let config = ScraperConfig {
+            pairs_cache_ttl_secs: 60,
+            ..ScraperConfig::default_for("phoenix-1")
+        };
+        let scraper = AstroportScraper::from_sources(
+            "phoenix-1",
+            &deployment,
+            StaticPairs::default(),
+            KnownNames::default(),
+            &config,
+        )
+        .await
+        .unwrap();
+        assert_eq!(scraper.pairs_cache_ttl, Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn static_scraper_names_the_assets_and_lp_tokens() {
+        let mut scraper = static_scraper().await;
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets,
+            [
+                AnsAsset::new(
+                    "terra2>astro",
+                    AssetInfo::cw20(Addr::unchecked("astro_token"))
+                ),
+                AnsAsset::new("terra2>luna", AssetInfo::native("uluna")),
+                AnsAsset::new("terra2>usdc", AssetInfo::native("ibc/usdc")),
+                AnsAsset::new(
+                    "astroport/terra2>astro,terra2>luna",
+                    AssetInfo::cw20(Addr::unchecked("xyk_lp"))
+                ),
+                AnsAsset::new(
+                    "astroport/terra2>luna,terra2>usdc",
+                    AssetInfo::cw20(Addr::unchecked("stable_lp"))
+                ),
+            ]
+        );
+        assert_eq!(
+            scraper.not_found_assets(),
+            [UnresolvedAsset::new(
+                AssetInfo::cw20(Addr::unchecked("unknown_token")),
+                UnresolvedReason::NoRegistryMatch("no known name".to_string())
+            )]
+        );
+        assert_eq!(
+            scraper.pairs_cache_stats().fetched,
+            3,
+            "the pairs come from the pair source"
+        );
+    }
+
+    #[tokio::test]
+    async fn static_scraper_maps_the_pairs_to_pools() {
+        let mut scraper = static_scraper().await;
+        scraper.fetch_asset_infos().await.unwrap();
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        let pools = pools
+            .into_iter()
+            .map(|(address, metadata)| {
+                let assets = metadata
+                    .assets
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                (address, metadata.dex, metadata.pool_type, assets)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pools,
+            [
+                (
+                    UncheckedPoolAddress::contract("xyk"),
+                    "astroport".to_string(),
+                    PoolType::ConstantProduct,
+                    vec!["terra2>astro".to_string(), "terra2>luna".to_string()]
+                ),
+                (
+                    UncheckedPoolAddress::contract("stable"),
+                    "astroport".to_string(),
+                    PoolType::Stable,
+                    vec!["terra2>luna".to_string(), "terra2>usdc".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("unnamed"),
+                vec![AssetInfo::cw20(Addr::unchecked("unknown_token"))]
+            )]
+        );
+        assert_eq!(
+            scraper.fetch_contract_entries().await.unwrap(),
+            [(astroport_contract("router"), Addr::unchecked("router"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn deployment_without_factory_is_rejected() {
+        let deployment = StaticDeployment::new([("router_address", "router")]);
+
+        let err = AstroportScraper::from_sources(
+            "phoenix-1",
+            &deployment,
+            StaticPairs::default(),
+            KnownNames::default(),
+            &ScraperConfig::default_for("phoenix-1"),
+        )
+        .await
+        .err()
+        .expect("the factory is required");
+        match err {
+            ScraperError::DeploymentKeyMissing { key, available, .. } => {
+                assert_eq!(key, FACTORY_KEY);
+                assert_eq!(available, ["router_address"]);
             }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
 
-            let pool_metadata = PoolMetadata {
-                dex: ASTROPORT_DEX.to_string(),
-                pool_type,
-                assets,
-            };
-            ans_pools_to_add.push((pool_id, pool_metadata));
+    #[tokio::test]
+    async fn static_pairs_continue_after_the_cursor() {
+        let pairs = StaticPairs::new([
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![native("uluna"), token("other_token")]),
+        ]);
+
+        let after_first = pairs
+            .pairs_after(Some(vec![native("uluna"), token("astro_token")]))
+            .await
+            .unwrap();
+        assert_eq!(after_first.len(), 1);
+        assert_eq!(after_first[0].contract_addr, Addr::unchecked("second"));
+        assert_eq!(pairs.pairs_after(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn static_deployment_scrapes_through_the_pipeline() {
+        let scraper = static_scraper().await;
+
+        let output = ScrapePipeline::new(vec![Box::new(scraper)], &ScraperConfig::default())
+            .run()
+            .await
+            .unwrap();
+        let asset_names = output
+            .assets
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            asset_names,
+            [
+                "terra2>astro",
+                "terra2>luna",
+                "terra2>usdc",
+                "astroport/terra2>astro,terra2>luna",
+                "astroport/terra2>luna,terra2>usdc",
+            ]
+        );
+        let pools = output
+            .pools
+            .iter()
+            .map(|(address, _)| address.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pools,
+            [
+                UncheckedPoolAddress::contract("xyk"),
+                UncheckedPoolAddress::contract("stable"),
+            ]
+        );
+        assert_eq!(
+            output.pairings,
+            [
+                (
+                    DexAssetPairing::new(
+                        AssetEntry::new("terra2>astro"),
+                        AssetEntry::new("terra2>luna"),
+                        "astroport"
+                    ),
+                    vec![UncheckedPoolAddress::contract("xyk")]
+                ),
+                (
+                    DexAssetPairing::new(
+                        AssetEntry::new("terra2>luna"),
+                        AssetEntry::new("terra2>usdc"),
+                        "astroport"
+                    ),
+                    vec![UncheckedPoolAddress::contract("stable")]
+                ),
+            ]
+        );
+        assert_eq!(
+            output.contracts,
+            [(
+                UncheckedContractEntry::new("astroport", "router"),
+                "router".to_string()
+            )]
+        );
+        // the pair with the unnamed cw20 is reported, not registered
+        assert_eq!(output.reports.len(), 1);
+        assert_eq!(output.reports[0].dex, "astroport");
+        assert_eq!(output.reports[0].unresolved_assets.len(), 1);
+        assert_eq!(
+            output.reports[0].skipped_pools,
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("unnamed"),
+                vec![AssetInfo::cw20(Addr::unchecked("unknown_token"))]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn recreated_static_pair_is_skipped_for_the_last_one() {
+        let mut scraper = scraper_of(vec![
+            pair("old", vec![native("uluna"), token("astro_token")]),
+            pair("new", vec![native("uluna"), token("astro_token")]),
+        ])
+        .await;
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        // the LP token of the old pair would conflict with the one of the new pair
+        assert_eq!(
+            assets.last(),
+            Some(&AnsAsset::new(
+                "astroport/terra2>astro,terra2>luna",
+                AssetInfo::cw20(Addr::unchecked("new_lp"))
+            ))
+        );
+        assert_eq!(assets.len(), 3);
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].0, UncheckedPoolAddress::contract("new"));
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::duplicate_of(
+                UncheckedPoolAddress::contract("old"),
+                UncheckedPoolAddress::contract("new")
+            )]
+        );
+    }
+
+    fn pool_of(pool_type: PoolType, assets: [&str; 2]) -> PoolMetadata {
+        PoolMetadata::new("astroport", pool_type, assets.map(AssetEntry::new).to_vec())
+    }
+
+    /// The output files of the scrape before the one of [`static_scraper`]: ASTRO had another
+    /// address, the stable pair didn't exist and a retired pair was still listed.
+    fn write_previous_scrape(out_dir: &Path) -> PathBuf {
+        let previous = ScrapeOutput {
+            meta: ScrapeMeta::new("phoenix-1", Some(100)),
+            assets: vec![
+                (
+                    "terra2>astro".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("old_astro_token")),
+                ),
+                ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+                (
+                    "astroport/terra2>astro,terra2>luna".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("xyk_lp")),
+                ),
+                (
+                    "astroport/terra2>luna,terra2>old".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("retired_lp")),
+                ),
+            ],
+            contracts: vec![(
+                UncheckedContractEntry::new("astroport", "router"),
+                "router".to_string(),
+            )],
+            pools: vec![
+                (
+                    UncheckedPoolAddress::contract("xyk"),
+                    pool_of(PoolType::ConstantProduct, ["terra2>astro", "terra2>luna"]),
+                ),
+                (
+                    UncheckedPoolAddress::contract("retired"),
+                    pool_of(PoolType::ConstantProduct, ["terra2>luna", "terra2>old"]),
+                ),
+            ],
+            ..Default::default()
+        };
+        let summary = ScrapeSummary::new("phoenix-1", &previous);
+        let chain_dir = write_output(out_dir, &previous.assets, &previous.pools, &summary).unwrap();
+        write_contracts(out_dir, &previous.meta, &previous.contracts).unwrap();
+        chain_dir
+    }
+
+    /// The whole flow without a chain: scrape a static deployment, write the output files, diff
+    /// the scrape against the files of the previous one and plan the registration.
+    #[tokio::test]
+    async fn static_deployment_is_planned_against_the_previous_scrape() {
+        let out_dir =
+            std::env::temp_dir().join(format!("ans-scraper-end-to-end-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let previous_dir = write_previous_scrape(&out_dir.join("previous"));
+        let mut previous = AnsHostEntries::from_export(ScrapeExport::load(&previous_dir).unwrap());
+        // the output files don't have the unique ids AnsHost removes pools by
+        previous.pool_ids = vec![
+            (1, UncheckedPoolAddress::contract("xyk")),
+            (2, UncheckedPoolAddress::contract("retired")),
+        ];
+
+        let output = ScrapePipeline::new(
+            vec![Box::new(static_scraper().await)],
+            &ScraperConfig::default(),
+        )
+        .run()
+        .await
+        .unwrap();
+        let summary = ScrapeSummary::new("phoenix-1", &output);
+        assert_eq!((summary.assets, summary.pools, summary.pairings), (5, 2, 2));
+        assert_eq!(summary.not_found_assets.len(), 1);
+        assert_eq!(
+            summary.skipped_pools,
+            [UncheckedPoolAddress::contract("unnamed")]
+        );
+
+        // the files read back to the scrape
+        let chain_dir = write_output(
+            &out_dir.join("current"),
+            &output.assets,
+            &output.pools,
+            &summary,
+        )
+        .unwrap();
+        let written = ScrapeExport::load(&chain_dir).unwrap();
+        assert_eq!(written.assets, output.assets);
+        assert_eq!(written.pools, output.pools);
+
+        let diff = AnsDiff::new(&previous, &output, DEFAULT_MAX_HEIGHT_GAP).unwrap();
+        let counts = diff.summary();
+        let assets = &counts["assets"];
+        assert_eq!(
+            (
+                assets["-"].to_add,
+                assets["-"].to_update,
+                assets["-"].unchanged
+            ),
+            (1, 1, 1)
+        );
+        let lp_tokens = &assets["astroport"];
+        assert_eq!(
+            (lp_tokens.to_add, lp_tokens.to_remove, lp_tokens.unchanged),
+            (1, 1, 1)
+        );
+        let pools = &counts["pools"]["astroport"];
+        assert_eq!((pools.to_add, pools.to_remove, pools.unchanged), (1, 1, 1));
+        assert_eq!(counts["contracts"]["astroport"].unchanged, 1);
+
+        let batches = plan_batches(
+            &previous,
+            &output,
+            TargetVersion::LATEST,
+            DEFAULT_BATCH_SIZE,
+            false,
+        )
+        .unwrap();
+        let msgs = batches
+            .into_iter()
+            .map(|batch| batch.msg)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            msgs,
+            [
+                ExecuteMsg::UpdateAssetAddresses {
+                    to_add: vec![
+                        (
+                            "terra2>usdc".to_string(),
+                            AssetInfoUnchecked::native("ibc/usdc")
+                        ),
+                        (
+                            "astroport/terra2>luna,terra2>usdc".to_string(),
+                            AssetInfoUnchecked::cw20("stable_lp")
+                        ),
+                        (
+                            "terra2>astro".to_string(),
+                            AssetInfoUnchecked::cw20("astro_token")
+                        ),
+                    ],
+                    to_remove: vec![],
+                },
+                // astroport is registered, only the new pool is added
+                ExecuteMsg::UpdatePools {
+                    to_add: vec![(
+                        UncheckedPoolAddress::contract("stable"),
+                        pool_of(PoolType::Stable, ["terra2>luna", "terra2>usdc"]),
+                    )],
+                    to_remove: vec![],
+                },
+            ]
+        );
+
+        // pruning removes the retired pool and its LP token before anything is added
+        let batches = plan_batches(
+            &previous,
+            &output,
+            TargetVersion::LATEST,
+            DEFAULT_BATCH_SIZE,
+            true,
+        )
+        .unwrap();
+        assert_eq!(batches.len(), 4);
+        assert_eq!(
+            batches[..2]
+                .iter()
+                .map(|batch| batch.msg.clone())
+                .collect::<Vec<_>>(),
+            [
+                ExecuteMsg::UpdatePools {
+                    to_add: vec![],
+                    to_remove: vec![UniquePoolId::new(2)],
+                },
+                ExecuteMsg::UpdateAssetAddresses {
+                    to_add: vec![],
+                    to_remove: vec!["astroport/terra2>luna,terra2>old".to_string()],
+                },
+            ]
+        );
+        assert_eq!(batches[2..].len(), msgs.len());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    /// Names the assets like a scrape of an offline chain does, from cached query responses and
+    /// an in-memory chain registry.
+    struct OfflineNames {
+        clients: Arc<ChainClients>,
+        chain_registry: LazyChainRegistry,
+        resolution_cache: ResolutionCache,
+    }
+
+    #[async_trait(?Send)]
+    impl DenomResolver for OfflineNames {
+        async fn resolve(&self, asset_infos: Vec<AssetInfo>) -> anyhow::Result<ResolvedAssets> {
+            AssetResolver {
+                clients: &self.clients,
+                chain_registry: &self.chain_registry,
+                resolution_cache: &self.resolution_cache,
+                ans_prefix: "terra2",
+                concurrency: 1,
+            }
+            .resolve(asset_infos)
+            .await
+        }
+    }
+
+    /// LUNA is in the asset list of terra2, USDC came from noble, ampLUNA is a token factory
+    /// denom no list has, and two cw20s are both called axlUSDC.
+    fn offline_names() -> OfflineNames {
+        let clients = ChainClients::offline("phoenix-1");
+        let usdc = DenomTraceFixture::transfer("channel-2", "uusdc");
+        clients
+            .query_cache
+            .insert_denom_trace(&usdc.hash(), usdc.trace());
+        for (contract_addr, symbol) in [
+            ("astro_token", "ASTRO"),
+            ("usdc_a", "axlUSDC"),
+            ("usdc_b", "axlUSDC"),
+        ] {
+            clients.query_cache.insert_cw20_info(
+                &Addr::unchecked(contract_addr),
+                cw20::TokenInfoResponse {
+                    name: symbol.to_string(),
+                    symbol: symbol.to_string(),
+                    decimals: 6,
+                    total_supply: Default::default(),
+                },
+            );
         }
+        let chain_registry = registry_from_lists(vec![
+            AssetListBuilder::chain("terra2")
+                .asset("LUNA", "uluna", 6)
+                .build(),
+            AssetListBuilder::chain("noble")
+                .asset("USDC", "uusdc", 6)
+                .build(),
+        ]);
+
+        OfflineNames {
+            clients,
+            chain_registry: LazyChainRegistry::from(chain_registry),
+            resolution_cache: ResolutionCache::new(),
+        }
+    }
+
+    /// Every name, pool, pairing and staking key of a scrape, one per line.
+    fn emitted_entries(output: &ScrapeOutput) -> String {
+        let address = |pool: &UncheckedPoolAddress| match pool {
+            UncheckedPoolAddress::Contract(address) => address.clone(),
+            UncheckedPoolAddress::Id(id) => id.to_string(),
+        };
+        let joined = |entries: Vec<String>| entries.join(", ");
+
+        let mut lines = vec!["assets:".to_string()];
+        lines.extend(
+            output
+                .assets
+                .iter()
+                .map(|(name, info)| format!("  {} = {}", name, info)),
+        );
+        lines.push("pools:".to_string());
+        lines.extend(output.pools.iter().map(|(pool, metadata)| {
+            format!(
+                "  {}: {} {:?} {}",
+                address(pool),
+                metadata.dex,
+                metadata.pool_type,
+                joined(metadata.assets.iter().map(ToString::to_string).collect())
+            )
+        }));
+        lines.push("pairings:".to_string());
+        lines.extend(output.pairings.iter().map(|(pairing, pools)| {
+            format!(
+                "  {} {} {}: {}",
+                pairing.dex(),
+                pairing.asset_x(),
+                pairing.asset_y(),
+                joined(pools.iter().map(address).collect())
+            )
+        }));
+        lines.push("staking keys:".to_string());
+        lines.extend(
+            output
+                .assets
+                .iter()
+                .filter(|(name, _)| name.contains(LP_TOKEN_SEPARATOR))
+                .map(|(name, _)| {
+                    let (entry, _) =
+                        staking_contract_entry(ASTROPORT_DEX, name, Addr::unchecked("generator"));
+                    format!("  {} {}", entry.protocol, entry.contract)
+                }),
+        );
+        lines.push("contracts:".to_string());
+        lines.extend(output.contracts.iter().map(|(entry, address)| {
+            format!("  {} {} = {}", entry.protocol, entry.contract, address)
+        }));
+        lines.push("conflicts:".to_string());
+        lines.extend(output.asset_conflicts.iter().map(|conflict| {
+            format!(
+                "  {}: {}",
+                conflict.name,
+                joined(conflict.infos.iter().map(ToString::to_string).collect())
+            )
+        }));
+        lines.push("skipped pools:".to_string());
+        lines.extend(
+            output
+                .reports
+                .iter()
+                .flat_map(|report| &report.skipped_pools)
+                .map(|skipped| format!("  {}: {:?}", address(&skipped.pool), skipped.reason)),
+        );
+        lines.join("\n")
+    }
+
+    /// The names and pool metadata are what adapters look up on chain, any change to them has to
+    /// show up here and be reviewed.
+    #[tokio::test]
+    async fn emitted_names_and_pool_metadata() {
+        let usdc = DenomTraceFixture::transfer("channel-2", "uusdc").ibc_denom();
+        let mut stable = pair("stable", vec![native("uluna"), native(&usdc)]);
+        stable.pair_type = PairType::Stable {};
+        let mut pcl = pair(
+            "pcl",
+            vec![
+                native("uluna"),
+                token("astro_token"),
+                native("factory/terra1minter/ampLUNA"),
+            ],
+        );
+        pcl.pair_type = PairType::Concentrated {};
+        let pairs = vec![
+            pair("xyk", vec![native("uluna"), token("astro_token")]),
+            stable,
+            pcl,
+            pair("axl", vec![native("uluna"), token("usdc_a")]),
+            pair("other_axl", vec![token("usdc_b"), native("uluna")]),
+        ];
+        let scraper = scraper_naming(pairs, offline_names()).await;
+
+        let output = ScrapePipeline::new(vec![Box::new(scraper)], &ScraperConfig::default())
+            .with_conflict_policy(ConflictPolicy::Drop)
+            .run()
+            .await
+            .unwrap();
 
-        Ok(ans_pools_to_add)
+        insta::assert_snapshot!(emitted_entries(&output), @r###"
+        assets:
+          noble>usdc = native:ibc/F082B65C88E4B6D5EF1DB243CDA1D331D002759E938A0F5CD3FFDC5D53B3E349
+          terra2>ampluna = native:factory/terra1minter/ampLUNA
+          terra2>astro = cw20:astro_token
+          terra2>luna = native:uluna
+          astroport/terra2>astro,terra2>luna = cw20:xyk_lp
+          astroport/noble>usdc,terra2>luna = cw20:stable_lp
+          astroport/terra2>ampluna,terra2>astro,terra2>luna = cw20:pcl_lp
+        pools:
+          xyk: astroport ConstantProduct terra2>astro, terra2>luna
+          stable: astroport Stable noble>usdc, terra2>luna
+          pcl: astroport ConcentratedLiquidity terra2>ampluna, terra2>astro, terra2>luna
+        pairings:
+          astroport noble>usdc terra2>luna: stable
+          astroport terra2>ampluna terra2>astro: pcl
+          astroport terra2>ampluna terra2>luna: pcl
+          astroport terra2>astro terra2>luna: xyk, pcl
+        staking keys:
+          astroport staking/astroport/terra2>astro,terra2>luna
+          astroport staking/astroport/noble>usdc,terra2>luna
+          astroport staking/astroport/terra2>ampluna,terra2>astro,terra2>luna
+        contracts:
+          astroport router = router
+        conflicts:
+          terra2>axlusdc: cw20:usdc_a, cw20:usdc_b
+          astroport/terra2>axlusdc,terra2>luna: cw20:axl_lp, cw20:other_axl_lp
+        skipped pools:
+          axl: ConflictingNames(["terra2>axlusdc"])
+          other_axl: ConflictingNames(["terra2>axlusdc"])
+        "###);
     }
 }
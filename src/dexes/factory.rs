@@ -0,0 +1,426 @@
+//! Pairs of the terraswap-like factories, shared by the scrapers of terraswap and its forks.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ScraperConfig;
+use crate::error::ScraperError;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::names::lp_token_name;
+use crate::helpers::pagination::{FactoryPair, PairPages};
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::helpers::resolve::AssetResolver;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset};
+use crate::traits::dex::AnsAsset;
+use crate::LazyChainRegistry;
+
+/// Number of pairs requested per factory query.
+const PAIRS_PAGE_LIMIT: u32 = 30;
+
+/// Asset info as used by the terraswap-like contracts.
+///
+/// Terraswap and white whale write `{"token": {"contract_addr": ..}}` and
+/// `{"native_token": {"denom": ..}}`, wyndex `{"token": ".."}` and `{"native": ".."}`. An asset
+/// is serialized back in the format it was listed in, so cursors go back to the factory as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FactoryAssetInfo {
+    Token(FactoryToken),
+    NativeToken { denom: String },
+    Native(String),
+}
+
+/// The cw20 of a [`FactoryAssetInfo::Token`], in the format of its factory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FactoryToken {
+    Contract { contract_addr: String },
+    Address(String),
+}
+
+impl From<&FactoryAssetInfo> for AssetInfo {
+    fn from(info: &FactoryAssetInfo) -> Self {
+        match info {
+            FactoryAssetInfo::Token(FactoryToken::Contract { contract_addr })
+            | FactoryAssetInfo::Token(FactoryToken::Address(contract_addr)) => {
+                AssetInfo::cw20(Addr::unchecked(contract_addr))
+            }
+            FactoryAssetInfo::NativeToken { denom } | FactoryAssetInfo::Native(denom) => {
+                AssetInfo::native(denom)
+            }
+        }
+    }
+}
+
+/// A pair of a terraswap-like factory, with the dex specific parts of its ANS entries.
+pub(crate) trait FactoryPairInfo: FactoryPair + Clone + DeserializeOwned {
+    /// LP token of the pair.
+    fn liquidity_token(&self) -> AssetInfo;
+
+    /// ANS pool type of the pair, or the name of its pair type if ANS doesn't support it.
+    fn pool_type(&self) -> Result<PoolType, String>;
+
+    /// Name of the pair type, two pairs with the same assets and pair type are the same pool.
+    fn pair_type(&self) -> String;
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FactoryQueryMsg<A> {
+    Pairs {
+        start_after: Option<Vec<A>>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Deserialize)]
+struct PairsResponse<P> {
+    pairs: Vec<P>,
+}
+
+/// The pairs of a factory and the ANS names of their assets.
+pub(crate) struct FactoryPairs<P: FactoryPair> {
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    dex_id: &'static str,
+    ans_prefix: String,
+    factory: Addr,
+    pub(crate) loaded_pairs: Vec<P>,
+    /// Pairs that were created again, mapped to the pair that is kept, looked up once
+    duplicate_pairs: Option<BTreeMap<Addr, Addr>>,
+    asset_info_to_name: HashMap<P::AssetInfo, String>,
+    not_found_assets: Vec<UnresolvedAsset>,
+    skipped_pools: Vec<SkippedPool>,
+    query_concurrency: usize,
+    /// Number of pages of pairs queried from the factory before the pagination is given up
+    max_pair_pages: usize,
+}
+
+impl<P> FactoryPairs<P>
+where
+    P: FactoryPairInfo,
+    P::AssetInfo: Eq + Hash + Serialize,
+    for<'a> AssetInfo: From<&'a P::AssetInfo>,
+{
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: LazyChainRegistry,
+        dex_id: &'static str,
+        ans_prefix: &str,
+        factory: Addr,
+        config: &ScraperConfig,
+    ) -> Self {
+        Self {
+            clients,
+            chain_registry,
+            resolution_cache: Default::default(),
+            dex_id,
+            ans_prefix: ans_prefix.to_string(),
+            factory,
+            loaded_pairs: vec![],
+            duplicate_pairs: None,
+            asset_info_to_name: HashMap::new(),
+            not_found_assets: vec![],
+            skipped_pools: vec![],
+            query_concurrency: config.query_concurrency,
+            max_pair_pages: config.max_pair_pages,
+        }
+    }
+
+    pub fn clients(&self) -> &ChainClients {
+        &self.clients
+    }
+
+    pub fn set_resolution_cache(&mut self, resolution_cache: Arc<ResolutionCache>) {
+        self.resolution_cache = resolution_cache;
+    }
+
+    pub fn set_query_concurrency(&mut self, concurrency: usize) {
+        self.query_concurrency = concurrency;
+    }
+
+    pub fn query_concurrency(&self) -> usize {
+        self.query_concurrency
+    }
+
+    /// Query the pairs of `factory` instead, the loaded pairs are dropped.
+    pub fn set_factory(&mut self, factory: Addr) {
+        self.factory = factory;
+        self.loaded_pairs.clear();
+        self.duplicate_pairs = None;
+    }
+
+    /// Resolve the ANS names of all the assets in the kept pairs, plus the LP tokens of the pairs
+    /// whose assets all resolved.
+    pub async fn resolve_assets(&mut self) -> Result<Vec<(String, AssetInfo)>, ScraperError> {
+        self.not_found_assets.clear();
+        self.asset_info_to_name.clear();
+
+        let pairs = self.kept_pairs().await?;
+        let mut seen = HashSet::new();
+        let asset_infos: Vec<P::AssetInfo> = pairs
+            .iter()
+            .flat_map(|p| p.asset_infos().iter())
+            .filter(|info| seen.insert(*info))
+            .cloned()
+            .collect();
+
+        let resolved = AssetResolver {
+            clients: &self.clients,
+            chain_registry: &self.chain_registry,
+            resolution_cache: &self.resolution_cache,
+            ans_prefix: &self.ans_prefix,
+            concurrency: self.query_concurrency,
+        }
+        .resolve(asset_infos.iter().map(AssetInfo::from).collect())
+        .await?;
+
+        let names = resolved.names_by_info();
+        for info in asset_infos {
+            if let Some(name) = names.get(&AssetInfo::from(&info).to_string()) {
+                self.asset_info_to_name.insert(info, name.to_string());
+            }
+        }
+        self.not_found_assets = resolved.not_found;
+        let mut ans_assets_to_add = resolved.names;
+
+        // LP tokens of the pairs, named after the pair's assets
+        for pair in &pairs {
+            if let Some(names) = self.asset_names(pair) {
+                ans_assets_to_add
+                    .push((lp_token_name(self.dex_id, &names), pair.liquidity_token()));
+            }
+        }
+
+        Ok(ans_assets_to_add)
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        &self.not_found_assets
+    }
+
+    /// Pools skipped by the last [`Self::fetch_dex_pools`] call because an asset wasn't resolved,
+    /// the pair type is not supported by ANS or the pair was created again.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        &self.skipped_pools
+    }
+
+    /// The resolved names of the pair's assets, `None` if one isn't resolved.
+    pub fn asset_names(&self, pair: &P) -> Option<Vec<String>> {
+        pair.asset_infos()
+            .iter()
+            .map(|info| self.asset_info_to_name.get(info).cloned())
+            .collect()
+    }
+
+    /// All the pairs of the factory, queried once and kept for the lifetime of the scraper.
+    pub async fn load_pairs(&mut self) -> anyhow::Result<Vec<P>> {
+        if self.loaded_pairs.is_empty() {
+            let batcher = WasmQueryBatcher::new(&self.clients.wasm, 1);
+            let mut pages = PairPages::new(
+                self.dex_id,
+                &self.clients.chain_id,
+                None,
+                self.max_pair_pages,
+            );
+            let mut all_pairs: Vec<P> = vec![];
+            while let Some(start_after) = pages.cursor() {
+                let query = FactoryQueryMsg::Pairs {
+                    start_after,
+                    limit: Some(PAIRS_PAGE_LIMIT),
+                };
+                let response = batcher
+                    .query(&self.factory, &query)
+                    .await
+                    .with_context(|| {
+                        format!("querying pairs of {} factory {}", self.dex_id, self.factory)
+                    })?;
+                let PairsResponse { pairs } =
+                    serde_json::from_slice(&response).with_context(|| {
+                        format!("parsing pairs of {} factory {}", self.dex_id, self.factory)
+                    })?;
+                all_pairs.extend(pages.advance(pairs));
+            }
+            self.loaded_pairs = all_pairs;
+        }
+
+        Ok(self.loaded_pairs.clone())
+    }
+
+    /// The loaded pairs without the ones that were created again, see
+    /// [`Self::find_duplicate_pairs`].
+    async fn kept_pairs(&mut self) -> anyhow::Result<Vec<P>> {
+        let mut pairs = self.load_pairs().await?;
+        if self.duplicate_pairs.is_none() {
+            self.duplicate_pairs = Some(self.find_duplicate_pairs(&pairs).await);
+        }
+        let duplicate_pairs = self
+            .duplicate_pairs
+            .as_ref()
+            .expect("duplicate pairs are looked up");
+        pairs.retain(|pair| !duplicate_pairs.contains_key(pair.contract_addr()));
+        Ok(pairs)
+    }
+
+    /// Pairs with the same assets and pair type as another pair, a pair that was deregistered and
+    /// created again, mapped to the pair that is kept: the most recently instantiated one, or the
+    /// factory's last pair without creation heights.
+    async fn find_duplicate_pairs(&self, pairs: &[P]) -> BTreeMap<Addr, Addr> {
+        // pair type and sorted assets -> pairs
+        let mut groups = BTreeMap::<(String, Vec<String>), Vec<&P>>::new();
+        for pair in pairs {
+            let mut assets = pair
+                .asset_infos()
+                .iter()
+                .map(|info| AssetInfo::from(info).to_string())
+                .collect::<Vec<_>>();
+            assets.sort();
+            groups
+                .entry((pair.pair_type(), assets))
+                .or_default()
+                .push(pair);
+        }
+
+        let mut duplicate_pairs = BTreeMap::new();
+        for group in groups.into_values().filter(|group| group.len() > 1) {
+            let mut newest = *group.last().expect("groups are not empty");
+            let mut newest_created = None;
+            for pair in &group {
+                let created = self
+                    .clients
+                    .contract_created(pair.contract_addr().as_str())
+                    .await;
+                if created.is_some() && created >= newest_created {
+                    newest = pair;
+                    newest_created = created;
+                }
+            }
+            for pair in group {
+                if pair.contract_addr() != newest.contract_addr() {
+                    log::warn!(
+                        "{}: dropping {} pair {}, it was created again as {}",
+                        self.clients.chain_id,
+                        self.dex_id,
+                        pair.contract_addr(),
+                        newest.contract_addr()
+                    );
+                    duplicate_pairs
+                        .insert(pair.contract_addr().clone(), newest.contract_addr().clone());
+                }
+            }
+        }
+        duplicate_pairs
+    }
+
+    /// The pools of the kept pairs with a supported pair type and resolved assets, the others are
+    /// skipped.
+    pub async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.skipped_pools.clear();
+        let mut ans_pools_to_add = vec![];
+
+        let pairs = self.kept_pairs().await?;
+        if let Some(duplicate_pairs) = &self.duplicate_pairs {
+            self.skipped_pools
+                .extend(duplicate_pairs.iter().map(|(pair, kept)| {
+                    SkippedPool::duplicate_of(
+                        UncheckedPoolAddress::contract(pair),
+                        UncheckedPoolAddress::contract(kept),
+                    )
+                }));
+        }
+        for pair in pairs {
+            let pool_id = UncheckedPoolAddress::contract(pair.contract_addr().clone());
+            let pool_type = match pair.pool_type() {
+                Ok(pool_type) => pool_type,
+                Err(pair_type) => {
+                    log::warn!(
+                        "Skipping pool {} with pair type {}",
+                        pair.contract_addr(),
+                        pair_type
+                    );
+                    self.skipped_pools
+                        .push(SkippedPool::unsupported_pair_type(pool_id, pair_type));
+                    continue;
+                }
+            };
+
+            let Some(names) = self.asset_names(&pair) else {
+                let missing = pair
+                    .asset_infos()
+                    .iter()
+                    .filter(|info| !self.asset_info_to_name.contains_key(*info))
+                    .map(AssetInfo::from)
+                    .collect();
+                self.skipped_pools
+                    .push(SkippedPool::missing_assets(pool_id, missing));
+                continue;
+            };
+
+            let pool_metadata = PoolMetadata {
+                dex: self.dex_id.to_string(),
+                pool_type,
+                assets: names.into_iter().map(AssetEntry::from).collect(),
+            };
+            ans_pools_to_add.push((pool_id, pool_metadata));
+        }
+
+        Ok(ans_pools_to_add)
+    }
+
+    pub fn report(&self) -> DexReport {
+        DexReport::new(
+            self.dex_id,
+            self.not_found_assets.clone(),
+            self.skipped_pools.clone(),
+        )
+    }
+}
+
+/// The resolved assets of [`FactoryPairs::resolve_assets`] as ANS assets.
+pub(crate) fn ans_assets(assets: Vec<(String, AssetInfo)>) -> Vec<AnsAsset> {
+    assets
+        .into_iter()
+        .map(|(entry, info)| AnsAsset::new(entry, info))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn asset_infos_are_written_back_in_their_format() {
+        for listed in [
+            json!({"token": {"contract_addr": "astro_token"}}),
+            json!({"native_token": {"denom": "uluna"}}),
+            json!({"token": "wynd_token"}),
+            json!({"native": "ujuno"}),
+        ] {
+            let info: FactoryAssetInfo = serde_json::from_value(listed.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&info).unwrap(), listed);
+        }
+
+        let wyndex_token: FactoryAssetInfo =
+            serde_json::from_value(json!({"token": "wynd_token"})).unwrap();
+        assert_eq!(
+            AssetInfo::from(&wyndex_token),
+            AssetInfo::cw20(Addr::unchecked("wynd_token"))
+        );
+    }
+}
@@ -0,0 +1,663 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use anyhow::Context;
+use async_trait::async_trait;
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmwasm::wasm::v1::query_client::QueryClient as WasmQueryClient;
+use cosmos_sdk_proto::cosmwasm::wasm::v1::QueryContractsByCodeRequest;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::ScraperConfig;
+use crate::error::ScraperError;
+use crate::helpers::chain_registry::{ResolveError, ResolvedAsset};
+use crate::helpers::clients::ChainClients;
+use crate::helpers::denoms::{factory_denom_parts, ibc_denom_hash};
+use crate::helpers::names::lp_token_name;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::helpers::retry::retry;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset, UnresolvedReason};
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::LazyChainRegistry;
+
+const FIN_DEX: &str = "fin";
+const BOW_DEX: &str = "bow";
+
+/// Chain name of kujira in the chain registry, also the ANS prefix of its native assets.
+const KUJIRA_CHAIN_NAME: &str = "kujira";
+
+/// Networks with a FIN and BOW deployment.
+const KUJIRA_NETWORKS: [&str; 2] = ["kaiyo-1", "harpoon-4"];
+
+/// Number of contracts requested per `ContractsByCode` query.
+const CONTRACTS_PAGE_LIMIT: u64 = 100;
+
+/// Pair type the FIN markets are reported as skipped with when the deployment doesn't set a
+/// `fin_pool_type`.
+const FIN_PAIR_TYPE: &str = "orderbook";
+
+/// Code ids of the FIN and BOW contracts, every instance of them is scraped.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KujiraDeployment {
+    pub fin_code_ids: Vec<u64>,
+    #[serde(default)]
+    pub bow_code_ids: Vec<u64>,
+    /// ANS pool type the FIN markets are registered as. ANS has no orderbook pool type, so the
+    /// markets are skipped unless one is chosen, e.g. `constant_product`
+    #[serde(default)]
+    pub fin_pool_type: Option<String>,
+}
+
+impl KujiraDeployment {
+    /// The ANS pool type of the FIN markets, `None` if the deployment doesn't set one.
+    pub fn fin_pool_type(&self) -> anyhow::Result<Option<PoolType>> {
+        let Some(name) = &self.fin_pool_type else {
+            return Ok(None);
+        };
+        serde_json::from_value(Value::String(name.clone()))
+            .map(Some)
+            .with_context(|| format!("unknown fin_pool_type {}", name))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ConfigQueryMsg {
+    Config {},
+}
+
+/// Config of a FIN market, only the traded denoms are used.
+#[derive(Deserialize)]
+struct FinConfigResponse {
+    denoms: Vec<Value>,
+}
+
+/// Config of a BOW vault, only the market it provides liquidity to is used.
+#[derive(Deserialize)]
+struct BowConfigResponse {
+    fin_contract: Addr,
+}
+
+/// A FIN market with its base and quote denoms.
+#[derive(Debug, Clone)]
+pub(crate) struct FinMarket {
+    pub(crate) contract: Addr,
+    pub(crate) denoms: Vec<String>,
+}
+
+/// A BOW vault and the FIN market it provides liquidity to.
+#[derive(Debug, Clone)]
+pub(crate) struct BowVault {
+    pub(crate) contract: Addr,
+    pub(crate) market: Addr,
+}
+
+impl BowVault {
+    /// Token factory denom of the vault's LP token.
+    fn lp_denom(&self) -> String {
+        format!("factory/{}/ulp", self.contract)
+    }
+}
+
+pub struct KujiraScraper {
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    deployment: KujiraDeployment,
+    /// Pool type the FIN markets are registered as, they are skipped without one
+    fin_pool_type: Option<PoolType>,
+    pub(crate) loaded_markets: Vec<FinMarket>,
+    pub(crate) loaded_vaults: Vec<BowVault>,
+    denom_to_name: HashMap<String, String>,
+    not_found_assets: Vec<UnresolvedAsset>,
+    skipped_pools: Vec<SkippedPool>,
+    /// Contracts whose config doesn't have the expected shape, with the reason
+    unsupported_contracts: BTreeMap<Addr, String>,
+    query_concurrency: usize,
+}
+
+impl DexId for KujiraScraper {
+    fn dex_id(&self) -> &'static str {
+        FIN_DEX
+    }
+}
+
+impl KujiraScraper {
+    /// Create the scraper for the chain, with the configured kujira deployment of the chain.
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        config.dex_ans_prefix(FIN_DEX, &KUJIRA_NETWORKS, &clients.chain_id)?;
+        let deployment = Self::deployment(config, &clients.chain_id)?;
+        let fin_pool_type = deployment.fin_pool_type()?;
+
+        Ok(Self {
+            clients,
+            chain_registry: chain_registry.into(),
+            resolution_cache: Default::default(),
+            deployment,
+            fin_pool_type,
+            loaded_markets: vec![],
+            loaded_vaults: vec![],
+            denom_to_name: HashMap::new(),
+            not_found_assets: vec![],
+            skipped_pools: vec![],
+            unsupported_contracts: BTreeMap::new(),
+            query_concurrency: config.query_concurrency,
+        })
+    }
+
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.resolution_cache = resolution_cache;
+        self
+    }
+
+    /// Set the number of config and denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.query_concurrency = concurrency;
+        self
+    }
+
+    /// Use another deployment than the configured one.
+    pub fn with_deployment(mut self, deployment: KujiraDeployment) -> Result<Self, ScraperError> {
+        self.fin_pool_type = deployment.fin_pool_type()?;
+        self.deployment = deployment;
+        self.loaded_markets.clear();
+        self.loaded_vaults.clear();
+        Ok(self)
+    }
+
+    /// Register the FIN markets as pools of `pool_type` instead of the one of the deployment.
+    pub fn with_fin_pool_type(mut self, pool_type: PoolType) -> Self {
+        self.fin_pool_type = Some(pool_type);
+        self
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(FIN_DEX, &KUJIRA_NETWORKS, chain_id)
+    }
+
+    /// Deployment of the chain from the `kujira_deployments` of `config`.
+    pub fn deployment(
+        config: &ScraperConfig,
+        chain_id: &str,
+    ) -> Result<KujiraDeployment, ScraperError> {
+        config.dex_ans_prefix(FIN_DEX, &KUJIRA_NETWORKS, chain_id)?;
+        config
+            .kujira_deployments
+            .get(chain_id)
+            .cloned()
+            .ok_or_else(|| ScraperError::DeploymentKeyMissing {
+                protocol: KUJIRA_CHAIN_NAME.to_string(),
+                key: "fin_code_ids".to_string(),
+                available: vec![],
+            })
+    }
+
+    /// Resolve the ANS names of all the denoms of the markets, plus the LP tokens of the BOW
+    /// vaults whose market denoms all resolved. The vaults of markets that are skipped have no LP
+    /// token entry, their pool isn't registered.
+    pub async fn resolve_assets(&mut self) -> anyhow::Result<Vec<(String, AssetInfo)>> {
+        self.not_found_assets.clear();
+        self.denom_to_name.clear();
+
+        let markets = self.load_markets().await?;
+        let mut seen = HashSet::new();
+        let denoms: Vec<String> = markets
+            .iter()
+            .flat_map(|m| m.denoms.iter())
+            .filter(|denom| seen.insert(*denom))
+            .cloned()
+            .collect();
+
+        let chain_id = self.clients.chain_id.clone();
+        let mut cached_names = HashMap::new();
+        for denom in &denoms {
+            if let Some(name) = self
+                .resolution_cache
+                .get(&chain_id, &AssetInfo::native(denom))
+            {
+                cached_names.insert(denom.clone(), name);
+            }
+        }
+
+        let to_resolve = denoms
+            .iter()
+            .filter(|denom| !cached_names.contains_key(*denom))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut resolved = if to_resolve.is_empty() {
+            BTreeMap::new()
+        } else {
+            let chain_registry = self.chain_registry.get().await?;
+
+            // Native denoms are in kujira's own asset list, the rest went over IBC. Token factory
+            // denoms (`factory/kujira1.../uusk`) are named by the registry, which falls back to
+            // their subdenom.
+            let (registry_denoms, local_denoms): (Vec<_>, Vec<_>) =
+                to_resolve.into_iter().partition(|denom| {
+                    ibc_denom_hash(denom).is_some() || factory_denom_parts(denom).is_some()
+                });
+
+            let mut resolved = chain_registry
+                .resolve_native_assets(&self.clients, registry_denoms, self.query_concurrency)
+                .await;
+            for denom in local_denoms {
+                let name = chain_registry
+                    .name_local_asset(KUJIRA_CHAIN_NAME, &denom)
+                    .map(ResolvedAsset::from)
+                    .map_err(ResolveError::NoRegistryMatch);
+                resolved.insert(denom, name);
+            }
+            resolved
+        };
+
+        let mut ans_assets_to_add = vec![];
+        for denom in denoms {
+            let asset_info = AssetInfo::native(&denom);
+            let resolved = match cached_names.remove(&denom) {
+                Some(name) => ResolvedAsset::new(name),
+                None => match resolved.remove(&denom).expect("all denoms are resolved") {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        let reason = UnresolvedReason::from(err);
+                        log::warn!("Skipping native asset {}: {}", denom, reason);
+                        self.not_found_assets
+                            .push(UnresolvedAsset::new(asset_info, reason));
+                        continue;
+                    }
+                },
+            };
+
+            self.resolution_cache
+                .insert_resolved(&chain_id, &asset_info, &resolved);
+            let name = resolved.name;
+            self.denom_to_name.insert(denom, name.clone());
+            ans_assets_to_add.push((name, asset_info));
+        }
+
+        // LP tokens of the BOW vaults, named after the denoms of their market
+        for vault in self.load_vaults().await? {
+            let Some(market) = markets.iter().find(|m| m.contract == vault.market) else {
+                log::warn!(
+                    "Skipping BOW vault {} of unknown market {}",
+                    vault.contract,
+                    vault.market
+                );
+                continue;
+            };
+            if let Some(names) = self.asset_names(market) {
+                ans_assets_to_add.push((
+                    lp_token_name(BOW_DEX, &names),
+                    AssetInfo::native(vault.lp_denom()),
+                ));
+            }
+        }
+
+        Ok(ans_assets_to_add)
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        &self.not_found_assets
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] call because a denom wasn't
+    /// resolved.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        &self.skipped_pools
+    }
+
+    /// FIN and BOW contracts that were left out because their config couldn't be read.
+    pub fn unsupported_contracts(&self) -> &BTreeMap<Addr, String> {
+        &self.unsupported_contracts
+    }
+
+    /// The resolved names of the market's denoms, `None` if one isn't resolved.
+    fn asset_names(&self, market: &FinMarket) -> Option<Vec<String>> {
+        market
+            .denoms
+            .iter()
+            .map(|denom| self.denom_to_name.get(denom).cloned())
+            .collect()
+    }
+
+    /// All the instances of the deployment's FIN code ids, queried once and kept for the
+    /// lifetime of the scraper.
+    async fn load_markets(&mut self) -> anyhow::Result<Vec<FinMarket>> {
+        if self.loaded_markets.is_empty() {
+            let contracts = self
+                .contracts_by_codes(&self.deployment.fin_code_ids)
+                .await?;
+            let configs = self.query_configs(contracts).await;
+
+            let mut markets = vec![];
+            for (contract, config) in configs {
+                let config = config.and_then(|config| {
+                    let FinConfigResponse { denoms } = serde_json::from_slice(&config)?;
+                    denoms.iter().map(denom_from_value).collect()
+                });
+                match config {
+                    Ok(denoms) => markets.push(FinMarket { contract, denoms }),
+                    Err(err) => self.report_unsupported(contract, err),
+                }
+            }
+            self.loaded_markets = markets;
+        }
+
+        Ok(self.loaded_markets.clone())
+    }
+
+    /// All the instances of the deployment's BOW code ids, queried once and kept for the
+    /// lifetime of the scraper.
+    async fn load_vaults(&mut self) -> anyhow::Result<Vec<BowVault>> {
+        if self.loaded_vaults.is_empty() {
+            let contracts = self
+                .contracts_by_codes(&self.deployment.bow_code_ids)
+                .await?;
+            let configs = self.query_configs(contracts).await;
+
+            let mut vaults = vec![];
+            for (contract, config) in configs {
+                let config = config.and_then(|config| {
+                    let BowConfigResponse { fin_contract } = serde_json::from_slice(&config)?;
+                    Ok(fin_contract)
+                });
+                match config {
+                    Ok(market) => vaults.push(BowVault { contract, market }),
+                    Err(err) => self.report_unsupported(contract, err),
+                }
+            }
+            self.loaded_vaults = vaults;
+        }
+
+        Ok(self.loaded_vaults.clone())
+    }
+
+    fn report_unsupported(&mut self, contract: Addr, err: anyhow::Error) {
+        log::warn!("Skipping kujira contract {}: {:#}", contract, err);
+        self.unsupported_contracts
+            .insert(contract, format!("{:#}", err));
+    }
+
+    /// `Config {}` of every contract.
+    async fn query_configs(&self, contracts: Vec<Addr>) -> BTreeMap<Addr, anyhow::Result<Vec<u8>>> {
+        WasmQueryBatcher::new(&self.clients.wasm, self.query_concurrency)
+            .query_each(contracts, &ConfigQueryMsg::Config {})
+            .await
+    }
+
+    /// Addresses of all the contracts instantiated from `code_ids`.
+    async fn contracts_by_codes(&self, code_ids: &[u64]) -> anyhow::Result<Vec<Addr>> {
+        let client = WasmQueryClient::new(self.clients.channel.clone());
+        let mut contracts = vec![];
+        for &code_id in code_ids {
+            let mut next_key = vec![];
+            loop {
+                let request = QueryContractsByCodeRequest {
+                    code_id,
+                    pagination: Some(PageRequest {
+                        key: next_key,
+                        limit: CONTRACTS_PAGE_LIMIT,
+                        ..Default::default()
+                    }),
+                };
+                let response = retry(format!("contracts of code {}", code_id), || {
+                    let (mut client, request) = (client.clone(), request.clone());
+                    async move {
+                        client
+                            .contracts_by_code(request)
+                            .await
+                            .map_err(anyhow::Error::from)
+                    }
+                })
+                .await
+                .with_context(|| format!("querying contracts of code {}", code_id))?
+                .into_inner();
+                contracts.extend(response.contracts.into_iter().map(Addr::unchecked));
+                match response.pagination {
+                    Some(page) if !page.next_key.is_empty() => next_key = page.next_key,
+                    _ => break,
+                }
+            }
+        }
+        Ok(contracts)
+    }
+}
+
+/// Denom of a FIN config, either a plain string or `{"native": denom}`.
+fn denom_from_value(value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::String(denom) => Ok(denom.clone()),
+        Value::Object(denom) => match denom.get("native") {
+            Some(Value::String(denom)) => Ok(denom.clone()),
+            _ => anyhow::bail!("unsupported denom {}", value),
+        },
+        _ => anyhow::bail!("unsupported denom {}", value),
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for KujiraScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(self
+            .resolve_assets()
+            .await?
+            .into_iter()
+            .map(|(entry, info)| AnsAsset::new(entry, info))
+            .collect())
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for KujiraScraper {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.skipped_pools.clear();
+        let markets = self.load_markets().await?;
+
+        let Some(fin_pool_type) = self.fin_pool_type.clone() else {
+            log::warn!(
+                "No fin_pool_type in the kujira deployment of {}, skipping the FIN markets",
+                self.clients.chain_id
+            );
+            self.skipped_pools.extend(markets.into_iter().map(|market| {
+                SkippedPool::unsupported_pair_type(
+                    UncheckedPoolAddress::contract(market.contract),
+                    FIN_PAIR_TYPE,
+                )
+            }));
+            return Ok(vec![]);
+        };
+
+        let mut ans_pools_to_add = vec![];
+        for market in markets {
+            let pool_id = UncheckedPoolAddress::contract(market.contract.clone());
+            let Some(names) = self.asset_names(&market) else {
+                let missing = market
+                    .denoms
+                    .iter()
+                    .filter(|denom| !self.denom_to_name.contains_key(*denom))
+                    .map(AssetInfo::native)
+                    .collect();
+                self.skipped_pools
+                    .push(SkippedPool::missing_assets(pool_id, missing));
+                continue;
+            };
+
+            let pool_metadata = PoolMetadata {
+                dex: FIN_DEX.to_string(),
+                pool_type: fin_pool_type.clone(),
+                assets: names.into_iter().map(AssetEntry::from).collect(),
+            };
+            ans_pools_to_add.push((pool_id, pool_metadata));
+        }
+
+        Ok(ans_pools_to_add)
+    }
+
+    fn report(&self) -> DexReport {
+        DexReport::new(
+            FIN_DEX,
+            self.not_found_assets.clone(),
+            self.skipped_pools.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::kujira::{config, market, scraper_of};
+
+    #[test]
+    fn deployment_is_read_from_the_config() {
+        assert_eq!(
+            KujiraScraper::deployment(&config(), "kaiyo-1").unwrap(),
+            KujiraDeployment {
+                fin_code_ids: vec![1],
+                bow_code_ids: vec![],
+                fin_pool_type: Some("constant_product".to_string()),
+            }
+        );
+
+        let err = KujiraScraper::deployment(&ScraperConfig::default_for("harpoon-4"), "harpoon-4")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ScraperError::DeploymentKeyMissing { ref protocol, .. } if protocol == KUJIRA_CHAIN_NAME
+        ));
+    }
+
+    #[tokio::test]
+    async fn markets_are_mapped_to_pools_of_the_fin_pool_type() {
+        let mut scraper = scraper_of(
+            vec![
+                market("kuji_usk", &["ukuji", "uusk"]),
+                market("kuji_unknown", &["ukuji", "unknown"]),
+            ],
+            vec![
+                BowVault {
+                    contract: Addr::unchecked("bow_vault"),
+                    market: Addr::unchecked("kuji_usk"),
+                },
+                // the market is skipped, so is the LP token of its vault
+                BowVault {
+                    contract: Addr::unchecked("bow_unknown"),
+                    market: Addr::unchecked("kuji_unknown"),
+                },
+            ],
+        );
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets,
+            [
+                AnsAsset::new("kujira>kuji", AssetInfo::native("ukuji")),
+                AnsAsset::new("kujira>usk", AssetInfo::native("uusk")),
+                AnsAsset::new(
+                    "bow/kujira>kuji,kujira>usk",
+                    AssetInfo::native("factory/bow_vault/ulp")
+                ),
+            ]
+        );
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(
+            pools,
+            [(
+                UncheckedPoolAddress::contract("kuji_usk"),
+                PoolMetadata {
+                    dex: FIN_DEX.to_string(),
+                    pool_type: PoolType::ConstantProduct,
+                    assets: vec![
+                        AssetEntry::new("kujira>kuji"),
+                        AssetEntry::new("kujira>usk")
+                    ],
+                }
+            )]
+        );
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("kuji_unknown"),
+                vec![AssetInfo::native("unknown")]
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn markets_are_skipped_without_a_fin_pool_type() {
+        let mut scraper = scraper_of(vec![market("kuji_usk", &["ukuji", "uusk"])], vec![]);
+        scraper.fin_pool_type = None;
+
+        assert!(scraper.fetch_dex_pools().await.unwrap().is_empty());
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::unsupported_pair_type(
+                UncheckedPoolAddress::contract("kuji_usk"),
+                FIN_PAIR_TYPE
+            )]
+        );
+    }
+
+    #[test]
+    fn fin_pool_type_is_read_from_the_deployment() {
+        let mut deployment = KujiraDeployment::default();
+        assert_eq!(deployment.fin_pool_type().unwrap(), None);
+
+        deployment.fin_pool_type = Some("constant_product".to_string());
+        assert_eq!(
+            deployment.fin_pool_type().unwrap(),
+            Some(PoolType::ConstantProduct)
+        );
+
+        deployment.fin_pool_type = Some("stable".to_string());
+        assert_eq!(deployment.fin_pool_type().unwrap(), Some(PoolType::Stable));
+
+        deployment.fin_pool_type = Some("orderbook".to_string());
+        assert!(deployment.fin_pool_type().is_err());
+
+        let mut config = config();
+        config
+            .kujira_deployments
+            .get_mut("kaiyo-1")
+            .unwrap()
+            .fin_pool_type = Some("orderbook".to_string());
+        assert!(KujiraScraper::new(
+            ChainClients::offline("kaiyo-1"),
+            LazyChainRegistry::new(),
+            &config
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn denoms_of_the_fin_config_are_read() {
+        assert_eq!(
+            denom_from_value(&serde_json::json!("ukuji")).unwrap(),
+            "ukuji"
+        );
+        assert_eq!(
+            denom_from_value(&serde_json::json!({"native": "uusk"})).unwrap(),
+            "uusk"
+        );
+        assert!(denom_from_value(&serde_json::json!({"cw20": "kujira1..."})).is_err());
+    }
+}
@@ -1 +1,9 @@
 pub mod astroport;
+mod factory;
+pub mod kujira;
+pub mod osmosis;
+pub mod terraswap;
+pub mod whitewhale;
+pub mod wyndex;
+
+pub use factory::{FactoryAssetInfo, FactoryToken};
@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use anyhow::Context;
+use async_trait::async_trait;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use osmosis_std::shim::Any;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ConcentratedPool;
+use osmosis_std::types::osmosis::cosmwasmpool::v1beta1::CosmWasmPool;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableswapPool;
+use osmosis_std::types::osmosis::gamm::v1beta1::Pool as BalancerPool;
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{AllPoolsRequest, AllPoolsResponse};
+use prost::Message;
+
+use crate::config::ScraperConfig;
+use crate::error::ScraperError;
+use crate::helpers::chain_registry::{ResolveError, ResolvedAsset};
+use crate::helpers::clients::ChainClients;
+use crate::helpers::denoms::{factory_denom_parts, ibc_denom_hash, pool_share_id};
+use crate::helpers::grpc::grpc_query;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset, UnresolvedReason};
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::LazyChainRegistry;
+
+const OSMOSIS_DEX: &str = "osmosis";
+
+/// Chain name of osmosis in the chain registry, also the ANS prefix of its native assets.
+const OSMOSIS_CHAIN_NAME: &str = "osmosis";
+
+/// Networks of osmosis.
+const OSMOSIS_NETWORKS: [&str; 2] = ["osmosis-1", "osmo-test-5"];
+
+const ALL_POOLS_PATH: &str = "/osmosis.poolmanager.v1beta1.Query/AllPools";
+
+const BALANCER_POOL_TYPE_URL: &str = "/osmosis.gamm.v1beta1.Pool";
+const STABLESWAP_POOL_TYPE_URL: &str = "/osmosis.gamm.poolmodels.stableswap.v1beta1.Pool";
+const CONCENTRATED_POOL_TYPE_URL: &str = "/osmosis.concentratedliquidity.v1beta1.Pool";
+const COSMWASM_POOL_TYPE_URL: &str = "/osmosis.cosmwasmpool.v1beta1.CosmWasmPool";
+
+/// A pool of the osmosis pool manager, reduced to what ANS needs.
+#[derive(Debug, Clone)]
+pub(crate) struct OsmosisPool {
+    pub(crate) id: u64,
+    pub(crate) pool_type: PoolType,
+    pub(crate) denoms: Vec<String>,
+}
+
+pub struct OsmosisScraper {
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    pub(crate) loaded_pools: Vec<OsmosisPool>,
+    denom_to_name: HashMap<String, String>,
+    not_found_assets: Vec<UnresolvedAsset>,
+    skipped_pools: Vec<SkippedPool>,
+    /// Pools of a type ANS has no pool type for (cosmwasm pools, ...), by type url
+    unsupported_pools: BTreeMap<String, Vec<u64>>,
+    query_concurrency: usize,
+}
+
+impl DexId for OsmosisScraper {
+    fn dex_id(&self) -> &'static str {
+        OSMOSIS_DEX
+    }
+}
+
+impl OsmosisScraper {
+    /// Create the scraper for the chain. The chain registry is only fetched once a denom has to
+    /// be resolved.
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        config.dex_ans_prefix(OSMOSIS_DEX, &OSMOSIS_NETWORKS, &clients.chain_id)?;
+
+        Ok(Self {
+            clients,
+            chain_registry: chain_registry.into(),
+            resolution_cache: Default::default(),
+            loaded_pools: vec![],
+            denom_to_name: HashMap::new(),
+            not_found_assets: vec![],
+            skipped_pools: vec![],
+            unsupported_pools: BTreeMap::new(),
+            query_concurrency: config.query_concurrency,
+        })
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(OSMOSIS_DEX, &OSMOSIS_NETWORKS, chain_id)
+    }
+
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.resolution_cache = resolution_cache;
+        self
+    }
+
+    /// Set the number of denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.query_concurrency = concurrency;
+        self
+    }
+
+    /// Resolve the ANS names of all the denoms in the pools.
+    ///
+    /// Resolved names are remembered so [`DexScraper::fetch_dex_pools`] can build the pool metadata,
+    /// denoms that can't be named are available through [`Self::not_found_assets`].
+    pub async fn resolve_assets(&mut self) -> anyhow::Result<Vec<(String, AssetInfo)>> {
+        self.not_found_assets.clear();
+        self.denom_to_name.clear();
+
+        let mut denoms = vec![];
+        for pool in self.load_pools().await? {
+            for denom in pool.denoms {
+                if !denoms.contains(&denom) {
+                    denoms.push(denom);
+                }
+            }
+        }
+
+        let chain_id = self.clients.chain_id.clone();
+        let mut cached_names = HashMap::new();
+        for denom in &denoms {
+            if let Some(name) = self
+                .resolution_cache
+                .get(&chain_id, &AssetInfo::native(denom))
+            {
+                cached_names.insert(denom.clone(), name);
+            }
+        }
+
+        let to_resolve = denoms
+            .iter()
+            .filter(|denom| !cached_names.contains_key(*denom))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut resolved = if to_resolve.is_empty() {
+            BTreeMap::new()
+        } else {
+            let chain_registry = self.chain_registry.get().await?;
+
+            // Denoms issued on osmosis itself are in its own asset list, the rest went over IBC.
+            // The registry also names the token factory denoms and skips the pool shares.
+            let (registry_denoms, local_denoms): (Vec<_>, Vec<_>) =
+                to_resolve.into_iter().partition(|denom| {
+                    ibc_denom_hash(denom).is_some()
+                        || factory_denom_parts(denom).is_some()
+                        || pool_share_id(denom).is_some()
+                });
+
+            let mut resolved = chain_registry
+                .resolve_native_assets(&self.clients, registry_denoms, self.query_concurrency)
+                .await;
+            for denom in local_denoms {
+                let name = chain_registry
+                    .name_local_asset(OSMOSIS_CHAIN_NAME, &denom)
+                    .map(ResolvedAsset::from)
+                    .map_err(ResolveError::NoRegistryMatch);
+                resolved.insert(denom, name);
+            }
+            resolved
+        };
+
+        let mut ans_assets_to_add = vec![];
+        for denom in denoms {
+            let asset_info = AssetInfo::native(&denom);
+            let resolved = match cached_names.remove(&denom) {
+                Some(name) => ResolvedAsset::new(name),
+                None => match resolved.remove(&denom).expect("all denoms are resolved") {
+                    Ok(resolved) => resolved,
+                    Err(err) => {
+                        let reason = UnresolvedReason::from(err);
+                        log::warn!("Skipping native asset {}: {}", denom, reason);
+                        self.not_found_assets
+                            .push(UnresolvedAsset::new(asset_info, reason));
+                        continue;
+                    }
+                },
+            };
+
+            self.resolution_cache
+                .insert_resolved(&chain_id, &asset_info, &resolved);
+            let name = resolved.name;
+            self.denom_to_name.insert(denom, name.clone());
+            ans_assets_to_add.push((name, asset_info));
+        }
+
+        Ok(ans_assets_to_add)
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        &self.not_found_assets
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] call because a denom wasn't
+    /// resolved.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        &self.skipped_pools
+    }
+
+    /// Pool ids of the pool types that have no ANS pool type, by type url.
+    pub fn unsupported_pools(&self) -> &BTreeMap<String, Vec<u64>> {
+        &self.unsupported_pools
+    }
+
+    /// All the pools of the pool manager, queried once and kept for the lifetime of the scraper.
+    async fn load_pools(&mut self) -> anyhow::Result<Vec<OsmosisPool>> {
+        if self.loaded_pools.is_empty() {
+            let AllPoolsResponse { pools } = grpc_query::<_, AllPoolsResponse>(
+                self.clients.channel.clone(),
+                ALL_POOLS_PATH,
+                AllPoolsRequest {},
+            )
+            .await
+            .context("querying osmosis pools")?;
+
+            self.unsupported_pools.clear();
+            let mut loaded_pools = Vec::with_capacity(pools.len());
+            for pool in pools {
+                match (decode_pool(&pool)?, unsupported_pool_id(&pool)) {
+                    (Some(pool), _) => loaded_pools.push(pool),
+                    (None, Some(id)) => self
+                        .unsupported_pools
+                        .entry(pool.type_url)
+                        .or_default()
+                        .push(id),
+                    (None, None) => {
+                        log::warn!("Skipping a pool of unknown type {}", pool.type_url)
+                    }
+                }
+            }
+            for (type_url, ids) in &self.unsupported_pools {
+                log::warn!("Skipping {} pools of type {}", ids.len(), type_url);
+            }
+            self.loaded_pools = loaded_pools;
+        }
+
+        Ok(self.loaded_pools.clone())
+    }
+}
+
+/// Decode a pool of the pool manager, `None` if ANS has no pool type for it.
+fn decode_pool(pool: &Any) -> anyhow::Result<Option<OsmosisPool>> {
+    let decode_err = || format!("decoding osmosis pool of type {}", pool.type_url);
+    let pool = match pool.type_url.as_str() {
+        BALANCER_POOL_TYPE_URL => {
+            let pool = BalancerPool::decode(pool.value.as_slice()).with_context(decode_err)?;
+            OsmosisPool {
+                id: pool.id,
+                pool_type: PoolType::Weighted,
+                denoms: pool
+                    .pool_assets
+                    .into_iter()
+                    .filter_map(|asset| asset.token.map(|coin| coin.denom))
+                    .collect(),
+            }
+        }
+        STABLESWAP_POOL_TYPE_URL => {
+            let pool = StableswapPool::decode(pool.value.as_slice()).with_context(decode_err)?;
+            OsmosisPool {
+                id: pool.id,
+                pool_type: PoolType::Stable,
+                denoms: pool
+                    .pool_liquidity
+                    .into_iter()
+                    .map(|coin| coin.denom)
+                    .collect(),
+            }
+        }
+        CONCENTRATED_POOL_TYPE_URL => {
+            let pool = ConcentratedPool::decode(pool.value.as_slice()).with_context(decode_err)?;
+            OsmosisPool {
+                id: pool.id,
+                pool_type: PoolType::ConcentratedLiquidity,
+                denoms: vec![pool.token0, pool.token1],
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(pool))
+}
+
+/// Id of a pool ANS has no pool type for, `None` if its type is unknown or it can't be decoded.
+fn unsupported_pool_id(pool: &Any) -> Option<u64> {
+    match pool.type_url.as_str() {
+        COSMWASM_POOL_TYPE_URL => CosmWasmPool::decode(pool.value.as_slice())
+            .ok()
+            .map(|pool| pool.pool_id),
+        _ => None,
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for OsmosisScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(self
+            .resolve_assets()
+            .await?
+            .into_iter()
+            .map(|(entry, info)| AnsAsset::new(entry, info))
+            .collect())
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for OsmosisScraper {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.skipped_pools.clear();
+        let mut ans_pools_to_add = vec![];
+
+        for pool in self.load_pools().await? {
+            let pool_id = UncheckedPoolAddress::id(pool.id);
+            let assets = pool
+                .denoms
+                .iter()
+                .map(|denom| self.denom_to_name.get(denom).cloned().map(AssetEntry::from))
+                .collect::<Option<Vec<_>>>();
+
+            let Some(assets) = assets else {
+                let missing = pool
+                    .denoms
+                    .iter()
+                    .filter(|denom| !self.denom_to_name.contains_key(*denom))
+                    .map(AssetInfo::native)
+                    .collect();
+                self.skipped_pools
+                    .push(SkippedPool::missing_assets(pool_id, missing));
+                continue;
+            };
+
+            let pool_metadata = PoolMetadata {
+                dex: OSMOSIS_DEX.to_string(),
+                pool_type: pool.pool_type,
+                assets,
+            };
+            ans_pools_to_add.push((pool_id, pool_metadata));
+        }
+
+        Ok(ans_pools_to_add)
+    }
+
+    fn report(&self) -> DexReport {
+        // Pools of unsupported types are never loaded, they're only known by id
+        let unsupported = self.unsupported_pools.iter().flat_map(|(type_url, ids)| {
+            ids.iter().map(|id| {
+                SkippedPool::unsupported_pair_type(UncheckedPoolAddress::id(*id), type_url)
+            })
+        });
+        DexReport::new(
+            OSMOSIS_DEX,
+            self.not_found_assets.clone(),
+            self.skipped_pools
+                .iter()
+                .cloned()
+                .chain(unsupported)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use osmosis_std::types::cosmos::base::v1beta1::Coin;
+    use osmosis_std::types::osmosis::gamm::v1beta1::PoolAsset;
+
+    use super::*;
+    use crate::test_utils::osmosis::{pool, scraper_of};
+
+    fn coin(denom: &str) -> Coin {
+        Coin {
+            denom: denom.to_string(),
+            amount: "1000000".to_string(),
+        }
+    }
+
+    fn any(type_url: &str, value: impl Message) -> Any {
+        Any {
+            type_url: type_url.to_string(),
+            value: value.encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn pools_are_decoded_to_their_pool_type() {
+        // the pools start with their address, the ids are in later fields
+        let balancer = BalancerPool {
+            address: "osmo1balancer".to_string(),
+            id: 1,
+            pool_assets: vec![
+                PoolAsset {
+                    token: Some(coin("uosmo")),
+                    weight: "1".to_string(),
+                },
+                PoolAsset {
+                    token: Some(coin("uion")),
+                    weight: "1".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+        let pool = decode_pool(&any(BALANCER_POOL_TYPE_URL, balancer))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (pool.id, pool.pool_type, pool.denoms),
+            (
+                1,
+                PoolType::Weighted,
+                vec!["uosmo".to_string(), "uion".to_string()]
+            )
+        );
+
+        let stableswap = StableswapPool {
+            address: "osmo1stableswap".to_string(),
+            id: 2,
+            pool_liquidity: vec![coin("uosmo"), coin("uion")],
+            ..Default::default()
+        };
+        let pool = decode_pool(&any(STABLESWAP_POOL_TYPE_URL, stableswap))
+            .unwrap()
+            .unwrap();
+        assert_eq!((pool.id, pool.pool_type), (2, PoolType::Stable));
+
+        let concentrated = ConcentratedPool {
+            address: "osmo1concentrated".to_string(),
+            id: 3,
+            token0: "uosmo".to_string(),
+            token1: "uion".to_string(),
+            ..Default::default()
+        };
+        let pool = decode_pool(&any(CONCENTRATED_POOL_TYPE_URL, concentrated))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (pool.id, pool.pool_type, pool.denoms),
+            (
+                3,
+                PoolType::ConcentratedLiquidity,
+                vec!["uosmo".to_string(), "uion".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn pools_of_other_types_are_only_known_by_id() {
+        let cosmwasm = any(
+            COSMWASM_POOL_TYPE_URL,
+            CosmWasmPool {
+                contract_address: "osmo1pool".to_string(),
+                pool_id: 4,
+                code_id: 503,
+                ..Default::default()
+            },
+        );
+        assert!(decode_pool(&cosmwasm).unwrap().is_none());
+        assert_eq!(unsupported_pool_id(&cosmwasm), Some(4));
+
+        // the ids of other pool types are in other fields, so unknown types have no id
+        let unknown = any(
+            "/osmosis.unknown.v1beta1.Pool",
+            CosmWasmPool {
+                pool_id: 5,
+                ..Default::default()
+            },
+        );
+        assert!(decode_pool(&unknown).unwrap().is_none());
+        assert_eq!(unsupported_pool_id(&unknown), None);
+    }
+
+    #[tokio::test]
+    async fn pools_with_pool_shares_or_unnamed_denoms_are_skipped() {
+        let mut scraper = scraper_of(vec![
+            pool(1, PoolType::Weighted, &["uosmo", "uion"]),
+            pool(2, PoolType::Weighted, &["uosmo", "gamm/pool/1"]),
+            pool(3, PoolType::Stable, &["uosmo", "uunknown"]),
+        ]);
+        scraper
+            .unsupported_pools
+            .insert(COSMWASM_POOL_TYPE_URL.to_string(), vec![4]);
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets,
+            [
+                AnsAsset::new("osmosis>osmo", AssetInfo::native("uosmo")),
+                AnsAsset::new("osmosis>ion", AssetInfo::native("uion")),
+            ]
+        );
+        let reasons: Vec<(&AssetInfo, &UnresolvedReason)> = scraper
+            .not_found_assets()
+            .iter()
+            .map(|asset| (&asset.info, &asset.reason))
+            .collect();
+        assert!(matches!(
+            reasons[..],
+            [
+                (_, UnresolvedReason::PoolShare(ref pool_id)),
+                (_, UnresolvedReason::NoRegistryMatch(_)),
+            ] if pool_id == "1"
+        ));
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(
+            pools,
+            [(
+                UncheckedPoolAddress::id(1),
+                PoolMetadata {
+                    dex: OSMOSIS_DEX.to_string(),
+                    pool_type: PoolType::Weighted,
+                    assets: vec![
+                        AssetEntry::new("osmosis>osmo"),
+                        AssetEntry::new("osmosis>ion")
+                    ],
+                }
+            )]
+        );
+        assert_eq!(
+            scraper.report().skipped_pools,
+            [
+                SkippedPool::missing_assets(
+                    UncheckedPoolAddress::id(2),
+                    vec![AssetInfo::native("gamm/pool/1")]
+                ),
+                SkippedPool::missing_assets(
+                    UncheckedPoolAddress::id(3),
+                    vec![AssetInfo::native("uunknown")]
+                ),
+                SkippedPool::unsupported_pair_type(
+                    UncheckedPoolAddress::id(4),
+                    COSMWASM_POOL_TYPE_URL
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, PoolType};
+use async_trait::async_trait;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ScraperConfig;
+use crate::dexes::factory::{ans_assets, FactoryAssetInfo, FactoryPairInfo, FactoryPairs};
+use crate::error::ScraperError;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::pagination::FactoryPair;
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset};
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::LazyChainRegistry;
+
+/// Dex id of the original terraswap deployment.
+pub const TERRASWAP_DEX: &str = "terraswap";
+
+/// Networks with terraswap or one of its forks.
+const TERRASWAP_NETWORKS: [&str; 4] = ["phoenix-1", "pisco-1", "migaloo-1", "narwhal-1"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerraswapPairInfo {
+    pub asset_infos: Vec<FactoryAssetInfo>,
+    pub contract_addr: Addr,
+    pub liquidity_token: Addr,
+}
+
+impl FactoryPair for TerraswapPairInfo {
+    type AssetInfo = FactoryAssetInfo;
+
+    fn contract_addr(&self) -> &Addr {
+        &self.contract_addr
+    }
+
+    fn asset_infos(&self) -> &[FactoryAssetInfo] {
+        &self.asset_infos
+    }
+}
+
+impl FactoryPairInfo for TerraswapPairInfo {
+    fn liquidity_token(&self) -> AssetInfo {
+        AssetInfo::cw20(self.liquidity_token.clone())
+    }
+
+    fn pool_type(&self) -> Result<PoolType, String> {
+        Ok(PoolType::ConstantProduct)
+    }
+
+    fn pair_type(&self) -> String {
+        "xyk".to_string()
+    }
+}
+
+/// Scraper of a terraswap-compatible factory, terraswap itself or one of its forks.
+pub struct TerraswapScraper {
+    dex_id: &'static str,
+    pub(crate) pairs: FactoryPairs<TerraswapPairInfo>,
+}
+
+impl DexId for TerraswapScraper {
+    fn dex_id(&self) -> &'static str {
+        self.dex_id
+    }
+}
+
+impl TerraswapScraper {
+    /// Create the scraper for the pairs of `factory`, registered under `dex_id` (e.g.
+    /// [`TERRASWAP_DEX`]). Forks are supported on the networks of terraswap and on those `config`
+    /// has a deployment of `dex_id` on.
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        dex_id: &'static str,
+        factory: Addr,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let ans_prefix = config.dex_ans_prefix(dex_id, &TERRASWAP_NETWORKS, &clients.chain_id)?;
+
+        Ok(Self {
+            dex_id,
+            pairs: FactoryPairs::new(
+                clients,
+                chain_registry.into(),
+                dex_id,
+                ans_prefix,
+                factory,
+                config,
+            ),
+        })
+    }
+
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.pairs.set_resolution_cache(resolution_cache);
+        self
+    }
+
+    /// Set the number of cw20 `TokenInfo` and denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.pairs.set_query_concurrency(concurrency);
+        self
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(TERRASWAP_DEX, &TERRASWAP_NETWORKS, chain_id)
+    }
+
+    /// Resolve the ANS names of all the assets in the pairs, plus the LP tokens of the pairs
+    /// whose assets all resolved.
+    pub async fn resolve_assets(&mut self) -> Result<Vec<(String, AssetInfo)>, ScraperError> {
+        self.pairs.resolve_assets().await
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        self.pairs.not_found_assets()
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] call because an asset wasn't
+    /// resolved.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        self.pairs.skipped_pools()
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for TerraswapScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(ans_assets(self.resolve_assets().await?))
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for TerraswapScraper {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.pairs.fetch_dex_pools().await
+    }
+
+    fn report(&self) -> DexReport {
+        self.pairs.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::AssetEntry;
+    use cw_asset::AssetInfoUnchecked;
+
+    use super::*;
+    use crate::helpers::pagination::PairPages;
+    use crate::test_utils::terraswap::{native, pair, scraper_of, token};
+    use crate::test_utils::{registry_from_lists, AssetListBuilder};
+
+    #[tokio::test]
+    async fn other_networks_are_unsupported() {
+        let scraper = TerraswapScraper::new(
+            ChainClients::offline("osmosis-1"),
+            LazyChainRegistry::from(registry_from_lists(vec![])),
+            TERRASWAP_DEX,
+            Addr::unchecked("factory"),
+            &ScraperConfig::default_for("osmosis-1"),
+        );
+        assert!(matches!(
+            scraper,
+            Err(ScraperError::NetworkUnsupported(
+                NetworkError::UnsupportedDex { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn pairs_are_named_under_the_dex_id() {
+        let mut scraper = scraper_of(
+            "terraswap_fork",
+            vec![pair(
+                "luna_astro",
+                vec![native("uluna"), token("astro_token")],
+            )],
+        );
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets,
+            [
+                AnsAsset::new(
+                    "terra2>astro",
+                    AssetInfo::cw20(Addr::unchecked("astro_token"))
+                ),
+                AnsAsset::new("terra2>luna", AssetInfo::native("uluna")),
+                AnsAsset::new(
+                    "terraswap_fork/terra2>astro,terra2>luna",
+                    AssetInfo::cw20(Addr::unchecked("luna_astro_lp"))
+                ),
+            ]
+        );
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(
+            pools,
+            [(
+                UncheckedPoolAddress::contract("luna_astro"),
+                PoolMetadata {
+                    dex: "terraswap_fork".to_string(),
+                    pool_type: PoolType::ConstantProduct,
+                    assets: vec![
+                        AssetEntry::new("terra2>luna"),
+                        AssetEntry::new("terra2>astro")
+                    ],
+                }
+            )]
+        );
+        assert_eq!(scraper.report().dex, "terraswap_fork");
+    }
+
+    #[tokio::test]
+    async fn assets_of_several_pairs_are_resolved_once() {
+        let mut scraper = scraper_of(
+            TERRASWAP_DEX,
+            vec![
+                pair("first", vec![native("uluna"), token("astro_token")]),
+                pair("second", vec![token("astro_token"), native("uluna")]),
+            ],
+        );
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        let names: Vec<&str> = assets.iter().map(|asset| asset.entry.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "terra2>astro",
+                "terra2>luna",
+                "terraswap/terra2>astro,terra2>luna",
+            ]
+        );
+
+        // the factory's last pair is kept without creation heights
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].0, UncheckedPoolAddress::contract("second"));
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::duplicate_of(
+                UncheckedPoolAddress::contract("first"),
+                UncheckedPoolAddress::contract("second")
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn pairs_with_unnamed_assets_are_skipped() {
+        let mut scraper = scraper_of(
+            TERRASWAP_DEX,
+            vec![
+                pair("named", vec![native("uluna"), token("astro_token")]),
+                pair("unnamed", vec![native("uluna"), native("unknown")]),
+            ],
+        );
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert!(!assets
+            .iter()
+            .any(|asset| asset.info == AssetInfoUnchecked::native("unknown")));
+        assert_eq!(
+            scraper
+                .not_found_assets()
+                .iter()
+                .map(|asset| &asset.info)
+                .collect::<Vec<_>>(),
+            [&AssetInfo::native("unknown")]
+        );
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].0, UncheckedPoolAddress::contract("named"));
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("unnamed"),
+                vec![AssetInfo::native("unknown")]
+            )]
+        );
+    }
+
+    #[test]
+    fn pagination_stops_when_the_factory_repeats_a_page() {
+        let page = vec![
+            pair("first", vec![native("uluna"), token("astro_token")]),
+            pair("second", vec![native("uluna"), token("other_token")]),
+        ];
+        let mut pages = PairPages::new(TERRASWAP_DEX, "phoenix-1", None, 10);
+
+        assert_eq!(pages.advance(page.clone()).len(), 2);
+        assert_eq!(
+            pages.cursor(),
+            Some(Some(vec![native("uluna"), token("other_token")]))
+        );
+        // the node ignores the cursor and answers the first page again
+        assert!(pages.advance(page).is_empty());
+        assert_eq!(pages.cursor(), None);
+    }
+}
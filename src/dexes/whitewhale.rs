@@ -0,0 +1,426 @@
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, PoolType};
+use anyhow::Context;
+use async_trait::async_trait;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::ScraperConfig;
+use crate::dexes::factory::{ans_assets, FactoryAssetInfo, FactoryPairInfo, FactoryPairs};
+use crate::error::ScraperError;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::names::lp_token_name;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::pagination::FactoryPair;
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset};
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::LazyChainRegistry;
+
+pub(crate) const WHITE_WHALE_DEX: &str = "whitewhale";
+
+/// Networks with a White Whale deployment.
+const WHITE_WHALE_NETWORKS: [&str; 5] = ["phoenix-1", "pisco-1", "juno-1", "uni-6", "chihuahua-1"];
+
+/// Key of the pool factory in the configured deployment addresses of white whale.
+pub(crate) const POOL_FACTORY_KEY: &str = "pool_factory_address";
+/// Key of the incentive factory in the configured deployment addresses of white whale.
+const INCENTIVE_FACTORY_KEY: &str = "incentive_factory_address";
+
+/// Contracts of a white whale deployment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhiteWhaleDeployment {
+    pub pool_factory: Addr,
+    /// Factory of the incentive (staking) contracts of the pairs
+    pub incentive_factory: Option<Addr>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhiteWhalePairInfo {
+    pub asset_infos: Vec<FactoryAssetInfo>,
+    pub contract_addr: Addr,
+    pub liquidity_token: FactoryAssetInfo,
+    /// `"constant_product"` or `{"stable_swap": {"amp": ..}}`
+    pub pair_type: Value,
+}
+
+impl FactoryPair for WhiteWhalePairInfo {
+    type AssetInfo = FactoryAssetInfo;
+
+    fn contract_addr(&self) -> &Addr {
+        &self.contract_addr
+    }
+
+    fn asset_infos(&self) -> &[FactoryAssetInfo] {
+        &self.asset_infos
+    }
+}
+
+impl FactoryPairInfo for WhiteWhalePairInfo {
+    fn liquidity_token(&self) -> AssetInfo {
+        AssetInfo::from(&self.liquidity_token)
+    }
+
+    fn pool_type(&self) -> Result<PoolType, String> {
+        pool_type(&self.pair_type).ok_or_else(|| pair_type_name(&self.pair_type))
+    }
+
+    fn pair_type(&self) -> String {
+        pair_type_name(&self.pair_type)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum IncentiveFactoryQueryMsg {
+    Incentive { lp_asset: FactoryAssetInfo },
+}
+
+pub struct WhiteWhaleScraper {
+    deployment: WhiteWhaleDeployment,
+    pub(crate) pairs: FactoryPairs<WhiteWhalePairInfo>,
+}
+
+impl DexId for WhiteWhaleScraper {
+    fn dex_id(&self) -> &'static str {
+        WHITE_WHALE_DEX
+    }
+}
+
+impl WhiteWhaleScraper {
+    /// Create the scraper for the chain, with the deployment of the configured white whale
+    /// deployment addresses.
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let chain_id = clients.chain_id.clone();
+        let ans_prefix =
+            config.dex_ans_prefix(WHITE_WHALE_DEX, &WHITE_WHALE_NETWORKS, &chain_id)?;
+        let deployment = Self::deployment(config, &chain_id)?;
+
+        Ok(Self {
+            pairs: FactoryPairs::new(
+                clients,
+                chain_registry.into(),
+                WHITE_WHALE_DEX,
+                ans_prefix,
+                deployment.pool_factory.clone(),
+                config,
+            ),
+            deployment,
+        })
+    }
+
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.pairs.set_resolution_cache(resolution_cache);
+        self
+    }
+
+    /// Set the number of cw20 `TokenInfo` and denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.pairs.set_query_concurrency(concurrency);
+        self
+    }
+
+    /// Use another deployment than the configured one.
+    pub fn with_deployment(mut self, deployment: WhiteWhaleDeployment) -> Self {
+        self.pairs.set_factory(deployment.pool_factory.clone());
+        self.deployment = deployment;
+        self
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(WHITE_WHALE_DEX, &WHITE_WHALE_NETWORKS, chain_id)
+    }
+
+    /// Deployment of the chain from the `pool_factory_address` and the optional
+    /// `incentive_factory_address` of the white whale deployment addresses of `config`.
+    pub fn deployment(
+        config: &ScraperConfig,
+        chain_id: &str,
+    ) -> Result<WhiteWhaleDeployment, ScraperError> {
+        let addresses = config.configured_addresses(WHITE_WHALE_DEX, chain_id);
+        let Some(pool_factory) = addresses.and_then(|addresses| addresses.get(POOL_FACTORY_KEY))
+        else {
+            return Err(ScraperError::DeploymentKeyMissing {
+                protocol: WHITE_WHALE_DEX.to_string(),
+                key: POOL_FACTORY_KEY.to_string(),
+                available: addresses
+                    .map(|addresses| addresses.keys().cloned().collect())
+                    .unwrap_or_default(),
+            });
+        };
+        let incentive_factory =
+            addresses.and_then(|addresses| addresses.get(INCENTIVE_FACTORY_KEY));
+
+        Ok(WhiteWhaleDeployment {
+            pool_factory: Addr::unchecked(pool_factory),
+            incentive_factory: incentive_factory.map(Addr::unchecked),
+        })
+    }
+
+    /// Resolve the ANS names of all the assets in the pairs, plus the LP tokens of the pairs
+    /// whose assets all resolved.
+    pub async fn resolve_assets(&mut self) -> Result<Vec<(String, AssetInfo)>, ScraperError> {
+        self.pairs.resolve_assets().await
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        self.pairs.not_found_assets()
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] call because an asset wasn't
+    /// resolved or the pair type is not supported by ANS.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        self.pairs.skipped_pools()
+    }
+}
+
+/// ANS pool type of a white whale pair type.
+fn pool_type(pair_type: &Value) -> Option<PoolType> {
+    match pair_type {
+        Value::String(pair_type) if pair_type == "constant_product" => {
+            Some(PoolType::ConstantProduct)
+        }
+        Value::Object(pair_type) if pair_type.contains_key("stable_swap") => Some(PoolType::Stable),
+        _ => None,
+    }
+}
+
+/// Name of a white whale pair type, the key of the object ones.
+fn pair_type_name(pair_type: &Value) -> String {
+    match pair_type {
+        Value::String(name) => name.clone(),
+        Value::Object(fields) if fields.len() == 1 => fields.keys().next().cloned().unwrap(),
+        _ => pair_type.to_string(),
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for WhiteWhaleScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(ans_assets(self.resolve_assets().await?))
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for WhiteWhaleScraper {
+    /// The incentive contract of every pair with resolved assets, named after the pair's assets.
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        let Some(incentive_factory) = self.deployment.incentive_factory.clone() else {
+            log::info!(
+                "{}: no white whale incentive factory, skipping staking contracts",
+                self.pairs.clients().chain_id
+            );
+            return Ok(vec![]);
+        };
+
+        let pairs = self.pairs.load_pairs().await?;
+        let named_pairs = pairs
+            .iter()
+            .filter_map(|pair| Some((self.pairs.asset_names(pair)?, pair)))
+            .collect::<Vec<_>>();
+
+        let queries = named_pairs.iter().enumerate().map(|(idx, (_, pair))| {
+            (
+                idx,
+                incentive_factory.clone(),
+                IncentiveFactoryQueryMsg::Incentive {
+                    lp_asset: pair.liquidity_token.clone(),
+                },
+            )
+        });
+        let clients = self.pairs.clients();
+        let mut responses = WasmQueryBatcher::new(&clients.wasm, self.pairs.query_concurrency())
+            .query_all(queries)
+            .await;
+
+        let mut staking_contracts = vec![];
+        for (idx, (names, pair)) in named_pairs.into_iter().enumerate() {
+            let response = responses
+                .remove(&idx)
+                .expect("one response per pair")
+                .with_context(|| {
+                    format!("querying incentive contract of pair {}", pair.contract_addr)
+                })?;
+            let incentive: Option<Addr> = serde_json::from_slice(&response).with_context(|| {
+                format!("parsing incentive contract of pair {}", pair.contract_addr)
+            })?;
+            if let Some(incentive) = incentive {
+                staking_contracts.push((lp_token_name(WHITE_WHALE_DEX, &names), incentive));
+            }
+        }
+
+        Ok(staking_contracts)
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.pairs.fetch_dex_pools().await
+    }
+
+    fn report(&self) -> DexReport {
+        self.pairs.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::AssetEntry;
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_utils::whitewhale::{config_with, native, pair, scraper_of, token};
+
+    #[test]
+    fn deployment_is_read_from_the_config() {
+        let deployment = WhiteWhaleScraper::deployment(
+            &config_with(&[
+                (POOL_FACTORY_KEY, "pool_factory"),
+                (INCENTIVE_FACTORY_KEY, "incentive_factory"),
+            ]),
+            "phoenix-1",
+        )
+        .unwrap();
+        assert_eq!(deployment.pool_factory, Addr::unchecked("pool_factory"));
+        assert_eq!(
+            deployment.incentive_factory,
+            Some(Addr::unchecked("incentive_factory"))
+        );
+
+        let deployment = WhiteWhaleScraper::deployment(
+            &config_with(&[(POOL_FACTORY_KEY, "pool_factory")]),
+            "phoenix-1",
+        )
+        .unwrap();
+        assert_eq!(deployment.incentive_factory, None);
+    }
+
+    #[test]
+    fn missing_pool_factory_is_reported() {
+        let err = WhiteWhaleScraper::deployment(
+            &config_with(&[(INCENTIVE_FACTORY_KEY, "incentive_factory")]),
+            "phoenix-1",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ScraperError::DeploymentKeyMissing { ref protocol, ref key, ref available }
+                if protocol == WHITE_WHALE_DEX
+                    && key == POOL_FACTORY_KEY
+                    && available == &[INCENTIVE_FACTORY_KEY.to_string()]
+        ));
+    }
+
+    #[test]
+    fn pair_types_are_mapped_to_pool_types() {
+        assert_eq!(
+            pool_type(&json!("constant_product")),
+            Some(PoolType::ConstantProduct)
+        );
+        assert_eq!(
+            pool_type(&json!({"stable_swap": {"amp": 100}})),
+            Some(PoolType::Stable)
+        );
+        assert_eq!(pool_type(&json!("weighted")), None);
+        assert_eq!(pool_type(&json!({"weighted": {}})), None);
+
+        assert_eq!(pair_type_name(&json!("weighted")), "weighted");
+        assert_eq!(
+            pair_type_name(&json!({"stable_swap": {"amp": 100}})),
+            "stable_swap"
+        );
+    }
+
+    #[tokio::test]
+    async fn unsupported_and_unnamed_pairs_are_skipped() {
+        let mut scraper = scraper_of(vec![
+            pair(
+                "constant_product",
+                vec![native("uluna"), token("whale_token")],
+                json!("constant_product"),
+            ),
+            pair(
+                "stable",
+                vec![native("uluna"), token("whale_token")],
+                json!({"stable_swap": {"amp": 100}}),
+            ),
+            pair(
+                "weighted",
+                vec![native("uluna"), token("whale_token")],
+                json!({"weighted": {}}),
+            ),
+            pair(
+                "unnamed",
+                vec![native("uluna"), native("unknown")],
+                json!("constant_product"),
+            ),
+        ]);
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        let names: Vec<&str> = assets.iter().map(|asset| asset.entry.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "terra2>luna",
+                "terra2>whale",
+                "whitewhale/terra2>luna,terra2>whale",
+                "whitewhale/terra2>luna,terra2>whale",
+                "whitewhale/terra2>luna,terra2>whale",
+            ]
+        );
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(
+            pools,
+            [
+                (
+                    UncheckedPoolAddress::contract("constant_product"),
+                    PoolMetadata {
+                        dex: WHITE_WHALE_DEX.to_string(),
+                        pool_type: PoolType::ConstantProduct,
+                        assets: vec![
+                            AssetEntry::new("terra2>luna"),
+                            AssetEntry::new("terra2>whale")
+                        ],
+                    }
+                ),
+                (
+                    UncheckedPoolAddress::contract("stable"),
+                    PoolMetadata {
+                        dex: WHITE_WHALE_DEX.to_string(),
+                        pool_type: PoolType::Stable,
+                        assets: vec![
+                            AssetEntry::new("terra2>luna"),
+                            AssetEntry::new("terra2>whale")
+                        ],
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            scraper.skipped_pools(),
+            [
+                SkippedPool::unsupported_pair_type(
+                    UncheckedPoolAddress::contract("weighted"),
+                    "weighted"
+                ),
+                SkippedPool::missing_assets(
+                    UncheckedPoolAddress::contract("unnamed"),
+                    vec![AssetInfo::native("unknown")]
+                ),
+            ]
+        );
+    }
+}
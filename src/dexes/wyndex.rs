@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, PoolType};
+use async_trait::async_trait;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ScraperConfig;
+use crate::dexes::factory::{ans_assets, FactoryAssetInfo, FactoryPairInfo, FactoryPairs};
+use crate::error::ScraperError;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::networks::{dex_ans_prefix, NetworkError};
+use crate::helpers::pagination::FactoryPair;
+use crate::helpers::resolution_cache::ResolutionCache;
+use crate::report::{DexReport, SkippedPool, UnresolvedAsset};
+use crate::traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
+use crate::LazyChainRegistry;
+
+pub(crate) const WYNDEX_DEX: &str = "wyndex";
+
+/// Networks with a Wyndex deployment.
+const WYNDEX_NETWORKS: [&str; 2] = ["juno-1", "uni-6"];
+
+const WYNDEX_JUNO_FACTORY: &str = "juno16jzpxp0e8550c9aht6q9svcux30vtyyyyxv5w2l2djjra46580wsazcjwp";
+
+/// Key of the factory in the configured deployment addresses of wyndex.
+pub(crate) const FACTORY_KEY: &str = "factory_address";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WyndexPairType {
+    Xyk {},
+    Stable {},
+    Lsd {},
+    Custom(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WyndexPairInfo {
+    pub asset_infos: Vec<FactoryAssetInfo>,
+    pub contract_addr: Addr,
+    pub liquidity_token: Addr,
+    pub pair_type: WyndexPairType,
+}
+
+impl FactoryPair for WyndexPairInfo {
+    type AssetInfo = FactoryAssetInfo;
+
+    fn contract_addr(&self) -> &Addr {
+        &self.contract_addr
+    }
+
+    fn asset_infos(&self) -> &[FactoryAssetInfo] {
+        &self.asset_infos
+    }
+}
+
+impl FactoryPairInfo for WyndexPairInfo {
+    fn liquidity_token(&self) -> AssetInfo {
+        AssetInfo::cw20(self.liquidity_token.clone())
+    }
+
+    fn pool_type(&self) -> Result<PoolType, String> {
+        match &self.pair_type {
+            WyndexPairType::Xyk {} => Ok(PoolType::ConstantProduct),
+            WyndexPairType::Stable {} | WyndexPairType::Lsd {} => Ok(PoolType::Stable),
+            WyndexPairType::Custom(pair_type) => Err(pair_type.clone()),
+        }
+    }
+
+    fn pair_type(&self) -> String {
+        match &self.pair_type {
+            WyndexPairType::Xyk {} => "xyk".to_string(),
+            WyndexPairType::Stable {} => "stable".to_string(),
+            WyndexPairType::Lsd {} => "lsd".to_string(),
+            WyndexPairType::Custom(pair_type) => pair_type.clone(),
+        }
+    }
+}
+
+pub struct WyndexScraper {
+    pub(crate) pairs: FactoryPairs<WyndexPairInfo>,
+}
+
+impl DexId for WyndexScraper {
+    fn dex_id(&self) -> &'static str {
+        WYNDEX_DEX
+    }
+}
+
+impl WyndexScraper {
+    /// Create the scraper for the chain, with the factory of the configured wyndex deployment
+    /// addresses or the known one. The chain registry is only fetched once a native denom has to
+    /// be resolved.
+    pub fn new(
+        clients: Arc<ChainClients>,
+        chain_registry: impl Into<LazyChainRegistry>,
+        config: &ScraperConfig,
+    ) -> Result<Self, ScraperError> {
+        let chain_id = clients.chain_id.clone();
+        let ans_prefix = config.dex_ans_prefix(WYNDEX_DEX, &WYNDEX_NETWORKS, &chain_id)?;
+        let factory = Self::factory_address(config, &chain_id)?;
+
+        Ok(Self {
+            pairs: FactoryPairs::new(
+                clients,
+                chain_registry.into(),
+                WYNDEX_DEX,
+                ans_prefix,
+                Addr::unchecked(factory),
+                config,
+            ),
+        })
+    }
+
+    /// Share the resolved asset names with the other scrapers of the run.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.pairs.set_resolution_cache(resolution_cache);
+        self
+    }
+
+    /// Set the number of cw20 `TokenInfo` and denom trace queries that are run concurrently.
+    pub fn with_query_concurrency(mut self, concurrency: usize) -> Self {
+        self.pairs.set_query_concurrency(concurrency);
+        self
+    }
+
+    /// Use another factory than the known deployment of the chain.
+    pub fn with_factory(mut self, factory: Addr) -> Self {
+        self.pairs.set_factory(factory);
+        self
+    }
+
+    /// ANS prefix of the assets native to the chain.
+    pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+        dex_ans_prefix(WYNDEX_DEX, &WYNDEX_NETWORKS, chain_id)
+    }
+
+    /// Factory address of the chain, the `factory_address` of the wyndex deployment addresses
+    /// of `config` takes precedence over the known deployment.
+    pub fn factory_address(config: &ScraperConfig, chain_id: &str) -> Result<String, ScraperError> {
+        config.dex_ans_prefix(WYNDEX_DEX, &WYNDEX_NETWORKS, chain_id)?;
+        let configured = config.configured_addresses(WYNDEX_DEX, chain_id);
+        if let Some(factory) = configured.and_then(|addresses| addresses.get(FACTORY_KEY)) {
+            return Ok(factory.clone());
+        }
+        match chain_id {
+            "juno-1" => Ok(WYNDEX_JUNO_FACTORY.to_string()),
+            _ => Err(ScraperError::DeploymentKeyMissing {
+                protocol: WYNDEX_DEX.to_string(),
+                key: FACTORY_KEY.to_string(),
+                available: configured
+                    .map(|addresses| addresses.keys().cloned().collect())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+
+    /// Resolve the ANS names of all the assets in the pairs, plus the LP tokens of the pairs
+    /// whose assets all resolved.
+    pub async fn resolve_assets(&mut self) -> Result<Vec<(String, AssetInfo)>, ScraperError> {
+        self.pairs.resolve_assets().await
+    }
+
+    /// Assets that could not be resolved by the last [`Self::resolve_assets`] call.
+    pub fn not_found_assets(&self) -> &[UnresolvedAsset] {
+        self.pairs.not_found_assets()
+    }
+
+    /// Pools skipped by the last [`DexScraper::fetch_dex_pools`] call because an asset wasn't
+    /// resolved or the pair type is not supported by ANS.
+    pub fn skipped_pools(&self) -> &[SkippedPool] {
+        self.pairs.skipped_pools()
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for WyndexScraper {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(ans_assets(self.resolve_assets().await?))
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for WyndexScraper {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        Ok(vec![])
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        self.pairs.fetch_dex_pools().await
+    }
+
+    fn report(&self) -> DexReport {
+        self.pairs.report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cw_asset::AssetInfoUnchecked;
+
+    use super::*;
+    use crate::test_utils::wyndex::{config_with_factory, native, pair, scraper_of, token};
+
+    #[test]
+    fn factory_is_read_from_the_config() {
+        assert_eq!(
+            WyndexScraper::factory_address(&ScraperConfig::default_for("juno-1"), "juno-1")
+                .unwrap(),
+            WYNDEX_JUNO_FACTORY
+        );
+        assert_eq!(
+            WyndexScraper::factory_address(
+                &config_with_factory("juno-1", "juno1factory"),
+                "juno-1"
+            )
+            .unwrap(),
+            "juno1factory"
+        );
+        assert_eq!(
+            WyndexScraper::factory_address(&config_with_factory("uni-6", "juno1testnet"), "uni-6")
+                .unwrap(),
+            "juno1testnet"
+        );
+    }
+
+    #[test]
+    fn missing_factory_is_reported() {
+        let err = WyndexScraper::factory_address(&ScraperConfig::default_for("uni-6"), "uni-6")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ScraperError::DeploymentKeyMissing { ref protocol, ref key, ref available }
+                if protocol == WYNDEX_DEX && key == FACTORY_KEY && available.is_empty()
+        ));
+    }
+
+    #[tokio::test]
+    async fn pair_types_are_mapped_to_pool_types() {
+        let mut scraper = scraper_of(vec![
+            pair(
+                "xyk",
+                vec![native("ujuno"), token("wynd_token")],
+                WyndexPairType::Xyk {},
+            ),
+            pair(
+                "stable",
+                vec![native("ujuno"), token("wynd_token")],
+                WyndexPairType::Stable {},
+            ),
+            pair(
+                "lsd",
+                vec![native("ujuno"), token("wynd_token")],
+                WyndexPairType::Lsd {},
+            ),
+            pair(
+                "custom",
+                vec![native("ujuno"), token("wynd_token")],
+                WyndexPairType::Custom("concentrated".to_string()),
+            ),
+        ]);
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets[..2],
+            [
+                AnsAsset::new("juno>juno", AssetInfo::native("ujuno")),
+                AnsAsset::new("juno>wynd", AssetInfo::cw20(Addr::unchecked("wynd_token"))),
+            ]
+        );
+        assert_eq!(
+            assets[2],
+            AnsAsset::new(
+                "wyndex/juno>juno,juno>wynd",
+                AssetInfo::cw20(Addr::unchecked("xyk_lp"))
+            )
+        );
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        let pool_types: Vec<(UncheckedPoolAddress, PoolType)> = pools
+            .into_iter()
+            .map(|(pool, metadata)| (pool, metadata.pool_type))
+            .collect();
+        assert_eq!(
+            pool_types,
+            [
+                (
+                    UncheckedPoolAddress::contract("xyk"),
+                    PoolType::ConstantProduct
+                ),
+                (UncheckedPoolAddress::contract("stable"), PoolType::Stable),
+                (UncheckedPoolAddress::contract("lsd"), PoolType::Stable),
+            ]
+        );
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::unsupported_pair_type(
+                UncheckedPoolAddress::contract("custom"),
+                "concentrated"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn pairs_with_unnamed_assets_are_skipped() {
+        let mut scraper = scraper_of(vec![
+            pair(
+                "named",
+                vec![native("ujuno"), token("wynd_token")],
+                WyndexPairType::Xyk {},
+            ),
+            pair(
+                "unnamed",
+                vec![native("ujuno"), native("unknown")],
+                WyndexPairType::Xyk {},
+            ),
+        ]);
+
+        let assets = scraper.fetch_asset_infos().await.unwrap();
+        assert!(!assets
+            .iter()
+            .any(|asset| asset.info == AssetInfoUnchecked::cw20("unnamed_lp")));
+
+        let pools = scraper.fetch_dex_pools().await.unwrap();
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].0, UncheckedPoolAddress::contract("named"));
+        assert_eq!(
+            scraper.skipped_pools(),
+            [SkippedPool::missing_assets(
+                UncheckedPoolAddress::contract("unnamed"),
+                vec![AssetInfo::native("unknown")]
+            )]
+        );
+    }
+}
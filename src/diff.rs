@@ -0,0 +1,343 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, UncheckedChannelEntry, UncheckedContractEntry};
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::export::ScrapeExport;
+use crate::helpers::names::LP_TOKEN_SEPARATOR;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::pipeline::{ScrapeMeta, ScrapeOutput};
+use crate::ChainClients;
+
+/// Number of entries requested per AnsHost list query.
+const QUERY_PAGE_LIMIT: u8 = 25;
+
+/// Label of the assets that don't belong to a dex in the diff summary.
+const NO_DEX: &str = "-";
+
+/// Number of blocks a scrape and the AnsHost entries it is compared to can be apart before the
+/// diff warns that they may not describe the same state.
+pub const DEFAULT_MAX_HEIGHT_GAP: u64 = 1_000;
+
+/// The entries registered on an AnsHost contract.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnsHostEntries {
+    /// Chain and block height the entries were queried at
+    #[serde(default)]
+    pub meta: ScrapeMeta,
+    pub assets: Vec<(String, AssetInfo)>,
+    pub contracts: Vec<(UncheckedContractEntry, String)>,
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    /// Unique id of every registered pool, pools are removed by id
+    #[serde(default)]
+    pub pool_ids: Vec<(u64, UncheckedPoolAddress)>,
+    pub dexes: Vec<String>,
+    #[serde(default)]
+    pub channels: Vec<(UncheckedChannelEntry, String)>,
+}
+
+#[derive(Deserialize)]
+struct PoolReference {
+    unique_id: u64,
+    pool_address: UncheckedPoolAddress,
+}
+
+impl AnsHostEntries {
+    /// Query all the entries of `ans_host` through its paginated list queries.
+    pub async fn query(clients: &ChainClients, ans_host: &Addr) -> anyhow::Result<Self> {
+        let meta = ScrapeMeta::query(clients).await?;
+        let batcher = WasmQueryBatcher::new(&clients.wasm, 1);
+
+        let assets = list_entries(&batcher, ans_host, "asset_list", "assets").await?;
+        let contracts = list_entries(&batcher, ans_host, "contract_list", "contracts").await?;
+        let channels = list_entries(&batcher, ans_host, "channel_list", "channels").await?;
+
+        // Pools are listed by asset pairing, a pool shows up once for every pair of its assets
+        let pairings: Vec<(Value, Vec<PoolReference>)> =
+            list_entries(&batcher, ans_host, "pool_list", "pools").await?;
+        let pool_addresses: BTreeMap<u64, UncheckedPoolAddress> = pairings
+            .into_iter()
+            .flat_map(|(_, references)| references)
+            .map(|reference| (reference.unique_id, reference.pool_address))
+            .collect();
+        let metadatas: Vec<(u64, PoolMetadata)> =
+            list_entries(&batcher, ans_host, "pool_metadata_list", "metadatas").await?;
+        let pools = metadatas
+            .into_iter()
+            .filter_map(|(unique_id, metadata)| {
+                Some((pool_addresses.get(&unique_id)?.clone(), metadata))
+            })
+            .collect();
+        let pool_ids = pool_addresses.into_iter().collect();
+
+        let response = batcher
+            .query(ans_host, &json!({"registered_dexes": {}}))
+            .await
+            .with_context(|| format!("querying registered dexes of AnsHost {}", ans_host))?;
+        #[derive(Deserialize)]
+        struct RegisteredDexesResponse {
+            dexes: Vec<String>,
+        }
+        let RegisteredDexesResponse { dexes } = serde_json::from_slice(&response)
+            .with_context(|| format!("parsing registered dexes of AnsHost {}", ans_host))?;
+
+        Ok(Self {
+            meta,
+            assets,
+            contracts,
+            pools,
+            pool_ids,
+            dexes,
+            channels,
+        })
+    }
+
+    /// The entries of an earlier scrape as if they were registered, to diff a scrape against
+    /// the output files of the previous one. The dexes are the ones of its pools.
+    pub fn from_export(export: ScrapeExport) -> Self {
+        let dexes = export
+            .pools
+            .iter()
+            .map(|(_, metadata)| metadata.dex.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        Self {
+            meta: export.meta,
+            assets: export.assets,
+            contracts: export.contracts,
+            pools: export.pools,
+            pool_ids: vec![],
+            dexes,
+            channels: vec![],
+        }
+    }
+}
+
+/// All the entries of a paginated AnsHost list query.
+///
+/// Every entry is a `[key, value]` pair, the last key is the `start_after` of the next page.
+async fn list_entries<K: DeserializeOwned + Serialize, V: DeserializeOwned>(
+    batcher: &WasmQueryBatcher<'_>,
+    ans_host: &Addr,
+    query: &str,
+    field: &str,
+) -> anyhow::Result<Vec<(K, V)>> {
+    let mut entries: Vec<(K, V)> = vec![];
+    loop {
+        let msg = json!({
+            query: {
+                "start_after": entries.last().map(|(key, _)| key),
+                "limit": QUERY_PAGE_LIMIT,
+            }
+        });
+        let response = batcher
+            .query(ans_host, &msg)
+            .await
+            .with_context(|| format!("querying {} of AnsHost {}", query, ans_host))?;
+        let mut response: Value = serde_json::from_slice(&response)
+            .with_context(|| format!("parsing {} of AnsHost {}", query, ans_host))?;
+        let page: Vec<(K, V)> = serde_json::from_value(response[field].take())
+            .with_context(|| format!("parsing {} of {} of AnsHost {}", field, query, ans_host))?;
+        if page.is_empty() {
+            break;
+        }
+        entries.extend(page);
+    }
+
+    Ok(entries)
+}
+
+/// How the entries of one kind differ between a scrape and AnsHost.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntriesDiff<K, V> {
+    pub to_add: Vec<(K, V)>,
+    /// Scraped entries whose key is registered with another value
+    pub to_update: Vec<(K, V)>,
+    /// Registered entries of the scraped dexes that the scrape didn't find anymore
+    pub to_remove: Vec<(K, V)>,
+    pub unchanged: Vec<(K, V)>,
+}
+
+impl<K: PartialEq + Clone, V: PartialEq + Clone> EntriesDiff<K, V> {
+    /// Compare the scraped entries to the registered ones. Only registered entries for which
+    /// `removable` returns true can end up in `to_remove`.
+    fn new(registered: &[(K, V)], scraped: &[(K, V)], removable: impl Fn(&K, &V) -> bool) -> Self {
+        let mut diff = Self {
+            to_add: vec![],
+            to_update: vec![],
+            to_remove: vec![],
+            unchanged: vec![],
+        };
+        for (key, value) in scraped {
+            match registered.iter().find(|(k, _)| k == key) {
+                None => diff.to_add.push((key.clone(), value.clone())),
+                Some((_, v)) if v == value => diff.unchanged.push((key.clone(), value.clone())),
+                Some(_) => diff.to_update.push((key.clone(), value.clone())),
+            }
+        }
+        for (key, value) in registered {
+            if removable(key, value) && !scraped.iter().any(|(k, _)| k == key) {
+                diff.to_remove.push((key.clone(), value.clone()));
+            }
+        }
+        diff
+    }
+
+    /// Counts of the diff per dex.
+    fn counts(&self, dex: impl Fn(&K, &V) -> String) -> BTreeMap<String, DiffCounts> {
+        let mut counts = BTreeMap::<String, DiffCounts>::new();
+        let mut count = |entries: &[(K, V)], field: fn(&mut DiffCounts) -> &mut usize| {
+            for (key, value) in entries {
+                *field(counts.entry(dex(key, value)).or_default()) += 1;
+            }
+        };
+        count(&self.to_add, |c| &mut c.to_add);
+        count(&self.to_update, |c| &mut c.to_update);
+        count(&self.to_remove, |c| &mut c.to_remove);
+        count(&self.unchanged, |c| &mut c.unchanged);
+        counts
+    }
+}
+
+/// Number of entries per category of an [`EntriesDiff`].
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DiffCounts {
+    pub to_add: usize,
+    pub to_update: usize,
+    pub to_remove: usize,
+    pub unchanged: usize,
+}
+
+/// Difference between a scrape and the entries registered on AnsHost.
+#[derive(Debug, Clone, Serialize)]
+pub struct AnsDiff {
+    pub assets: EntriesDiff<String, AssetInfo>,
+    pub contracts: EntriesDiff<UncheckedContractEntry, String>,
+    pub pools: EntriesDiff<UncheckedPoolAddress, PoolMetadata>,
+    pub channels: EntriesDiff<UncheckedChannelEntry, String>,
+}
+
+impl AnsDiff {
+    /// Compare the scrape to the registered entries.
+    ///
+    /// Only entries of the dexes in the scrape can be removed: their pools, their contracts and
+    /// their LP tokens. Other assets can be shared with dexes that weren't scraped, and channels
+    /// are never removed, the scrape only sees the ones its assets came over.
+    ///
+    /// Fails if they are of different chains, and warns if their block heights are more than
+    /// `max_height_gap` apart.
+    pub fn new(
+        registered: &AnsHostEntries,
+        scraped: &ScrapeOutput,
+        max_height_gap: u64,
+    ) -> anyhow::Result<Self> {
+        check_same_state(&registered.meta, &scraped.meta, max_height_gap)?;
+        let dexes: BTreeSet<&str> = scraped
+            .pools
+            .iter()
+            .map(|(_, metadata)| metadata.dex.as_str())
+            .chain(
+                scraped
+                    .contracts
+                    .iter()
+                    .map(|(entry, _)| entry.protocol.as_str()),
+            )
+            .collect();
+
+        Ok(Self {
+            assets: EntriesDiff::new(&registered.assets, &scraped.assets, |name, _| {
+                lp_token_dex(name).map_or(false, |dex| dexes.contains(dex))
+            }),
+            contracts: EntriesDiff::new(&registered.contracts, &scraped.contracts, |entry, _| {
+                dexes.contains(entry.protocol.as_str())
+            }),
+            pools: EntriesDiff::new(&registered.pools, &scraped.pools, |_, metadata| {
+                dexes.contains(metadata.dex.as_str())
+            }),
+            channels: EntriesDiff::new(&registered.channels, &scraped.channels, |_, _| false),
+        })
+    }
+
+    /// Counts per dex of the assets, contracts and pools, and of the channels.
+    pub fn summary(&self) -> BTreeMap<&'static str, BTreeMap<String, DiffCounts>> {
+        BTreeMap::from([
+            (
+                "assets",
+                self.assets
+                    .counts(|name, _| lp_token_dex(name).unwrap_or(NO_DEX).to_string()),
+            ),
+            (
+                "contracts",
+                self.contracts.counts(|entry, _| entry.protocol.clone()),
+            ),
+            (
+                "pools",
+                self.pools.counts(|_, metadata| metadata.dex.clone()),
+            ),
+            ("channels", self.channels.counts(|_, _| NO_DEX.to_string())),
+        ])
+    }
+
+    pub fn log_summary(&self) {
+        for (category, counts) in self.summary() {
+            for (dex, count) in counts {
+                log::info!(
+                    "{} {}: {} to add, {} to update, {} to remove, {} unchanged",
+                    category,
+                    dex,
+                    count.to_add,
+                    count.to_update,
+                    count.to_remove,
+                    count.unchanged
+                );
+            }
+        }
+    }
+}
+
+/// Refuse to compare entries of different chains, warn if they are far apart in blocks.
+///
+/// Entries without a chain id or height (e.g. read from older files) are compared anyway.
+fn check_same_state(
+    registered: &ScrapeMeta,
+    scraped: &ScrapeMeta,
+    max_height_gap: u64,
+) -> anyhow::Result<()> {
+    if !registered.chain_id.is_empty()
+        && !scraped.chain_id.is_empty()
+        && registered.chain_id != scraped.chain_id
+    {
+        anyhow::bail!(
+            "can't compare a scrape of {} to the AnsHost entries of {}",
+            scraped.chain_id,
+            registered.chain_id
+        );
+    }
+    if let (Some(registered_height), Some(scraped_height)) =
+        (registered.block_height, scraped.block_height)
+    {
+        let gap = registered_height.abs_diff(scraped_height);
+        if gap > max_height_gap {
+            log::warn!(
+                "{}: the scrape (height {}) and the AnsHost entries (height {}) are {} blocks apart",
+                scraped.chain_id,
+                scraped_height,
+                registered_height,
+                gap
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The dex of an LP token entry (`{dex}/{assets}`), `None` for other assets.
+fn lp_token_dex(name: &str) -> Option<&str> {
+    name.split_once(LP_TOKEN_SEPARATOR).map(|(dex, _)| dex)
+}
@@ -0,0 +1,183 @@
+//! The errors of the library, for callers that handle some failures differently than others.
+
+use std::path::PathBuf;
+
+use crate::helpers::chain_registry::ResolveError;
+use crate::helpers::deployments::DeploymentError;
+use crate::helpers::networks::NetworkError;
+use crate::pipeline::AssetConflictsError;
+use crate::registrar::RegistrationError;
+
+/// Why a scraper, the chain registry or a trait method of the scrapers failed.
+///
+/// Errors of the helpers are still `anyhow` errors internally, they are sorted into the variants
+/// when they leave the library, see the `From<anyhow::Error>` impl.
+#[derive(Debug, thiserror::Error)]
+pub enum ScraperError {
+    /// The network is unknown, or the dex isn't deployed on it.
+    #[error(transparent)]
+    NetworkUnsupported(#[from] NetworkError),
+    /// The deployment of the dex doesn't have a contract the scraper needs.
+    #[error(
+        "{protocol} deployment has no {key}, found: {}",
+        available.join(", ")
+    )]
+    DeploymentKeyMissing {
+        protocol: String,
+        key: String,
+        /// Keys the deployment does have, empty if the source can't list them
+        available: Vec<String>,
+    },
+    /// A gRPC query to the node failed, after its retries.
+    #[error("gRPC query failed: {0:#}")]
+    GrpcQuery(#[source] anyhow::Error),
+    /// The chain registry asset lists of `chain` could not be fetched.
+    #[error("fetching the chain registry asset lists of {chain}: {source:#}")]
+    RegistryFetch {
+        chain: String,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error(transparent)]
+    AssetResolution(#[from] ResolveError),
+    /// Asset names map to different assets and the pipeline fails on conflicts.
+    #[error(transparent)]
+    AssetConflicts(#[from] AssetConflictsError),
+    /// An AnsHost update message failed, after the batches before it were executed.
+    #[error(transparent)]
+    Registration(#[from] RegistrationError),
+    /// A cache file can't be parsed and could not be removed to be refetched.
+    #[error("cache file {} is corrupted: {source}", path.display())]
+    CacheCorrupted {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// An output (or cache) file could not be written.
+    #[error("writing {}: {source}", path.display())]
+    OutputIo {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ScraperError {
+    /// Sorts an error of the helpers into its variant by the typed errors of its chain, errors
+    /// without one are [`ScraperError::Other`].
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<ScraperError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<AssetConflictsError>() {
+            Ok(err) => return Self::AssetConflicts(err),
+            Err(err) => err,
+        };
+        for cause in err.chain() {
+            if let Some(err) = cause.downcast_ref::<NetworkError>() {
+                return Self::NetworkUnsupported(err.clone());
+            }
+            if let Some(err) = cause.downcast_ref::<ResolveError>() {
+                return Self::AssetResolution(err.clone());
+            }
+            if let Some(DeploymentError::MissingKey {
+                protocol,
+                key,
+                found,
+                ..
+            }) = cause.downcast_ref::<DeploymentError>()
+            {
+                return Self::DeploymentKeyMissing {
+                    protocol: protocol.clone(),
+                    key: key.clone(),
+                    available: found.clone(),
+                };
+            }
+        }
+        if err
+            .chain()
+            .any(|cause| cause.is::<tonic::Status>() || cause.is::<tonic::transport::Error>())
+        {
+            return Self::GrpcQuery(err);
+        }
+        Self::Other(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn missing_deployment_keys_are_sorted_through_the_context() {
+        let err = anyhow::Error::new(DeploymentError::MissingKey {
+            protocol: "astroport".to_string(),
+            key: "factory".to_string(),
+            url: "https://example.com/deployment.json".to_string(),
+            found: vec!["router".to_string()],
+        })
+        .context("creating the astroport scraper");
+
+        match ScraperError::from(err) {
+            ScraperError::DeploymentKeyMissing {
+                protocol,
+                key,
+                available,
+            } => {
+                assert_eq!(protocol, "astroport");
+                assert_eq!(key, "factory");
+                assert_eq!(available, vec!["router".to_string()]);
+            }
+            err => panic!("expected a missing deployment key, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn typed_errors_are_sorted_into_their_variant() {
+        let network = NetworkError::Unknown {
+            chain_id: "nowhere-1".to_string(),
+        };
+        assert!(matches!(
+            ScraperError::from(anyhow::Error::new(network)),
+            ScraperError::NetworkUnsupported(NetworkError::Unknown { chain_id }) if chain_id == "nowhere-1"
+        ));
+
+        let resolve: anyhow::Result<()> =
+            Err(ResolveError::NoRegistryMatch("ibc/ABC".to_string()).into());
+        let err = resolve.context("naming ibc/ABC").unwrap_err();
+        assert!(matches!(
+            ScraperError::from(err),
+            ScraperError::AssetResolution(ResolveError::NoRegistryMatch(denom)) if denom == "ibc/ABC"
+        ));
+    }
+
+    #[test]
+    fn scraper_errors_are_passed_through() {
+        let err = anyhow::Error::new(ScraperError::RegistryFetch {
+            chain: "osmosis".to_string(),
+            source: anyhow::anyhow!("404"),
+        });
+
+        assert!(matches!(
+            ScraperError::from(err),
+            ScraperError::RegistryFetch { chain, .. } if chain == "osmosis"
+        ));
+    }
+
+    #[test]
+    fn untyped_errors_are_other() {
+        let err = anyhow::anyhow!("something else").context("scraping");
+
+        match ScraperError::from(err) {
+            ScraperError::Other(err) => {
+                assert_eq!(format!("{:#}", err), "scraping: something else")
+            }
+            err => panic!("expected other, got {:?}", err),
+        }
+    }
+}
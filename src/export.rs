@@ -0,0 +1,350 @@
+//! Exports of a scrape for the people who review the entries before they are registered.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{PoolMetadata, UncheckedContractEntry};
+use anyhow::Context;
+use cw_asset::AssetInfo;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::cache::write_atomic;
+use crate::helpers::decimals::AssetMetadata;
+use crate::output::{
+    ASSETS_FILE, ASSET_METADATA_FILE, CONTRACTS_FILE, POOLS_FILE, UNVERIFIED_ASSETS_FILE,
+};
+use crate::pipeline::{ScrapeMeta, ScrapeOutput};
+
+pub const ASSETS_CSV_FILE: &str = "assets.csv";
+pub const POOLS_CSV_FILE: &str = "pools.csv";
+pub const CONTRACTS_CSV_FILE: &str = "contracts.csv";
+
+/// Columns of the exported files, in order.
+pub const ASSETS_CSV_HEADER: [&str; 5] = ["name", "type", "address", "decimals", "verified"];
+pub const POOLS_CSV_HEADER: [&str; 4] = ["address", "type", "dex", "assets"];
+pub const CONTRACTS_CSV_HEADER: [&str; 2] = ["entry", "address"];
+
+/// Format of the exported files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One CSV file per kind of entry
+    #[default]
+    Csv,
+}
+
+/// The entries of a scrape, from a [`ScrapeOutput`] or from the files of an earlier one.
+#[derive(Debug, Clone)]
+pub struct ScrapeExport {
+    pub meta: ScrapeMeta,
+    pub assets: Vec<(String, AssetInfo)>,
+    /// cw20s left out because they aren't verified, exported with `verified` false
+    pub unverified_assets: Vec<(String, AssetInfo)>,
+    /// Decimals of the assets, the column is empty for the assets without
+    pub asset_metadata: Vec<AssetMetadata>,
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    pub contracts: Vec<(UncheckedContractEntry, String)>,
+}
+
+/// Layout of the entry files written by [`crate::output::write_entries`].
+#[derive(Deserialize)]
+struct MetaEntries<T> {
+    meta: ScrapeMeta,
+    entries: Vec<T>,
+}
+
+impl ScrapeExport {
+    pub fn from_output(output: &ScrapeOutput) -> Self {
+        Self {
+            meta: output.meta.clone(),
+            assets: output.assets.clone(),
+            unverified_assets: output.unverified_assets.clone(),
+            asset_metadata: output.asset_metadata.clone(),
+            pools: output.pools.clone(),
+            contracts: output.contracts.clone(),
+        }
+    }
+
+    /// Load the output files of a scrape in `chain_dir` (`<out_dir>/<chain_id>`).
+    ///
+    /// The assets and pools are required, the other files are left out by some scrapes (and by
+    /// older versions of the scraper) and are empty when missing.
+    pub fn load(chain_dir: &Path) -> anyhow::Result<Self> {
+        let assets = read_entries::<(String, AssetInfo)>(&chain_dir.join(ASSETS_FILE))?
+            .with_context(|| format!("no {} in {}", ASSETS_FILE, chain_dir.display()))?;
+        let pools = read_entries(&chain_dir.join(POOLS_FILE))?
+            .with_context(|| format!("no {} in {}", POOLS_FILE, chain_dir.display()))?;
+        Ok(Self {
+            meta: assets.meta,
+            assets: assets.entries,
+            unverified_assets: read_optional_entries(&chain_dir.join(UNVERIFIED_ASSETS_FILE))?,
+            asset_metadata: read_optional_entries(&chain_dir.join(ASSET_METADATA_FILE))?,
+            pools: pools.entries,
+            contracts: read_optional_entries(&chain_dir.join(CONTRACTS_FILE))?,
+        })
+    }
+
+    /// Write the entries to `<out_dir>/<chain_id>/`, returns the paths of the written files.
+    pub fn write(&self, out_dir: &Path, format: ExportFormat) -> anyhow::Result<Vec<PathBuf>> {
+        let chain_dir = out_dir.join(&self.meta.chain_id);
+        std::fs::create_dir_all(&chain_dir)?;
+        match format {
+            ExportFormat::Csv => {
+                let files = [
+                    (
+                        ASSETS_CSV_FILE,
+                        to_csv(&ASSETS_CSV_HEADER, self.asset_rows())?,
+                    ),
+                    (POOLS_CSV_FILE, to_csv(&POOLS_CSV_HEADER, self.pool_rows())?),
+                    (
+                        CONTRACTS_CSV_FILE,
+                        to_csv(&CONTRACTS_CSV_HEADER, self.contract_rows())?,
+                    ),
+                ];
+                files
+                    .into_iter()
+                    .map(|(file, csv)| {
+                        let path = chain_dir.join(file);
+                        write_atomic(&path, &csv)?;
+                        Ok(path)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// `name, type, address, decimals, verified`, the verified assets first.
+    fn asset_rows(&self) -> Vec<Vec<String>> {
+        let decimals: BTreeMap<String, u8> = self
+            .asset_metadata
+            .iter()
+            .filter_map(|asset| Some((asset.info.to_string(), asset.decimals?)))
+            .collect();
+        let verified = self.assets.iter().map(|asset| (asset, true));
+        let unverified = self.unverified_assets.iter().map(|asset| (asset, false));
+        verified
+            .chain(unverified)
+            .map(|((name, info), verified)| {
+                let info = info.to_string();
+                // cw-asset formats the infos as `<type>:<address or denom>`
+                let (kind, address) = info.split_once(':').unwrap_or(("", &info));
+                vec![
+                    name.clone(),
+                    kind.to_string(),
+                    address.to_string(),
+                    decimals
+                        .get(&info)
+                        .map(ToString::to_string)
+                        .unwrap_or_default(),
+                    verified.to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// `address, type, dex, assets`, the address being the pool id of pools that aren't
+    /// contracts and the assets their ANS names.
+    fn pool_rows(&self) -> Vec<Vec<String>> {
+        self.pools
+            .iter()
+            .map(|(pool, metadata)| {
+                let address = match pool {
+                    UncheckedPoolAddress::Contract(addr) => addr.clone(),
+                    UncheckedPoolAddress::Id(id) => id.to_string(),
+                };
+                let assets = metadata
+                    .assets
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                vec![
+                    address,
+                    serde_name(&metadata.pool_type),
+                    metadata.dex.clone(),
+                    assets,
+                ]
+            })
+            .collect()
+    }
+
+    /// `entry, address`, the entry being `<protocol>:<contract>`.
+    fn contract_rows(&self) -> Vec<Vec<String>> {
+        self.contracts
+            .iter()
+            .map(|(entry, address)| {
+                vec![
+                    format!("{}:{}", entry.protocol, entry.contract),
+                    address.clone(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// The rows under `header` as CSV, fields with commas, quotes or line breaks are quoted.
+pub fn to_csv(header: &[&str], rows: Vec<Vec<String>>) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("writing CSV: {}", e))
+}
+
+fn read_entries<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Option<MetaEntries<T>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&json)
+        .map(Some)
+        .with_context(|| format!("parsing {}", path.display()))
+}
+
+fn read_optional_entries<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Vec<T>> {
+    Ok(read_entries(path)?.map_or_else(Vec::new, |file| file.entries))
+}
+
+/// Name a value serializes to, e.g. `constant_product` for a pool type.
+fn serde_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::{AssetEntry, PoolType};
+
+    use super::*;
+
+    fn read_csv(csv: &[u8]) -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(csv)
+            .records()
+            .map(|record| record.unwrap().iter().map(ToString::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn tricky_symbols_are_escaped() {
+        let symbols = [
+            "plain",
+            "with, comma",
+            "with \"quotes\"",
+            "with\nline break",
+            " padded ",
+        ];
+        let rows = symbols
+            .iter()
+            .map(|symbol| vec![symbol.to_string(), "1".to_string()])
+            .collect();
+
+        let csv = to_csv(&["name", "n"], rows).unwrap();
+
+        let text = String::from_utf8(csv.clone()).unwrap();
+        assert!(text.starts_with("name,n\nplain,1\n\"with, comma\",1\n"));
+        assert!(text.contains("\"with \"\"quotes\"\"\",1\n"));
+        let mut expected = vec![vec!["name".to_string(), "n".to_string()]];
+        expected.extend(
+            symbols
+                .iter()
+                .map(|symbol| vec![symbol.to_string(), "1".to_string()]),
+        );
+        assert_eq!(read_csv(&csv), expected);
+    }
+
+    #[test]
+    fn columns_are_written_in_the_header_order() {
+        let out_dir =
+            std::env::temp_dir().join(format!("ans-scraper-export-columns-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let luna = AssetInfo::native("uluna");
+        let astro = AssetInfo::cw20(cosmwasm_std::Addr::unchecked("terra1astro"));
+        let export = ScrapeExport {
+            meta: ScrapeMeta::new("phoenix-1", None),
+            assets: vec![("terra2>luna, \"classic\"".to_string(), luna.clone())],
+            unverified_assets: vec![("terra2>astro".to_string(), astro)],
+            asset_metadata: vec![AssetMetadata {
+                name: "terra2>luna, \"classic\"".to_string(),
+                info: luna,
+                decimals: Some(6),
+            }],
+            pools: vec![(
+                UncheckedPoolAddress::contract("terra1pair"),
+                PoolMetadata {
+                    dex: "astroport".to_string(),
+                    pool_type: PoolType::ConstantProduct,
+                    assets: vec![
+                        AssetEntry::new("terra2>astro"),
+                        AssetEntry::new("terra2>luna"),
+                    ],
+                },
+            )],
+            contracts: vec![(
+                UncheckedContractEntry::new("astroport", "router"),
+                "terra1router".to_string(),
+            )],
+        };
+
+        let paths = export.write(&out_dir, ExportFormat::Csv).unwrap();
+
+        let chain_dir = out_dir.join("phoenix-1");
+        assert_eq!(
+            paths,
+            vec![
+                chain_dir.join(ASSETS_CSV_FILE),
+                chain_dir.join(POOLS_CSV_FILE),
+                chain_dir.join(CONTRACTS_CSV_FILE),
+            ]
+        );
+        let read = |file: &str| read_csv(&std::fs::read(chain_dir.join(file)).unwrap());
+        assert_eq!(
+            read(ASSETS_CSV_FILE),
+            vec![
+                ASSETS_CSV_HEADER.map(String::from).to_vec(),
+                vec![
+                    "terra2>luna, \"classic\"".to_string(),
+                    "native".to_string(),
+                    "uluna".to_string(),
+                    "6".to_string(),
+                    "true".to_string(),
+                ],
+                vec![
+                    "terra2>astro".to_string(),
+                    "cw20".to_string(),
+                    "terra1astro".to_string(),
+                    String::new(),
+                    "false".to_string(),
+                ],
+            ]
+        );
+        assert_eq!(
+            read(POOLS_CSV_FILE),
+            vec![
+                POOLS_CSV_HEADER.map(String::from).to_vec(),
+                vec![
+                    "terra1pair".to_string(),
+                    "constant_product".to_string(),
+                    "astroport".to_string(),
+                    "terra2>astro, terra2>luna".to_string(),
+                ],
+            ]
+        );
+        assert_eq!(
+            read(CONTRACTS_CSV_FILE),
+            vec![
+                CONTRACTS_CSV_HEADER.map(String::from).to_vec(),
+                vec!["astroport:router".to_string(), "terra1router".to_string()],
+            ]
+        );
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
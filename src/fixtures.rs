@@ -0,0 +1,320 @@
+//! Recorded chain data to run the scraping pipeline without any network calls.
+//!
+//! Fixtures are recorded from a real chain with `ans-scraper record-fixtures` and replayed by
+//! `ans-scraper bench`, so performance changes can be compared on realistic data.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::PoolMetadata;
+use anyhow::Context;
+use astroport::asset::PairInfo;
+use cosmwasm_std::Addr;
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use cw_asset::AssetInfo;
+use ibc_chain_registry::asset_list::AssetList as ChainRegistryAssetList;
+use serde::{Deserialize, Serialize};
+
+use crate::dexes::astroport::AstroportScraper;
+use crate::dexes::astroport::{
+    pair_asset_infos, pair_lp_tokens, pairs_to_pools, to_astroport_info,
+};
+use crate::helpers::cache::write_json_atomic;
+use crate::helpers::cw20::{registry_cw20_entry, token_info_entry};
+use crate::helpers::denoms::{ibc_denom_hash, DenomTrace};
+use crate::report::{SkippedPool, UnresolvedAsset, UnresolvedReason};
+use crate::{ChainClients, ChainRegistry, ScraperConfig, WasmQueryBatcher};
+
+/// Everything the astroport pipeline queries from a chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixtures {
+    pub chain_id: String,
+    pub ans_prefix: String,
+    pub pairs: Vec<PairInfo>,
+    /// `TokenInfo` responses, contracts that didn't answer are missing
+    pub token_infos: BTreeMap<Addr, TokenInfoResponse>,
+    /// ibc denom -> denom trace, failed traces are missing
+    pub denom_traces: BTreeMap<String, DenomTrace>,
+    pub asset_lists: Vec<ChainRegistryAssetList>,
+}
+
+impl Fixtures {
+    /// Query all the data the pipeline needs from the chain.
+    pub async fn record(
+        clients: Arc<ChainClients>,
+        chain_registry: ChainRegistry,
+        config: &ScraperConfig,
+    ) -> anyhow::Result<Self> {
+        let chain_id = clients.chain_id.clone();
+        let ans_prefix = config.ans_prefix(&chain_id)?.to_string();
+
+        let mut astroport =
+            AstroportScraper::new(clients.clone(), chain_registry.clone(), config).await?;
+        let pairs = astroport.load_pairs().await?;
+        let asset_infos = pair_asset_infos(&pairs);
+
+        let cw20s = asset_infos.iter().filter_map(|info| match info {
+            AssetInfo::Cw20(contract_addr) => Some(contract_addr.clone()),
+            _ => None,
+        });
+        let token_infos = WasmQueryBatcher::new(&clients.wasm, config.query_concurrency)
+            .query_each(cw20s, &Cw20QueryMsg::TokenInfo {})
+            .await
+            .into_iter()
+            .filter_map(|(contract_addr, response)| {
+                let info = serde_json::from_slice(&response.ok()?).ok()?;
+                Some((contract_addr, info))
+            })
+            .collect();
+
+        let mut denom_traces = BTreeMap::new();
+        for info in &asset_infos {
+            let AssetInfo::Native(denom) = info else {
+                continue;
+            };
+            let Some(hash) = ibc_denom_hash(denom) else {
+                continue;
+            };
+            match clients.ibc.denom_trace(hash.to_string()).await {
+                Ok(trace) => {
+                    denom_traces.insert(
+                        denom.clone(),
+                        DenomTrace::new(&trace.path, &trace.base_denom),
+                    );
+                }
+                Err(err) => log::warn!("Not recording denom trace of {}: {}", denom, err),
+            }
+        }
+
+        Ok(Self {
+            chain_id,
+            ans_prefix,
+            pairs,
+            token_infos,
+            denom_traces,
+            asset_lists: chain_registry.get_asset_lists().to_vec(),
+        })
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file =
+            std::fs::read(path).with_context(|| format!("reading fixtures {}", path.display()))?;
+        serde_json::from_slice(&file)
+            .with_context(|| format!("parsing fixtures {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        write_json_atomic(path, self)
+    }
+}
+
+/// Output of an offline pipeline run.
+#[derive(Debug, Default)]
+pub struct PipelineOutput {
+    pub assets: Vec<(String, AssetInfo)>,
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    pub not_found_assets: Vec<UnresolvedAsset>,
+    pub skipped_pools: Vec<SkippedPool>,
+}
+
+/// Run the asset resolution and pool assembly of the astroport scraper on recorded data.
+///
+/// The naming and pool mapping are the ones the scraper uses, only the queries are replaced by
+/// lookups in the fixtures.
+pub fn run_pipeline(fixtures: &Fixtures, chain_registry: &ChainRegistry) -> PipelineOutput {
+    let mut output = PipelineOutput::default();
+    let mut asset_info_to_name = HashMap::new();
+
+    for asset_info in pair_asset_infos(&fixtures.pairs) {
+        let name = match &asset_info {
+            AssetInfo::Cw20(contract_addr) => {
+                match registry_cw20_entry(Some(chain_registry), &fixtures.ans_prefix, contract_addr)
+                {
+                    Some(entry) => Ok(entry.entry),
+                    None => match fixtures.token_infos.get(contract_addr) {
+                        Some(info) => token_info_entry(&fixtures.ans_prefix, contract_addr, info)
+                            .map(|name| name.entry)
+                            .map_err(|err| UnresolvedReason::Cw20Query(err.to_string())),
+                        None => Err(UnresolvedReason::Cw20Query("no recorded token info".into())),
+                    },
+                }
+            }
+            AssetInfo::Native(denom) => match fixtures.denom_traces.get(denom) {
+                Some(trace) => chain_registry
+                    .name_traced_asset(denom, trace, None)
+                    .map(|name| name.entry)
+                    .map_err(UnresolvedReason::from),
+                None => Err(UnresolvedReason::DenomTrace(
+                    "no recorded denom trace".into(),
+                )),
+            },
+            _ => Err(UnresolvedReason::Unsupported(format!("{:?}", asset_info))),
+        };
+
+        match (name, to_astroport_info(&asset_info)) {
+            (Ok(name), Some(astroport_info)) => {
+                asset_info_to_name.insert(astroport_info, name.clone());
+                output.assets.push((name, asset_info));
+            }
+            (Ok(_), None) => {
+                let reason = UnresolvedReason::Unsupported(format!("{:?}", asset_info));
+                output
+                    .not_found_assets
+                    .push(UnresolvedAsset::new(asset_info, reason));
+            }
+            (Err(reason), _) => output
+                .not_found_assets
+                .push(UnresolvedAsset::new(asset_info, reason)),
+        }
+    }
+
+    output
+        .assets
+        .extend(pair_lp_tokens(&fixtures.pairs, &asset_info_to_name));
+    let assembled = pairs_to_pools(fixtures.pairs.clone(), &asset_info_to_name, &HashMap::new());
+    output.pools = assembled.pools;
+    output.skipped_pools = assembled.skipped_pools;
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Uint128;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::output::{write_output, ScrapeSummary, ASSETS_FILE, POOLS_FILE};
+    use crate::test_utils::astroport::{native, pair, token};
+
+    /// `transfer/channel-1/uatom`
+    const ATOM: &str = "ibc/C4CFF46FD6DE35CA4CF4CE031E643C8FDC9BA4B99AE598E9B0ED98FE3A2319F9";
+
+    fn token_info(symbol: &str) -> TokenInfoResponse {
+        TokenInfoResponse {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            total_supply: Uint128::zero(),
+        }
+    }
+
+    fn cosmoshub_asset_list() -> ChainRegistryAssetList {
+        serde_json::from_value(json!({
+            "$schema": "../assetlist.schema.json",
+            "chain_name": "cosmoshub",
+            "assets": [{
+                "description": "The native staking and governance token of the Cosmos Hub.",
+                "denom_units": [
+                    { "denom": "uatom", "exponent": 0 },
+                    { "denom": "atom", "exponent": 6 }
+                ],
+                "base": "uatom",
+                "name": "Cosmos Hub Atom",
+                "display": "atom",
+                "symbol": "ATOM"
+            }]
+        }))
+        .unwrap()
+    }
+
+    /// cw20s of a pair list are named after their token info, the ones with an invalid token info
+    /// are reported as not found instead of failing the run.
+    #[test]
+    fn cw20s_of_the_pairs_are_resolved() {
+        let fixtures = Fixtures {
+            chain_id: "phoenix-1".to_string(),
+            ans_prefix: "terra2".to_string(),
+            pairs: vec![
+                pair(
+                    "astro_xastro",
+                    vec![token("astro_token"), token("xastro_token")],
+                ),
+                pair("invalid", vec![token("astro_token"), token("bad_token")]),
+            ],
+            token_infos: BTreeMap::from([
+                (Addr::unchecked("astro_token"), token_info("ASTRO")),
+                (Addr::unchecked("xastro_token"), token_info("xASTRO")),
+                (Addr::unchecked("bad_token"), token_info("NOT ASTRO")),
+            ]),
+            denom_traces: BTreeMap::new(),
+            asset_lists: vec![],
+        };
+
+        let output = run_pipeline(&fixtures, &ChainRegistry::from_asset_lists(vec![]));
+
+        let cw20 = |contract_addr: &str| AssetInfo::cw20(Addr::unchecked(contract_addr));
+        assert!(output
+            .assets
+            .iter()
+            .any(|(name, info)| name == "terra2>astro" && *info == cw20("astro_token")));
+        assert!(output
+            .assets
+            .iter()
+            .any(|(name, info)| name == "terra2>xastro" && *info == cw20("xastro_token")));
+        assert!(output
+            .not_found_assets
+            .iter()
+            .any(|asset| asset.info == cw20("bad_token")));
+        assert_eq!(output.pools.len(), 1);
+    }
+
+    /// The offline part of a scrape end to end: the recorded queries of a deployment go through
+    /// the resolution and pool assembly of the astroport scraper and are written to the output
+    /// files.
+    #[test]
+    fn recorded_deployment_is_written_to_the_output_files() {
+        let fixtures = Fixtures {
+            chain_id: "phoenix-1".to_string(),
+            ans_prefix: "terra2".to_string(),
+            pairs: vec![
+                pair("astro_atom", vec![token("astro_token"), native(ATOM)]),
+                pair("unnamed", vec![token("unknown_token"), native(ATOM)]),
+            ],
+            token_infos: BTreeMap::from([(Addr::unchecked("astro_token"), token_info("ASTRO"))]),
+            denom_traces: BTreeMap::from([(
+                ATOM.to_string(),
+                DenomTrace::new("transfer/channel-1", "uatom"),
+            )]),
+            asset_lists: vec![cosmoshub_asset_list()],
+        };
+        let chain_registry = ChainRegistry::from_asset_lists(fixtures.asset_lists.clone());
+
+        let output = run_pipeline(&fixtures, &chain_registry);
+
+        let astro = AssetInfo::cw20(Addr::unchecked("astro_token"));
+        assert!(output.assets.contains(&("terra2>astro".to_string(), astro)));
+        assert!(output
+            .assets
+            .contains(&("cosmoshub>atom".to_string(), AssetInfo::native(ATOM))));
+        let not_found = output.not_found_assets.iter().map(|asset| &asset.info);
+        assert_eq!(
+            not_found.collect::<Vec<_>>(),
+            [&AssetInfo::cw20(Addr::unchecked("unknown_token"))]
+        );
+        assert_eq!(output.pools.len(), 1);
+        let skipped = output.skipped_pools.iter().map(|pool| &pool.pool);
+        assert_eq!(
+            skipped.collect::<Vec<_>>(),
+            [&UncheckedPoolAddress::contract("unnamed")]
+        );
+
+        let out_dir =
+            std::env::temp_dir().join(format!("ans-scraper-fixtures-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let summary = ScrapeSummary {
+            chain_id: fixtures.chain_id.clone(),
+            assets: output.assets.len(),
+            pools: output.pools.len(),
+            ..Default::default()
+        };
+        let chain_dir = write_output(&out_dir, &output.assets, &output.pools, &summary).unwrap();
+        let read = |file: &str| -> Value {
+            serde_json::from_slice(&std::fs::read(chain_dir.join(file)).unwrap()).unwrap()
+        };
+        assert_eq!(read(ASSETS_FILE)["entries"], json!(output.assets));
+        assert_eq!(read(POOLS_FILE)["entries"], json!(output.pools));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
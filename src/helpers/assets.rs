@@ -0,0 +1,120 @@
+use cw_asset::AssetInfo;
+use serde::Serialize;
+
+use super::names::{CHAIN_SEPARATOR, MAX_SEGMENT_LENGTH, NAME_REPLACEMENT_CHAR};
+
+/// An ANS name that was produced for more than one distinct asset.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetConflict {
+    pub name: String,
+    pub infos: Vec<AssetInfo>,
+}
+
+impl AssetConflict {
+    /// Disambiguated names for the conflicting assets, in the order they were found: the first
+    /// asset keeps the name, the next ones get `{name}_2`, `{name}_3`, ... which are valid ANS
+    /// names too.
+    pub fn suggested_names(&self) -> Vec<(String, AssetInfo)> {
+        self.infos
+            .iter()
+            .enumerate()
+            .map(|(idx, info)| match idx {
+                0 => (self.name.clone(), info.clone()),
+                _ => (suffixed_name(&self.name, idx + 1), info.clone()),
+            })
+            .collect()
+    }
+}
+
+/// `name` with `_{n}` appended to its symbol, which is shortened to stay a valid segment.
+fn suffixed_name(name: &str, n: usize) -> String {
+    let suffix = format!("{}{}", NAME_REPLACEMENT_CHAR, n);
+    let symbol_start = name.rfind(CHAIN_SEPARATOR).map_or(0, |idx| idx + 1);
+    let symbol: String = name[symbol_start..]
+        .chars()
+        .take(MAX_SEGMENT_LENGTH.saturating_sub(suffix.len()))
+        .collect();
+    format!("{}{}{}", &name[..symbol_start], symbol, suffix)
+}
+
+/// Consolidate a list of `(name, info)` asset entries.
+///
+/// Identical entries are deduplicated. Names that point at different infos are removed from the
+/// output and returned as conflicts, so the emitted list is guaranteed to have unique names.
+/// The order of first occurrence is preserved.
+pub fn consolidate_assets(
+    entries: impl IntoIterator<Item = (String, AssetInfo)>,
+) -> (Vec<(String, AssetInfo)>, Vec<AssetConflict>) {
+    let mut unique: Vec<(String, AssetInfo)> = vec![];
+    let mut conflicts: Vec<AssetConflict> = vec![];
+
+    for (name, info) in entries {
+        if let Some(conflict) = conflicts.iter_mut().find(|c| c.name == name) {
+            if !conflict.infos.contains(&info) {
+                conflict.infos.push(info);
+            }
+            continue;
+        }
+
+        match unique.iter().position(|(existing, _)| *existing == name) {
+            None => unique.push((name, info)),
+            Some(idx) if unique[idx].1 == info => {}
+            Some(idx) => {
+                let (name, existing_info) = unique.remove(idx);
+                conflicts.push(AssetConflict {
+                    name,
+                    infos: vec![existing_info, info],
+                });
+            }
+        }
+    }
+
+    for conflict in &conflicts {
+        log::warn!(
+            "Asset name {} maps to {} different assets: {:?}",
+            conflict.name,
+            conflict.infos.len(),
+            conflict.infos
+        );
+    }
+
+    (unique, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Addr;
+
+    use super::*;
+    use crate::helpers::names::validate_asset_entry;
+
+    fn conflict(name: &str, infos: usize) -> AssetConflict {
+        AssetConflict {
+            name: name.to_string(),
+            infos: (0..infos)
+                .map(|idx| AssetInfo::cw20(Addr::unchecked(format!("token{}", idx))))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn suggested_names_are_valid_asset_entries() {
+        let names: Vec<String> = conflict("terra2>astro", 3)
+            .suggested_names()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["terra2>astro", "terra2>astro_2", "terra2>astro_3"]);
+
+        let longest_symbol = "a".repeat(MAX_SEGMENT_LENGTH);
+        for name in [
+            "terra2>astro".to_string(),
+            format!("terra2>{}", longest_symbol),
+            "astroport/terra2>astro,terra2>luna".to_string(),
+        ] {
+            for (suggested, _) in conflict(&name, 12).suggested_names() {
+                assert_eq!(validate_asset_entry(&suggested), Ok(()), "{}", suggested);
+            }
+        }
+    }
+}
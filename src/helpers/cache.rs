@@ -0,0 +1,277 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::ScraperError;
+
+/// Root directory of all the cached data, relative to the working directory, when the platform
+/// has no cache directory.
+pub const DEFAULT_CACHE_DIR: &str = "cache";
+
+/// Environment variable with the root directory of the cached data, e.g. for CI.
+pub const CACHE_DIR_ENV: &str = "ANS_SCRAPER_CACHE_DIR";
+
+/// Name of the directory of the scraper in the cache directory of the platform.
+const PLATFORM_CACHE_SUBDIR: &str = "ans-scraper";
+
+/// Name of the lock file of a cache directory.
+const LOCK_FILE: &str = ".lock";
+
+/// Age after which a lock is considered left behind by a killed run and taken over.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// Interval at which a held lock is checked again.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Number of the next temp file of [`write_atomic`], unique within the process.
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Root directory of the cached data: `ANS_SCRAPER_CACHE_DIR` if set, otherwise the scraper's
+/// directory in the cache directory of the platform (e.g. `~/.cache/ans-scraper`), so the caches
+/// don't depend on the working directory.
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(CACHE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    dirs::cache_dir()
+        .map(|dir| dir.join(PLATFORM_CACHE_SUBDIR))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR))
+}
+
+/// Seconds since the unix epoch, the timestamp format of the cache files.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Write a value as JSON without ever leaving a partially written file behind.
+pub fn write_json_atomic<T: Serialize + ?Sized>(
+    path: impl AsRef<Path>,
+    value: &T,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let json = serde_json::to_vec(value)
+        .with_context(|| format!("serializing cache file {}", path.display()))?;
+    write_atomic(path, &json)
+}
+
+/// Write bytes to a file without ever leaving a partially written file behind.
+///
+/// The data is written to a temp file in the same directory, synced and then renamed over `path`.
+/// Every call has its own temp file, so concurrent writes of the same path don't truncate each
+/// other's, the last rename wins.
+pub fn write_atomic(path: impl AsRef<Path>, contents: &[u8]) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("creating cache directory {}", dir.display()))?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("invalid cache path {}", path.display()))?;
+    let tmp_path = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&tmp_path, path)
+    };
+
+    if let Err(err) = write() {
+        let _ = std::fs::remove_file(&tmp_path);
+        return Err(ScraperError::OutputIo {
+            path: path.to_path_buf(),
+            source: err,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Read a JSON cache file.
+///
+/// Returns `None` if the file doesn't exist. A file that can't be parsed (e.g. truncated by an
+/// interrupted run) is removed so the caller refetches and rewrites it.
+pub fn read_json_or_invalidate<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<Option<T>> {
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json =
+        std::fs::read(path).with_context(|| format!("reading cache file {}", path.display()))?;
+    match serde_json::from_slice(&json) {
+        Ok(value) => Ok(Some(value)),
+        Err(err) => {
+            log::warn!(
+                "Discarding corrupted cache file {}: {}",
+                path.display(),
+                err
+            );
+            std::fs::remove_file(path).map_err(|source| ScraperError::CacheCorrupted {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            Ok(None)
+        }
+    }
+}
+
+/// Exclusive access to a cache directory across processes, released when dropped.
+///
+/// Writes are atomic on their own, the lock keeps concurrent runs from fetching and writing the
+/// same files at the same time, the second one waits and then reads what the first one wrote.
+#[derive(Debug)]
+pub struct CacheLock {
+    path: PathBuf,
+}
+
+impl CacheLock {
+    /// Wait until no other run holds the lock of `dir`, then take it.
+    pub async fn acquire(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating cache directory {}", dir.display()))?;
+        let path = dir.join(LOCK_FILE);
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // Only informative, for whoever finds a lock left behind
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    if lock_age(&path).map_or(false, |age| age > STALE_LOCK_AGE) {
+                        log::warn!("Taking over the stale cache lock {}", path.display());
+                        let _ = std::fs::remove_file(&path);
+                        continue;
+                    }
+                    if !waiting {
+                        log::info!("Waiting for another run to release {}", path.display());
+                        waiting = true;
+                    }
+                    tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("creating cache lock {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Time since the lock file was taken, `None` if it's gone.
+fn lock_age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ans-scraper-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn atomic_writes_replace_the_file_and_leave_no_temp_file() {
+        let dir = temp_cache_dir("atomic");
+        let path = dir.join("nested").join("values.json");
+
+        write_json_atomic(&path, &vec![1, 2]).unwrap();
+        write_json_atomic(&path, &vec![3]).unwrap();
+
+        let values: Option<Vec<u32>> = read_json_or_invalidate(&path).unwrap();
+        assert_eq!(values, Some(vec![3]));
+        let files: Vec<_> = std::fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["values.json"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_writes_of_the_same_path_keep_a_whole_file() {
+        let dir = temp_cache_dir("concurrent");
+        let path = dir.join("values.json");
+        let values: Vec<Vec<u32>> = (0..8).map(|i| vec![i; 10_000]).collect();
+
+        std::thread::scope(|scope| {
+            for value in &values {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..5 {
+                        write_json_atomic(path, value).unwrap();
+                    }
+                });
+            }
+        });
+
+        let written: Vec<u32> = read_json_or_invalidate(&path).unwrap().unwrap();
+        assert!(values.contains(&written));
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["values.json"]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupted_files_are_discarded() {
+        let dir = temp_cache_dir("corrupted");
+        let path = dir.join("values.json");
+        write_atomic(&path, b"[1, 2").unwrap();
+
+        let values: Option<Vec<u32>> = read_json_or_invalidate(&path).unwrap();
+        assert_eq!(values, None);
+        assert!(!path.exists());
+
+        let missing: Option<Vec<u32>> = read_json_or_invalidate(&path).unwrap();
+        assert_eq!(missing, None);
+
+        // a directory can't be read, the error says which file it was
+        std::fs::create_dir_all(&path).unwrap();
+        let err = read_json_or_invalidate::<Vec<u32>>(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("reading cache file {}", path.display())
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
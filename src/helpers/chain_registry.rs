@@ -1,120 +1,1356 @@
-use cw_orch::queriers::{DaemonQuerier, Ibc};
-use cw_orch::Daemon;
+use super::cache::{default_cache_dir, CacheLock};
+use super::clients::ChainClients;
+use super::denoms::{factory_denom_parts, ibc_denom_hash, pool_share_id, DenomTrace};
+use super::http::{fetch_with_cache, invalidate, is_offline, CachedBody};
+use super::ibc::channel_counterparty_chain_id;
+use super::names::{asset_entry_name, AssetName};
+use super::networks;
+use super::retry::retry;
+use crate::config::ScraperConfig;
+use crate::error::ScraperError;
+use crate::report::UnresolvedReason;
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use futures::stream::{self, StreamExt};
 use ibc_chain_registry::asset_list::{
     Asset as ChainRegistryAsset, AssetList as ChainRegistryAssetList,
 };
 use ibc_chain_registry::constants::ALL_CHAINS;
-use ibc_chain_registry::fetchable::Fetchable;
-use std::path::Path;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const CHAIN_REGISTRY_RAW_URL: &str =
+    "https://raw.githubusercontent.com/cosmos/chain-registry/master";
+
+/// Prefix of the base denom of cw20 assets in the chain registry, `cw20:<address>`.
+pub(crate) const REGISTRY_CW20_PREFIX: &str = "cw20:";
+
+/// Port of the fungible token transfers.
+pub(crate) const TRANSFER_PORT: &str = "transfer";
+
+/// Default number of asset lists fetched concurrently.
+pub const DEFAULT_ASSET_LIST_FETCH_CONCURRENCY: usize = 16;
+
+/// Age after which a cached asset list is revalidated with the chain registry.
+pub const DEFAULT_ASSET_LISTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How the asset lists are cached.
+#[derive(Debug, Clone)]
+pub struct ChainRegistryOptions {
+    /// Age after which a cached asset list is revalidated
+    pub ttl: Duration,
+    /// Revalidate every cached asset list, whatever its age
+    pub force_refresh: bool,
+    /// Directory of the cached asset lists
+    pub cache_dir: PathBuf,
+    /// Name IBC denoms that travelled through more than one channel. ANS usually only wants the
+    /// assets of direct channels.
+    pub allow_multihop: bool,
+    /// Number of asset lists fetched concurrently
+    pub fetch_concurrency: usize,
+    /// Where the raw files of the chain registry are fetched from, GitHub or a mirror of it
+    pub registry_url: String,
+}
+
+impl Default for ChainRegistryOptions {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_ASSET_LISTS_TTL,
+            force_refresh: false,
+            cache_dir: default_cache_dir().join("asset_lists"),
+            allow_multihop: false,
+            fetch_concurrency: DEFAULT_ASSET_LIST_FETCH_CONCURRENCY,
+            registry_url: CHAIN_REGISTRY_RAW_URL.to_string(),
+        }
+    }
+}
+
+impl ChainRegistryOptions {
+    /// Max age of a cached asset list for [`fetch_with_cache`].
+    fn max_age(&self) -> Duration {
+        if self.force_refresh {
+            Duration::ZERO
+        } else {
+            self.ttl
+        }
+    }
+}
+
+/// An asset of a chain registry asset list that could not be deserialized.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedRegistryAsset {
+    pub chain: String,
+    /// `base` or `symbol` of the asset, if present
+    pub asset: Option<String>,
+    pub error: String,
+}
+
+/// A native denom named by the chain registry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAsset {
+    pub name: String,
+    /// The name was made up from the denom, no asset list has the asset
+    pub low_confidence: bool,
+    /// The name before its symbol was sanitized, if sanitization changed it
+    pub unsanitized: Option<String>,
+}
+
+impl ResolvedAsset {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            low_confidence: false,
+            unsanitized: None,
+        }
+    }
+}
+
+impl From<AssetName> for ResolvedAsset {
+    fn from(name: AssetName) -> Self {
+        Self {
+            name: name.entry,
+            low_confidence: false,
+            unsanitized: name.unsanitized,
+        }
+    }
+}
+
+/// Why the chain registry could not name a native denom.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ResolveError {
+    #[error("no denom trace for {denom}: {error}")]
+    DenomTrace { denom: String, error: String },
+    #[error("denom trace port of {denom} is {port:?}, not transfer")]
+    NonTransferPort { denom: String, port: String },
+    #[error("{denom} is a multi-hop IBC denom ({path})")]
+    MultiHop { denom: String, path: String },
+    #[error("{denom} is the LP share of pool {pool_id}")]
+    PoolShare { denom: String, pool_id: String },
+    #[error("token factory denom {denom} can't be named: {error}")]
+    FactoryDenom { denom: String, error: String },
+    #[error("{0}")]
+    NoRegistryMatch(String),
+}
+
+impl From<ResolveError> for UnresolvedReason {
+    fn from(err: ResolveError) -> Self {
+        match err {
+            ResolveError::DenomTrace { error, .. } => UnresolvedReason::DenomTrace(error),
+            ResolveError::NonTransferPort { port, .. } => UnresolvedReason::NonTransferPort(port),
+            ResolveError::MultiHop { path, .. } => UnresolvedReason::MultiHop(path),
+            ResolveError::PoolShare { pool_id, .. } => UnresolvedReason::PoolShare(pool_id),
+            ResolveError::FactoryDenom { error, .. } => UnresolvedReason::FactoryDenom(error),
+            ResolveError::NoRegistryMatch(details) => UnresolvedReason::NoRegistryMatch(details),
+        }
+    }
+}
 
 /// THe chain registry somewhat acts like a singleton by caching all its data locally.
+///
+/// The data lives behind an [`Arc`], so the registry is a cheap handle that can be cloned into
+/// every scraper.
+#[derive(Clone)]
 pub struct ChainRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
     asset_lists: Vec<ChainRegistryAssetList>,
+    /// denom -> (asset list index, asset index), first match wins
+    denom_index: HashMap<String, (usize, usize)>,
+    skipped_assets: Vec<SkippedRegistryAsset>,
+    /// ibc hash -> denom trace
+    denom_traces: RwLock<HashMap<String, DenomTrace>>,
+    /// base denom -> chain it was transferred from, from the IBC traces of the asset lists
+    source_chains: HashMap<String, String>,
+    /// Chains whose asset list was requested at construction, fetched or not
+    requested_chains: BTreeSet<String>,
+    /// Requested chains whose asset list couldn't be fetched
+    failed_chains: Vec<String>,
+    /// How to fetch the asset lists of source chains on demand, `None` to never fetch them
+    options: Option<ChainRegistryOptions>,
+    /// Asset lists fetched on demand, `None` for chains whose list couldn't be fetched
+    source_chain_lists: RwLock<BTreeMap<String, Option<ChainRegistryAssetList>>>,
+    /// Name IBC denoms that travelled through more than one channel
+    allow_multihop: bool,
+    /// (chain id, channel id) -> chain id of the counterparty chain
+    channel_chains: RwLock<HashMap<(String, String), Result<String, String>>>,
+}
+
+/// An asset list fetched from the chain registry.
+struct FetchedAssetList {
+    list: ChainRegistryAssetList,
+    skipped: Vec<SkippedRegistryAsset>,
+    /// `(base denom, chain)` of the IBC traces of the assets
+    source_chains: Vec<(String, String)>,
+}
+
+/// A [`ChainRegistry`] that is only fetched the first time it is needed.
+///
+/// Clones share the same registry, so it is fetched at most once per run. Scrapes that never
+/// resolve a native denom don't fetch it at all.
+#[derive(Clone, Default)]
+pub struct LazyChainRegistry {
+    cell: Arc<tokio::sync::OnceCell<ChainRegistry>>,
+    options: ChainRegistryOptions,
+    /// Chains to fetch, all of them if `None`
+    chains: Option<Vec<String>>,
+}
+
+impl LazyChainRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the registry with `options` instead of the default ones.
+    pub fn with_options(options: ChainRegistryOptions) -> Self {
+        Self {
+            cell: Default::default(),
+            options,
+            chains: None,
+        }
+    }
+
+    /// Only fetch the asset lists of `chains`, see [`ChainRegistry::for_chains`].
+    pub fn for_chains(chains: &[&str], options: ChainRegistryOptions) -> Self {
+        Self {
+            cell: Default::default(),
+            options,
+            chains: Some(chains.iter().map(|chain| chain.to_string()).collect()),
+        }
+    }
+
+    /// Get the registry, fetching it on the first call.
+    pub async fn get(&self) -> Result<&ChainRegistry, ScraperError> {
+        self.cell
+            .get_or_try_init(|| async {
+                match &self.chains {
+                    Some(chains) => {
+                        let chains: Vec<&str> = chains.iter().map(String::as_str).collect();
+                        ChainRegistry::for_chains(&chains, self.options.clone()).await
+                    }
+                    None => ChainRegistry::for_chains(ALL_CHAINS, self.options.clone()).await,
+                }
+            })
+            .await
+    }
+
+    /// Whether the registry has been fetched yet.
+    pub fn is_initialized(&self) -> bool {
+        self.cell.initialized()
+    }
+
+    /// The registry if it has been fetched yet, without fetching it.
+    pub fn get_if_initialized(&self) -> Option<&ChainRegistry> {
+        self.cell.get()
+    }
+
+    /// See [`ChainRegistry::failed_chains`], none if the registry wasn't fetched.
+    pub fn failed_chains(&self) -> Vec<String> {
+        self.cell
+            .get()
+            .map_or_else(Vec::new, ChainRegistry::failed_chains)
+    }
+}
+
+impl From<ChainRegistry> for LazyChainRegistry {
+    fn from(chain_registry: ChainRegistry) -> Self {
+        Self {
+            cell: Arc::new(tokio::sync::OnceCell::new_with(Some(chain_registry))),
+            options: Default::default(),
+            chains: None,
+        }
+    }
 }
 
 impl ChainRegistry {
-    // TOOD: new name? ??
-    pub async fn new() -> anyhow::Result<Self> {
-        let asset_lists = Self::fetch_asset_lists().await?;
-        Ok(Self { asset_lists })
-    }
-
-    async fn fetch_asset_lists() -> anyhow::Result<Vec<ChainRegistryAssetList>> {
-        log::info!("Fetching asset lists from the chain registry");
-        // check for cache dir
-        if !Path::new("cache/asset_lists").exists() {
-            std::fs::create_dir_all("cache/asset_lists")?;
-        }
-
-        let mut lists = Vec::with_capacity(ALL_CHAINS.len());
-        for chain in ALL_CHAINS {
-            // check cache
-            let file_name = format!("cache/asset_lists/{}.json", chain);
-            if Path::new(&file_name).exists() {
-                let json = std::fs::read_to_string(file_name)?;
-                let list: ChainRegistryAssetList = serde_json::from_str(&json)?;
-                lists.push(list);
-                continue;
+    /// Fetch the asset lists of all the chains, cached in the cache directory of `config`.
+    pub async fn new(config: &ScraperConfig) -> Result<Self, ScraperError> {
+        Self::for_chains(ALL_CHAINS, config.chain_registry_options()).await
+    }
+
+    /// Only fetch the asset lists of `chains`, typically the scraped ones.
+    ///
+    /// IBC denoms whose base denom isn't in these lists get the asset list of the chain they were
+    /// transferred from fetched on demand. That chain is known from the IBC traces of the fetched
+    /// lists.
+    pub async fn for_chains(
+        chains: &[&str],
+        options: ChainRegistryOptions,
+    ) -> Result<Self, ScraperError> {
+        let fetched = Self::fetch_asset_lists(chains, &options).await?;
+
+        let failed_chains = fetched
+            .iter()
+            .filter(|(_, list)| list.is_none())
+            .map(|(chain, _)| chain.to_string())
+            .collect();
+        let mut asset_lists = Vec::with_capacity(fetched.len());
+        let mut skipped_assets = vec![];
+        let mut source_chains = HashMap::new();
+        for mut list in fetched.into_iter().flat_map(|(_, list)| list) {
+            asset_lists.push(list.list);
+            skipped_assets.append(&mut list.skipped);
+            for (base_denom, chain) in list.source_chains {
+                source_chains.entry(base_denom).or_insert(chain);
             }
+        }
 
-            let list = ChainRegistryAssetList::fetch(chain.to_string(), None)
-                .await
-                .ok()
-                .unwrap();
-            let json = serde_json::to_string(&list)?;
-            std::fs::write(file_name, json)?;
-            lists.push(list);
+        let denom_index = Self::build_denom_index(&asset_lists);
+        Ok(Self {
+            inner: Arc::new(RegistryInner {
+                asset_lists,
+                denom_index,
+                skipped_assets,
+                denom_traces: Default::default(),
+                source_chains,
+                requested_chains: chains.iter().map(|chain| chain.to_string()).collect(),
+                failed_chains,
+                allow_multihop: options.allow_multihop,
+                options: Some(options),
+                source_chain_lists: Default::default(),
+                channel_chains: Default::default(),
+            }),
+        })
+    }
+
+    /// Build the registry from already loaded asset lists. It never fetches other lists.
+    pub fn from_asset_lists(asset_lists: Vec<ChainRegistryAssetList>) -> Self {
+        let denom_index = Self::build_denom_index(&asset_lists);
+        Self {
+            inner: Arc::new(RegistryInner {
+                asset_lists,
+                denom_index,
+                skipped_assets: vec![],
+                denom_traces: Default::default(),
+                source_chains: HashMap::new(),
+                requested_chains: BTreeSet::new(),
+                failed_chains: vec![],
+                options: None,
+                source_chain_lists: Default::default(),
+                allow_multihop: false,
+                channel_chains: Default::default(),
+            }),
+        }
+    }
+
+    /// Assets that were dropped from freshly fetched asset lists because they didn't deserialize.
+    pub fn skipped_assets(&self) -> &[SkippedRegistryAsset] {
+        &self.inner.skipped_assets
+    }
+
+    /// Chains whose asset list couldn't be fetched, requested or source chains of IBC denoms.
+    /// Their assets can't be named.
+    pub fn failed_chains(&self) -> Vec<String> {
+        let source_chain_lists = self.inner.source_chain_lists.read().unwrap();
+        let failed_source_chains = source_chain_lists
+            .iter()
+            .filter(|(_, list)| list.is_none())
+            .map(|(chain, _)| chain.clone());
+        self.inner
+            .failed_chains
+            .iter()
+            .cloned()
+            .chain(failed_source_chains)
+            .collect()
+    }
+
+    /// Index every denom unit to the first asset that lists it.
+    fn build_denom_index(
+        asset_lists: &[ChainRegistryAssetList],
+    ) -> HashMap<String, (usize, usize)> {
+        let mut index = HashMap::new();
+        for (list_idx, asset_list) in asset_lists.iter().enumerate() {
+            for (asset_idx, asset) in asset_list.assets.iter().enumerate() {
+                for denom_unit in &asset.denom_units {
+                    index
+                        .entry(denom_unit.denom.clone())
+                        .or_insert((list_idx, asset_idx));
+                }
+            }
+        }
+        index
+    }
+
+    /// Fetch the asset lists of `chains`, at most [`ChainRegistryOptions::fetch_concurrency`]
+    /// at a time.
+    ///
+    /// Chains whose asset list can't be fetched (e.g. a renamed chain that 404s) are logged and
+    /// get `None`. The lists keep the order of `chains`, so denom lookups stay deterministic.
+    async fn fetch_asset_lists<'c>(
+        chains: &[&'c str],
+        options: &ChainRegistryOptions,
+    ) -> Result<Vec<(&'c str, Option<FetchedAssetList>)>, ScraperError> {
+        log::info!(
+            "Fetching {} asset lists from the chain registry",
+            chains.len()
+        );
+        // Concurrent runs would otherwise all fetch and write the same lists
+        let _lock = CacheLock::acquire(&options.cache_dir).await?;
+
+        let mut results: Vec<_> = stream::iter(chains.iter().copied().enumerate())
+            .map(|(idx, chain)| async move {
+                (idx, chain, Self::fetch_asset_list(chain, options).await)
+            })
+            .buffer_unordered(options.fetch_concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(idx, _, _)| *idx);
+
+        let mut lists = Vec::with_capacity(results.len());
+        let mut failed_chains = vec![];
+        for (_, chain, result) in results {
+            match result {
+                Ok(list) => lists.push((chain, Some(list))),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to fetch the chain registry asset list of {}: {:#}",
+                        chain,
+                        err
+                    );
+                    failed_chains.push(chain);
+                    lists.push((chain, None));
+                }
+            }
+        }
+        if !failed_chains.is_empty() && is_offline() {
+            return Err(ScraperError::RegistryFetch {
+                chain: failed_chains.join(", "),
+                source: anyhow::anyhow!("not cached, can't fetch them offline"),
+            });
+        }
+        if !failed_chains.is_empty() {
+            log::warn!(
+                "Missing the asset lists of {} chains: {}",
+                failed_chains.len(),
+                failed_chains.join(", ")
+            );
         }
 
         Ok(lists)
     }
 
+    /// Fetch the raw asset list of a chain (or its cached copy) and parse it leniently.
+    ///
+    /// Cached copies older than the TTL are revalidated, the time of the last validation is kept
+    /// next to the cached list.
+    async fn fetch_asset_list(
+        chain: &str,
+        options: &ChainRegistryOptions,
+    ) -> anyhow::Result<FetchedAssetList> {
+        let url = format!(
+            "{}/{}/assetlist.json",
+            options.registry_url.trim_end_matches('/'),
+            chain
+        );
+        let cache_path = options.cache_dir.join(format!("{}.json", chain));
+        let max_age = Some(options.max_age());
+
+        let CachedBody { body, from_cache } = fetch_with_cache(&url, &cache_path, max_age).await?;
+        let json = match serde_json::from_str::<Value>(&body) {
+            Ok(json) => json,
+            Err(err) if from_cache => {
+                log::warn!(
+                    "Discarding corrupted cache file {}: {}",
+                    cache_path.display(),
+                    err
+                );
+                invalidate(&cache_path)?;
+                let body = fetch_with_cache(&url, &cache_path, max_age).await?.body;
+                serde_json::from_str(&body)
+                    .with_context(|| format!("parsing asset list from {}", url))?
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("parsing asset list from {}", url))
+            }
+        };
+
+        let source_chains = ibc_source_chains(&json);
+        let (list, skipped) = Self::parse_asset_list(chain, json)?;
+        Ok(FetchedAssetList {
+            list,
+            skipped,
+            source_chains,
+        })
+    }
+
+    /// Deserialize an asset list one asset at a time, so a single malformed asset (schema drift)
+    /// doesn't drop the whole chain.
+    pub fn parse_asset_list(
+        chain: &str,
+        mut json: Value,
+    ) -> anyhow::Result<(ChainRegistryAssetList, Vec<SkippedRegistryAsset>)> {
+        let raw_assets = match json.get_mut("assets").map(Value::take) {
+            Some(Value::Array(assets)) => assets,
+            _ => anyhow::bail!("asset list of {} has no assets array", chain),
+        };
+
+        let mut assets = Vec::with_capacity(raw_assets.len());
+        let mut skipped = vec![];
+        for raw_asset in raw_assets {
+            let asset_id = raw_asset
+                .get("base")
+                .or_else(|| raw_asset.get("symbol"))
+                .and_then(Value::as_str)
+                .map(str::to_string);
+
+            match serde_json::from_value::<ChainRegistryAsset>(raw_asset.clone()) {
+                Ok(_) => assets.push(raw_asset),
+                Err(err) => {
+                    log::warn!(
+                        "Skipping asset {:?} of {} in the chain registry: {}",
+                        asset_id,
+                        chain,
+                        err
+                    );
+                    skipped.push(SkippedRegistryAsset {
+                        chain: chain.to_string(),
+                        asset: asset_id,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+
+        if let Some(obj) = json.as_object_mut() {
+            obj.insert("assets".to_string(), Value::Array(assets));
+            // tolerate a missing schema reference
+            obj.entry("$schema").or_insert(Value::String(String::new()));
+        }
+
+        let list = serde_json::from_value(json)
+            .with_context(|| format!("deserializing asset list of {}", chain))?;
+        Ok((list, skipped))
+    }
+
     /// Get the asset lists from the chain registry.
     pub fn get_asset_lists(&self) -> &[ChainRegistryAssetList] {
-        &self.asset_lists
+        &self.inner.asset_lists
+    }
+
+    /// Resolve the ANS name of a native denom of the chain of `clients`, see
+    /// [`Self::resolve_native_assets`].
+    pub async fn resolve_native_asset(
+        &self,
+        clients: &ChainClients,
+        denom: String,
+    ) -> Result<ResolvedAsset, ResolveError> {
+        let mut resolved = self
+            .resolve_native_assets(clients, [denom.clone()], 1)
+            .await;
+        resolved.remove(&denom).expect("the denom is resolved")
+    }
+
+    /// The ANS name of a native denom, `None` whatever the reason it can't be named.
+    pub async fn resolve_native_asset_opt(
+        &self,
+        clients: &ChainClients,
+        denom: String,
+    ) -> Option<String> {
+        self.resolve_native_asset(clients, denom)
+            .await
+            .ok()
+            .map(|resolved| resolved.name)
+    }
+
+    /// Resolve the ANS names of many native denoms of the chain of `clients`.
+    ///
+    /// The denom traces of all the IBC denoms are fetched first, with at most `concurrency` queries
+    /// in flight, after which the names are matched in memory. Local denoms (`uluna`, ...) and IBC
+    /// denoms without a trace are looked up in the chain's own asset list instead, token factory
+    /// denoms too but with a made up name as fallback. Pool shares (`gamm/pool/<id>`) and denoms
+    /// that can't be named get the reason instead.
+    pub async fn resolve_native_assets(
+        &self,
+        clients: &ChainClients,
+        denoms: impl IntoIterator<Item = String>,
+        concurrency: usize,
+    ) -> BTreeMap<String, Result<ResolvedAsset, ResolveError>> {
+        let denoms: BTreeSet<String> = denoms.into_iter().collect();
+
+        let traces: HashMap<&str, Result<DenomTrace, String>> = stream::iter(
+            denoms
+                .iter()
+                .filter_map(|denom| ibc_denom_hash(denom).map(|hash| (denom.as_str(), hash))),
+        )
+        .map(|(denom, hash)| async move { (denom, self.denom_trace(clients, hash).await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+
+        // Origin chain of the denoms that came over a single channel, from the channel's client
+        let origin_chains: HashMap<&str, String> = stream::iter(traces.iter().filter_map(
+            |(denom, trace)| match trace.as_ref().ok()?.hops()[..] {
+                [(_, channel_id)] => Some((*denom, channel_id)),
+                _ => None,
+            },
+        ))
+        .map(|(denom, channel_id)| async move {
+            (denom, self.channel_chain(clients, channel_id).await)
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|(denom, origin_chain)| async move {
+            match origin_chain {
+                Ok(origin_chain) => Some((denom, origin_chain)),
+                Err(err) => {
+                    log::warn!(
+                        "Can't tell the origin chain of {}, matching its base denom on any chain: {}",
+                        denom,
+                        err
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+        .await;
+
+        let source_chains = traces.iter().filter_map(|(denom, trace)| {
+            let trace = trace.as_ref().ok()?;
+            match origin_chains.get(denom) {
+                Some(origin_chain) => Some(origin_chain.as_str()),
+                None if self.find_by_denom(&trace.base_denom).is_none() => self
+                    .inner
+                    .source_chains
+                    .get(&trace.base_denom)
+                    .map(String::as_str),
+                None => None,
+            }
+        });
+        self.fetch_source_chains(source_chains).await;
+
+        denoms
+            .iter()
+            .map(|denom| {
+                if let Some(pool_id) = pool_share_id(denom) {
+                    let err = ResolveError::PoolShare {
+                        denom: denom.clone(),
+                        pool_id: pool_id.to_string(),
+                    };
+                    return (denom.clone(), Err(err));
+                }
+                if let Some((_, subdenom)) = factory_denom_parts(denom) {
+                    let resolved = self.name_factory_asset(&clients.chain_id, denom, subdenom);
+                    return (denom.clone(), resolved);
+                }
+
+                let resolved = match traces.get(denom.as_str()) {
+                    Some(Ok(trace)) => self
+                        .name_traced_asset(
+                            denom,
+                            trace,
+                            origin_chains.get(denom.as_str()).map(String::as_str),
+                        )
+                        .map(ResolvedAsset::from),
+                    Some(Err(err)) => self
+                        .name_chain_asset(&clients.chain_id, denom)
+                        .map(ResolvedAsset::from)
+                        .map_err(|_| ResolveError::DenomTrace {
+                            denom: denom.clone(),
+                            error: err.clone(),
+                        }),
+                    None => self
+                        .name_chain_asset(&clients.chain_id, denom)
+                        .map(ResolvedAsset::from)
+                        .map_err(ResolveError::NoRegistryMatch),
+                };
+                (denom.clone(), resolved)
+            })
+            .collect()
+    }
+
+    /// Fetch the asset lists of the chains IBC denoms were transferred from, for the chains that
+    /// aren't in the registry yet.
+    ///
+    /// Every chain is fetched at most once, chains that fail are not retried.
+    async fn fetch_source_chains<'c>(&self, chains: impl Iterator<Item = &'c str>) {
+        let Some(options) = &self.inner.options else {
+            return;
+        };
+        let missing: BTreeSet<&str> = {
+            let source_chain_lists = self.inner.source_chain_lists.read().unwrap();
+            chains
+                .filter(|chain| {
+                    !self.inner.requested_chains.contains(*chain)
+                        && !source_chain_lists.contains_key(*chain)
+                })
+                .collect()
+        };
+        if missing.is_empty() {
+            return;
+        }
+
+        let missing: Vec<&str> = missing.into_iter().collect();
+        log::info!(
+            "Fetching asset lists of source chains {}",
+            missing.join(", ")
+        );
+        let fetched = match Self::fetch_asset_lists(&missing, options).await {
+            Ok(fetched) => fetched,
+            Err(err) => {
+                log::warn!(
+                    "Failed to fetch the asset lists of source chains: {:#}",
+                    err
+                );
+                missing.iter().map(|chain| (*chain, None)).collect()
+            }
+        };
+
+        let mut source_chain_lists = self.inner.source_chain_lists.write().unwrap();
+        for (chain, list) in fetched {
+            source_chain_lists.insert(chain.to_string(), list.map(|list| list.list));
+        }
     }
 
-    pub async fn resolve_native_asset(&self, chain: Daemon, denom: String) -> Option<String> {
-        let ibc = Ibc::new(chain.state.grpc_channel.clone());
+    /// Chain registry name of the counterparty chain of a transfer channel of the chain of
+    /// `clients`.
+    async fn channel_chain(
+        &self,
+        clients: &ChainClients,
+        channel_id: &str,
+    ) -> Result<String, String> {
+        let chain_id = self.channel_counterparty(clients, channel_id).await?;
+        networks::registry_chain_name(&chain_id)
+            .map(str::to_string)
+            .ok_or_else(|| format!("no chain registry name for chain id {}", chain_id))
+    }
 
-        let denom_trace = ibc.denom_trace(denom.clone()).await.ok()?;
+    /// Chain id of the counterparty chain of a transfer channel of the chain of `clients`,
+    /// memoized for the lifetime of the registry.
+    pub(crate) async fn channel_counterparty(
+        &self,
+        clients: &ChainClients,
+        channel_id: &str,
+    ) -> Result<String, String> {
+        let key = (clients.chain_id.clone(), channel_id.to_string());
+        if let Some(chain_id) = self.inner.channel_chains.read().unwrap().get(&key) {
+            return chain_id.clone();
+        }
+        if clients.offline {
+            return Err(format!("{} is not queried offline", channel_id));
+        }
 
-        log::info!("Denom trace for {}: {:?}", denom, denom_trace);
+        let chain_id =
+            channel_counterparty_chain_id(clients.channel.clone(), TRANSFER_PORT, channel_id)
+                .await
+                .map_err(|err| format!("{:#}", err));
+        log::debug!(
+            "Counterparty of {} on {}: {:?}",
+            channel_id,
+            clients.chain_id,
+            chain_id
+        );
+        self.inner
+            .channel_chains
+            .write()
+            .unwrap()
+            .insert(key, chain_id.clone());
+        chain_id
+    }
+
+    /// Get the denom trace of an IBC hash, memoized for the lifetime of the registry and in the
+    /// query cache of `clients`.
+    pub(crate) async fn denom_trace(
+        &self,
+        clients: &ChainClients,
+        hash: &str,
+    ) -> Result<DenomTrace, String> {
+        if let Some(trace) = self.inner.denom_traces.read().unwrap().get(hash) {
+            return Ok(trace.clone());
+        }
+        if let Some(trace) = clients.query_cache.denom_trace(hash) {
+            return Ok(trace);
+        }
+        if clients.offline {
+            return Err(format!(
+                "denom trace of {} is not cached, can't query it offline",
+                hash
+            ));
+        }
 
-        let path = denom_trace.path;
-        let mut path_parts = path.split('/');
+        let raw_trace = retry(format!("denom trace of {}", hash), || async {
+            clients
+                .ibc
+                .denom_trace(hash.to_string())
+                .await
+                .map_err(anyhow::Error::from)
+        })
+        .await
+        .map_err(|e| format!("denom trace query for {} failed: {:#}", hash, e))?;
+        let trace = DenomTrace::new(&raw_trace.path, &raw_trace.base_denom);
 
-        let port_id = path_parts.next().unwrap();
+        log::info!("Denom trace for {}: {:?}", hash, trace);
+        clients.query_cache.insert_denom_trace(hash, trace.clone());
+        self.inner
+            .denom_traces
+            .write()
+            .unwrap()
+            .insert(hash.to_string(), trace.clone());
+        Ok(trace)
+    }
 
-        if port_id != "transfer" {
+    /// Name an IBC asset from its denom trace using the registry asset lists.
+    ///
+    /// When the chain it came from is known, only the asset list of that chain is searched for
+    /// the base denom.
+    pub(crate) fn name_traced_asset(
+        &self,
+        denom: &str,
+        denom_trace: &DenomTrace,
+        origin_chain: Option<&str>,
+    ) -> Result<AssetName, ResolveError> {
+        let hops = denom_trace.hops();
+        let port_id = hops
+            .iter()
+            .map(|(port_id, _)| *port_id)
+            .find(|port_id| *port_id != TRANSFER_PORT);
+        if hops.is_empty() || port_id.is_some() {
+            let port_id = port_id.unwrap_or_default();
             log::warn!(
                 "Denom trace path for {} is not transfer, but {}",
                 denom,
                 port_id
             );
-            return None;
+            return Err(ResolveError::NonTransferPort {
+                denom: denom.to_string(),
+                port: port_id.to_string(),
+            });
+        }
+        if hops.len() > 1 && !self.inner.allow_multihop {
+            log::warn!(
+                "Skipping {}, it travelled {} hops: {}",
+                denom,
+                hops.len(),
+                denom_trace.path
+            );
+            return Err(ResolveError::MultiHop {
+                denom: denom.to_string(),
+                path: denom_trace.path.clone(),
+            });
         }
 
-        let base_denom = denom_trace.base_denom;
-
+        let base_denom = &denom_trace.base_denom;
         log::info!("Base denom for {} is {}", denom, base_denom);
 
-        for asset_list in self.asset_lists.clone() {
-            let ChainRegistryAssetList {
-                chain_name, assets, ..
-            } = asset_list;
-
-            if let Some(matching_asset) = assets.iter().find(|asset| {
-                asset
-                    .denom_units
-                    .iter()
-                    .any(|denom_unit| denom_unit.denom == base_denom)
-            }) {
-                return Some(format!(
-                    "{}>{}",
-                    chain_name.to_ascii_lowercase(),
-                    matching_asset.symbol.to_ascii_lowercase()
-                ));
+        self.name_origin_asset(base_denom, origin_chain)
+            .map_err(|err| {
+                log::warn!("Can't name asset {}: {}", denom, err);
+                ResolveError::NoRegistryMatch(err)
+            })
+    }
+
+    /// Name the asset with `base_denom` after the chain that issued it.
+    ///
+    /// With a known `origin_chain` only its asset list is searched. Otherwise the chain the IBC
+    /// traces of the registry point at is preferred, other chains can list the same base denom
+    /// (factory denoms, assets that went through several hops), and then the first asset with
+    /// the denom wins, including the lists of source chains fetched on demand.
+    fn name_origin_asset(
+        &self,
+        base_denom: &str,
+        origin_chain: Option<&str>,
+    ) -> Result<AssetName, String> {
+        let name_asset = |asset_list: &ChainRegistryAssetList, asset: &ChainRegistryAsset| {
+            asset_entry_name(&asset_list.chain_name, &asset.symbol).map_err(|err| err.to_string())
+        };
+        let source_chain_lists = self.inner.source_chain_lists.read().unwrap();
+        let find_on_chain = |chain: &str| {
+            self.inner
+                .asset_lists
+                .iter()
+                .chain(source_chain_lists.values().flatten())
+                .filter(|asset_list| asset_list.chain_name == chain)
+                .find_map(|asset_list| {
+                    find_in_asset_list(asset_list, base_denom).map(|asset| (asset_list, asset))
+                })
+        };
+
+        if let Some(origin_chain) = origin_chain {
+            let (asset_list, asset) = find_on_chain(origin_chain).ok_or_else(|| {
+                format!(
+                    "no {} registry asset for base denom {}",
+                    origin_chain, base_denom
+                )
+            })?;
+            return name_asset(asset_list, asset);
+        }
+
+        if let Some(origin_chain) = self.inner.source_chains.get(base_denom) {
+            if let Some((asset_list, asset)) = find_on_chain(origin_chain.as_str()) {
+                return name_asset(asset_list, asset);
             }
         }
 
-        None
+        if let Some((asset_list, asset)) = self.find_by_denom(base_denom) {
+            return name_asset(asset_list, asset);
+        }
+        // Fall back to the lists of the source chains fetched on demand
+        let (asset_list, asset) = source_chain_lists
+            .values()
+            .flatten()
+            .find_map(|asset_list| {
+                find_in_asset_list(asset_list, base_denom).map(|asset| (asset_list, asset))
+            })
+            .ok_or_else(|| format!("no registry asset for base denom {}", base_denom))?;
+        name_asset(asset_list, asset)
+    }
+
+    /// Name a denom issued on the chain itself (native or token factory denom) from the chain's
+    /// own asset list.
+    ///
+    /// Only the chain's list is searched, other chains may list the same denom first.
+    pub fn name_local_asset(&self, chain_name: &str, denom: &str) -> Result<AssetName, String> {
+        let asset = self
+            .inner
+            .asset_lists
+            .iter()
+            .filter(|asset_list| asset_list.chain_name == chain_name)
+            .find_map(|asset_list| find_in_asset_list(asset_list, denom))
+            .ok_or_else(|| format!("no {} registry asset for {}", chain_name, denom))?;
+        asset_entry_name(chain_name, &asset.symbol).map_err(|err| err.to_string())
     }
 
-    pub fn asset_by_denom(&self, denom: String) -> Option<ChainRegistryAsset> {
-        for asset_list in self.asset_lists.clone() {
-            let ChainRegistryAssetList { assets, .. } = asset_list;
+    /// Name a denom of the chain with id `chain_id` from the chain's own asset list.
+    fn name_chain_asset(&self, chain_id: &str, denom: &str) -> Result<AssetName, String> {
+        let chain_name = networks::registry_chain_name(chain_id)
+            .ok_or_else(|| format!("no chain registry name for chain id {}", chain_id))?;
+        self.name_local_asset(chain_name, denom)
+    }
 
-            if let Some(matching_asset) = assets.iter().find(|asset| {
-                asset
-                    .denom_units
-                    .iter()
-                    .any(|denom_unit| denom_unit.denom == denom)
-            }) {
-                return Some(matching_asset.clone());
-            }
+    /// Name a token factory denom (`factory/<creator>/<subdenom>`) of the chain with id
+    /// `chain_id`.
+    ///
+    /// The chain's own asset list has the curated name. Without it the subdenom is named under the
+    /// chain's ANS prefix, which is a guess and returned as a low-confidence name.
+    fn name_factory_asset(
+        &self,
+        chain_id: &str,
+        denom: &str,
+        subdenom: &str,
+    ) -> Result<ResolvedAsset, ResolveError> {
+        if let Ok(name) = self.name_chain_asset(chain_id, denom) {
+            return Ok(name.into());
         }
 
-        None
+        let factory_error = |error: String| ResolveError::FactoryDenom {
+            denom: denom.to_string(),
+            error,
+        };
+        let prefix = networks::ans_prefix(chain_id)
+            .ok()
+            .or_else(|| networks::registry_chain_name(chain_id))
+            .ok_or_else(|| factory_error(format!("no ANS prefix for chain id {}", chain_id)))?;
+        let name =
+            asset_entry_name(prefix, subdenom).map_err(|err| factory_error(err.to_string()))?;
+        log::warn!(
+            "No registry asset for token factory denom {}, naming it {}",
+            denom,
+            name.entry
+        );
+        Ok(ResolvedAsset {
+            low_confidence: true,
+            ..name.into()
+        })
+    }
+
+    /// Find the first asset (and its asset list) that has a denom unit matching `denom`.
+    pub fn find_by_denom(
+        &self,
+        denom: &str,
+    ) -> Option<(&ChainRegistryAssetList, &ChainRegistryAsset)> {
+        let (list_idx, asset_idx) = *self.inner.denom_index.get(denom)?;
+        let asset_list = &self.inner.asset_lists[list_idx];
+        Some((asset_list, &asset_list.assets[asset_idx]))
+    }
+
+    pub fn asset_by_denom(&self, denom: &str) -> Option<&ChainRegistryAsset> {
+        self.find_by_denom(denom).map(|(_, asset)| asset)
+    }
+
+    /// The cw20 at `addr` in the asset list of `chain_name`, listed with the `cw20:<addr>` base
+    /// denom.
+    pub fn asset_by_cw20_address(
+        &self,
+        chain_name: &str,
+        addr: &Addr,
+    ) -> Option<&ChainRegistryAsset> {
+        let base = format!("{}{}", REGISTRY_CW20_PREFIX, addr);
+        self.inner
+            .asset_lists
+            .iter()
+            .filter(|asset_list| asset_list.chain_name == chain_name)
+            .flat_map(|asset_list| &asset_list.assets)
+            .find(|asset| asset.base == base)
+    }
+}
+
+/// The first asset of `asset_list` that has a denom unit matching `denom`.
+fn find_in_asset_list<'a>(
+    asset_list: &'a ChainRegistryAssetList,
+    denom: &str,
+) -> Option<&'a ChainRegistryAsset> {
+    asset_list
+        .assets
+        .iter()
+        .find(|asset| asset.denom_units.iter().any(|unit| unit.denom == denom))
+}
+
+/// `(base denom, chain)` of the IBC traces in a raw asset list, i.e. which chain every IBC asset
+/// of the list was transferred from.
+fn ibc_source_chains(asset_list: &Value) -> Vec<(String, String)> {
+    let Some(assets) = asset_list.get("assets").and_then(Value::as_array) else {
+        return vec![];
+    };
+    assets
+        .iter()
+        .filter_map(|asset| asset.get("traces")?.as_array())
+        .flatten()
+        .filter(|trace| trace.get("type").and_then(Value::as_str) == Some("ibc"))
+        .filter_map(|trace| {
+            let counterparty = trace.get("counterparty")?;
+            Some((
+                counterparty.get("base_denom")?.as_str()?.to_string(),
+                counterparty.get("chain_name")?.as_str()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::http::set_offline;
+    use crate::helpers::http::tests::FETCHING;
+    use crate::test_utils::{AssetListBuilder, DenomTraceFixture};
+    use std::path::Path;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const TERRA2: &str = include_str!("../../tests/fixtures/asset_lists/terra2.json");
+
+    fn options(server: &MockServer, name: &str) -> ChainRegistryOptions {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "ans-scraper-registry-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        ChainRegistryOptions {
+            cache_dir,
+            registry_url: server.uri(),
+            ..Default::default()
+        }
+    }
+
+    fn asset_list(status: u16) -> Mock {
+        Mock::given(method("GET"))
+            .and(path("/terra2/assetlist.json"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(TERRA2))
+    }
+
+    async fn fetch(chains: &[&str], options: &ChainRegistryOptions) -> ChainRegistry {
+        match ChainRegistry::for_chains(chains, options.clone()).await {
+            Ok(registry) => registry,
+            Err(err) => panic!("fetching {:?}: {}", chains, err),
+        }
+    }
+
+    fn cache_files(cache_dir: &Path) -> BTreeSet<String> {
+        std::fs::read_dir(cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn denom_index_keeps_the_first_listed_asset() {
+        let terra2 = AssetListBuilder::chain("terra2")
+            .asset("LUNA", "uluna", 6)
+            .build();
+        let other = AssetListBuilder::chain("other")
+            .asset("FAKE", "uluna", 6)
+            .asset("OTHER", "uother", 6)
+            .build();
+        let registry = ChainRegistry::from_asset_lists(vec![terra2, other]);
+
+        let (asset_list, asset) = registry.find_by_denom("uluna").unwrap();
+        assert_eq!(asset_list.chain_name, "terra2");
+        assert_eq!(asset.symbol, "LUNA");
+        // every denom unit is indexed, not only the base denom
+        assert_eq!(registry.asset_by_denom("luna").unwrap().symbol, "LUNA");
+        assert_eq!(registry.asset_by_denom("uother").unwrap().symbol, "OTHER");
+        assert!(registry.find_by_denom("uatom").is_none());
+    }
+
+    #[tokio::test]
+    async fn local_denoms_are_named_from_the_chain_asset_list() {
+        let clients = ChainClients::offline("phoenix-1");
+        let atom = DenomTraceFixture::transfer("channel-0", "uatom");
+        clients
+            .query_cache
+            .insert_denom_trace(&atom.hash(), atom.trace());
+        // listed by terra2, but its trace isn't cached
+        let usdc = DenomTraceFixture::transfer("channel-1", "uusdc");
+        let registry = ChainRegistry::from_asset_lists(vec![
+            AssetListBuilder::chain("other")
+                .asset("FAKE", "uluna", 6)
+                .build(),
+            AssetListBuilder::chain("terra2")
+                .asset("LUNA", "uluna", 6)
+                .ibc_asset("USDC", &usdc, "noble", 6)
+                .build(),
+            AssetListBuilder::chain("cosmoshub")
+                .asset("ATOM", "uatom", 6)
+                .build(),
+        ]);
+
+        let resolved = registry
+            .resolve_native_assets(
+                &clients,
+                ["uluna".to_string(), atom.ibc_denom(), usdc.ibc_denom()],
+                1,
+            )
+            .await;
+        let names: BTreeMap<String, String> = resolved
+            .into_iter()
+            .map(|(denom, resolved)| (denom, resolved.unwrap().name))
+            .collect();
+
+        assert_eq!(
+            names,
+            BTreeMap::from([
+                ("uluna".to_string(), "terra2>luna".to_string()),
+                (atom.ibc_denom(), "cosmoshub>atom".to_string()),
+                (usdc.ibc_denom(), "terra2>usdc".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn traced_denoms_are_named_after_every_hop() {
+        let mut registry =
+            ChainRegistry::from_asset_lists(vec![AssetListBuilder::chain("cosmoshub")
+                .asset("ATOM", "uatom", 6)
+                .build()]);
+        let name = |registry: &ChainRegistry, path: &str| {
+            registry
+                .name_traced_asset("ibc/ATOM", &DenomTrace::new(path, "uatom"), None)
+                .map(|name| name.entry)
+        };
+
+        assert_eq!(
+            name(&registry, "transfer/channel-0").unwrap(),
+            "cosmoshub>atom"
+        );
+        assert!(matches!(
+            name(&registry, "transfer/channel-1/transfer/channel-0"),
+            Err(ResolveError::MultiHop { path, .. }) if path == "transfer/channel-1/transfer/channel-0"
+        ));
+        assert!(matches!(
+            name(&registry, "transfer/channel-1/wasm.terra1bridge/channel-0"),
+            Err(ResolveError::NonTransferPort { port, .. }) if port == "wasm.terra1bridge"
+        ));
+        assert!(matches!(
+            name(&registry, ""),
+            Err(ResolveError::NonTransferPort { .. })
+        ));
+
+        Arc::get_mut(&mut registry.inner).unwrap().allow_multihop = true;
+        assert_eq!(
+            name(&registry, "transfer/channel-1/transfer/channel-0").unwrap(),
+            "cosmoshub>atom"
+        );
+    }
+
+    #[test]
+    fn clones_share_the_registry() {
+        let terra2 = AssetListBuilder::chain("terra2")
+            .asset("LUNA", "uluna", 6)
+            .build();
+        let registry = ChainRegistry::from_asset_lists(vec![terra2]);
+        let clone = registry.clone();
+
+        assert!(Arc::ptr_eq(&registry.inner, &clone.inner));
+        assert!(std::ptr::eq(
+            registry.asset_by_denom("uluna").unwrap(),
+            clone.asset_by_denom("uluna").unwrap()
+        ));
+        // what one handle caches, the others see
+        registry.inner.denom_traces.write().unwrap().insert(
+            "ABC".to_string(),
+            DenomTrace::new("transfer/channel-0", "uatom"),
+        );
+        assert!(clone.inner.denom_traces.read().unwrap().contains_key("ABC"));
+    }
+
+    #[tokio::test]
+    async fn fetched_asset_list_is_parsed_and_cached() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        asset_list(200).expect(1).mount(&server).await;
+        let options = options(&server, "fetch");
+
+        let registry = fetch(&["terra2"], &options).await;
+        assert_eq!(registry.get_asset_lists().len(), 1);
+        assert_eq!(registry.get_asset_lists()[0].assets[0].base, "uluna");
+        // the asset that drifted from the schema is dropped, not the list
+        assert_eq!(registry.skipped_assets().len(), 1);
+        assert!(registry.failed_chains().is_empty());
+        // written in one go, no temporary file or lock is left behind
+        assert_eq!(
+            cache_files(&options.cache_dir),
+            BTreeSet::from(["terra2.json".to_string(), "terra2.json.meta".to_string()])
+        );
+        assert_eq!(
+            std::fs::read_to_string(options.cache_dir.join("terra2.json")).unwrap(),
+            TERRA2
+        );
+
+        // fresh, served from the cache
+        fetch(&["terra2"], &options).await;
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_asset_list_is_revalidated() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/terra2/assetlist.json"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/terra2/assetlist.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string(TERRA2),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let options = options(&server, "revalidate");
+
+        fetch(&["terra2"], &options).await;
+        let refreshed = ChainRegistryOptions {
+            force_refresh: true,
+            ..options.clone()
+        };
+        let registry = fetch(&["terra2"], &refreshed).await;
+        assert_eq!(registry.get_asset_lists().len(), 1);
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn throttled_and_failing_fetches_are_retried() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        for status in [429, 500] {
+            Mock::given(method("GET"))
+                .and(path("/terra2/assetlist.json"))
+                .respond_with(ResponseTemplate::new(status))
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        asset_list(200).expect(1).mount(&server).await;
+        let options = options(&server, "retry");
+
+        let registry = fetch(&["terra2"], &options).await;
+        assert_eq!(registry.get_asset_lists().len(), 1);
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_asset_list_only_fails_its_chain() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        asset_list(200).mount(&server).await;
+        Mock::given(method("GET"))
+            .and(path("/renamed/assetlist.json"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let options = options(&server, "missing");
+
+        let registry = fetch(&["renamed", "terra2"], &options).await;
+        assert_eq!(registry.failed_chains(), ["renamed"]);
+        assert_eq!(registry.get_asset_lists().len(), 1);
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_source_chain_lists_are_failed_chains() {
+        let _fetching = FETCHING.lock().await;
+        let osmo = DenomTraceFixture::transfer("channel-1", "uosmo");
+        let terra2 = AssetListBuilder::chain("terra2")
+            .asset("LUNA", "uluna", 6)
+            .ibc_asset("OSMO", &osmo, "osmosis", 6);
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/terra2/assetlist.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(terra2.to_json()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/osmosis/assetlist.json"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let options = options(&server, "source-chains");
+        let registry = fetch(&["terra2"], &options).await;
+        assert!(registry.failed_chains().is_empty());
+
+        let clients = ChainClients::offline("phoenix-1");
+        clients
+            .query_cache
+            .insert_denom_trace(&osmo.hash(), osmo.trace());
+        let resolved = registry
+            .resolve_native_assets(&clients, [osmo.ibc_denom()], 1)
+            .await;
+
+        // the run goes on without the list, the list is only fetched once
+        assert!(matches!(
+            resolved[&osmo.ibc_denom()],
+            Err(ResolveError::NoRegistryMatch(_))
+        ));
+        assert_eq!(registry.failed_chains(), ["osmosis"]);
+        registry
+            .resolve_native_assets(&clients, [osmo.ibc_denom()], 1)
+            .await;
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn offline_only_uses_the_cached_asset_lists() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        asset_list(200).expect(0).mount(&server).await;
+        let options = options(&server, "offline");
+        std::fs::create_dir_all(&options.cache_dir).unwrap();
+        std::fs::write(options.cache_dir.join("terra2.json"), TERRA2).unwrap();
+
+        set_offline(true);
+        let cached = ChainRegistry::for_chains(&["terra2"], options.clone()).await;
+        let missing = ChainRegistry::for_chains(&["terra2", "neutron"], options.clone()).await;
+        set_offline(false);
+
+        assert_eq!(cached.ok().unwrap().get_asset_lists().len(), 1);
+        match missing.err() {
+            Some(ScraperError::RegistryFetch { chain, .. }) => assert_eq!(chain, "neutron"),
+            Some(err) => panic!("unexpected error: {}", err),
+            None => panic!("neutron isn't cached"),
+        }
+        std::fs::remove_dir_all(&options.cache_dir).unwrap();
     }
 }
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use abstract_core::objects::UncheckedChannelEntry;
+use cw_asset::AssetInfo;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use super::chain_registry::{ChainRegistry, LazyChainRegistry, TRANSFER_PORT};
+use super::clients::ChainClients;
+use super::denoms::ibc_denom_hash;
+use crate::error::ScraperError;
+
+/// ANS protocol of ICS20 transfer channels, the one the IBC client of abstract routes with.
+pub const ICS20_PROTOCOL: &str = "ics20";
+
+/// A counterparty chain the IBC assets of a scrape came from over more than one transfer channel.
+///
+/// ANS has a single channel per counterparty, neither is registered until one is picked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelConflict {
+    pub connected_chain: String,
+    /// Channel id -> IBC denoms that came over the channel
+    pub channels: BTreeMap<String, Vec<String>>,
+}
+
+/// Finds the transfer channels the IBC assets of a scrape came over, for the channel entries of
+/// ANS.
+///
+/// The denom traces and the counterparties of the channels come from the chain registry, which
+/// already looked them up to name the assets. It is only fetched if there are IBC assets.
+#[derive(Clone)]
+pub struct ChannelResolver {
+    chain_registry: LazyChainRegistry,
+    query_concurrency: usize,
+}
+
+impl ChannelResolver {
+    pub fn new(chain_registry: impl Into<LazyChainRegistry>, query_concurrency: usize) -> Self {
+        Self {
+            chain_registry: chain_registry.into(),
+            query_concurrency,
+        }
+    }
+
+    /// The `<counterparty>/ics20` channel entries of the single-hop IBC denoms of `assets`, and
+    /// the counterparties that more than one channel leads to.
+    pub async fn resolve(
+        &self,
+        clients: &ChainClients,
+        assets: &[(String, AssetInfo)],
+    ) -> Result<(Vec<(UncheckedChannelEntry, String)>, Vec<ChannelConflict>), ScraperError> {
+        let denoms: Vec<(&str, &str)> = assets
+            .iter()
+            .filter_map(|(_, info)| match info {
+                AssetInfo::Native(denom) => Some((denom.as_str(), ibc_denom_hash(denom)?)),
+                _ => None,
+            })
+            .collect();
+        if denoms.is_empty() {
+            return Ok((vec![], vec![]));
+        }
+        let chain_registry = self.chain_registry.get().await?;
+        let channels: Vec<(&str, Result<(String, String), String>)> =
+            stream::iter(denoms)
+                .map(|(denom, hash)| async move {
+                    (denom, channel(chain_registry, clients, hash).await)
+                })
+                .buffer_unordered(self.query_concurrency.max(1))
+                .collect()
+                .await;
+
+        // connected chain -> channel id -> denoms
+        let mut chains = BTreeMap::<String, BTreeMap<String, Vec<String>>>::new();
+        for (denom, channel) in channels {
+            match channel {
+                Ok((chain, channel_id)) => chains
+                    .entry(chain)
+                    .or_default()
+                    .entry(channel_id)
+                    .or_default()
+                    .push(denom.to_string()),
+                Err(err) => log::debug!("No channel entry for {}: {}", denom, err),
+            }
+        }
+
+        let mut entries = vec![];
+        let mut conflicts = vec![];
+        for (connected_chain, mut channels) in chains {
+            if channels.len() == 1 {
+                let channel_id = channels.into_keys().next().expect("one channel");
+                entries.push((
+                    UncheckedChannelEntry::new(connected_chain, ICS20_PROTOCOL.to_string()),
+                    channel_id,
+                ));
+                continue;
+            }
+            log::warn!(
+                "Assets came from {} over channels {}, not registering a channel for it",
+                connected_chain,
+                channels.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+            for denoms in channels.values_mut() {
+                denoms.sort();
+            }
+            conflicts.push(ChannelConflict {
+                connected_chain,
+                channels,
+            });
+        }
+        Ok((entries, conflicts))
+    }
+}
+
+/// `(connected chain, channel id)` of the transfer channel an IBC denom came over directly.
+async fn channel(
+    chain_registry: &ChainRegistry,
+    clients: &ChainClients,
+    hash: &str,
+) -> Result<(String, String), String> {
+    let trace = chain_registry.denom_trace(clients, hash).await?;
+    let channel_id = match trace.hops()[..] {
+        [(TRANSFER_PORT, channel_id)] => channel_id.to_string(),
+        _ => return Err(format!("{} is not a single transfer hop", trace.path)),
+    };
+    let chain_id = chain_registry
+        .channel_counterparty(clients, &channel_id)
+        .await?;
+    Ok((connected_chain_name(&chain_id), channel_id))
+}
+
+/// Name of a chain in the channel entries of ANS, its chain id without the revision number
+/// (`osmosis-1` -> `osmosis`).
+pub fn connected_chain_name(chain_id: &str) -> String {
+    match chain_id.rsplit_once('-') {
+        Some((name, revision)) if revision.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+        _ => chain_id.to_string(),
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use anyhow::Context;
+use cw_orch::queriers::{CosmWasm, DaemonQuerier, Ibc, Node};
+use cw_orch::Daemon;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+
+use super::query_cache::QueryCache;
+use super::retry::retry;
+
+/// Endpoint of the channel of offline clients, which is never connected to.
+const OFFLINE_ENDPOINT: &str = "http://127.0.0.1:9090";
+
+/// Long-lived gRPC queriers of a chain, created once per chain and shared by the registry and the
+/// scrapers so they all reuse the same connection.
+///
+/// They only need a gRPC endpoint: scraping never signs anything, so no wallet is required unless
+/// the results are registered through a [`Daemon`].
+pub struct ChainClients {
+    pub chain_id: String,
+    pub channel: Channel,
+    pub ibc: Ibc,
+    pub wasm: CosmWasm,
+    pub node: Node,
+    /// Never query the chain, everything has to come from the caches
+    pub offline: bool,
+    /// Token infos and denom traces queried through the clients
+    pub query_cache: QueryCache,
+}
+
+impl ChainClients {
+    /// The queriers of the daemon's gRPC channel.
+    pub fn new(chain: &Daemon) -> Arc<Self> {
+        Self::from_channel(
+            &chain.state.chain_id,
+            chain.state.grpc_channel.clone(),
+            false,
+        )
+    }
+
+    /// Connect to the gRPC endpoint of the chain without a daemon, for query-only runs.
+    pub async fn connect(chain_id: &str, grpc_url: &str) -> anyhow::Result<Arc<Self>> {
+        let mut endpoint = Endpoint::from_shared(grpc_url.to_string())
+            .with_context(|| format!("invalid gRPC url {}", grpc_url))?;
+        if grpc_url.starts_with("https") {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new())?;
+        }
+        let channel = retry(grpc_url, || async { Ok(endpoint.connect().await?) })
+            .await
+            .with_context(|| format!("connecting to {} at {}", chain_id, grpc_url))?;
+        Ok(Self::from_channel(chain_id, channel, false))
+    }
+
+    /// Clients that never connect to the chain, for runs that only work from the caches.
+    ///
+    /// Must be called within a tokio runtime.
+    pub fn offline(chain_id: &str) -> Arc<Self> {
+        let channel = Endpoint::from_static(OFFLINE_ENDPOINT).connect_lazy();
+        Self::from_channel(chain_id, channel, true)
+    }
+
+    /// `(block height, tx index)` of the instantiation of a contract, to tell which of two
+    /// contracts is newer. `None` offline or if it can't be queried.
+    pub async fn contract_created(&self, address: &str) -> Option<(u64, u64)> {
+        if self.offline {
+            return None;
+        }
+        let info = retry(address, || async {
+            self.wasm
+                .contract_info(address.to_string())
+                .await
+                .map_err(Into::into)
+        })
+        .await;
+        match info {
+            Ok(info) => info
+                .created
+                .map(|created| (created.block_height, created.tx_index)),
+            Err(err) => {
+                log::warn!("querying contract info of {}: {:#}", address, err);
+                None
+            }
+        }
+    }
+
+    fn from_channel(chain_id: &str, channel: Channel, offline: bool) -> Arc<Self> {
+        Arc::new(Self {
+            chain_id: chain_id.to_string(),
+            ibc: Ibc::new(channel.clone()),
+            wasm: CosmWasm::new(channel.clone()),
+            node: Node::new(channel.clone()),
+            channel,
+            offline,
+            query_cache: QueryCache::new(),
+        })
+    }
+}
@@ -0,0 +1,414 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::Addr;
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use tonic::Code;
+
+use super::chain_registry::ChainRegistry;
+use super::clients::ChainClients;
+use super::names::{asset_entry_name, AssetName};
+use super::retry::is_transient;
+use super::wasm_batch::WasmQueryBatcher;
+
+/// Highest decimals value we accept from a cw20 `TokenInfo` response.
+const MAX_CW20_DECIMALS: u8 = 18;
+
+/// Default number of `TokenInfo` (or denom trace) queries in flight at once.
+pub const DEFAULT_QUERY_CONCURRENCY: usize = 10;
+
+/// The ANS asset entry of a cw20.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cw20Entry {
+    pub entry: String,
+    /// Named after the curated symbol of the chain registry instead of the `TokenInfo` symbol,
+    /// which the deployer of the contract picked
+    pub verified: bool,
+    /// The entry before its symbol was sanitized, if sanitization changed it
+    pub unsanitized: Option<String>,
+}
+
+impl Cw20Entry {
+    fn unverified(name: AssetName) -> Self {
+        Self {
+            entry: name.entry,
+            verified: false,
+            unsanitized: name.unsanitized,
+        }
+    }
+}
+
+/// Reasons a cw20 contract could not be turned into an ANS asset entry.
+#[derive(Debug, thiserror::Error)]
+pub enum Cw20EntryError {
+    /// The contract does not answer the cw20 `TokenInfo` query (cw20-ics20 wrappers, missing contracts, ...).
+    #[error("contract {contract} does not support the cw20 TokenInfo query: {reason}")]
+    Unsupported { contract: Addr, reason: String },
+    /// The contract answered, but with a token info that can't be used to name the asset.
+    #[error("contract {contract} returned invalid token info (symbol: {symbol:?}, decimals: {decimals})")]
+    InvalidTokenInfo {
+        contract: Addr,
+        symbol: String,
+        decimals: u8,
+    },
+    /// The query itself failed (transport, node unavailable, ...). Worth retrying later.
+    #[error("failed to query cw20 contract {contract}: {error:#}")]
+    Query {
+        contract: Addr,
+        error: anyhow::Error,
+    },
+}
+
+impl Cw20EntryError {
+    /// Whether the contract itself can't be named, as opposed to a query that may succeed on the
+    /// next run.
+    pub fn is_skippable(&self) -> bool {
+        !matches!(self, Cw20EntryError::Query { .. })
+    }
+}
+
+/// Name the cw20 after its entry in the asset list of the chain, `None` if it isn't listed or
+/// its symbol isn't a valid name.
+pub(crate) fn registry_cw20_entry(
+    chain_registry: Option<&ChainRegistry>,
+    chain_ans_prefix: &str,
+    contract_addr: &Addr,
+) -> Option<Cw20Entry> {
+    let asset = chain_registry?.asset_by_cw20_address(chain_ans_prefix, contract_addr)?;
+    match asset_entry_name(chain_ans_prefix, &asset.symbol) {
+        Ok(name) => Some(Cw20Entry {
+            entry: name.entry,
+            verified: true,
+            unsanitized: name.unsanitized,
+        }),
+        Err(err) => {
+            log::warn!(
+                "Chain registry symbol of cw20 {} is not a valid name, using its token info: {}",
+                contract_addr,
+                err
+            );
+            None
+        }
+    }
+}
+
+/// Resolve the asset entries of many cw20s with at most `concurrency` queries in flight.
+///
+/// The cw20s of the chain's asset list in `chain_registry` are named after it. For the others,
+/// token infos in the query cache of `clients` are used as is, the other `TokenInfo` queries are
+/// pipelined through a [`WasmQueryBatcher`] and their responses cached. Offline clients don't
+/// query, uncached contracts fail. Results are keyed by contract address so the output order
+/// doesn't depend on query timing.
+pub async fn resolve_cw20_entries(
+    clients: &ChainClients,
+    chain_registry: Option<&ChainRegistry>,
+    chain_ans_prefix: &str,
+    contracts: impl IntoIterator<Item = Addr>,
+    concurrency: usize,
+) -> BTreeMap<Addr, Result<Cw20Entry, Cw20EntryError>> {
+    let mut entries = BTreeMap::new();
+    let mut uncached = vec![];
+    for contract_addr in contracts {
+        if let Some(entry) = registry_cw20_entry(chain_registry, chain_ans_prefix, &contract_addr) {
+            entries.insert(contract_addr, Ok(entry));
+            continue;
+        }
+        match clients.query_cache.cw20_info(&contract_addr) {
+            Some(info) => {
+                let entry = token_info_entry(chain_ans_prefix, &contract_addr, &info)
+                    .map(Cw20Entry::unverified);
+                entries.insert(contract_addr, entry);
+            }
+            None if clients.offline => {
+                let error = anyhow::anyhow!("token info is not cached, can't query it offline");
+                entries.insert(
+                    contract_addr.clone(),
+                    Err(Cw20EntryError::Query {
+                        contract: contract_addr,
+                        error,
+                    }),
+                );
+            }
+            None => uncached.push(contract_addr),
+        }
+    }
+
+    let responses = WasmQueryBatcher::new(&clients.wasm, concurrency)
+        .query_each(uncached, &Cw20QueryMsg::TokenInfo {})
+        .await;
+    for (contract_addr, response) in responses {
+        let info = parse_token_info_response(&contract_addr, response);
+        if let Ok(info) = &info {
+            clients
+                .query_cache
+                .insert_cw20_info(&contract_addr, info.clone());
+        }
+        let entry = info
+            .and_then(|info| token_info_entry(chain_ans_prefix, &contract_addr, &info))
+            .map(Cw20Entry::unverified);
+        entries.insert(contract_addr, entry);
+    }
+    entries
+}
+
+/// Deserialize the raw response of a `TokenInfo` query.
+fn parse_token_info_response(
+    contract_addr: &Addr,
+    response: anyhow::Result<Vec<u8>>,
+) -> Result<TokenInfoResponse, Cw20EntryError> {
+    let response = response.map_err(|e| classify_query_error(contract_addr, e))?;
+    serde_json::from_slice(&response).map_err(|e| Cw20EntryError::Unsupported {
+        contract: contract_addr.clone(),
+        reason: format!("unexpected TokenInfo response: {}", e),
+    })
+}
+
+/// Validate the token info and turn it into the asset entry name.
+pub(crate) fn token_info_entry(
+    chain_ans_prefix: &str,
+    contract_addr: &Addr,
+    info: &TokenInfoResponse,
+) -> Result<AssetName, Cw20EntryError> {
+    validate_token_info(contract_addr, info)?;
+
+    asset_entry_name(chain_ans_prefix, &info.symbol).map_err(|_| Cw20EntryError::InvalidTokenInfo {
+        contract: contract_addr.clone(),
+        symbol: info.symbol.clone(),
+        decimals: info.decimals,
+    })
+}
+
+/// Check that the token info looks like something a regular cw20 would return.
+pub fn validate_token_info(
+    contract_addr: &Addr,
+    info: &TokenInfoResponse,
+) -> Result<(), Cw20EntryError> {
+    let symbol_ok = !info.symbol.is_empty()
+        && info
+            .symbol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    if !symbol_ok || info.decimals > MAX_CW20_DECIMALS {
+        return Err(Cw20EntryError::InvalidTokenInfo {
+            contract: contract_addr.clone(),
+            symbol: info.symbol.clone(),
+            decimals: info.decimals,
+        });
+    }
+    Ok(())
+}
+
+/// Split query failures into "the contract can't answer this" and "we couldn't ask".
+///
+/// Only a final gRPC status of the node makes a contract unsupported: an unknown contract, a
+/// query it can't parse, or an error of the contract itself. Anything else, transient or not, may
+/// succeed on the next run.
+pub fn classify_query_error(contract_addr: &Addr, err: anyhow::Error) -> Cw20EntryError {
+    if !is_transient(&err) && contract_rejected_query(&err) {
+        Cw20EntryError::Unsupported {
+            contract: contract_addr.clone(),
+            reason: format!("{:#}", err),
+        }
+    } else {
+        Cw20EntryError::Query {
+            contract: contract_addr.clone(),
+            error: err,
+        }
+    }
+}
+
+/// Whether the node answered the query with an error of the contract, or of its address.
+fn contract_rejected_query(err: &anyhow::Error) -> bool {
+    let message = format!("{:#}", err).to_ascii_lowercase();
+    match grpc_code(err, &message) {
+        Some(Code::NotFound | Code::InvalidArgument) => true,
+        // wasmd reports the failures of the contract as unknown, like some failures of its own
+        Some(Code::Unknown) => CONTRACT_ERROR_MESSAGES
+            .iter()
+            .any(|pattern| message.contains(pattern)),
+        _ => false,
+    }
+}
+
+/// Messages of wasmd for queries the contract (or its address) failed.
+const CONTRACT_ERROR_MESSAGES: [&str; 2] = ["query wasm contract failed", "no such contract"];
+
+/// Code of the gRPC status of the error, from the message when cw-orch only kept that.
+fn grpc_code(err: &anyhow::Error, message: &str) -> Option<Code> {
+    if let Some(status) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<tonic::Status>())
+    {
+        return Some(status.code());
+    }
+    [
+        ("status: notfound", Code::NotFound),
+        ("status: invalidargument", Code::InvalidArgument),
+        ("status: unknown", Code::Unknown),
+    ]
+    .into_iter()
+    .find(|(pattern, _)| message.contains(pattern))
+    .map(|(_, code)| code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(err: anyhow::Error) -> Cw20EntryError {
+        classify_query_error(&Addr::unchecked("contract"), err)
+    }
+
+    /// The terra2 asset list of the chain registry, trimmed to LUNA and the ASTRO cw20.
+    const TERRA2: &str = include_str!("../../tests/fixtures/asset_lists/terra2_cw20.json");
+    const ASTRO: &str = "terra1nsuqsk6kh58ulczatwev87ttq2z6r3pusulg9r24mfj2fvtzd4uq3exn26";
+
+    fn terra2_registry() -> ChainRegistry {
+        let (asset_list, _) =
+            ChainRegistry::parse_asset_list("terra2", serde_json::from_str(TERRA2).unwrap())
+                .unwrap();
+        ChainRegistry::from_asset_lists(vec![asset_list])
+    }
+
+    #[test]
+    fn listed_cw20s_are_named_after_the_registry() {
+        let registry = terra2_registry();
+
+        assert_eq!(
+            registry_cw20_entry(Some(&registry), "terra2", &Addr::unchecked(ASTRO)),
+            Some(Cw20Entry {
+                entry: "terra2>astro".to_string(),
+                verified: true,
+                unsanitized: None,
+            })
+        );
+        assert_eq!(
+            registry_cw20_entry(Some(&registry), "terra2", &Addr::unchecked("terra1other")),
+            None
+        );
+        // the cw20 of another chain
+        assert_eq!(
+            registry_cw20_entry(Some(&registry), "neutron", &Addr::unchecked(ASTRO)),
+            None
+        );
+        assert_eq!(
+            registry_cw20_entry(None, "terra2", &Addr::unchecked(ASTRO)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn unlisted_cw20s_fall_back_to_their_token_info() {
+        let registry = terra2_registry();
+        let clients = ChainClients::offline("phoenix-1");
+        clients
+            .query_cache
+            .insert_cw20_info(&Addr::unchecked("terra1other"), token_info("OTHER", 6));
+
+        let entries = resolve_cw20_entries(
+            &clients,
+            Some(&registry),
+            "terra2",
+            [Addr::unchecked(ASTRO), Addr::unchecked("terra1other")],
+            DEFAULT_QUERY_CONCURRENCY,
+        )
+        .await;
+
+        // the registry name is used without querying, the token info isn't cached
+        let astro = entries[&Addr::unchecked(ASTRO)].as_ref().unwrap();
+        assert_eq!(
+            (astro.entry.as_str(), astro.verified),
+            ("terra2>astro", true)
+        );
+        let other = entries[&Addr::unchecked("terra1other")].as_ref().unwrap();
+        assert_eq!(
+            (other.entry.as_str(), other.verified),
+            ("terra2>other", false)
+        );
+    }
+
+    fn token_info(symbol: &str, decimals: u8) -> TokenInfoResponse {
+        TokenInfoResponse {
+            name: "Token".to_string(),
+            symbol: symbol.to_string(),
+            decimals,
+            total_supply: Default::default(),
+        }
+    }
+
+    #[test]
+    fn regular_token_infos_are_named() {
+        let contract = Addr::unchecked("contract");
+        let name = token_info_entry("terra2", &contract, &token_info("ASTRO", 6)).unwrap();
+        assert_eq!(name.entry, "terra2>astro");
+    }
+
+    #[test]
+    fn odd_token_infos_are_invalid() {
+        let contract = Addr::unchecked("contract");
+        for info in [
+            token_info("", 6),
+            token_info("AS TRO", 6),
+            token_info("ASTRO/LP", 6),
+            token_info("ASTRO", MAX_CW20_DECIMALS + 1),
+        ] {
+            let err = token_info_entry("terra2", &contract, &info).unwrap_err();
+            assert!(
+                matches!(err, Cw20EntryError::InvalidTokenInfo { .. }),
+                "{:?}",
+                info
+            );
+            assert!(err.is_skippable());
+        }
+    }
+
+    #[test]
+    fn non_cw20_responses_are_unsupported() {
+        let contract = Addr::unchecked("contract");
+        let response = br#"{"channels":[]}"#.to_vec();
+        let err = parse_token_info_response(&contract, Ok(response)).unwrap_err();
+        assert!(matches!(err, Cw20EntryError::Unsupported { .. }));
+
+        let response = serde_json::to_vec(&token_info("ASTRO", 6)).unwrap();
+        let info = parse_token_info_response(&contract, Ok(response)).unwrap();
+        assert_eq!(info, token_info("ASTRO", 6));
+    }
+
+    #[test]
+    fn contract_errors_are_unsupported() {
+        let err = tonic::Status::new(
+            Code::Unknown,
+            "query wasm contract failed: Error parsing into type cw20_ics20::msg::QueryMsg",
+        );
+        assert!(classify(err.into()).is_skippable());
+
+        let err = tonic::Status::new(Code::NotFound, "contract: not found");
+        assert!(classify(err.into()).is_skippable());
+    }
+
+    #[test]
+    fn transient_errors_are_retried_later_whatever_their_message() {
+        let err = tonic::Status::new(Code::Unavailable, "query wasm contract failed: not found");
+        assert!(!classify(err.into()).is_skippable());
+
+        let err = anyhow::anyhow!("transport error: connection reset, peer not found");
+        assert!(!classify(err).is_skippable());
+    }
+
+    #[test]
+    fn unknown_status_without_a_contract_error_is_retried_later() {
+        let err = tonic::Status::new(Code::Unknown, "block not found, height is not available");
+        assert!(!classify(err.into()).is_skippable());
+    }
+
+    #[test]
+    fn status_in_the_message_is_classified() {
+        let err = anyhow::anyhow!(
+            "status: Unknown, message: \"query wasm contract failed: unknown variant `token_info`\""
+        );
+        assert!(classify(err).is_skippable());
+
+        let err = anyhow::anyhow!("status: Internal, message: \"no such contract\"");
+        assert!(!classify(err).is_skippable());
+    }
+}
@@ -0,0 +1,172 @@
+use cw20::{Cw20QueryMsg, TokenInfoResponse};
+use cw_asset::AssetInfo;
+use ibc_chain_registry::asset_list::Asset as ChainRegistryAsset;
+use serde::{Deserialize, Serialize};
+
+use super::chain_registry::{ChainRegistry, LazyChainRegistry};
+use super::clients::ChainClients;
+use super::denoms::ibc_denom_hash;
+use super::wasm_batch::WasmQueryBatcher;
+use crate::error::ScraperError;
+
+/// An asset of the output with the number of decimals of its display unit, written to
+/// `asset_metadata.json` next to the assets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetMetadata {
+    pub name: String,
+    pub info: AssetInfo,
+    /// `None` if neither the chain registry nor the chain know it, or if they disagree
+    pub decimals: Option<u8>,
+}
+
+/// An asset whose decimals differ between the chain registry and its cw20 `TokenInfo`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecimalsMismatch {
+    pub name: String,
+    pub info: AssetInfo,
+    /// Exponent of the display unit in the chain registry
+    pub registry: u8,
+    /// Decimals of the `TokenInfo` of the cw20
+    pub onchain: u8,
+}
+
+/// Looks the decimals of the scraped assets up.
+///
+/// Native denoms get the exponent of the display unit of their chain registry asset, IBC denoms
+/// the one of the asset they were traced to. cw20s get the decimals of their `TokenInfo`, which
+/// is checked against the chain registry if the cw20 is listed there.
+///
+/// The chain registry is only fetched for native denoms, cw20s are only checked against it once
+/// something else fetched it.
+#[derive(Clone)]
+pub struct DecimalsResolver {
+    chain_registry: LazyChainRegistry,
+    /// Chain registry name of the chain, whose asset list has its cw20s
+    chain_name: String,
+    query_concurrency: usize,
+}
+
+impl DecimalsResolver {
+    pub fn new(
+        chain_registry: impl Into<LazyChainRegistry>,
+        chain_name: impl Into<String>,
+        query_concurrency: usize,
+    ) -> Self {
+        Self {
+            chain_registry: chain_registry.into(),
+            chain_name: chain_name.into(),
+            query_concurrency,
+        }
+    }
+
+    /// The decimals of `assets`, and the ones the chain registry and the chain disagree on.
+    ///
+    /// Token infos come from the query cache of `clients`, the missing ones are queried (unless
+    /// offline) and cached.
+    pub async fn resolve(
+        &self,
+        clients: &ChainClients,
+        assets: &[(String, AssetInfo)],
+    ) -> Result<(Vec<AssetMetadata>, Vec<DecimalsMismatch>), ScraperError> {
+        let has_natives = assets
+            .iter()
+            .any(|(_, info)| matches!(info, AssetInfo::Native(_)));
+        let chain_registry = if has_natives {
+            Some(self.chain_registry.get().await?)
+        } else {
+            self.chain_registry.get_if_initialized()
+        };
+        let uncached = assets
+            .iter()
+            .filter_map(|(_, info)| match info {
+                AssetInfo::Cw20(addr) if clients.query_cache.cw20_info(addr).is_none() => {
+                    Some(addr.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if !clients.offline && !uncached.is_empty() {
+            let responses = WasmQueryBatcher::new(&clients.wasm, self.query_concurrency)
+                .query_each(uncached, &Cw20QueryMsg::TokenInfo {})
+                .await;
+            for (addr, response) in responses {
+                match response.and_then(|response| {
+                    serde_json::from_slice::<TokenInfoResponse>(&response).map_err(Into::into)
+                }) {
+                    Ok(info) => clients.query_cache.insert_cw20_info(&addr, info),
+                    Err(err) => {
+                        log::debug!("No token info for the decimals of {}: {:#}", addr, err)
+                    }
+                }
+            }
+        }
+
+        let mut metadata = vec![];
+        let mut mismatches = vec![];
+        for (name, info) in assets {
+            let decimals = match info {
+                AssetInfo::Native(denom) => {
+                    chain_registry.and_then(|registry| native_decimals(registry, clients, denom))
+                }
+                AssetInfo::Cw20(addr) => {
+                    let onchain = clients
+                        .query_cache
+                        .cw20_info(addr)
+                        .map(|info| info.decimals);
+                    let registry = chain_registry
+                        .and_then(|registry| registry.asset_by_cw20_address(&self.chain_name, addr))
+                        .and_then(registry_decimals);
+                    match (registry, onchain) {
+                        (Some(registry), Some(onchain)) if registry != onchain => {
+                            log::warn!(
+                                "{} ({}) has {} decimals in the chain registry, {} on chain",
+                                name,
+                                info,
+                                registry,
+                                onchain
+                            );
+                            mismatches.push(DecimalsMismatch {
+                                name: name.clone(),
+                                info: info.clone(),
+                                registry,
+                                onchain,
+                            });
+                            None
+                        }
+                        _ => onchain.or(registry),
+                    }
+                }
+                _ => None,
+            };
+            metadata.push(AssetMetadata {
+                name: name.clone(),
+                info: info.clone(),
+                decimals,
+            });
+        }
+        Ok((metadata, mismatches))
+    }
+}
+
+/// Exponent of the display unit of the registry asset of `denom`, for an IBC denom the one of its
+/// base denom if the chain's asset list doesn't have it.
+fn native_decimals(
+    chain_registry: &ChainRegistry,
+    clients: &ChainClients,
+    denom: &str,
+) -> Option<u8> {
+    if let Some(asset) = chain_registry.asset_by_denom(denom) {
+        return registry_decimals(asset);
+    }
+    let trace = clients.query_cache.denom_trace(ibc_denom_hash(denom)?)?;
+    registry_decimals(chain_registry.asset_by_denom(&trace.base_denom)?)
+}
+
+/// Exponent of the display unit of a chain registry asset.
+pub fn registry_decimals(asset: &ChainRegistryAsset) -> Option<u8> {
+    asset
+        .denom_units
+        .iter()
+        .find(|unit| unit.denom == asset.display)
+        .and_then(|unit| u8::try_from(unit.exponent).ok())
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+/// Prefix of IBC voucher denoms.
+pub const IBC_DENOM_PREFIX: &str = "ibc/";
+
+/// Prefix of token factory denoms, `factory/<creator>/<subdenom>`.
+pub const FACTORY_DENOM_PREFIX: &str = "factory/";
+
+/// Prefix of the LP share denoms of osmosis pools, `gamm/pool/<id>`.
+pub const POOL_SHARE_DENOM_PREFIX: &str = "gamm/pool/";
+
+/// Length of the hex encoded sha256 hash of an IBC voucher denom.
+const IBC_HASH_LEN: usize = 64;
+
+/// Get the hash to query the denom trace for, or `None` if the denom can't be an IBC voucher.
+///
+/// Accepts both `ibc/<HASH>` and the bare hash. Anything else (`uluna`, `factory/...`, ...) is not
+/// worth a denom trace query.
+pub fn ibc_denom_hash(denom: &str) -> Option<&str> {
+    let hash = denom.strip_prefix(IBC_DENOM_PREFIX).unwrap_or(denom);
+    if hash.len() == IBC_HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash)
+    } else {
+        None
+    }
+}
+
+/// `(creator, subdenom)` of a token factory denom, or `None` if the denom isn't one.
+///
+/// The subdenom may itself contain slashes (`factory/kujira1.../ulp/usk`).
+pub fn factory_denom_parts(denom: &str) -> Option<(&str, &str)> {
+    let (creator, subdenom) = denom.strip_prefix(FACTORY_DENOM_PREFIX)?.split_once('/')?;
+    if creator.is_empty() || subdenom.is_empty() {
+        return None;
+    }
+    Some((creator, subdenom))
+}
+
+/// Pool id of an osmosis LP share denom, or `None` if the denom isn't one.
+pub fn pool_share_id(denom: &str) -> Option<&str> {
+    denom
+        .strip_prefix(POOL_SHARE_DENOM_PREFIX)
+        .filter(|id| !id.is_empty())
+}
+
+/// Normalize a denom trace into its `(path, base_denom)` parts.
+///
+/// Older nodes return the full path in `path` and the plain denom in `base_denom`. Some newer ones
+/// return an empty `path` and prefix the `base_denom` with the `port/channel` hops instead.
+pub fn normalize_denom_trace(path: &str, base_denom: &str) -> (String, String) {
+    if !path.is_empty() {
+        return (path.to_string(), base_denom.to_string());
+    }
+
+    let parts: Vec<&str> = base_denom.split('/').collect();
+    let mut hops = 0;
+    while parts.len() > (hops + 1) * 2 && parts[hops * 2 + 1].starts_with("channel-") {
+        hops += 1;
+    }
+
+    (parts[..hops * 2].join("/"), parts[hops * 2..].join("/"))
+}
+
+/// The origin of an IBC voucher, normalized from the denom trace query response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DenomTrace {
+    /// `port/channel` hops the token travelled through, e.g. `transfer/channel-0`
+    pub path: String,
+    pub base_denom: String,
+}
+
+impl DenomTrace {
+    pub fn new(path: &str, base_denom: &str) -> Self {
+        let (path, base_denom) = normalize_denom_trace(path, base_denom);
+        Self { path, base_denom }
+    }
+
+    /// Port of the first hop of the trace.
+    pub fn port_id(&self) -> Option<&str> {
+        self.path.split('/').next().filter(|p| !p.is_empty())
+    }
+
+    /// `(port, channel)` of every hop of the trace, the last hop first.
+    pub fn hops(&self) -> Vec<(&str, &str)> {
+        let segments: Vec<&str> = self.path.split('/').filter(|s| !s.is_empty()).collect();
+        segments
+            .chunks(2)
+            .map(|hop| (hop[0], hop.get(1).copied().unwrap_or_default()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATOM_HASH: &str = "27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2";
+
+    #[test]
+    fn only_ibc_hashes_are_traced() {
+        assert_eq!(
+            ibc_denom_hash(&format!("ibc/{}", ATOM_HASH)),
+            Some(ATOM_HASH)
+        );
+        assert_eq!(ibc_denom_hash(ATOM_HASH), Some(ATOM_HASH));
+        assert_eq!(ibc_denom_hash("uluna"), None);
+        assert_eq!(ibc_denom_hash("ibc/27394FB0"), None);
+        assert_eq!(
+            ibc_denom_hash(&format!("ibc/{}", ATOM_HASH.replace('F', "G"))),
+            None
+        );
+        assert_eq!(ibc_denom_hash("factory/terra1creator/ampluna"), None);
+    }
+
+    #[test]
+    fn denom_traces_are_normalized() {
+        let expected = DenomTrace {
+            path: "transfer/channel-0".to_string(),
+            base_denom: "uatom".to_string(),
+        };
+        assert_eq!(DenomTrace::new("transfer/channel-0", "uatom"), expected);
+        assert_eq!(DenomTrace::new("", "transfer/channel-0/uatom"), expected);
+
+        // the slashes of the base denom itself are kept
+        let trace = DenomTrace::new("", "transfer/channel-1/gamm/pool/1");
+        assert_eq!(trace.path, "transfer/channel-1");
+        assert_eq!(trace.base_denom, "gamm/pool/1");
+
+        let trace = DenomTrace::new("", "transfer/channel-1/transfer/channel-0/uatom");
+        assert_eq!(trace.path, "transfer/channel-1/transfer/channel-0");
+        assert_eq!(trace.base_denom, "uatom");
+
+        let trace = DenomTrace::new("", "uatom");
+        assert_eq!(trace.path, "");
+        assert_eq!(trace.base_denom, "uatom");
+        assert_eq!(trace.port_id(), None);
+    }
+
+    #[test]
+    fn every_hop_of_the_path_is_listed() {
+        let trace = DenomTrace::new("transfer/channel-1/transfer/channel-0", "uatom");
+        assert_eq!(
+            trace.hops(),
+            [("transfer", "channel-1"), ("transfer", "channel-0")]
+        );
+        assert_eq!(trace.port_id(), Some("transfer"));
+
+        let trace = DenomTrace::new("wasm.terra1bridge/channel-2", "uatom");
+        assert_eq!(trace.hops(), [("wasm.terra1bridge", "channel-2")]);
+        assert!(DenomTrace::new("", "uatom").hops().is_empty());
+    }
+}
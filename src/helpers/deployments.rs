@@ -0,0 +1,398 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use serde_json::Value;
+
+use super::cache::default_cache_dir;
+use super::http::fetch_with_cache;
+use super::json::{find_string, parse_relaxed_json, string_keys};
+
+/// `(protocol, chain id, url)` of the deployment files published by the protocols.
+const KNOWN_DEPLOYMENTS: [(&str, &str, &str); 5] = [
+    (
+        "astroport",
+        "phoenix-1",
+        "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/terra-2/phoenix-1/core_phoenix.json",
+    ),
+    (
+        "astroport",
+        "pisco-1",
+        "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/terra-2/pisco-1/core_pisco.json",
+    ),
+    (
+        "astroport",
+        "neutron-1",
+        "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/neutron/neutron-1/core_neutron.json",
+    ),
+    (
+        "astroport",
+        "pion-1",
+        "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/neutron/pion-1/core_pion.json",
+    ),
+    (
+        "astroport",
+        "injective-1",
+        "https://raw.githubusercontent.com/astroport-fi/astroport-changelog/main/injective/injective-1/core_injective.json",
+    ),
+];
+
+/// Reasons an address could not be read from a deployment file.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum DeploymentError {
+    #[error("no {protocol} deployment is known for network {chain_id}")]
+    UnknownDeployment { protocol: String, chain_id: String },
+    #[error("{protocol} deployment {url} is not valid JSON: {error}")]
+    InvalidJson {
+        protocol: String,
+        url: String,
+        error: String,
+    },
+    #[error("{key} not found in {protocol} deployment {url}, found: {}", found.join(", "))]
+    MissingKey {
+        protocol: String,
+        key: String,
+        url: String,
+        found: Vec<String>,
+    },
+}
+
+/// The deployment files of the protocols, by protocol name and chain id.
+///
+/// Files are cached in the cache directory and revalidated once per registry, clones share the
+/// fetched files. More protocols (or networks) are added with [`Self::with_url`], single
+/// addresses with [`Self::with_address`].
+#[derive(Debug, Clone)]
+pub struct DeploymentRegistry {
+    /// (protocol, chain id) -> url of the deployment file
+    urls: BTreeMap<(String, String), String>,
+    /// (protocol, chain id) -> contract key -> address, used instead of the deployment file
+    addresses: BTreeMap<(String, String), BTreeMap<String, String>>,
+    cache_dir: PathBuf,
+    fetched: Arc<Mutex<HashMap<(String, String), Deployment>>>,
+}
+
+impl Default for DeploymentRegistry {
+    fn default() -> Self {
+        Self {
+            urls: KNOWN_DEPLOYMENTS
+                .iter()
+                .map(|(protocol, chain_id, url)| {
+                    (
+                        (protocol.to_string(), chain_id.to_string()),
+                        url.to_string(),
+                    )
+                })
+                .collect(),
+            addresses: BTreeMap::new(),
+            cache_dir: default_cache_dir().join("deployments"),
+            fetched: Default::default(),
+        }
+    }
+}
+
+impl DeploymentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fetch the deployment of `protocol` on `chain_id` from `url`, replacing any known one.
+    pub fn with_url(
+        mut self,
+        protocol: impl Into<String>,
+        chain_id: impl Into<String>,
+        url: impl Into<String>,
+    ) -> Self {
+        self.urls
+            .insert((protocol.into(), chain_id.into()), url.into());
+        self
+    }
+
+    /// Use `address` for the contract `key` of `protocol` on `chain_id` instead of the one of the
+    /// deployment file. A network with only such addresses needs no deployment file.
+    pub fn with_address(
+        mut self,
+        protocol: impl Into<String>,
+        chain_id: impl Into<String>,
+        key: impl Into<String>,
+        address: impl Into<String>,
+    ) -> Self {
+        self.addresses
+            .entry((protocol.into(), chain_id.into()))
+            .or_default()
+            .insert(key.into(), address.into());
+        self
+    }
+
+    /// Cache the deployment files in `cache_dir` instead of `deployments` in the default cache
+    /// directory.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// URL of the deployment file of `protocol` on `chain_id`.
+    pub fn url(&self, protocol: &str, chain_id: &str) -> Result<&str, DeploymentError> {
+        self.urls
+            .get(&(protocol.to_string(), chain_id.to_string()))
+            .map(String::as_str)
+            .ok_or_else(|| DeploymentError::UnknownDeployment {
+                protocol: protocol.to_string(),
+                chain_id: chain_id.to_string(),
+            })
+    }
+
+    /// Address of the contract `key` of `protocol` on `chain_id`, `None` if the deployment doesn't
+    /// have it.
+    pub async fn address(
+        &self,
+        protocol: &str,
+        chain_id: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<Addr>> {
+        let configured = self
+            .addresses
+            .get(&(protocol.to_string(), chain_id.to_string()));
+        if let Some(address) = configured.and_then(|addresses| addresses.get(key)) {
+            return Ok(Some(Addr::unchecked(address)));
+        }
+        if configured.is_some() && self.url(protocol, chain_id).is_err() {
+            return Ok(None);
+        }
+
+        match self.deployment(protocol, chain_id).await?.get_address(key) {
+            Ok(address) => Ok(Some(address)),
+            Err(err @ DeploymentError::MissingKey { .. }) => {
+                log::debug!("{}: {}", chain_id, err);
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Keys of the configured addresses and of the deployment file of `protocol` on `chain_id`.
+    pub async fn keys(&self, protocol: &str, chain_id: &str) -> anyhow::Result<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .addresses
+            .get(&(protocol.to_string(), chain_id.to_string()))
+            .map(|addresses| addresses.keys().cloned().collect())
+            .unwrap_or_default();
+        if self.url(protocol, chain_id).is_ok() {
+            keys.extend(self.deployment(protocol, chain_id).await?.keys());
+        }
+        keys.sort();
+        keys.dedup();
+        Ok(keys)
+    }
+
+    /// The deployment of `protocol` on `chain_id`, fetched on the first call.
+    pub async fn deployment(&self, protocol: &str, chain_id: &str) -> anyhow::Result<Deployment> {
+        let key = (protocol.to_string(), chain_id.to_string());
+        if let Some(deployment) = self.fetched.lock().unwrap().get(&key) {
+            return Ok(deployment.clone());
+        }
+
+        let url = self.url(protocol, chain_id)?;
+        // Revalidated, but only downloaded again when it actually changed
+        let cache_path = self
+            .cache_dir
+            .join(format!("{}_{}.json", protocol, chain_id));
+        let response_text = fetch_with_cache(url, &cache_path, Some(Duration::ZERO))
+            .await
+            .with_context(|| format!("fetching {} deployment from {}", protocol, url))?
+            .body;
+
+        let deployment = Deployment::parse(protocol, url, &response_text)?;
+        self.fetched.lock().unwrap().insert(key, deployment.clone());
+        Ok(deployment)
+    }
+}
+
+/// `(protocol, url)` of the deployment files published for `chain_id`.
+pub(crate) fn known_deployments(
+    chain_id: &str,
+) -> impl Iterator<Item = (&'static str, &'static str)> + '_ {
+    KNOWN_DEPLOYMENTS
+        .iter()
+        .filter(move |(_, id, _)| *id == chain_id)
+        .map(|(protocol, _, url)| (*protocol, *url))
+}
+
+/// A fetched deployment file.
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    pub protocol: String,
+    pub url: String,
+    json: Arc<Value>,
+}
+
+impl Deployment {
+    /// Parse the deployment file of `protocol` downloaded from `url`.
+    pub fn parse(protocol: &str, url: &str, text: &str) -> Result<Self, DeploymentError> {
+        // Deployment files are maintained by hand and not always valid JSON
+        let json = parse_relaxed_json(text).map_err(|e| DeploymentError::InvalidJson {
+            protocol: protocol.to_string(),
+            url: url.to_string(),
+            error: e.to_string(),
+        })?;
+        Ok(Self {
+            protocol: protocol.to_string(),
+            url: url.to_string(),
+            json: Arc::new(json),
+        })
+    }
+
+    /// Address of the contract `key` (e.g. `factory_address`), wherever it is nested.
+    pub fn get_address(&self, key: &str) -> Result<Addr, DeploymentError> {
+        find_string(&self.json, key)
+            .map(Addr::unchecked)
+            .ok_or_else(|| DeploymentError::MissingKey {
+                protocol: self.protocol.clone(),
+                key: key.to_string(),
+                url: self.url.clone(),
+                found: self.keys(),
+            })
+    }
+
+    /// Keys of all the string values of the deployment.
+    pub fn keys(&self) -> Vec<String> {
+        string_keys(&self.json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHOENIX: &str = include_str!("../../tests/fixtures/deployments/core_phoenix.json");
+    const NEUTRON: &str = include_str!("../../tests/fixtures/deployments/core_neutron.json");
+    const MANGLED: &str = include_str!("../../tests/fixtures/deployments/core_mangled.json");
+
+    fn astroport(text: &str) -> Result<Deployment, DeploymentError> {
+        Deployment::parse("astroport", "https://changelog/core.json", text)
+    }
+
+    #[test]
+    fn core_contracts_are_found_at_any_depth() {
+        let phoenix = astroport(PHOENIX).unwrap();
+        assert_eq!(
+            phoenix.get_address("factory_address").unwrap(),
+            Addr::unchecked("terra1factoryaddress")
+        );
+        assert_eq!(
+            phoenix.get_address("xastro_address").unwrap(),
+            Addr::unchecked("terra1xastroaddress")
+        );
+
+        // hand maintained, with comments and trailing commas
+        let neutron = astroport(NEUTRON).unwrap();
+        assert_eq!(
+            neutron.get_address("factory_address").unwrap(),
+            Addr::unchecked("neutron1factoryaddress")
+        );
+        assert_eq!(
+            neutron.get_address("incentives_address").unwrap(),
+            Addr::unchecked("neutron1incentivesaddress")
+        );
+    }
+
+    #[test]
+    fn missing_key_lists_the_keys_of_the_deployment() {
+        let neutron = astroport(NEUTRON).unwrap();
+
+        match neutron.get_address("generator_address") {
+            Err(DeploymentError::MissingKey {
+                key, url, found, ..
+            }) => {
+                assert_eq!(key, "generator_address");
+                assert_eq!(url, "https://changelog/core.json");
+                let mut found = found;
+                found.sort();
+                assert_eq!(
+                    found,
+                    [
+                        "factory_address",
+                        "incentives_address",
+                        "router_address",
+                        "staking_address"
+                    ]
+                );
+            }
+            other => panic!("expected a missing key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mangled_deployment_is_invalid_json_of_its_url() {
+        let err = astroport(MANGLED).unwrap_err();
+
+        assert!(matches!(err, DeploymentError::InvalidJson { .. }));
+        let message = err.to_string();
+        assert!(
+            message
+                .starts_with("astroport deployment https://changelog/core.json is not valid JSON"),
+            "{}",
+            message
+        );
+    }
+
+    #[tokio::test]
+    async fn configured_addresses_need_no_deployment_file() {
+        let registry = DeploymentRegistry::new().with_address(
+            "mydex",
+            "pisco-1",
+            "factory_address",
+            "terra1factory",
+        );
+
+        assert_eq!(
+            registry
+                .address("mydex", "pisco-1", "factory_address")
+                .await
+                .unwrap(),
+            Some(Addr::unchecked("terra1factory"))
+        );
+        assert_eq!(
+            registry
+                .address("mydex", "pisco-1", "router_address")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            registry.keys("mydex", "pisco-1").await.unwrap(),
+            ["factory_address"]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_fetches_name_the_deployment() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .respond_with(wiremock::ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let url = format!("{}/core_pisco.json", server.uri());
+        let cache_dir = std::env::temp_dir().join(format!(
+            "ans-scraper-deployments-context-{}",
+            std::process::id()
+        ));
+        let registry = DeploymentRegistry::new()
+            .with_url("astroport", "pisco-1", &url)
+            .with_cache_dir(&cache_dir);
+
+        let err = registry
+            .address("astroport", "pisco-1", "factory_address")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("fetching astroport deployment from {}", url)
+        );
+
+        let _ = std::fs::remove_dir_all(cache_dir);
+    }
+}
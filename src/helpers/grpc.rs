@@ -0,0 +1,51 @@
+use anyhow::Context;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::Channel;
+
+use super::retry::retry;
+
+/// Send a unary gRPC query to `path` for services that cosmos-sdk-proto has no client for.
+///
+/// `path` is the full method path, e.g. `/osmosis.poolmanager.v1beta1.Query/AllPools`. Failures
+/// to reach the node are retried.
+pub async fn grpc_query<Req, Resp>(
+    channel: Channel,
+    path: &'static str,
+    request: Req,
+) -> anyhow::Result<Resp>
+where
+    Req: prost::Message + Clone + 'static,
+    Resp: prost::Message + Default + 'static,
+{
+    retry(path, || {
+        grpc_query_once(channel.clone(), path, request.clone())
+    })
+    .await
+}
+
+async fn grpc_query_once<Req, Resp>(
+    channel: Channel,
+    path: &'static str,
+    request: Req,
+) -> anyhow::Result<Resp>
+where
+    Req: prost::Message + 'static,
+    Resp: prost::Message + Default + 'static,
+{
+    let mut client = tonic::client::Grpc::new(channel);
+    client
+        .ready()
+        .await
+        .with_context(|| format!("connecting for {}", path))?;
+
+    let response = client
+        .unary(
+            tonic::Request::new(request),
+            PathAndQuery::from_static(path),
+            ProstCodec::default(),
+        )
+        .await
+        .with_context(|| format!("querying {}", path))?;
+    Ok(response.into_inner())
+}
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use lazy_static::lazy_static;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use super::cache::{read_json_or_invalidate, unix_now, write_atomic, write_json_atomic};
+use super::retry::retry;
+
+/// Host that throttles us when we hit it too hard.
+const GITHUB_RAW_HOST: &str = "raw.githubusercontent.com";
+/// Requests to [`GITHUB_RAW_HOST`] in flight at once.
+const GITHUB_MAX_CONCURRENT_REQUESTS: usize = 4;
+/// Minimum time between the start of two requests to [`GITHUB_RAW_HOST`].
+const GITHUB_POLITENESS_DELAY: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    static ref FETCHER: Fetcher = Fetcher::new();
+}
+
+/// Serve every fetch from the cache, see [`set_offline`].
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+type SharedFetch = Shared<BoxFuture<'static, Result<CachedBody, String>>>;
+
+/// Process-wide HTTP client that every fetch of the crate goes through.
+///
+/// Requests to the same URL that overlap are only sent once, and requests to
+/// [`GITHUB_RAW_HOST`] are capped and spaced out so we don't get throttled.
+struct Fetcher {
+    client: reqwest::Client,
+    github_permits: Semaphore,
+    /// Earliest time the next github request may start
+    github_next_slot: tokio::sync::Mutex<Instant>,
+    in_flight: Mutex<HashMap<String, SharedFetch>>,
+    requests: AtomicUsize,
+    deduplicated: AtomicUsize,
+    throttle_waits: AtomicUsize,
+    throttled_ms: AtomicU64,
+}
+
+/// Request counters of the fetch service.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct FetchStats {
+    /// Requests actually sent
+    pub requests: usize,
+    /// Fetches that joined an identical fetch already in flight
+    pub deduplicated: usize,
+    /// Requests that had to wait for the politeness delay
+    pub throttle_waits: usize,
+    /// Total time spent waiting for the politeness delay
+    pub throttled_for: Duration,
+}
+
+/// Serve every fetch of the process from the cache whatever its age, and fail the fetches of
+/// anything that isn't cached instead of sending a request.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// Whether fetches are only served from the cache.
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Request counters of the fetch service since the start of the process.
+pub fn fetch_stats() -> FetchStats {
+    FETCHER.stats()
+}
+
+impl Fetcher {
+    fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            github_permits: Semaphore::new(GITHUB_MAX_CONCURRENT_REQUESTS),
+            github_next_slot: tokio::sync::Mutex::new(Instant::now()),
+            in_flight: Default::default(),
+            requests: Default::default(),
+            deduplicated: Default::default(),
+            throttle_waits: Default::default(),
+            throttled_ms: Default::default(),
+        }
+    }
+
+    fn stats(&self) -> FetchStats {
+        FetchStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            deduplicated: self.deduplicated.load(Ordering::Relaxed),
+            throttle_waits: self.throttle_waits.load(Ordering::Relaxed),
+            throttled_for: Duration::from_millis(self.throttled_ms.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Run `fetch` for `url`, or wait for the one that is already running.
+    async fn dedup(
+        &self,
+        url: &str,
+        fetch: impl std::future::Future<Output = anyhow::Result<CachedBody>> + Send + 'static,
+    ) -> anyhow::Result<CachedBody> {
+        let shared = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(url) {
+                Some(shared) => {
+                    self.deduplicated.fetch_add(1, Ordering::Relaxed);
+                    shared.clone()
+                }
+                None => {
+                    let shared = fetch
+                        .map(|result| result.map_err(|e| format!("{:#}", e)))
+                        .boxed()
+                        .shared();
+                    in_flight.insert(url.to_string(), shared.clone());
+                    shared
+                }
+            }
+        };
+
+        let result = shared.clone().await;
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight
+            .get(url)
+            .map_or(false, |current| current.ptr_eq(&shared))
+        {
+            in_flight.remove(url);
+        }
+        result.map_err(anyhow::Error::msg)
+    }
+
+    /// Send a request, respecting the concurrency cap and politeness delay of its host.
+    async fn send(&self, request: RequestBuilder, url: &str) -> anyhow::Result<Response> {
+        let is_github = reqwest::Url::parse(url)
+            .map(|url| url.host_str() == Some(GITHUB_RAW_HOST))
+            .unwrap_or(false);
+
+        let _permit = if is_github {
+            let permit = self.github_permits.acquire().await?;
+            let mut next_slot = self.github_next_slot.lock().await;
+            let now = Instant::now();
+            if *next_slot > now {
+                let wait = *next_slot - now;
+                self.throttle_waits.fetch_add(1, Ordering::Relaxed);
+                self.throttled_ms
+                    .fetch_add(wait.as_millis() as u64, Ordering::Relaxed);
+                tokio::time::sleep(wait).await;
+            }
+            *next_slot = Instant::now() + GITHUB_POLITENESS_DELAY;
+            Some(permit)
+        } else {
+            None
+        };
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        request
+            .send()
+            .await
+            .with_context(|| format!("requesting {}", url))
+    }
+}
+
+/// HTTP validators stored next to a cached response body.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix timestamp of the last time the server confirmed the body
+    fetched_at: u64,
+}
+
+/// A response body, possibly served from the local cache.
+#[derive(Clone)]
+pub struct CachedBody {
+    pub body: String,
+    /// Whether the body was read from disk rather than downloaded
+    pub from_cache: bool,
+}
+
+fn validators_path(cache_path: &Path) -> PathBuf {
+    let mut file_name = cache_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".meta");
+    cache_path.with_file_name(file_name)
+}
+
+/// GET `url`, caching the body at `cache_path`.
+///
+/// A cached body younger than `max_age` is used as is (`None` means it never expires). Older
+/// bodies are revalidated with `If-None-Match`/`If-Modified-Since`: a `304` keeps the cached body
+/// and restarts its clock, a `200` replaces it.
+///
+/// Concurrent fetches of the same URL share a single request. Failed requests (timeouts, 5xx and
+/// 429 responses) are retried. Offline, the cached body is returned whatever its age.
+pub async fn fetch_with_cache(
+    url: &str,
+    cache_path: &Path,
+    max_age: Option<Duration>,
+) -> anyhow::Result<CachedBody> {
+    if is_offline() {
+        if !cache_path.exists() {
+            anyhow::bail!(
+                "{} is not cached at {}, can't fetch it offline",
+                url,
+                cache_path.display()
+            );
+        }
+        let body = std::fs::read_to_string(cache_path)
+            .with_context(|| format!("reading cache file {}", cache_path.display()))?;
+        return Ok(CachedBody {
+            body,
+            from_cache: true,
+        });
+    }
+    let (url_owned, cache_path) = (url.to_string(), cache_path.to_path_buf());
+    let fetch = retry(url_owned.clone(), move || {
+        fetch_and_cache(url_owned.clone(), cache_path.clone(), max_age)
+    });
+    FETCHER.dedup(url, fetch).await
+}
+
+async fn fetch_and_cache(
+    url: String,
+    cache_path: PathBuf,
+    max_age: Option<Duration>,
+) -> anyhow::Result<CachedBody> {
+    let (url, cache_path) = (url.as_str(), cache_path.as_path());
+    let meta_path = validators_path(cache_path);
+    let cached_body = if cache_path.exists() {
+        Some(
+            std::fs::read_to_string(cache_path)
+                .with_context(|| format!("reading cache file {}", cache_path.display()))?,
+        )
+    } else {
+        None
+    };
+    let validators: Option<CacheValidators> = read_json_or_invalidate(&meta_path)?;
+
+    if let Some(body) = &cached_body {
+        let fresh = match (max_age, &validators) {
+            (None, _) => true,
+            (Some(max_age), Some(validators)) => {
+                unix_now().saturating_sub(validators.fetched_at) < max_age.as_secs()
+            }
+            (Some(_), None) => false,
+        };
+        if fresh {
+            return Ok(CachedBody {
+                body: body.clone(),
+                from_cache: true,
+            });
+        }
+    }
+
+    let mut request = FETCHER.client.get(url);
+    if let (Some(_), Some(validators)) = (&cached_body, &validators) {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = FETCHER.send(request, url).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let (Some(body), Some(mut validators)) = (cached_body, validators) {
+            log::debug!("{} not modified, keeping cached copy", url);
+            validators.fetched_at = unix_now();
+            write_json_atomic(&meta_path, &validators)?;
+            return Ok(CachedBody {
+                body,
+                from_cache: true,
+            });
+        }
+        anyhow::bail!("{} returned 304 without a cached copy", url);
+    }
+
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("requesting {}", url))?;
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let validators = CacheValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        fetched_at: unix_now(),
+    };
+    let body = response
+        .text()
+        .await
+        .with_context(|| format!("reading {}", url))?;
+
+    write_atomic(cache_path, body.as_bytes())?;
+    write_json_atomic(&meta_path, &validators)?;
+
+    Ok(CachedBody {
+        body,
+        from_cache: false,
+    })
+}
+
+/// Remove a cached body (and its validators), e.g. because it turned out to be corrupted.
+pub fn invalidate(cache_path: &Path) -> anyhow::Result<()> {
+    for path in [cache_path.to_path_buf(), validators_path(cache_path)] {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("removing cache file {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    lazy_static! {
+        /// Held by the tests that fetch, fetches fail while a test is offline.
+        pub(crate) static ref FETCHING: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    }
+
+    const LAST_MODIFIED_DATE: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+
+    fn temp_cache_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ans-scraper-http-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("body.json")
+    }
+
+    #[tokio::test]
+    async fn stale_bodies_are_revalidated_with_their_last_modified_date() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/list.json"))
+            .and(header("If-Modified-Since", LAST_MODIFIED_DATE))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/list.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Last-Modified", LAST_MODIFIED_DATE)
+                    .set_body_string("[1]"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let url = format!("{}/list.json", server.uri());
+        let cache_path = temp_cache_path("last-modified");
+
+        let fetched = fetch_with_cache(&url, &cache_path, Some(Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!((fetched.body.as_str(), fetched.from_cache), ("[1]", false));
+
+        let revalidated = fetch_with_cache(&url, &cache_path, Some(Duration::ZERO))
+            .await
+            .unwrap();
+        assert_eq!(
+            (revalidated.body.as_str(), revalidated.from_cache),
+            ("[1]", true)
+        );
+
+        // never expires, no request at all
+        let cached = fetch_with_cache(&url, &cache_path, None).await.unwrap();
+        assert_eq!((cached.body.as_str(), cached.from_cache), ("[1]", true));
+
+        std::fs::remove_dir_all(cache_path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn changed_bodies_replace_the_cached_one() {
+        let _fetching = FETCHING.lock().await;
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/list.json"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v2\"")
+                    .set_body_string("[2]"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/list.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string("[1]"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let url = format!("{}/list.json", server.uri());
+        let cache_path = temp_cache_path("changed");
+
+        fetch_with_cache(&url, &cache_path, Some(Duration::ZERO))
+            .await
+            .unwrap();
+        let changed = fetch_with_cache(&url, &cache_path, Some(Duration::ZERO))
+            .await
+            .unwrap();
+
+        assert_eq!((changed.body.as_str(), changed.from_cache), ("[2]", false));
+        assert_eq!(std::fs::read_to_string(&cache_path).unwrap(), "[2]");
+        let validators: CacheValidators = read_json_or_invalidate(validators_path(&cache_path))
+            .unwrap()
+            .unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"v2\""));
+
+        std::fs::remove_dir_all(cache_path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::Context;
+use prost::Message;
+use tonic::transport::Channel;
+
+use super::grpc::grpc_query;
+
+const CHANNEL_CLIENT_STATE_PATH: &str = "/ibc.core.channel.v1.Query/ChannelClientState";
+
+/// Type url of the client states of tendermint light clients, the only ones that have a chain id.
+const TENDERMINT_CLIENT_STATE_TYPE_URL: &str = "/ibc.lightclients.tendermint.v1.ClientState";
+
+#[derive(Clone, PartialEq, Message)]
+struct QueryChannelClientStateRequest {
+    #[prost(string, tag = "1")]
+    port_id: String,
+    #[prost(string, tag = "2")]
+    channel_id: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct QueryChannelClientStateResponse {
+    #[prost(message, optional, tag = "1")]
+    identified_client_state: Option<IdentifiedClientState>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct IdentifiedClientState {
+    #[prost(message, optional, tag = "2")]
+    client_state: Option<ProtoAny>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ProtoAny {
+    #[prost(string, tag = "1")]
+    type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    value: Vec<u8>,
+}
+
+/// Only the chain id of the tendermint client state, the other fields are skipped when decoding.
+#[derive(Clone, PartialEq, Message)]
+struct TendermintClientState {
+    #[prost(string, tag = "1")]
+    chain_id: String,
+}
+
+/// Chain id of the counterparty of a channel, read from the light client of the channel's
+/// connection.
+pub async fn channel_counterparty_chain_id(
+    channel: Channel,
+    port_id: &str,
+    channel_id: &str,
+) -> anyhow::Result<String> {
+    let response: QueryChannelClientStateResponse = grpc_query(
+        channel,
+        CHANNEL_CLIENT_STATE_PATH,
+        QueryChannelClientStateRequest {
+            port_id: port_id.to_string(),
+            channel_id: channel_id.to_string(),
+        },
+    )
+    .await?;
+
+    let client_state = response
+        .identified_client_state
+        .and_then(|state| state.client_state)
+        .with_context(|| format!("channel {}/{} has no client state", port_id, channel_id))?;
+    if client_state.type_url != TENDERMINT_CLIENT_STATE_TYPE_URL {
+        anyhow::bail!(
+            "client of channel {}/{} is a {}, not a tendermint client",
+            port_id,
+            channel_id,
+            client_state.type_url
+        );
+    }
+
+    let TendermintClientState { chain_id } =
+        TendermintClientState::decode(client_state.value.as_slice()).with_context(|| {
+            format!(
+                "decoding the client state of channel {}/{}",
+                port_id, channel_id
+            )
+        })?;
+    Ok(chain_id)
+}
@@ -0,0 +1,144 @@
+use serde_json::{Map, Value};
+
+/// Parse JSON that may have been written by hand.
+///
+/// Strict parsing is tried first. If that fails, `//` and `/* */` comments and trailing commas
+/// are stripped and the result is parsed again. The error of the strict parse is returned if the
+/// relaxed one fails too.
+pub fn parse_relaxed_json(text: &str) -> serde_json::Result<Value> {
+    match serde_json::from_str(text) {
+        Ok(value) => Ok(value),
+        Err(err) => serde_json::from_str(&relax_json(text)).map_err(|_| err),
+    }
+}
+
+/// Strip comments and trailing commas outside of strings.
+fn relax_json(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    let mut in_string = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if in_string {
+            out.push(c);
+            if c == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if c == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match (c, chars.get(i + 1)) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+                i += 1;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            (',', _) => {
+                // drop the comma if the next significant char closes the object/array
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(c);
+                }
+                i += 1;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Find the string value of `key`, at the top level first and then in nested objects.
+pub fn find_string<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    let object = value.as_object()?;
+    if let Some(found) = object.get(key).and_then(Value::as_str) {
+        return Some(found);
+    }
+    object.values().find_map(|nested| find_string(nested, key))
+}
+
+/// All the keys with a string value, nested keys included.
+pub fn string_keys(value: &Value) -> Vec<String> {
+    fn collect(object: &Map<String, Value>, keys: &mut Vec<String>) {
+        for (key, value) in object {
+            match value {
+                Value::String(_) => keys.push(key.clone()),
+                Value::Object(nested) => collect(nested, keys),
+                _ => {}
+            }
+        }
+    }
+
+    let mut keys = vec![];
+    if let Some(object) = value.as_object() {
+        collect(object, &mut keys);
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn comments_and_trailing_commas_are_relaxed() {
+        let text = r#"{
+            // the factory
+            "factory_address": "terra1factory", /* block comment */
+            "url": "https://example.com/a,b//c",
+            "nested": { "pairs": ["a", "b",], },
+        }"#;
+        assert!(serde_json::from_str::<Value>(text).is_err());
+
+        assert_eq!(
+            parse_relaxed_json(text).unwrap(),
+            json!({
+                "factory_address": "terra1factory",
+                "url": "https://example.com/a,b//c",
+                "nested": { "pairs": ["a", "b"] },
+            })
+        );
+    }
+
+    #[test]
+    fn the_strict_error_is_kept() {
+        let strict = serde_json::from_str::<Value>("{\"a\": }").unwrap_err();
+        let relaxed = parse_relaxed_json("{\"a\": }").unwrap_err();
+        assert_eq!(relaxed.to_string(), strict.to_string());
+    }
+
+    #[test]
+    fn string_values_are_found_at_any_depth() {
+        let value = json!({
+            "top": "1",
+            "count": 2,
+            "nested": { "inner": "3", "top": "shadowed" },
+        });
+        assert_eq!(find_string(&value, "top"), Some("1"));
+        assert_eq!(find_string(&value, "inner"), Some("3"));
+        assert_eq!(find_string(&value, "count"), None);
+        assert_eq!(string_keys(&value), ["inner", "top", "top"]);
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use cosmwasm_std::Uint128;
+use cw_asset::AssetInfo;
+
+use crate::report::PoolReserve;
+
+/// Per-asset minimum reserves in the configuration directory of a chain.
+pub const MIN_RESERVES_FILE: &str = "min_reserves.json";
+
+/// Minimum reserves a pool needs to be registered, dead pools only hold dust.
+///
+/// Every asset of a pool needs a reserve of at least its minimum, in the smallest unit of the
+/// asset. Assets without a minimum of their own use the default one.
+#[derive(Debug, Clone, Default)]
+pub struct LiquidityFilter {
+    min_reserve: Uint128,
+    /// denom or cw20 address -> minimum reserve
+    asset_min_reserves: BTreeMap<String, Uint128>,
+}
+
+impl LiquidityFilter {
+    pub fn new(min_reserve: impl Into<Uint128>) -> Self {
+        Self {
+            min_reserve: min_reserve.into(),
+            asset_min_reserves: BTreeMap::new(),
+        }
+    }
+
+    /// Filter with `min_reserve` and the minimums of `<config_dir>/<chain_id>/min_reserves.json`,
+    /// a JSON object of denom or cw20 address -> amount. The file is optional.
+    pub fn load(
+        config_dir: &Path,
+        chain_id: &str,
+        min_reserve: impl Into<Uint128>,
+    ) -> anyhow::Result<Self> {
+        let mut filter = Self::new(min_reserve);
+        let path = config_dir.join(chain_id).join(MIN_RESERVES_FILE);
+        if !path.exists() {
+            return Ok(filter);
+        }
+
+        let json = std::fs::read(&path)
+            .with_context(|| format!("reading minimum reserves from {}", path.display()))?;
+        filter.asset_min_reserves = serde_json::from_slice(&json)
+            .with_context(|| format!("parsing minimum reserves from {}", path.display()))?;
+        Ok(filter)
+    }
+
+    /// Minimum reserve of the asset.
+    pub fn min_reserve(&self, info: &AssetInfo) -> Uint128 {
+        let key = match info {
+            AssetInfo::Native(denom) => denom.as_str(),
+            AssetInfo::Cw20(contract_addr) => contract_addr.as_str(),
+            _ => return self.min_reserve,
+        };
+        self.asset_min_reserves
+            .get(key)
+            .copied()
+            .unwrap_or(self.min_reserve)
+    }
+
+    /// Whether every reserve of the pool reaches the minimum of its asset.
+    pub fn is_liquid(&self, reserves: &[PoolReserve]) -> bool {
+        reserves
+            .iter()
+            .all(|reserve| reserve.amount >= self.min_reserve(&reserve.info))
+    }
+}
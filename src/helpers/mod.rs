@@ -1 +1,24 @@
+pub mod assets;
+pub mod cache;
 pub mod chain_registry;
+pub mod channels;
+pub mod clients;
+pub mod cw20;
+pub mod decimals;
+pub mod denoms;
+pub mod deployments;
+pub mod grpc;
+pub mod http;
+pub mod ibc;
+pub mod json;
+pub mod liquidity;
+pub mod names;
+pub mod networks;
+pub mod overrides;
+pub mod pagination;
+pub mod query_cache;
+pub mod resolution_cache;
+pub mod resolve;
+pub mod retry;
+pub mod verify;
+pub mod wasm_batch;
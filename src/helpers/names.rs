@@ -0,0 +1,330 @@
+use abstract_core::objects::AssetEntry;
+
+/// Character that replaces anything not allowed in an ANS name.
+pub const NAME_REPLACEMENT_CHAR: char = '_';
+
+/// Separator between the chain prefix and the asset symbol in an [`AssetEntry`].
+pub const CHAIN_SEPARATOR: char = '>';
+
+/// Separator between the dex and the pair's assets in an LP token [`AssetEntry`].
+pub const LP_TOKEN_SEPARATOR: char = '/';
+
+/// Separator between the assets of an LP token [`AssetEntry`].
+const LP_ASSET_SEPARATOR: char = ',';
+
+/// Longest segment of an ANS name, longer symbols are truncated.
+pub const MAX_SEGMENT_LENGTH: usize = 32;
+
+/// A name ANS would reject.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid ANS name {name:?}: {reason}")]
+pub struct InvalidName {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Turn an arbitrary symbol (or chain name) into a valid ANS name segment.
+///
+/// Lowercases, replaces every character outside `[a-z0-9]` with [`NAME_REPLACEMENT_CHAR`],
+/// collapses repeated replacements, truncates to [`MAX_SEGMENT_LENGTH`] and trims replacements
+/// from both ends. Returns `None` if nothing is left.
+pub fn sanitize_name_segment(raw: &str) -> Option<String> {
+    let mut sanitized = String::with_capacity(raw.len());
+    for c in raw.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with(NAME_REPLACEMENT_CHAR) {
+            sanitized.push(NAME_REPLACEMENT_CHAR);
+        }
+    }
+    // Only ASCII is left, so every char is one byte
+    sanitized.truncate(MAX_SEGMENT_LENGTH);
+    while sanitized.ends_with(NAME_REPLACEMENT_CHAR) {
+        sanitized.pop();
+    }
+
+    if sanitized.is_empty() {
+        None
+    } else {
+        Some(sanitized)
+    }
+}
+
+/// An asset entry name built from a chain prefix and a symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetName {
+    /// The sanitized `{prefix}>{symbol}` entry.
+    pub entry: String,
+    /// The `{prefix}>{symbol}` entry before sanitization, only set when sanitization changed the
+    /// symbol.
+    pub unsanitized: Option<String>,
+}
+
+impl AssetName {
+    pub fn asset_entry(&self) -> AssetEntry {
+        AssetEntry::from(self.entry.clone())
+    }
+}
+
+/// Build the `{prefix}>{symbol}` asset entry name, sanitizing both parts.
+pub fn asset_entry_name(chain_prefix: &str, symbol: &str) -> anyhow::Result<AssetName> {
+    let prefix = sanitize_name_segment(chain_prefix)
+        .ok_or_else(|| anyhow::anyhow!("chain prefix {:?} is not a valid name", chain_prefix))?;
+    let sanitized_symbol = sanitize_name_segment(symbol)
+        .ok_or_else(|| anyhow::anyhow!("symbol {:?} is not a valid name", symbol))?;
+
+    let entry = format!("{}{}{}", prefix, CHAIN_SEPARATOR, sanitized_symbol);
+    // lowercasing is expected, only record actual character changes
+    let unsanitized = if sanitized_symbol != symbol.to_lowercase() {
+        log::info!("Sanitized symbol {:?} to {:?}", symbol, sanitized_symbol);
+        Some(format!("{}{}{}", chain_prefix, CHAIN_SEPARATOR, symbol))
+    } else {
+        None
+    };
+
+    Ok(AssetName { entry, unsanitized })
+}
+
+/// Check a segment of a name: `[a-z0-9_]`, at most [`MAX_SEGMENT_LENGTH`] long.
+fn validate_name_segment(segment: &str) -> Result<(), String> {
+    if segment.is_empty() {
+        return Err("empty segment".to_string());
+    }
+    if let Some(c) = segment
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || *c == NAME_REPLACEMENT_CHAR))
+    {
+        return Err(match c {
+            c if c.is_whitespace() => "whitespace".to_string(),
+            c if c.is_uppercase() => format!("uppercase character {:?}", c),
+            c => format!("illegal character {:?}", c),
+        });
+    }
+    if segment.len() > MAX_SEGMENT_LENGTH {
+        return Err(format!(
+            "segment {:?} is longer than {} characters",
+            segment, MAX_SEGMENT_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// Check a name of a single segment, like a dex or a contract name, the way ANS does.
+pub fn validate_name(name: &str) -> Result<(), InvalidName> {
+    validate_name_segment(name).map_err(|reason| InvalidName {
+        name: name.to_string(),
+        reason,
+    })
+}
+
+/// Check an [`AssetEntry`] name the way ANS does: `{prefix}>{symbol}`, or the
+/// `{dex}/{asset},{asset}` of an LP token, made of valid segments.
+pub fn validate_asset_entry(name: &str) -> Result<(), InvalidName> {
+    let invalid = |reason: String| InvalidName {
+        name: name.to_string(),
+        reason,
+    };
+    if let Some((dex, assets)) = name.split_once(LP_TOKEN_SEPARATOR) {
+        validate_name_segment(dex).map_err(invalid)?;
+        for asset in assets.split(LP_ASSET_SEPARATOR) {
+            validate_asset_entry(asset).map_err(|err| invalid(err.to_string()))?;
+        }
+        return Ok(());
+    }
+
+    let (prefix, symbol) = name
+        .split_once(CHAIN_SEPARATOR)
+        .ok_or_else(|| invalid(format!("no {:?} after the chain prefix", CHAIN_SEPARATOR)))?;
+    validate_name_segment(prefix).map_err(invalid)?;
+    validate_name_segment(symbol).map_err(invalid)
+}
+
+/// The closest valid name to an invalid [`AssetEntry`] name, every segment sanitized with
+/// [`sanitize_name_segment`]. `None` if a segment has nothing left or there is no chain prefix.
+pub fn sanitize_asset_entry(name: &str) -> Option<String> {
+    if let Some((dex, assets)) = name.split_once(LP_TOKEN_SEPARATOR) {
+        let dex = sanitize_name_segment(dex)?;
+        let assets = assets
+            .split(LP_ASSET_SEPARATOR)
+            .map(sanitize_asset_entry)
+            .collect::<Option<Vec<_>>>()?;
+        return Some(lp_token_name(&dex, &assets));
+    }
+
+    let (prefix, symbol) = name.split_once(CHAIN_SEPARATOR)?;
+    Some(format!(
+        "{}{}{}",
+        sanitize_name_segment(prefix)?,
+        CHAIN_SEPARATOR,
+        sanitize_name_segment(symbol)?
+    ))
+}
+
+/// Build the `{dex}/{asset_a},{asset_b}` entry name of an LP token, with the asset names sorted
+/// like ANS does.
+pub fn lp_token_name(dex: &str, asset_names: &[String]) -> String {
+    let mut asset_names = asset_names.to_vec();
+    asset_names.sort();
+    format!(
+        "{}{}{}",
+        dex,
+        LP_TOKEN_SEPARATOR,
+        asset_names.join(&LP_ASSET_SEPARATOR.to_string())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Symbols and chain names as they come from asset lists and token infos: any unicode,
+    /// punctuation, empty or very long.
+    fn raw_name() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<String>(),
+            "[a-zA-Z0-9 ._/>,-]{0,80}",
+            "\\PC{0,200}",
+            Just(String::new()),
+        ]
+    }
+
+    /// Valid `{prefix}>{symbol}` names.
+    fn asset_entry() -> impl Strategy<Value = String> {
+        ("[a-z0-9]{1,8}", "[a-z0-9]{1,4}(_[a-z0-9]{1,4}){0,2}")
+            .prop_map(|(prefix, symbol)| format!("{}{}{}", prefix, CHAIN_SEPARATOR, symbol))
+    }
+
+    #[test]
+    fn symbols_are_sanitized() {
+        let name = asset_entry_name("terra2", "axlUSDC").unwrap();
+        assert_eq!(name.entry, "terra2>axlusdc");
+        assert_eq!(name.unsanitized, None);
+
+        let name = asset_entry_name("terra2", "USDC.axl").unwrap();
+        assert_eq!(name.entry, "terra2>usdc_axl");
+        assert_eq!(name.unsanitized.as_deref(), Some("terra2>USDC.axl"));
+
+        let name = asset_entry_name("terra2", "wBTC.axl").unwrap();
+        assert_eq!(name.entry, "terra2>wbtc_axl");
+        assert_eq!(name.unsanitized.as_deref(), Some("terra2>wBTC.axl"));
+
+        let name = asset_entry_name("terra2", "USDC (Wormhole)").unwrap();
+        assert_eq!(name.entry, "terra2>usdc_wormhole");
+        assert_eq!(name.unsanitized.as_deref(), Some("terra2>USDC (Wormhole)"));
+
+        assert_eq!(
+            sanitize_name_segment("--Wrapped  Ether (Wormhole)--").as_deref(),
+            Some("wrapped_ether_wormhole")
+        );
+        assert_eq!(
+            sanitize_name_segment(&"a".repeat(MAX_SEGMENT_LENGTH + 5)).map(|s| s.len()),
+            Some(MAX_SEGMENT_LENGTH)
+        );
+        assert!(asset_entry_name("terra2", "🚀").is_err());
+        assert!(asset_entry_name("", "luna").is_err());
+    }
+
+    /// The names adapters look up on chain, any change to them has to show up here and be
+    /// reviewed.
+    #[test]
+    fn emitted_asset_names() {
+        let names = [
+            ("terra2", "LUNA"),
+            ("terra2", "ampLUNA"),
+            ("terra2", "axlUSDC"),
+            ("terra2", "USDC.axl"),
+            ("terra2", "Wrapped Ether (Wormhole)"),
+            ("cosmoshub", "ATOM"),
+            ("terra2", "🚀"),
+        ]
+        .iter()
+        .map(|(prefix, symbol)| match asset_entry_name(prefix, symbol) {
+            Ok(name) => format!("{} {:?} = {}", prefix, symbol, name.entry),
+            Err(err) => format!("{} {:?}: {}", prefix, symbol, err),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+        insta::assert_snapshot!(names, @r###"
+        terra2 "LUNA" = terra2>luna
+        terra2 "ampLUNA" = terra2>ampluna
+        terra2 "axlUSDC" = terra2>axlusdc
+        terra2 "USDC.axl" = terra2>usdc_axl
+        terra2 "Wrapped Ether (Wormhole)" = terra2>wrapped_ether_wormhole
+        cosmoshub "ATOM" = cosmoshub>atom
+        terra2 "🚀": symbol "🚀" is not a valid name
+        "###);
+    }
+
+    #[test]
+    fn invalid_asset_entries_are_sanitized() {
+        for (name, sanitized) in [
+            ("terra2>wBTC.axl", "terra2>wbtc_axl"),
+            ("terra2>USDC (Wormhole)", "terra2>usdc_wormhole"),
+            (
+                "astroport/terra2>USDC (Wormhole),terra2>luna",
+                "astroport/terra2>luna,terra2>usdc_wormhole",
+            ),
+        ] {
+            assert!(validate_asset_entry(name).is_err(), "{}", name);
+            assert_eq!(sanitize_asset_entry(name).as_deref(), Some(sanitized));
+            assert_eq!(validate_asset_entry(sanitized), Ok(()));
+        }
+        // no chain prefix, or nothing left of a segment
+        assert_eq!(sanitize_asset_entry("luna"), None);
+        assert_eq!(sanitize_asset_entry("terra2>🚀"), None);
+    }
+
+    proptest! {
+        #[test]
+        fn sanitized_segments_are_valid_and_stable(raw in raw_name()) {
+            if let Some(segment) = sanitize_name_segment(&raw) {
+                prop_assert!(validate_name_segment(&segment).is_ok(), "{:?}", segment);
+                prop_assert_eq!(sanitize_name_segment(&segment), Some(segment));
+            }
+        }
+
+        #[test]
+        fn asset_entry_names_are_valid_or_rejected(prefix in raw_name(), symbol in raw_name()) {
+            match asset_entry_name(&prefix, &symbol) {
+                Ok(name) => {
+                    prop_assert!(validate_asset_entry(&name.entry).is_ok(), "{:?}", name.entry);
+                    prop_assert_eq!(name.asset_entry().to_string(), name.entry);
+                }
+                Err(_) => prop_assert!(
+                    sanitize_name_segment(&prefix).is_none()
+                        || sanitize_name_segment(&symbol).is_none()
+                ),
+            }
+        }
+
+        #[test]
+        fn sanitized_asset_entries_are_valid(name in raw_name()) {
+            if let Some(sanitized) = sanitize_asset_entry(&name) {
+                prop_assert!(validate_asset_entry(&sanitized).is_ok(), "{:?}", sanitized);
+                prop_assert_eq!(sanitize_asset_entry(&sanitized), Some(sanitized));
+            }
+        }
+
+        #[test]
+        fn valid_asset_entries_are_left_as_they_are(name in asset_entry()) {
+            prop_assert!(validate_asset_entry(&name).is_ok());
+            prop_assert_eq!(sanitize_asset_entry(&name), Some(name));
+        }
+
+        #[test]
+        fn lp_token_names_ignore_the_asset_order(
+            assets in prop::collection::vec(asset_entry(), 2..4).prop_shuffle(),
+        ) {
+            let mut sorted = assets.clone();
+            sorted.sort();
+            let name = lp_token_name("astroport", &assets);
+
+            prop_assert_eq!(&name, &lp_token_name("astroport", &sorted));
+            prop_assert!(validate_asset_entry(&name).is_ok(), "{:?}", name);
+            prop_assert_eq!(sanitize_asset_entry(&name), Some(name));
+        }
+    }
+}
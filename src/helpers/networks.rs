@@ -0,0 +1,161 @@
+use cw_orch::networks::ChainInfo;
+
+/// Overrides the gRPC endpoint of the network, takes precedence over the cw-orch default.
+pub const GRPC_URL_ENV: &str = "GRPC_URL";
+
+/// Variables cw-orch reads the wallet mnemonic from, for local, test and main networks.
+const MNEMONIC_ENVS: [&str; 3] = ["LOCAL_MNEMONIC", "TEST_MNEMONIC", "MAIN_MNEMONIC"];
+
+/// Chain id, ANS prefix and chain registry name of every network a scraper supports.
+///
+/// The ANS prefix of a testnet is the one of its mainnet. Testnets and chains whose asset lists
+/// aren't fetched have no registry name, their native denoms can't be named after their own asset
+/// list.
+pub const NETWORKS: [(&str, &str, Option<&str>); 14] = [
+    ("phoenix-1", "terra2", Some("terra2")),
+    ("pisco-1", "terra2", None),
+    ("neutron-1", "neutron", Some("neutron")),
+    ("pion-1", "neutron", None),
+    ("injective-1", "injective", Some("injective")),
+    ("juno-1", "juno", Some("juno")),
+    ("uni-6", "juno", None),
+    ("chihuahua-1", "chihuahua", None),
+    ("migaloo-1", "migaloo", Some("migaloo")),
+    ("narwhal-1", "migaloo", None),
+    ("kaiyo-1", "kujira", Some("kujira")),
+    ("harpoon-4", "kujira", None),
+    ("osmosis-1", "osmosis", Some("osmosis")),
+    ("osmo-test-5", "osmosis", None),
+];
+
+/// Chain id and chain registry name of the chains no scraper supports that assets of the
+/// [`NETWORKS`] come from over IBC.
+const IBC_CHAINS: [(&str, &str); 1] = [("cosmoshub-4", "cosmoshub")];
+
+/// Reasons a network can't be scraped.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NetworkError {
+    /// The chain id is not in [`NETWORKS`].
+    #[error(
+        "unknown network {chain_id}, supported networks: {}",
+        supported_networks()
+    )]
+    Unknown { chain_id: String },
+    /// The network is known, the dex just isn't deployed on it.
+    #[error("{dex} is not supported on network {chain_id}")]
+    UnsupportedDex { dex: String, chain_id: String },
+}
+
+/// ANS prefix of the assets native to the chain.
+pub fn ans_prefix(chain_id: &str) -> Result<&'static str, NetworkError> {
+    NETWORKS
+        .iter()
+        .find(|(id, _, _)| *id == chain_id)
+        .map(|(_, prefix, _)| *prefix)
+        .ok_or_else(|| NetworkError::Unknown {
+            chain_id: chain_id.to_string(),
+        })
+}
+
+/// Chain registry name of a chain id, for the chains whose native denoms can be named.
+pub fn registry_chain_name(chain_id: &str) -> Option<&'static str> {
+    NETWORKS
+        .iter()
+        .find(|(id, _, _)| *id == chain_id)
+        .and_then(|(_, _, registry_name)| *registry_name)
+        .or_else(|| {
+            IBC_CHAINS
+                .iter()
+                .find(|(id, _)| *id == chain_id)
+                .map(|(_, registry_name)| *registry_name)
+        })
+}
+
+/// ANS prefix of the chain for `dex`, which is deployed on the networks in `chain_ids`.
+pub fn dex_ans_prefix(
+    dex: &str,
+    chain_ids: &[&str],
+    chain_id: &str,
+) -> Result<&'static str, NetworkError> {
+    let prefix = ans_prefix(chain_id)?;
+    if !chain_ids.contains(&chain_id) {
+        return Err(NetworkError::UnsupportedDex {
+            dex: dex.to_string(),
+            chain_id: chain_id.to_string(),
+        });
+    }
+    Ok(prefix)
+}
+
+/// The cw-orch network of a chain id, an error instead of cw-orch's panic for unknown ids.
+pub fn parse_network(chain_id: &str) -> Result<ChainInfo, NetworkError> {
+    ans_prefix(chain_id)?;
+    Ok(cw_orch::networks::parse_network(chain_id))
+}
+
+/// The gRPC endpoint to query the network on: `grpc_url`, [`GRPC_URL_ENV`] or the first endpoint
+/// cw-orch knows for the network.
+pub fn grpc_url(network: &ChainInfo, grpc_url: Option<&str>) -> anyhow::Result<String> {
+    if let Some(grpc_url) = grpc_url {
+        return Ok(grpc_url.to_string());
+    }
+    if let Ok(grpc_url) = std::env::var(GRPC_URL_ENV) {
+        return Ok(grpc_url);
+    }
+    network
+        .grpc_urls
+        .first()
+        .map(|grpc_url| grpc_url.to_string())
+        .ok_or_else(|| anyhow::anyhow!("no gRPC endpoint for network {}", network.chain_id))
+}
+
+/// The network with [its gRPC endpoint](grpc_url) as only endpoint, for the daemon builder.
+pub fn with_grpc_url(mut network: ChainInfo, grpc_url: Option<&str>) -> anyhow::Result<ChainInfo> {
+    let grpc_url = self::grpc_url(&network, grpc_url)?;
+    // cw-orch wants static endpoints, there is one network per run
+    let grpc_url: &'static str = Box::leak(grpc_url.into_boxed_str());
+    network.grpc_urls = Box::leak(Box::new([grpc_url]));
+    Ok(network)
+}
+
+/// Fail unless a wallet mnemonic is set, broadcasting needs a signer.
+pub fn require_signer(action: &str) -> anyhow::Result<()> {
+    if MNEMONIC_ENVS.iter().any(|env| std::env::var(env).is_ok()) {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{} needs a signer: set the mnemonic of the wallet in one of {}",
+        action,
+        MNEMONIC_ENVS.join(", ")
+    )
+}
+
+/// `chain-id (prefix)` of every network in [`NETWORKS`].
+fn supported_networks() -> String {
+    NETWORKS
+        .iter()
+        .map(|(chain_id, prefix, _)| format!("{} ({})", chain_id, prefix))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_names_come_from_the_network_table() {
+        assert_eq!(registry_chain_name("phoenix-1"), Some("terra2"));
+        assert_eq!(registry_chain_name("osmosis-1"), Some("osmosis"));
+        // Testnets share the ANS prefix of their mainnet, not its asset list
+        assert_eq!(ans_prefix("pisco-1"), Ok("terra2"));
+        assert_eq!(registry_chain_name("pisco-1"), None);
+    }
+
+    #[test]
+    fn ibc_chains_have_a_registry_name_but_no_ans_prefix() {
+        assert_eq!(registry_chain_name("cosmoshub-4"), Some("cosmoshub"));
+        assert!(ans_prefix("cosmoshub-4").is_err());
+        assert_eq!(registry_chain_name("unknown-1"), None);
+    }
+}
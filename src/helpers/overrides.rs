@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use anyhow::Context;
+use cw_asset::AssetInfo;
+use serde::Deserialize;
+
+/// Overrides of the scrape results in the configuration directory of a chain.
+pub const OVERRIDES_FILE: &str = "overrides.toml";
+
+/// Corrections of the scrape results by the operator, from `<config_dir>/<chain_id>/overrides.toml`.
+///
+/// Assets are keyed by denom or cw20 address, pools by contract address:
+///
+/// ```toml
+/// asset_denylist = ["terra1scam..."]
+/// pool_denylist = ["terra1pair..."]
+///
+/// [asset_overrides]
+/// "ibc/B3504E..." = "cosmoshub>atom"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Overrides {
+    /// Denom or cw20 address -> ANS name, used instead of the resolved name (or for assets that
+    /// can't be resolved)
+    #[serde(default)]
+    pub asset_overrides: BTreeMap<String, String>,
+    /// Assets that are never registered, nor the pools that have them
+    #[serde(default)]
+    pub asset_denylist: BTreeSet<String>,
+    /// Pools that are never registered
+    #[serde(default)]
+    pub pool_denylist: BTreeSet<String>,
+}
+
+/// Entries of the overrides that match nothing in the scrape, probably typos.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{} override entries match nothing that was scraped: {}", unknown.len(), unknown.join(", "))]
+pub struct UnknownOverrides {
+    pub unknown: Vec<String>,
+}
+
+impl Overrides {
+    /// Load the overrides of the chain. A chain without the file has none.
+    pub fn load(config_dir: &Path, chain_id: &str) -> anyhow::Result<Self> {
+        let path = config_dir.join(chain_id).join(OVERRIDES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let toml = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading overrides from {}", path.display()))?;
+        let overrides: Self = toml::from_str(&toml)
+            .with_context(|| format!("parsing overrides from {}", path.display()))?;
+        log::info!(
+            "{}: {} asset overrides, {} denied assets, {} denied pools",
+            chain_id,
+            overrides.asset_overrides.len(),
+            overrides.asset_denylist.len(),
+            overrides.pool_denylist.len()
+        );
+        Ok(overrides)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.asset_overrides.is_empty()
+            && self.asset_denylist.is_empty()
+            && self.pool_denylist.is_empty()
+    }
+
+    /// The forced name of the asset.
+    pub fn asset_name(&self, info: &AssetInfo) -> Option<&str> {
+        asset_key(info)
+            .and_then(|key| self.asset_overrides.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn is_denied_asset(&self, info: &AssetInfo) -> bool {
+        asset_key(info).map_or(false, |key| self.asset_denylist.contains(key))
+    }
+
+    pub fn is_denied_pool(&self, pool: &UncheckedPoolAddress) -> bool {
+        match pool {
+            UncheckedPoolAddress::Contract(addr) => self.pool_denylist.contains(addr),
+            UncheckedPoolAddress::Id(_) => false,
+        }
+    }
+
+    /// Fail on the asset overrides, denied assets and denied pools that aren't any of the
+    /// scraped assets or pools.
+    pub fn check_known<'a>(
+        &self,
+        assets: impl IntoIterator<Item = &'a AssetInfo>,
+        pools: impl IntoIterator<Item = &'a UncheckedPoolAddress>,
+    ) -> Result<(), UnknownOverrides> {
+        let assets: BTreeSet<&str> = assets.into_iter().filter_map(asset_key).collect();
+        let pools: BTreeSet<&str> = pools
+            .into_iter()
+            .filter_map(|pool| match pool {
+                UncheckedPoolAddress::Contract(addr) => Some(addr.as_str()),
+                UncheckedPoolAddress::Id(_) => None,
+            })
+            .collect();
+
+        let unknown: Vec<String> = self
+            .asset_overrides
+            .keys()
+            .filter(|key| !assets.contains(key.as_str()))
+            .map(|key| format!("asset override {}", key))
+            .chain(
+                self.asset_denylist
+                    .iter()
+                    .filter(|key| !assets.contains(key.as_str()))
+                    .map(|key| format!("denied asset {}", key)),
+            )
+            .chain(
+                self.pool_denylist
+                    .iter()
+                    .filter(|addr| !pools.contains(addr.as_str()))
+                    .map(|addr| format!("denied pool {}", addr)),
+            )
+            .collect();
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(UnknownOverrides { unknown })
+        }
+    }
+}
+
+/// The denom or cw20 address an asset is keyed by in the overrides.
+fn asset_key(info: &AssetInfo) -> Option<&str> {
+    match info {
+        AssetInfo::Native(denom) => Some(denom.as_str()),
+        AssetInfo::Cw20(contract_addr) => Some(contract_addr.as_str()),
+        _ => None,
+    }
+}
@@ -0,0 +1,100 @@
+//! Pagination of the `pairs` query of the factories, which page with the assets of the last pair.
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+use cosmwasm_std::Addr;
+
+/// A pair as listed by the `pairs` query of a factory.
+pub(crate) trait FactoryPair {
+    /// Asset info of the factory, the cursor of the query is the asset infos of a pair
+    type AssetInfo: Clone + Debug + PartialEq;
+
+    fn contract_addr(&self) -> &Addr;
+
+    fn asset_infos(&self) -> &[Self::AssetInfo];
+}
+
+/// Guards the pagination of a factory against nodes that don't honour `start_after`.
+///
+/// Some nodes answer the same page whatever the cursor, which would be paginated forever. The
+/// pagination stops when a page has no pair that wasn't seen yet, when the cursor doesn't advance
+/// or after `max_pages` pages, and the pairs seen before are dropped from the pages.
+pub(crate) struct PairPages<P: FactoryPair> {
+    dex: &'static str,
+    chain_id: String,
+    start_after: Option<Vec<P::AssetInfo>>,
+    seen: HashSet<Addr>,
+    pages: usize,
+    max_pages: usize,
+    done: bool,
+}
+
+impl<P: FactoryPair> PairPages<P> {
+    pub fn new(
+        dex: &'static str,
+        chain_id: &str,
+        start_after: Option<Vec<P::AssetInfo>>,
+        max_pages: usize,
+    ) -> Self {
+        Self {
+            dex,
+            chain_id: chain_id.to_string(),
+            start_after,
+            seen: HashSet::new(),
+            pages: 0,
+            max_pages,
+            done: false,
+        }
+    }
+
+    /// Cursor of the next page, `None` once the pagination is over.
+    pub fn cursor(&self) -> Option<Option<Vec<P::AssetInfo>>> {
+        (!self.done).then(|| self.start_after.clone())
+    }
+
+    /// The pairs of `page` that weren't seen yet, and the cursor moved after it.
+    pub fn advance(&mut self, page: Vec<P>) -> Vec<P> {
+        self.pages += 1;
+        let cursor = page.last().map(|p| p.asset_infos().to_vec());
+        let fetched = page.len();
+        let new_pairs: Vec<P> = page
+            .into_iter()
+            .filter(|pair| self.seen.insert(pair.contract_addr().clone()))
+            .collect();
+
+        if fetched == 0 {
+            self.done = true;
+        } else if new_pairs.is_empty() {
+            log::warn!(
+                "{}: page {} of the {} factory only has pairs of the previous pages, stopping the \
+                 pagination",
+                self.chain_id,
+                self.pages,
+                self.dex
+            );
+            self.done = true;
+        } else if cursor == self.start_after {
+            log::warn!(
+                "{}: the cursor of the {} factory didn't advance after page {}, stopping the \
+                 pagination",
+                self.chain_id,
+                self.dex,
+                self.pages
+            );
+            self.done = true;
+        } else if self.pages >= self.max_pages {
+            log::warn!(
+                "{}: stopping the pagination of the {} factory after {} pages, the pairs after \
+                 {:?} are missing",
+                self.chain_id,
+                self.dex,
+                self.pages,
+                cursor
+            );
+            self.done = true;
+        }
+        self.start_after = cursor;
+        new_pairs
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use cosmwasm_std::Addr;
+use cw20::TokenInfoResponse;
+
+use super::cache::{read_json_or_invalidate, write_json_atomic};
+use super::denoms::DenomTrace;
+
+const CW20_INFOS_FILE: &str = "cw20_infos.json";
+const DENOM_TRACES_FILE: &str = "denom_traces.json";
+
+/// Responses of the queries that name the assets of a chain: cw20 `TokenInfo`s and the denom
+/// traces of IBC denoms.
+///
+/// They practically never change, so they are persisted to
+/// `<cache_dir>/<chain_id>/cw20_infos.json` and `denom_traces.json`, and are all an offline run
+/// needs to name the assets. A corrupted file is discarded with a warning and filled again by the
+/// queries of the run.
+#[derive(Default)]
+pub struct QueryCache {
+    /// contract -> token info
+    cw20_infos: RwLock<BTreeMap<Addr, TokenInfoResponse>>,
+    /// ibc hash -> denom trace
+    denom_traces: RwLock<BTreeMap<String, DenomTrace>>,
+}
+
+/// What an offline run needs that isn't in the cache.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error(
+    "{chain_id}: {} entries are not cached and can't be queried offline: {}",
+    missing.len(),
+    missing.join(", ")
+)]
+pub struct MissingCacheEntries {
+    pub chain_id: String,
+    pub missing: Vec<String>,
+}
+
+impl QueryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn cache_file(cache_dir: &Path, chain_id: &str, file: &str) -> PathBuf {
+        cache_dir.join(chain_id).join(file)
+    }
+
+    /// Load the persisted responses of a chain into the cache.
+    pub fn load(&self, cache_dir: &Path, chain_id: &str) -> anyhow::Result<()> {
+        let cw20_infos: Option<BTreeMap<Addr, TokenInfoResponse>> =
+            read_json_or_invalidate(Self::cache_file(cache_dir, chain_id, CW20_INFOS_FILE))?;
+        if let Some(cw20_infos) = cw20_infos {
+            self.cw20_infos.write().unwrap().extend(cw20_infos);
+        }
+
+        let denom_traces: Option<BTreeMap<String, DenomTrace>> =
+            read_json_or_invalidate(Self::cache_file(cache_dir, chain_id, DENOM_TRACES_FILE))?;
+        if let Some(denom_traces) = denom_traces {
+            self.denom_traces.write().unwrap().extend(denom_traces);
+        }
+        Ok(())
+    }
+
+    /// Persist the responses of a chain, merged into the persisted ones so a run that only
+    /// queried some of them (e.g. a refresh) doesn't drop the others.
+    pub fn save(&self, cache_dir: &Path, chain_id: &str) -> anyhow::Result<()> {
+        let cw20_infos_path = Self::cache_file(cache_dir, chain_id, CW20_INFOS_FILE);
+        let mut cw20_infos: BTreeMap<Addr, TokenInfoResponse> =
+            read_json_or_invalidate(&cw20_infos_path)?.unwrap_or_default();
+        cw20_infos.extend(self.cw20_infos.read().unwrap().clone());
+        write_json_atomic(cw20_infos_path, &cw20_infos)?;
+
+        let denom_traces_path = Self::cache_file(cache_dir, chain_id, DENOM_TRACES_FILE);
+        let mut denom_traces: BTreeMap<String, DenomTrace> =
+            read_json_or_invalidate(&denom_traces_path)?.unwrap_or_default();
+        denom_traces.extend(self.denom_traces.read().unwrap().clone());
+        write_json_atomic(denom_traces_path, &denom_traces)
+    }
+
+    pub fn cw20_info(&self, contract_addr: &Addr) -> Option<TokenInfoResponse> {
+        self.cw20_infos.read().unwrap().get(contract_addr).cloned()
+    }
+
+    pub fn insert_cw20_info(&self, contract_addr: &Addr, info: TokenInfoResponse) {
+        self.cw20_infos
+            .write()
+            .unwrap()
+            .insert(contract_addr.clone(), info);
+    }
+
+    /// Denom trace of an IBC hash.
+    pub fn denom_trace(&self, hash: &str) -> Option<DenomTrace> {
+        self.denom_traces.read().unwrap().get(hash).cloned()
+    }
+
+    pub fn insert_denom_trace(&self, hash: &str, trace: DenomTrace) {
+        self.denom_traces
+            .write()
+            .unwrap()
+            .insert(hash.to_string(), trace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::Uint128;
+
+    use super::*;
+
+    fn token_info(symbol: &str) -> TokenInfoResponse {
+        TokenInfoResponse {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            total_supply: Uint128::zero(),
+        }
+    }
+
+    #[test]
+    fn save_keeps_the_persisted_entries_the_run_did_not_query() {
+        let cache_dir =
+            std::env::temp_dir().join(format!("ans-scraper-query-cache-{}", std::process::id()));
+        let first_run = QueryCache::new();
+        first_run.insert_cw20_info(&Addr::unchecked("astro"), token_info("ASTRO"));
+        first_run.insert_cw20_info(&Addr::unchecked("xastro"), token_info("XASTRO"));
+        first_run.save(&cache_dir, "pisco-1").unwrap();
+
+        // a refresh that only queried one of the token infos again
+        let refresh = QueryCache::new();
+        refresh.insert_cw20_info(&Addr::unchecked("astro"), token_info("ASTRO2"));
+        refresh.save(&cache_dir, "pisco-1").unwrap();
+
+        let loaded = QueryCache::new();
+        loaded.load(&cache_dir, "pisco-1").unwrap();
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+        assert_eq!(
+            loaded.cw20_info(&Addr::unchecked("astro")),
+            Some(token_info("ASTRO2"))
+        );
+        assert_eq!(
+            loaded.cw20_info(&Addr::unchecked("xastro")),
+            Some(token_info("XASTRO"))
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+use cw_asset::AssetInfo;
+
+use super::cache::{read_json_or_invalidate, write_json_atomic};
+use super::chain_registry::ResolvedAsset;
+use super::overrides::Overrides;
+
+/// Process-wide memo of resolved asset names, shared by all the scrapers of a run.
+///
+/// Keyed by chain-id and asset info so scrapers on the same chain don't resolve the same
+/// denoms/cw20s again. Can be persisted to `<cache_dir>/<chain_id>/names.json`.
+///
+/// The forced names of the [`Overrides`] of a chain take precedence over the resolved ones and are
+/// never persisted, so removing an override brings the resolved name back.
+///
+/// It also records, for the report of the run, which names were sanitized and which were made up.
+/// Those records are never persisted either.
+#[derive(Default)]
+pub struct ResolutionCache {
+    /// chain-id -> asset info -> ANS name
+    names: RwLock<HashMap<String, BTreeMap<String, String>>>,
+    /// chain-id -> overrides
+    overrides: RwLock<HashMap<String, Overrides>>,
+    /// chain-id -> sanitized ANS name -> name before sanitization
+    unsanitized: RwLock<HashMap<String, BTreeMap<String, String>>>,
+    /// chain-id -> made up ANS name -> denom it was made up for
+    low_confidence: RwLock<HashMap<String, BTreeMap<String, String>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ResolutionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn names_file(cache_dir: &Path, chain_id: &str) -> PathBuf {
+        cache_dir.join(chain_id).join("names.json")
+    }
+
+    /// Load the persisted names of a chain into the cache.
+    pub fn load(&self, cache_dir: &Path, chain_id: &str) -> anyhow::Result<()> {
+        let names: Option<BTreeMap<String, String>> =
+            read_json_or_invalidate(Self::names_file(cache_dir, chain_id))?;
+        if let Some(names) = names {
+            self.names
+                .write()
+                .unwrap()
+                .entry(chain_id.to_string())
+                .or_default()
+                .extend(names);
+        }
+        Ok(())
+    }
+
+    /// Persist the names of a chain, merged into the persisted ones so a run that only resolved
+    /// some of them (e.g. a refresh) doesn't drop the others.
+    pub fn save(&self, cache_dir: &Path, chain_id: &str) -> anyhow::Result<()> {
+        let Some(chain_names) = self.names.read().unwrap().get(chain_id).cloned() else {
+            return Ok(());
+        };
+        let path = Self::names_file(cache_dir, chain_id);
+        let mut names: BTreeMap<String, String> =
+            read_json_or_invalidate(&path)?.unwrap_or_default();
+        names.extend(chain_names);
+        write_json_atomic(path, &names)
+    }
+
+    /// Name the assets of the chain with the forced names of `overrides`.
+    pub fn set_overrides(&self, chain_id: &str, overrides: &Overrides) {
+        self.overrides
+            .write()
+            .unwrap()
+            .insert(chain_id.to_string(), overrides.clone());
+    }
+
+    fn forced_name(&self, chain_id: &str, asset_info: &AssetInfo) -> Option<String> {
+        self.overrides
+            .read()
+            .unwrap()
+            .get(chain_id)
+            .and_then(|overrides| overrides.asset_name(asset_info))
+            .map(str::to_string)
+    }
+
+    pub fn get(&self, chain_id: &str, asset_info: &AssetInfo) -> Option<String> {
+        let name = self.forced_name(chain_id, asset_info).or_else(|| {
+            self.names
+                .read()
+                .unwrap()
+                .get(chain_id)
+                .and_then(|names| names.get(&asset_info.to_string()))
+                .cloned()
+        });
+
+        if name.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        name
+    }
+
+    /// Remember the resolved name of an asset, unless it has a forced name.
+    pub fn insert(&self, chain_id: &str, asset_info: &AssetInfo, name: String) {
+        if self.forced_name(chain_id, asset_info).is_some() {
+            return;
+        }
+        self.names
+            .write()
+            .unwrap()
+            .entry(chain_id.to_string())
+            .or_default()
+            .insert(asset_info.to_string(), name);
+    }
+
+    /// Remember how the chain registry or the token info named an asset.
+    ///
+    /// Made up names are recorded for the report but not cached, so every run checks them again.
+    pub fn insert_resolved(
+        &self,
+        chain_id: &str,
+        asset_info: &AssetInfo,
+        resolved: &ResolvedAsset,
+    ) {
+        if let Some(unsanitized) = &resolved.unsanitized {
+            self.unsanitized
+                .write()
+                .unwrap()
+                .entry(chain_id.to_string())
+                .or_default()
+                .insert(resolved.name.clone(), unsanitized.clone());
+        }
+        if resolved.low_confidence {
+            let denom = match asset_info {
+                AssetInfo::Native(denom) => denom.clone(),
+                _ => asset_info.to_string(),
+            };
+            self.low_confidence
+                .write()
+                .unwrap()
+                .entry(chain_id.to_string())
+                .or_default()
+                .insert(resolved.name.clone(), denom);
+        } else {
+            self.insert(chain_id, asset_info, resolved.name.clone());
+        }
+    }
+
+    /// The name `name` was sanitized from on the chain in this run.
+    ///
+    /// Names taken from the persisted cache have no record.
+    pub fn unsanitized_name(&self, chain_id: &str, name: &str) -> Option<String> {
+        self.unsanitized
+            .read()
+            .unwrap()
+            .get(chain_id)?
+            .get(name)
+            .cloned()
+    }
+
+    /// The denom `name` was made up for on the chain in this run, if it was.
+    pub fn low_confidence_denom(&self, chain_id: &str, name: &str) -> Option<String> {
+        self.low_confidence
+            .read()
+            .unwrap()
+            .get(chain_id)?
+            .get(name)
+            .cloned()
+    }
+
+    /// `(hits, misses)` since the cache was created.
+    pub fn stats(&self) -> (usize, usize) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(name: &str, low_confidence: bool, unsanitized: Option<&str>) -> ResolvedAsset {
+        ResolvedAsset {
+            name: name.to_string(),
+            low_confidence,
+            unsanitized: unsanitized.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn made_up_names_are_recorded_but_not_cached() {
+        let cache = ResolutionCache::new();
+        let asset_info = AssetInfo::native("factory/osmo1creator/moon");
+        cache.insert_resolved(
+            "osmosis-1",
+            &asset_info,
+            &resolved("osmosis>moon", true, None),
+        );
+
+        assert_eq!(cache.get("osmosis-1", &asset_info), None);
+        assert_eq!(
+            cache.low_confidence_denom("osmosis-1", "osmosis>moon"),
+            Some("factory/osmo1creator/moon".to_string())
+        );
+    }
+
+    #[test]
+    fn names_are_recorded_per_chain() {
+        let cache = ResolutionCache::new();
+        let asset_info = AssetInfo::native("uluna");
+        cache.insert_resolved(
+            "phoenix-1",
+            &asset_info,
+            &resolved("terra2>luna_x", false, Some("terra2>luna.x")),
+        );
+        cache.insert_resolved(
+            "phoenix-1",
+            &AssetInfo::native("factory/terra1creator/moon"),
+            &resolved("terra2>moon", true, None),
+        );
+
+        assert_eq!(
+            cache.get("phoenix-1", &asset_info),
+            Some("terra2>luna_x".to_string())
+        );
+        assert_eq!(
+            cache.unsanitized_name("phoenix-1", "terra2>luna_x"),
+            Some("terra2>luna.x".to_string())
+        );
+        // Another chain, or another run with its own cache, has no record of them
+        assert_eq!(cache.unsanitized_name("pisco-1", "terra2>luna_x"), None);
+        assert_eq!(cache.low_confidence_denom("pisco-1", "terra2>moon"), None);
+        let other_run = ResolutionCache::new();
+        assert_eq!(
+            other_run.unsanitized_name("phoenix-1", "terra2>luna_x"),
+            None
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::collections::{BTreeMap, HashMap};
+
+use cw_asset::AssetInfo;
+
+use super::chain_registry::{LazyChainRegistry, ResolvedAsset};
+use super::clients::ChainClients;
+use super::cw20::{resolve_cw20_entries, Cw20Entry};
+use super::denoms::ibc_denom_hash;
+use super::query_cache::MissingCacheEntries;
+use super::resolution_cache::ResolutionCache;
+use crate::report::{UnresolvedAsset, UnresolvedReason};
+
+/// Names cw20 and native assets of a chain, for scrapers of factory-based dexes.
+///
+/// Names in the resolution cache are used as is, the rest is queried concurrently: the cw20s with
+/// their `TokenInfo`, the IBC denoms through the chain registry. A failing query doesn't abort the
+/// resolution, the asset is reported as not found. Offline clients only use the cached query
+/// responses and fail on the first resolution that would need a query, listing all of them.
+pub struct AssetResolver<'a> {
+    pub clients: &'a ChainClients,
+    pub chain_registry: &'a LazyChainRegistry,
+    pub resolution_cache: &'a ResolutionCache,
+    pub ans_prefix: &'a str,
+    pub concurrency: usize,
+}
+
+/// Outcome of [`AssetResolver::resolve`].
+#[derive(Debug, Default)]
+pub struct ResolvedAssets {
+    /// Names of the resolved assets, sorted by name
+    pub names: Vec<(String, AssetInfo)>,
+    /// Assets without a name, with the reason
+    pub not_found: Vec<UnresolvedAsset>,
+}
+
+impl ResolvedAssets {
+    /// Names of the resolved assets, keyed by the string of their asset info.
+    pub fn names_by_info(&self) -> HashMap<String, &str> {
+        self.names
+            .iter()
+            .map(|(name, info)| (info.to_string(), name.as_str()))
+            .collect()
+    }
+}
+
+impl AssetResolver<'_> {
+    pub async fn resolve(&self, asset_infos: Vec<AssetInfo>) -> anyhow::Result<ResolvedAssets> {
+        let chain_id = &self.clients.chain_id;
+
+        // Names resolved earlier in the run (or a previous one) don't need any queries
+        let mut cached_names = HashMap::new();
+        for info in &asset_infos {
+            if let Some(name) = self.resolution_cache.get(chain_id, info) {
+                cached_names.insert(info.to_string(), name);
+            }
+        }
+        if self.clients.offline {
+            self.check_cached(&asset_infos, &cached_names)?;
+        }
+
+        let cw20s = asset_infos
+            .iter()
+            .filter_map(|info| match info {
+                AssetInfo::Cw20(contract_addr) if !cached_names.contains_key(&info.to_string()) => {
+                    Some(contract_addr.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // The curated names of the chain registry come before the symbols of the contracts
+        let cw20_registry = if cw20s.is_empty() {
+            None
+        } else {
+            Some(self.chain_registry.get().await?)
+        };
+        let mut cw20_entries = resolve_cw20_entries(
+            self.clients,
+            cw20_registry,
+            self.ans_prefix,
+            cw20s,
+            self.concurrency,
+        )
+        .await;
+
+        let denoms = asset_infos
+            .iter()
+            .filter_map(|info| match info {
+                AssetInfo::Native(denom) if !cached_names.contains_key(&info.to_string()) => {
+                    Some(denom.clone())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // Only fetch the registry when there's something to look up in it
+        let mut native_entries = if denoms.is_empty() {
+            BTreeMap::new()
+        } else {
+            let chain_registry = self.chain_registry.get().await?;
+            chain_registry
+                .resolve_native_assets(self.clients, denoms, self.concurrency)
+                .await
+        };
+
+        let mut resolved = ResolvedAssets::default();
+        for asset_info in asset_infos {
+            let name = match &asset_info {
+                _ if cached_names.contains_key(&asset_info.to_string()) => {
+                    ResolvedAsset::new(cached_names[&asset_info.to_string()].clone())
+                }
+                AssetInfo::Cw20(contract_addr) => {
+                    match cw20_entries
+                        .remove(contract_addr)
+                        .expect("all cw20s are resolved")
+                    {
+                        Ok(Cw20Entry {
+                            entry,
+                            verified,
+                            unsanitized,
+                        }) => {
+                            if !verified {
+                                log::debug!("Naming cw20 {} after its token info", contract_addr);
+                            }
+                            ResolvedAsset {
+                                name: entry,
+                                low_confidence: false,
+                                unsanitized,
+                            }
+                        }
+                        Err(err) => {
+                            if err.is_skippable() {
+                                log::warn!("Skipping cw20 asset: {}", err);
+                            } else {
+                                log::warn!("Skipping cw20 asset until the next run: {}", err);
+                            }
+                            let reason = UnresolvedReason::Cw20Query(err.to_string());
+                            resolved
+                                .not_found
+                                .push(UnresolvedAsset::new(asset_info, reason));
+                            continue;
+                        }
+                    }
+                }
+                AssetInfo::Native(denom) => {
+                    match native_entries
+                        .remove(denom)
+                        .expect("all denoms are resolved")
+                    {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            let reason = UnresolvedReason::from(err);
+                            log::warn!("Skipping native asset {}: {}", denom, reason);
+                            resolved
+                                .not_found
+                                .push(UnresolvedAsset::new(asset_info, reason));
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    log::warn!("AssetInfo not supported: {:?}", asset_info);
+                    let reason = UnresolvedReason::Unsupported(format!("{:?}", asset_info));
+                    resolved
+                        .not_found
+                        .push(UnresolvedAsset::new(asset_info, reason));
+                    continue;
+                }
+            };
+
+            self.resolution_cache
+                .insert_resolved(chain_id, &asset_info, &name);
+            resolved.names.push((name.name, asset_info));
+        }
+
+        // Query timing must not change the output
+        resolved.names.sort_by(|a, b| {
+            a.0.cmp(&b.0)
+                .then_with(|| a.1.to_string().cmp(&b.1.to_string()))
+        });
+        Ok(resolved)
+    }
+
+    /// Fail with every token info and denom trace that resolving `asset_infos` offline lacks.
+    fn check_cached(
+        &self,
+        asset_infos: &[AssetInfo],
+        cached_names: &HashMap<String, String>,
+    ) -> Result<(), MissingCacheEntries> {
+        let query_cache = &self.clients.query_cache;
+        let missing: Vec<String> = asset_infos
+            .iter()
+            .filter(|info| !cached_names.contains_key(&info.to_string()))
+            .filter_map(|info| match info {
+                AssetInfo::Cw20(contract_addr)
+                    if query_cache.cw20_info(contract_addr).is_none() =>
+                {
+                    Some(format!("token info of {}", contract_addr))
+                }
+                AssetInfo::Native(denom) => ibc_denom_hash(denom)
+                    .filter(|hash| query_cache.denom_trace(hash).is_none())
+                    .map(|_| format!("denom trace of {}", denom)),
+                _ => None,
+            })
+            .collect();
+
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(MissingCacheEntries {
+            chain_id: self.clients.chain_id.clone(),
+            missing,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{Addr, Uint128};
+    use cw20::TokenInfoResponse;
+
+    use super::*;
+    use crate::test_utils::{registry_from_lists, AssetListBuilder, DenomTraceFixture};
+    use crate::ChainRegistry;
+
+    fn token_info(symbol: &str) -> TokenInfoResponse {
+        TokenInfoResponse {
+            name: symbol.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            total_supply: Uint128::zero(),
+        }
+    }
+
+    fn cw20(contract_addr: &str) -> AssetInfo {
+        AssetInfo::cw20(Addr::unchecked(contract_addr))
+    }
+
+    async fn resolve_offline(
+        clients: &ChainClients,
+        chain_registry: ChainRegistry,
+        resolution_cache: &ResolutionCache,
+        asset_infos: Vec<AssetInfo>,
+    ) -> anyhow::Result<ResolvedAssets> {
+        let chain_registry = LazyChainRegistry::from(chain_registry);
+        AssetResolver {
+            clients,
+            chain_registry: &chain_registry,
+            resolution_cache,
+            ans_prefix: "terra2",
+            concurrency: 1,
+        }
+        .resolve(asset_infos)
+        .await
+    }
+
+    #[tokio::test]
+    async fn cw20s_are_named_after_their_cached_token_info() {
+        let clients = ChainClients::offline("phoenix-1");
+        let query_cache = &clients.query_cache;
+        query_cache.insert_cw20_info(&Addr::unchecked("astro_token"), token_info("ASTRO"));
+        query_cache.insert_cw20_info(&Addr::unchecked("bad_token"), token_info("NOT ASTRO"));
+        let resolution_cache = ResolutionCache::new();
+
+        let resolved = resolve_offline(
+            &clients,
+            registry_from_lists(vec![]),
+            &resolution_cache,
+            vec![cw20("bad_token"), cw20("astro_token")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            resolved.names,
+            [("terra2>astro".to_string(), cw20("astro_token"))]
+        );
+        // an invalid token info skips the cw20 instead of failing the resolution
+        assert_eq!(resolved.not_found.len(), 1);
+        assert_eq!(resolved.not_found[0].info, cw20("bad_token"));
+        assert_eq!(
+            resolution_cache.get("phoenix-1", &cw20("astro_token")),
+            Some("terra2>astro".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_resolution_lists_the_uncached_token_infos() {
+        let clients = ChainClients::offline("phoenix-1");
+        clients
+            .query_cache
+            .insert_cw20_info(&Addr::unchecked("astro_token"), token_info("ASTRO"));
+
+        let err = resolve_offline(
+            &clients,
+            registry_from_lists(vec![]),
+            &ResolutionCache::new(),
+            vec![cw20("astro_token"), cw20("other_token")],
+        )
+        .await
+        .unwrap_err();
+        let missing = err
+            .downcast::<MissingCacheEntries>()
+            .expect("missing cache entries");
+        assert_eq!(missing.missing, ["token info of other_token"]);
+    }
+
+    #[tokio::test]
+    async fn natives_are_named_after_their_registry_asset() {
+        let clients = ChainClients::offline("phoenix-1");
+        let atom = DenomTraceFixture::transfer("channel-0", "uatom");
+        clients
+            .query_cache
+            .insert_denom_trace(&atom.hash(), atom.trace());
+        let chain_registry = registry_from_lists(vec![
+            AssetListBuilder::chain("terra2")
+                .asset("LUNA", "uluna", 6)
+                .ibc_asset("ATOM", &atom, "cosmoshub", 6)
+                .build(),
+            AssetListBuilder::chain("cosmoshub")
+                .asset("ATOM", "uatom", 6)
+                .build(),
+        ]);
+
+        let resolved = resolve_offline(
+            &clients,
+            chain_registry,
+            &ResolutionCache::new(),
+            vec![
+                AssetInfo::native("uluna"),
+                AssetInfo::native(atom.ibc_denom()),
+                AssetInfo::native("ufoo"),
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            resolved.names,
+            [
+                (
+                    "cosmoshub>atom".to_string(),
+                    AssetInfo::native(atom.ibc_denom())
+                ),
+                ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+            ]
+        );
+        assert_eq!(resolved.not_found.len(), 1);
+        assert_eq!(resolved.not_found[0].info, AssetInfo::native("ufoo"));
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+use tonic::Code;
+
+/// Default number of retries of a failed network call, on top of the first attempt.
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Default delay before the first retry, doubled for every following one.
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest delay between two attempts.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// gRPC codes of failures that may go away on their own.
+const TRANSIENT_GRPC_CODES: [Code; 5] = [
+    Code::Unavailable,
+    Code::DeadlineExceeded,
+    Code::ResourceExhausted,
+    Code::Aborted,
+    Code::Cancelled,
+];
+
+/// Messages of transient failures whose error type got lost on the way, e.g. in cw-orch errors.
+const TRANSIENT_MESSAGES: [&str; 8] = [
+    "status: unavailable",
+    "status: deadlineexceeded",
+    "status: resourceexhausted",
+    "status: aborted",
+    "transport error",
+    "connection reset",
+    "broken pipe",
+    "timed out",
+];
+
+tokio::task_local! {
+    /// Policy of the scrape running on the task, see [`RetryPolicy::scope`].
+    static RETRY_POLICY: RetryPolicy;
+}
+
+/// How often and how patiently failed network calls are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run `fut` with this policy for every network call it makes, calls outside of a scope use
+    /// the default policy.
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        RETRY_POLICY.scope(self, fut).await
+    }
+
+    /// Delay before retry number `retry` (starting at 1): exponential, capped at
+    /// [`MAX_RETRY_DELAY`], with up to half of it random so concurrent callers don't retry in
+    /// lockstep.
+    fn delay(&self, retry: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(MAX_RETRY_DELAY);
+        exponential.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Policy of the innermost [scope](RetryPolicy::scope) the task runs in.
+fn retry_policy() -> RetryPolicy {
+    RETRY_POLICY.try_with(|policy| *policy).unwrap_or_default()
+}
+
+/// Run `op` until it succeeds, fails with an error that isn't [transient](is_transient) or runs
+/// out of retries. `target` names what is called in the logs.
+pub async fn retry<T, F, Fut>(target: impl Display, mut op: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let policy = retry_policy();
+    let mut retries = 0;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if retries < policy.max_retries && is_transient(&err) => {
+                retries += 1;
+                let delay = policy.delay(retries);
+                log::warn!(
+                    "{}: attempt {} of {} failed, retrying in {:?}: {:#}",
+                    target,
+                    retries,
+                    policy.max_retries + 1,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Whether the call may succeed when tried again: unavailable nodes, timeouts, dropped
+/// connections, 5xx and 429 responses. Errors of the contract or the response itself are final.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    for cause in err.chain() {
+        if let Some(status) = cause.downcast_ref::<tonic::Status>() {
+            return TRANSIENT_GRPC_CODES.contains(&status.code());
+        }
+        if cause.is::<tonic::transport::Error>() {
+            return true;
+        }
+        if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+            return match err.status() {
+                Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+            };
+        }
+    }
+
+    // cw-orch only keeps the message of the gRPC status
+    let message = format!("{:#}", err).to_ascii_lowercase();
+    TRANSIENT_MESSAGES
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    /// Number of attempts of a call that always fails with a transient error.
+    async fn attempts() -> u32 {
+        let attempts = &AtomicU32::new(0);
+        let result: anyhow::Result<()> = retry("node", || async move {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(tonic::Status::unavailable("node unavailable").into())
+        })
+        .await;
+        assert!(result.is_err());
+        attempts.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn calls_are_retried_with_the_policy_of_their_scope() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+        };
+        assert_eq!(policy.scope(attempts()).await, 3);
+
+        let no_retries = RetryPolicy {
+            max_retries: 0,
+            ..policy
+        };
+        let nested = policy.scope(async { no_retries.scope(attempts()).await });
+        assert_eq!(nested.await, 1);
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+
+use super::chain_registry::{ChainRegistry, LazyChainRegistry, REGISTRY_CW20_PREFIX};
+use super::names::LP_TOKEN_SEPARATOR;
+use crate::error::ScraperError;
+
+/// Directory of the per-chain scraper configuration, `config/<chain-id>/`.
+pub const DEFAULT_CONFIG_DIR: &str = "config";
+
+/// Allow-list of cw20 addresses in the configuration directory of a chain.
+pub const VERIFIED_CW20S_FILE: &str = "verified_cw20s.json";
+
+/// The cw20 contracts whose `TokenInfo` symbol can be trusted to name them.
+///
+/// Anyone can deploy a cw20 that calls itself "USDC", so only allow-listed contracts (or the ones
+/// listed in the chain registry) are registered. LP tokens are always trusted, the dex minted them.
+#[derive(Clone, Default)]
+pub struct Cw20Verifier {
+    verified: BTreeSet<String>,
+    /// Registry and chain name of the asset list with more trusted cw20s, only fetched for the
+    /// cw20s that aren't allow-listed
+    chain_registry: Option<(LazyChainRegistry, String)>,
+}
+
+impl Cw20Verifier {
+    /// Load the allow-list of the chain, `<config_dir>/<chain_id>/verified_cw20s.json`, a JSON
+    /// array of addresses. A chain without the file has an empty allow-list.
+    pub fn load(config_dir: &Path, chain_id: &str) -> anyhow::Result<Self> {
+        let path = config_dir.join(chain_id).join(VERIFIED_CW20S_FILE);
+        if !path.exists() {
+            log::warn!(
+                "{}: no verified cw20s file at {}, only chain registry cw20s are verified",
+                chain_id,
+                path.display()
+            );
+            return Ok(Self::default());
+        }
+
+        let json = std::fs::read(&path)
+            .with_context(|| format!("reading verified cw20s from {}", path.display()))?;
+        let verified: BTreeSet<String> = serde_json::from_slice(&json)
+            .with_context(|| format!("parsing verified cw20s from {}", path.display()))?;
+        Ok(Self {
+            verified,
+            chain_registry: None,
+        })
+    }
+
+    /// Also trust the cw20s of the asset list of `chain_name` in the chain registry. The registry
+    /// is only fetched if a cw20 isn't allow-listed.
+    pub fn with_registry_cw20s(
+        mut self,
+        chain_registry: impl Into<LazyChainRegistry>,
+        chain_name: impl Into<String>,
+    ) -> Self {
+        self.chain_registry = Some((chain_registry.into(), chain_name.into()));
+        self
+    }
+
+    /// Whether the cw20 is allow-listed.
+    pub fn is_verified(&self, contract_addr: &Addr) -> bool {
+        self.verified.contains(contract_addr.as_str())
+    }
+
+    /// Split the assets into the ones that can be registered and the unverified cw20s.
+    pub async fn partition(
+        &self,
+        assets: Vec<(String, AssetInfo)>,
+    ) -> Result<(Vec<(String, AssetInfo)>, Vec<(String, AssetInfo)>), ScraperError> {
+        let trusted = |name: &str, info: &AssetInfo| match info {
+            AssetInfo::Cw20(contract_addr) => {
+                name.contains(LP_TOKEN_SEPARATOR) || self.is_verified(contract_addr)
+            }
+            _ => true,
+        };
+        let registry_cw20s = match &self.chain_registry {
+            Some((chain_registry, chain_name))
+                if assets.iter().any(|(name, info)| !trusted(name, info)) =>
+            {
+                registry_cw20s(chain_registry.get().await?, chain_name)
+            }
+            _ => BTreeSet::new(),
+        };
+        Ok(assets.into_iter().partition(|(name, info)| {
+            trusted(name, info)
+                || matches!(info, AssetInfo::Cw20(addr) if registry_cw20s.contains(addr.as_str()))
+        }))
+    }
+}
+
+/// Addresses of the cw20s of the asset list of `chain_name`.
+fn registry_cw20s<'a>(chain_registry: &'a ChainRegistry, chain_name: &str) -> BTreeSet<&'a str> {
+    chain_registry
+        .get_asset_lists()
+        .iter()
+        .filter(|asset_list| asset_list.chain_name == chain_name)
+        .flat_map(|asset_list| &asset_list.assets)
+        .filter_map(|asset| asset.base.strip_prefix(REGISTRY_CW20_PREFIX))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{registry_from_lists, AssetListBuilder};
+
+    fn allow_list(addresses: &[&str]) -> Cw20Verifier {
+        Cw20Verifier {
+            verified: addresses.iter().map(|addr| addr.to_string()).collect(),
+            chain_registry: None,
+        }
+    }
+
+    fn cw20(name: &str, addr: &str) -> (String, AssetInfo) {
+        (name.to_string(), AssetInfo::cw20(Addr::unchecked(addr)))
+    }
+
+    #[tokio::test]
+    async fn allow_listed_cw20s_and_natives_do_not_fetch_the_registry() {
+        let chain_registry = LazyChainRegistry::new();
+        let verifier =
+            allow_list(&["terra1astro"]).with_registry_cw20s(chain_registry.clone(), "terra2");
+        let assets = vec![
+            cw20("terra2>astro", "terra1astro"),
+            ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+            cw20(
+                &format!("terra2>astro{}terra2>luna", LP_TOKEN_SEPARATOR),
+                "terra1lp",
+            ),
+        ];
+
+        let (verified, unverified) = verifier.partition(assets.clone()).await.unwrap();
+        assert_eq!(verified, assets);
+        assert!(unverified.is_empty());
+        assert!(!chain_registry.is_initialized());
+    }
+
+    #[tokio::test]
+    async fn other_cw20s_are_verified_by_the_asset_list_of_the_chain() {
+        let chain_registry = registry_from_lists(vec![
+            AssetListBuilder::chain("terra2")
+                .asset("ROAR", "cw20:terra1roar", 6)
+                .build(),
+            AssetListBuilder::chain("juno")
+                .asset("FAKE", "cw20:terra1fake", 6)
+                .build(),
+        ]);
+        let verifier = allow_list(&[]).with_registry_cw20s(chain_registry, "terra2");
+
+        let (verified, unverified) = verifier
+            .partition(vec![
+                cw20("terra2>roar", "terra1roar"),
+                cw20("terra2>fake", "terra1fake"),
+                cw20("terra2>usdc", "terra1usdc"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(verified, [cw20("terra2>roar", "terra1roar")]);
+        assert_eq!(
+            unverified,
+            [
+                cw20("terra2>fake", "terra1fake"),
+                cw20("terra2>usdc", "terra1usdc")
+            ]
+        );
+    }
+}
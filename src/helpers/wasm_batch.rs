@@ -0,0 +1,67 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use cosmwasm_std::Addr;
+use cw_orch::queriers::CosmWasm;
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+
+use super::retry::retry;
+
+/// Pipelines wasm smart queries over the gRPC channel of a [`CosmWasm`] querier.
+///
+/// Up to `max_in_flight` queries are sent before the first response is awaited. They all share the
+/// one HTTP/2 connection of the channel, so no special node support is needed.
+pub struct WasmQueryBatcher<'a> {
+    querier: &'a CosmWasm,
+    max_in_flight: usize,
+}
+
+impl<'a> WasmQueryBatcher<'a> {
+    pub fn new(querier: &'a CosmWasm, max_in_flight: usize) -> Self {
+        Self {
+            querier,
+            max_in_flight: max_in_flight.max(1),
+        }
+    }
+
+    /// Send a smart query to every contract and return the raw responses keyed by `key`.
+    ///
+    /// A failed query only fails its own entry.
+    pub async fn query_all<K: Ord, Q: Serialize>(
+        &self,
+        queries: impl IntoIterator<Item = (K, Addr, Q)>,
+    ) -> BTreeMap<K, anyhow::Result<Vec<u8>>> {
+        stream::iter(queries)
+            .map(|(key, contract, msg)| async move { (key, self.query(&contract, &msg).await) })
+            .buffer_unordered(self.max_in_flight)
+            .collect()
+            .await
+    }
+
+    /// Send the same smart query to many contracts, responses are keyed by contract address.
+    pub async fn query_each<Q: Serialize>(
+        &self,
+        contracts: impl IntoIterator<Item = Addr>,
+        msg: &Q,
+    ) -> BTreeMap<Addr, anyhow::Result<Vec<u8>>> {
+        self.query_all(
+            contracts
+                .into_iter()
+                .map(|contract| (contract.clone(), contract, msg)),
+        )
+        .await
+    }
+
+    /// Send a single smart query, retried when the node can't be reached.
+    pub async fn query<Q: Serialize>(&self, contract: &Addr, msg: &Q) -> anyhow::Result<Vec<u8>> {
+        let query = serde_json::to_vec(msg).context("serializing wasm query")?;
+        retry(contract, || async {
+            self.querier
+                .contract_state(contract.to_string(), query.clone())
+                .await
+                .map_err(Into::into)
+        })
+        .await
+    }
+}
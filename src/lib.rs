@@ -1,4 +1,84 @@
+//! Scrapes the assets, pools and contracts of the dexes of a chain into Abstract Name Service
+//! (ANS) entries.
+//!
+//! The binaries are thin wrappers around the library, which can be called from any Tokio
+//! runtime:
+//!
+//! ```no_run
+//! use ans_scraper_rs::{scrape, write_scrape, Dex, ScrapeOptions, ScraperConfig};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let config = ScraperConfig::default_for("phoenix-1");
+//! let options = ScrapeOptions {
+//!     dexes: vec![Dex::Astroport],
+//!     ..Default::default()
+//! };
+//! let output = scrape("phoenix-1", &config, &options).await?;
+//! println!("{} assets, {} pools", output.assets.len(), output.pools.len());
+//! write_scrape(&config.out_dir, "phoenix-1", &output)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Custom sets of scrapers run through a [`ScrapePipeline`], every scraper implements
+//! [`DexScraper`].
+
+pub mod ans_version;
+pub mod cassette;
+pub mod checkpoint;
+pub mod config;
 pub mod dexes;
+pub mod diff;
+pub mod error;
+pub mod export;
+pub mod fixtures;
 mod helpers;
+pub mod logging;
+pub mod output;
+pub mod pipeline;
+pub mod registrar;
+pub mod report;
+pub mod scrape;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod traits;
-pub use helpers::chain_registry::ChainRegistry;
+pub mod verification;
+pub use config::{ScraperConfig, DEFAULT_OUT_DIR};
+pub use dexes::astroport::AstroportScraper;
+pub use dexes::kujira::KujiraScraper;
+pub use dexes::osmosis::OsmosisScraper;
+pub use dexes::terraswap::TerraswapScraper;
+pub use dexes::whitewhale::WhiteWhaleScraper;
+pub use dexes::wyndex::WyndexScraper;
+pub use error::ScraperError;
+pub use helpers::assets::{consolidate_assets, AssetConflict};
+pub use helpers::cache::{default_cache_dir, CACHE_DIR_ENV, DEFAULT_CACHE_DIR};
+pub use helpers::chain_registry::{
+    ChainRegistry, ChainRegistryOptions, LazyChainRegistry, ResolveError, ResolvedAsset,
+    DEFAULT_ASSET_LIST_FETCH_CONCURRENCY,
+};
+pub use helpers::channels::{ChannelConflict, ChannelResolver, ICS20_PROTOCOL};
+pub use helpers::clients::ChainClients;
+pub use helpers::cw20::{Cw20Entry, Cw20EntryError, DEFAULT_QUERY_CONCURRENCY};
+pub use helpers::decimals::{AssetMetadata, DecimalsMismatch, DecimalsResolver};
+pub use helpers::denoms::DenomTrace;
+pub use helpers::deployments::{Deployment, DeploymentError, DeploymentRegistry};
+pub use helpers::http::{fetch_stats, set_offline, FetchStats};
+pub use helpers::liquidity::LiquidityFilter;
+pub use helpers::networks::{
+    ans_prefix, grpc_url, parse_network, require_signer, with_grpc_url, NetworkError, GRPC_URL_ENV,
+    NETWORKS,
+};
+pub use helpers::overrides::{Overrides, UnknownOverrides};
+pub use helpers::query_cache::{MissingCacheEntries, QueryCache};
+pub use helpers::resolution_cache::ResolutionCache;
+pub use helpers::resolve::ResolvedAssets;
+pub use helpers::retry::{RetryPolicy, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_BASE_DELAY};
+pub use helpers::verify::{Cw20Verifier, DEFAULT_CONFIG_DIR};
+pub use helpers::wasm_batch::WasmQueryBatcher;
+pub use pipeline::{ConflictPolicy, ScrapeOutput, ScrapePipeline, ABSTRACT_VERSION};
+pub use scrape::{
+    scrape, scrape_network, scrape_network_paged, write_paged_scrape, write_scrape, Dex,
+    ScrapeOptions,
+};
+pub use traits::dex::{AnsAsset, AssetSource, DexId, DexScraper};
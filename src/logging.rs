@@ -0,0 +1,101 @@
+//! Log setup of the binaries, as text or as one JSON object per line for CI, and the structured
+//! events the scraper logs along its messages.
+
+use std::io::Write;
+
+use log::{Level, LevelFilter, Record};
+use serde_json::{json, Map, Value};
+
+/// Target of the structured events, below the crate so `RUST_LOG=ans_scraper_rs=..` covers them.
+pub const EVENT_TARGET: &str = "ans_scraper_rs::event";
+
+/// How log lines are written to stderr.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// `[timestamp level target] message key=value ..`
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields of the events as keys
+    Json,
+}
+
+/// Log `message` with structured `fields` (a JSON object), e.g. the chain id, dex, phase and
+/// counts of a step of the scrape.
+///
+/// The fields are keys of the line with [`LogFormat::Json`], `key=value` pairs after the message
+/// with [`LogFormat::Text`].
+pub fn log_event(level: Level, message: &str, fields: Value) {
+    log::log!(
+        target: EVENT_TARGET,
+        level,
+        "{}",
+        json!({ "message": message, "fields": fields })
+    );
+}
+
+/// Set up the logger of a binary.
+///
+/// `RUST_LOG` is used when no `level` is set, `quiet` only keeps the warnings and errors.
+pub fn init_logger(level: Option<LevelFilter>, quiet: bool, format: LogFormat) {
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = level {
+        logger.filter_level(level);
+    }
+    if quiet {
+        logger.filter_level(LevelFilter::Warn);
+    }
+    logger.format(move |buf, record| {
+        let (message, fields) = split_event(record);
+        match format {
+            LogFormat::Text => {
+                write!(
+                    buf,
+                    "[{} {} {}] {}",
+                    buf.timestamp(),
+                    record.level(),
+                    record.target(),
+                    message
+                )?;
+                for (key, value) in fields {
+                    match value {
+                        Value::String(value) => write!(buf, " {}={}", key, value)?,
+                        value => write!(buf, " {}={}", key, value)?,
+                    }
+                }
+                writeln!(buf)
+            }
+            LogFormat::Json => {
+                let mut line = Map::new();
+                line.insert("timestamp".into(), buf.timestamp().to_string().into());
+                line.insert("level".into(), record.level().as_str().into());
+                line.insert("target".into(), record.target().into());
+                line.insert("message".into(), message.into());
+                line.extend(fields);
+                writeln!(buf, "{}", Value::Object(line))
+            }
+        }
+    });
+    logger.init();
+}
+
+/// Message and fields of a record, the fields of a plain log message are empty.
+fn split_event(record: &Record) -> (String, Map<String, Value>) {
+    let text = record.args().to_string();
+    if record.target() != EVENT_TARGET {
+        return (text, Map::new());
+    }
+    match serde_json::from_str::<Value>(&text) {
+        Ok(Value::Object(mut event)) => {
+            let message = match event.remove("message") {
+                Some(Value::String(message)) => message,
+                _ => String::new(),
+            };
+            let fields = match event.remove("fields") {
+                Some(Value::Object(fields)) => fields,
+                _ => Map::new(),
+            };
+            (message, fields)
+        }
+        _ => (text, Map::new()),
+    }
+}
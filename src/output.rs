@@ -0,0 +1,870 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{
+    DexAssetPairing, PoolMetadata, UncheckedChannelEntry, UncheckedContractEntry,
+};
+use anyhow::Context;
+use cw_asset::AssetInfo;
+use log::Level;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::helpers::cache::{write_atomic, write_json_atomic};
+use crate::helpers::decimals::AssetMetadata;
+use crate::logging::log_event;
+use crate::pipeline::{ScrapeMeta, ScrapeOutput};
+use crate::AssetConflict;
+
+pub const ASSETS_FILE: &str = "assets.json";
+pub const POOLS_FILE: &str = "pools.json";
+pub const CONTRACTS_FILE: &str = "contracts.json";
+pub const CHANNELS_FILE: &str = "channels.json";
+pub const SUMMARY_FILE: &str = "summary.json";
+/// The run summary as markdown, at the root of the output directory.
+pub const SUMMARY_MARKDOWN_FILE: &str = "summary.md";
+pub const UNVERIFIED_ASSETS_FILE: &str = "unverified_assets.json";
+pub const PAIRINGS_FILE: &str = "pairings.json";
+pub const ASSET_METADATA_FILE: &str = "asset_metadata.json";
+
+/// Entry count above which output files are streamed to disk instead of serialized in memory.
+pub const STREAMING_THRESHOLD: usize = 10_000;
+
+/// Counts and leftovers of a scrape, written next to the output files.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScrapeSummary {
+    pub chain_id: String,
+    pub meta: ScrapeMeta,
+    pub assets: usize,
+    pub pools: usize,
+    /// Number of asset pairings of the pools, not counted by paged scrapes
+    pub pairings: usize,
+    pub not_found_assets: Vec<AssetInfo>,
+    pub skipped_pools: Vec<UncheckedPoolAddress>,
+    pub asset_conflicts: Vec<AssetConflict>,
+    /// Number of cw20s left out because they aren't verified
+    pub unverified_assets: usize,
+}
+
+impl ScrapeSummary {
+    /// Summary of a pipeline run, with the leftovers of all its scrapers.
+    pub fn new(chain_id: impl Into<String>, output: &ScrapeOutput) -> Self {
+        Self {
+            chain_id: chain_id.into(),
+            meta: output.meta.clone(),
+            assets: output.assets.len(),
+            pools: output.pools.len(),
+            pairings: output.pairings.len(),
+            not_found_assets: output
+                .reports
+                .iter()
+                .flat_map(|report| &report.unresolved_assets)
+                .map(|asset| asset.info.clone())
+                .collect(),
+            skipped_pools: output
+                .reports
+                .iter()
+                .flat_map(|report| &report.skipped_pools)
+                .map(|pool| pool.pool.clone())
+                .collect(),
+            asset_conflicts: output.asset_conflicts.clone(),
+            unverified_assets: output.unverified_assets.len(),
+        }
+    }
+
+    pub fn log(&self) {
+        log_event(
+            Level::Info,
+            "scrape summary",
+            json!({
+                "chain_id": self.chain_id,
+                "block_height": self.meta.block_height,
+                "assets": self.assets,
+                "pools": self.pools,
+                "pairings": self.pairings,
+                "not_found_assets": self.not_found_assets.len(),
+                "skipped_pools": self.skipped_pools.len(),
+                "asset_conflicts": self.asset_conflicts.len(),
+                "unverified_assets": self.unverified_assets,
+            }),
+        );
+    }
+
+    /// A row of the markdown table of [`RunSummary::to_markdown`].
+    fn markdown_row(&self) -> String {
+        format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            self.chain_id,
+            self.meta
+                .block_height
+                .map_or_else(|| "-".to_string(), |height| height.to_string()),
+            self.assets,
+            self.pools,
+            self.not_found_assets.len(),
+            self.skipped_pools.len(),
+            self.asset_conflicts.len(),
+            self.unverified_assets
+        )
+    }
+}
+
+/// A network of a multi-network run that failed to scrape.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedNetwork {
+    pub network_id: String,
+    pub error: String,
+}
+
+/// Combined summary of a run over several networks, written to `<out_dir>/summary.json`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+    pub networks: Vec<ScrapeSummary>,
+    pub failed_networks: Vec<FailedNetwork>,
+}
+
+impl RunSummary {
+    pub fn log(&self) {
+        self.networks.iter().for_each(ScrapeSummary::log);
+        for failed in &self.failed_networks {
+            log_event(
+                Level::Error,
+                "network failed",
+                json!({ "chain_id": failed.network_id, "error": failed.error }),
+            );
+        }
+    }
+
+    /// The counts of every network and the failed networks as markdown, e.g. for a PR comment.
+    pub fn to_markdown(&self) -> String {
+        let columns = [
+            "chain",
+            "height",
+            "assets",
+            "pools",
+            "assets not found",
+            "skipped pools",
+            "asset conflicts",
+            "unverified assets",
+        ];
+        let mut lines = vec![
+            format!("| {} |", columns.join(" | ")),
+            format!("|{}", "---|".repeat(columns.len())),
+        ];
+        lines.extend(self.networks.iter().map(ScrapeSummary::markdown_row));
+        if !self.failed_networks.is_empty() {
+            lines.push(String::new());
+            lines.push("Failed networks:".to_string());
+            lines.push(String::new());
+            lines.extend(
+                self.failed_networks
+                    .iter()
+                    .map(|failed| format!("- {}: {}", failed.network_id, failed.error)),
+            );
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Write the combined summary to `<out_dir>/summary.json`, and as markdown to
+    /// `<out_dir>/summary.md`.
+    pub fn write(&self, out_dir: &Path) -> anyhow::Result<PathBuf> {
+        std::fs::create_dir_all(out_dir)?;
+        let path = out_dir.join(SUMMARY_FILE);
+        write_json_atomic(&path, self)?;
+        write_atomic(
+            out_dir.join(SUMMARY_MARKDOWN_FILE),
+            self.to_markdown().as_bytes(),
+        )?;
+        Ok(path)
+    }
+}
+
+/// Write the scraped assets, pools and the summary to `<out_dir>/<chain_id>/`.
+///
+/// The asset and pool files are `{"meta": .., "entries": [..]}` objects, with the
+/// [`ScrapeMeta`] of the summary.
+///
+/// Returns the directory the files were written to.
+pub fn write_output(
+    out_dir: &Path,
+    assets: &[(String, AssetInfo)],
+    pools: &[(UncheckedPoolAddress, PoolMetadata)],
+    summary: &ScrapeSummary,
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&summary.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+
+    write_entries(chain_dir.join(ASSETS_FILE), &summary.meta, assets)?;
+    write_entries(chain_dir.join(POOLS_FILE), &summary.meta, pools)?;
+    write_json_atomic(chain_dir.join(SUMMARY_FILE), summary)?;
+
+    Ok(chain_dir)
+}
+
+/// Write the unverified cw20s of a scrape to `<out_dir>/<chain_id>/unverified_assets.json`, for
+/// a human to review. They are never registered.
+pub fn write_unverified_assets(
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    assets: &[(String, AssetInfo)],
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&meta.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+    let path = chain_dir.join(UNVERIFIED_ASSETS_FILE);
+    write_entries(path.clone(), meta, assets)?;
+    Ok(path)
+}
+
+/// Write the staking and protocol contracts of a scrape to `<out_dir>/<chain_id>/contracts.json`,
+/// as `[{"protocol": .., "contract": ..}, address]` entries.
+pub fn write_contracts(
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    contracts: &[(UncheckedContractEntry, String)],
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&meta.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+    let path = chain_dir.join(CONTRACTS_FILE);
+    write_entries(path.clone(), meta, contracts)?;
+    Ok(path)
+}
+
+/// Write the transfer channels of the IBC assets of a scrape to
+/// `<out_dir>/<chain_id>/channels.json`, as `[{"connected_chain": .., "protocol": ..}, channel]`
+/// entries.
+pub fn write_channels(
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    channels: &[(UncheckedChannelEntry, String)],
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&meta.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+    let path = chain_dir.join(CHANNELS_FILE);
+    write_entries(path.clone(), meta, channels)?;
+    Ok(path)
+}
+
+/// Write the asset pairings of the pools of a scrape to `<out_dir>/<chain_id>/pairings.json`,
+/// as `[[asset, asset, dex], [pool, ..]]` entries.
+pub fn write_pairings(
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    pairings: &[(DexAssetPairing, Vec<UncheckedPoolAddress>)],
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&meta.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+    let path = chain_dir.join(PAIRINGS_FILE);
+    write_entries(path.clone(), meta, pairings)?;
+    Ok(path)
+}
+
+/// Write the assets of a scrape with their decimals to `<out_dir>/<chain_id>/asset_metadata.json`,
+/// in the order of `assets.json`.
+pub fn write_asset_metadata(
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    asset_metadata: &[AssetMetadata],
+) -> anyhow::Result<PathBuf> {
+    let chain_dir = out_dir.join(&meta.chain_id);
+    std::fs::create_dir_all(&chain_dir)?;
+    let path = chain_dir.join(ASSET_METADATA_FILE);
+    write_entries(path.clone(), meta, asset_metadata)?;
+    Ok(path)
+}
+
+/// Layout of the entry files, serialized in field order like [`JsonArrayWriter`] writes them.
+#[derive(Serialize)]
+struct MetaEntries<'a, T> {
+    meta: &'a ScrapeMeta,
+    entries: &'a [T],
+}
+
+/// Write the entries as the `entries` array of a `{"meta": .., "entries": [..]}` object.
+///
+/// Streamed like [`write_json_array`], the bytes are the same as serializing the object at once.
+pub fn write_entries<T: Serialize>(
+    path: PathBuf,
+    meta: &ScrapeMeta,
+    entries: &[T],
+) -> anyhow::Result<()> {
+    if entries.len() <= STREAMING_THRESHOLD {
+        return write_json_atomic(path, &MetaEntries { meta, entries });
+    }
+
+    let mut writer = JsonArrayWriter::create_with_meta(path, meta)?;
+    entries.iter().try_for_each(|entry| writer.append(entry))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write the entries as a JSON array.
+///
+/// Large arrays (more than [`STREAMING_THRESHOLD`] entries) are written element by element so the
+/// serialized file never has to fit in memory. Both ways produce the same bytes, so the entries
+/// must already be in their canonical order.
+pub fn write_json_array<T: Serialize>(path: PathBuf, entries: &[T]) -> anyhow::Result<()> {
+    if entries.len() <= STREAMING_THRESHOLD {
+        return write_json_atomic(path, entries);
+    }
+
+    let mut writer = JsonArrayWriter::create(path)?;
+    entries.iter().try_for_each(|entry| writer.append(entry))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes a JSON array one element at a time so the whole array never has to be in memory.
+///
+/// The output is byte-identical to serializing the complete array with `serde_json::to_vec`.
+/// Elements go to a temp file that is only renamed to `path` by [`JsonArrayWriter::finish`].
+pub struct JsonArrayWriter {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    writer: BufWriter<File>,
+    len: usize,
+    /// Whether the array is the `entries` of an object that has to be closed as well
+    in_object: bool,
+}
+
+impl JsonArrayWriter {
+    pub fn create(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        Self::open(path.into(), None)
+    }
+
+    /// Write the array as the `entries` of a `{"meta": .., "entries": [..]}` object.
+    pub fn create_with_meta(path: impl Into<PathBuf>, meta: &ScrapeMeta) -> anyhow::Result<Self> {
+        Self::open(path.into(), Some(meta))
+    }
+
+    fn open(path: PathBuf, meta: Option<&ScrapeMeta>) -> anyhow::Result<Self> {
+        let tmp_path = path.with_extension("json.partial");
+        let mut writer = BufWriter::new(
+            File::create(&tmp_path)
+                .with_context(|| format!("creating output file {}", tmp_path.display()))?,
+        );
+        if let Some(meta) = meta {
+            writer.write_all(b"{\"meta\":")?;
+            serde_json::to_writer(&mut writer, meta)?;
+            writer.write_all(b",\"entries\":")?;
+        }
+        writer.write_all(b"[")?;
+        Ok(Self {
+            path,
+            tmp_path,
+            writer,
+            len: 0,
+            in_object: meta.is_some(),
+        })
+    }
+
+    pub fn append<T: Serialize>(&mut self, value: &T) -> anyhow::Result<()> {
+        if self.len > 0 {
+            self.writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.writer, value)
+            .with_context(|| format!("writing to {}", self.tmp_path.display()))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Close the array and move the file in place. Returns the number of elements written.
+    pub fn finish(mut self) -> anyhow::Result<usize> {
+        self.writer.write_all(b"]")?;
+        if self.in_object {
+            self.writer.write_all(b"}")?;
+        }
+        let file = self
+            .writer
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("flushing {}: {}", self.tmp_path.display(), e))?;
+        file.sync_all()?;
+        std::fs::rename(&self.tmp_path, &self.path)
+            .with_context(|| format!("moving output file to {}", self.path.display()))?;
+        Ok(self.len)
+    }
+}
+
+/// Output files of a scrape that is written incrementally, page by page.
+pub struct StreamingOutput {
+    chain_dir: PathBuf,
+    assets: JsonArrayWriter,
+    pools: JsonArrayWriter,
+}
+
+impl StreamingOutput {
+    pub fn create(out_dir: &Path, meta: &ScrapeMeta) -> anyhow::Result<Self> {
+        let chain_dir = out_dir.join(&meta.chain_id);
+        std::fs::create_dir_all(&chain_dir)?;
+        Ok(Self {
+            assets: JsonArrayWriter::create_with_meta(chain_dir.join(ASSETS_FILE), meta)?,
+            pools: JsonArrayWriter::create_with_meta(chain_dir.join(POOLS_FILE), meta)?,
+            chain_dir,
+        })
+    }
+
+    pub fn append_assets(&mut self, assets: &[(String, AssetInfo)]) -> anyhow::Result<()> {
+        assets
+            .iter()
+            .try_for_each(|asset| self.assets.append(asset))
+    }
+
+    pub fn append_pools(
+        &mut self,
+        pools: &[(UncheckedPoolAddress, PoolMetadata)],
+    ) -> anyhow::Result<()> {
+        pools.iter().try_for_each(|pool| self.pools.append(pool))
+    }
+
+    /// Finish the asset and pool files and write the summary, filling in the entry counts.
+    pub fn finish(self, mut summary: ScrapeSummary) -> anyhow::Result<(PathBuf, ScrapeSummary)> {
+        summary.assets = self.assets.finish()?;
+        summary.pools = self.pools.finish()?;
+        write_json_atomic(self.chain_dir.join(SUMMARY_FILE), &summary)?;
+        Ok((self.chain_dir, summary))
+    }
+}
+
+/// Serializes the read-merge-write of the ANS files between networks scraped in parallel.
+static ANS_FILES_LOCK: Mutex<()> = Mutex::new(());
+
+/// Merge the output of a scrape into the ANS files of abstract's deploy tooling in `out_dir`.
+///
+/// Every file is an object of chain-id -> entries:
+/// - `assets.json`: `[name, {"native"|"cw20": ..}]`
+/// - `contracts.json`: `[[protocol, contract], address]`
+/// - `channels.json`: `[[connected chain, protocol], channel]`
+/// - `pools.json`: `[["contract"|"id", address], metadata]`
+///
+/// Entries of the chain with the same key are replaced, all the other entries (other chains,
+/// other dexes) are kept.
+pub fn write_ans_files(
+    chain_id: &str,
+    out_dir: &Path,
+    output: &ScrapeOutput,
+) -> anyhow::Result<()> {
+    let assets = output
+        .assets
+        .iter()
+        .map(|(name, info)| json!([name, info]))
+        .collect();
+    merge_ans_file(&out_dir.join(ASSETS_FILE), chain_id, assets)?;
+
+    let contracts = output
+        .contracts
+        .iter()
+        .map(|(entry, address)| json!([[entry.protocol, entry.contract], address]))
+        .collect();
+    merge_ans_file(&out_dir.join(CONTRACTS_FILE), chain_id, contracts)?;
+
+    let channels = output
+        .channels
+        .iter()
+        .map(|(entry, channel)| json!([[entry.connected_chain, entry.protocol], channel]))
+        .collect();
+    merge_ans_file(&out_dir.join(CHANNELS_FILE), chain_id, channels)?;
+
+    let pools = output
+        .pools
+        .iter()
+        .map(|(pool_address, metadata)| {
+            let pool_address = match pool_address {
+                UncheckedPoolAddress::Contract(addr) => json!(["contract", addr]),
+                UncheckedPoolAddress::Id(id) => json!(["id", id]),
+            };
+            json!([pool_address, metadata])
+        })
+        .collect();
+    merge_ans_file(&out_dir.join(POOLS_FILE), chain_id, pools)?;
+
+    Ok(())
+}
+
+/// Merge `entries` into the chain's entries of the ANS file, keyed by their first element.
+fn merge_ans_file(path: &Path, chain_id: &str, entries: Vec<Value>) -> anyhow::Result<()> {
+    let _guard = ANS_FILES_LOCK.lock().unwrap();
+    let mut file: Map<String, Value> = if path.exists() {
+        let json =
+            std::fs::read(path).with_context(|| format!("reading ANS file {}", path.display()))?;
+        serde_json::from_slice(&json)
+            .with_context(|| format!("parsing ANS file {}", path.display()))?
+    } else {
+        Map::new()
+    };
+
+    let chain_entries = file
+        .entry(chain_id)
+        .or_insert_with(|| Value::Array(vec![]))
+        .as_array_mut()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} entries in {} are not an array",
+                chain_id,
+                path.display()
+            )
+        })?;
+    for entry in entries {
+        match chain_entries
+            .iter_mut()
+            .find(|existing| existing[0] == entry[0])
+        {
+            Some(existing) => *existing = entry,
+            None => chain_entries.push(entry),
+        }
+    }
+
+    write_json_atomic(path, &file)
+}
+
+/// Golden-file tests of the output formats. The files are in `tests/golden/`, after an intended
+/// change of a format they are rewritten with `just update-golden` and the diff is
+/// reviewed like any other change.
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::pool_id::PoolAddressBase;
+    use abstract_core::objects::{AssetEntry, PoolType};
+    use cosmwasm_std::Addr;
+
+    use super::*;
+    use crate::export::{ExportFormat, ScrapeExport};
+    use crate::report::{DexReport, ScrapeReport, SkippedPool, UnresolvedAsset, UnresolvedReason};
+
+    fn golden_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+    }
+
+    /// Compare the file at `path` to `tests/golden/<name>`, or replace the golden file with it
+    /// when `UPDATE_GOLDEN` is set.
+    fn assert_golden(name: &str, path: &Path) {
+        let actual = std::fs::read(path).unwrap();
+        let golden = golden_dir().join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(golden.parent().unwrap()).unwrap();
+            std::fs::write(&golden, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read(&golden)
+            .unwrap_or_else(|e| panic!("reading {}: {}", golden.display(), e));
+        assert_eq!(
+            String::from_utf8_lossy(&actual),
+            String::from_utf8_lossy(&expected),
+            "{} differs from its golden file, rerun with UPDATE_GOLDEN=1 if the change is intended",
+            name
+        );
+    }
+
+    fn temp_out_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "ans-scraper-golden-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn pool(dex: &str, pool_type: PoolType) -> PoolMetadata {
+        PoolMetadata {
+            dex: dex.to_string(),
+            pool_type,
+            assets: vec![
+                AssetEntry::new("terra2>astro"),
+                AssetEntry::new("terra2>luna"),
+            ],
+        }
+    }
+
+    /// A scrape of every kind of entry, with a fixed meta so the files don't change between
+    /// versions of the scraper.
+    fn golden_output() -> ScrapeOutput {
+        let pair = PoolAddressBase::Contract("terra1pair".to_string());
+        let stable_pool = PoolAddressBase::Id(7);
+        ScrapeOutput {
+            meta: ScrapeMeta {
+                chain_id: "pisco-1".to_string(),
+                block_height: Some(4242),
+                scraper_version: "golden".to_string(),
+            },
+            assets: vec![
+                (
+                    "terra2>astro".to_string(),
+                    AssetInfo::Cw20(Addr::unchecked("terra1astro")),
+                ),
+                (
+                    "terra2>luna".to_string(),
+                    AssetInfo::Native("uluna".to_string()),
+                ),
+            ],
+            contracts: vec![(
+                UncheckedContractEntry {
+                    protocol: "astroport".to_string(),
+                    contract: "staking/astroport/terra2>astro,terra2>luna".to_string(),
+                },
+                "terra1generator".to_string(),
+            )],
+            pools: vec![
+                (pair.clone(), pool("astroport", PoolType::ConstantProduct)),
+                (stable_pool.clone(), pool("astroport", PoolType::Stable)),
+            ],
+            pairings: vec![(
+                DexAssetPairing::new(
+                    AssetEntry::new("terra2>astro"),
+                    AssetEntry::new("terra2>luna"),
+                    "astroport",
+                ),
+                vec![pair, stable_pool],
+            )],
+            unverified_assets: vec![(
+                "terra2>scam".to_string(),
+                AssetInfo::Cw20(Addr::unchecked("terra1scam")),
+            )],
+            asset_metadata: vec![
+                AssetMetadata {
+                    name: "terra2>astro".to_string(),
+                    info: AssetInfo::Cw20(Addr::unchecked("terra1astro")),
+                    decimals: Some(6),
+                },
+                AssetMetadata {
+                    name: "terra2>luna".to_string(),
+                    info: AssetInfo::Native("uluna".to_string()),
+                    decimals: None,
+                },
+            ],
+            channels: vec![(
+                UncheckedChannelEntry {
+                    connected_chain: "osmosis".to_string(),
+                    protocol: "ics20".to_string(),
+                },
+                "channel-1".to_string(),
+            )],
+            reports: vec![DexReport::new(
+                "astroport",
+                vec![UnresolvedAsset::new(
+                    AssetInfo::Native("ibc/ABC".to_string()),
+                    UnresolvedReason::DenomTrace("not found".to_string()),
+                )],
+                vec![SkippedPool::unsupported_pair_type(
+                    PoolAddressBase::Contract("terra1xyk".to_string()),
+                    "custom-concentrated",
+                )],
+            )],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn golden_entry_files() {
+        let out_dir = temp_out_dir("entries");
+        let output = golden_output();
+        let meta = &output.meta;
+        let summary = ScrapeSummary::new(&meta.chain_id, &output);
+
+        let chain_dir = write_output(&out_dir, &output.assets, &output.pools, &summary).unwrap();
+        write_contracts(&out_dir, meta, &output.contracts).unwrap();
+        write_pairings(&out_dir, meta, &output.pairings).unwrap();
+        write_channels(&out_dir, meta, &output.channels).unwrap();
+        write_unverified_assets(&out_dir, meta, &output.unverified_assets).unwrap();
+        write_asset_metadata(&out_dir, meta, &output.asset_metadata).unwrap();
+
+        for file in [
+            ASSETS_FILE,
+            POOLS_FILE,
+            CONTRACTS_FILE,
+            PAIRINGS_FILE,
+            CHANNELS_FILE,
+            UNVERIFIED_ASSETS_FILE,
+            ASSET_METADATA_FILE,
+            SUMMARY_FILE,
+        ] {
+            assert_golden(&format!("entries/{}", file), &chain_dir.join(file));
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn golden_streamed_entry_files_match_the_serialized_ones() {
+        let out_dir = temp_out_dir("streamed");
+        let output = golden_output();
+        let summary = ScrapeSummary::new(&output.meta.chain_id, &output);
+
+        let mut streaming = StreamingOutput::create(&out_dir, &output.meta).unwrap();
+        for (asset, pool) in output.assets.iter().zip(&output.pools) {
+            streaming
+                .append_assets(std::slice::from_ref(asset))
+                .unwrap();
+            streaming.append_pools(std::slice::from_ref(pool)).unwrap();
+        }
+        let (chain_dir, _) = streaming.finish(summary).unwrap();
+
+        for file in [ASSETS_FILE, POOLS_FILE, SUMMARY_FILE] {
+            assert_golden(&format!("entries/{}", file), &chain_dir.join(file));
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn large_entry_files_are_streamed_to_the_same_bytes() {
+        let out_dir = temp_out_dir("large");
+        let meta = golden_output().meta;
+        let entries: Vec<(String, AssetInfo)> = (0..200_000)
+            .map(|i| {
+                (
+                    format!("terra2>token{:06}", i),
+                    AssetInfo::Cw20(Addr::unchecked(format!("terra1token{:06}", i))),
+                )
+            })
+            .collect();
+        assert!(entries.len() > STREAMING_THRESHOLD);
+        std::fs::create_dir_all(&out_dir).unwrap();
+
+        let path = out_dir.join(ASSETS_FILE);
+        write_entries(path.clone(), &meta, &entries).unwrap();
+        let expected = serde_json::to_vec(&MetaEntries {
+            meta: &meta,
+            entries: &entries,
+        })
+        .unwrap();
+        assert!(std::fs::read(&path).unwrap() == expected);
+
+        let path = out_dir.join(UNVERIFIED_ASSETS_FILE);
+        write_json_array(path.clone(), &entries).unwrap();
+        assert!(std::fs::read(&path).unwrap() == serde_json::to_vec(&entries).unwrap());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn streamed_pages_are_counted_in_the_summary() {
+        let out_dir = temp_out_dir("pages");
+        let output = golden_output();
+        let summary = ScrapeSummary::new(&output.meta.chain_id, &ScrapeOutput::default());
+
+        let mut streaming = StreamingOutput::create(&out_dir, &output.meta).unwrap();
+        streaming.append_assets(&[]).unwrap();
+        streaming.append_assets(&output.assets).unwrap();
+        for pool in &output.pools {
+            streaming.append_pools(std::slice::from_ref(pool)).unwrap();
+            streaming.append_pools(&[]).unwrap();
+        }
+        let (chain_dir, summary) = streaming.finish(summary).unwrap();
+
+        assert_eq!((summary.assets, summary.pools), (2, 2));
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(chain_dir.join(SUMMARY_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            (written["assets"].as_u64(), written["pools"].as_u64()),
+            (Some(2), Some(2))
+        );
+        let pools: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(chain_dir.join(POOLS_FILE)).unwrap()).unwrap();
+        assert_eq!(pools["entries"].as_array().map(Vec::len), Some(2));
+        // no temp file of the writers is left behind
+        let mut files: Vec<_> = std::fs::read_dir(&chain_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, [ASSETS_FILE, POOLS_FILE, SUMMARY_FILE]);
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn golden_run_summary() {
+        let out_dir = temp_out_dir("summary");
+        let output = golden_output();
+        let summary = RunSummary {
+            networks: vec![ScrapeSummary::new(&output.meta.chain_id, &output)],
+            failed_networks: vec![FailedNetwork {
+                network_id: "phoenix-1".to_string(),
+                error: "node unreachable".to_string(),
+            }],
+        };
+
+        summary.write(&out_dir).unwrap();
+
+        assert_golden("run/summary.json", &out_dir.join(SUMMARY_FILE));
+        assert_golden("run/summary.md", &out_dir.join(SUMMARY_MARKDOWN_FILE));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn golden_report() {
+        let out_dir = temp_out_dir("report");
+        let output = golden_output();
+        let report = ScrapeReport::new(output.meta.clone(), output.reports.clone());
+
+        let path = report.write(&out_dir).unwrap();
+
+        assert_golden("report/report.json", &path);
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn golden_ans_files_keep_the_entries_of_other_chains() {
+        let out_dir = temp_out_dir("ans");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        std::fs::write(
+            out_dir.join(ASSETS_FILE),
+            r#"{"phoenix-1":[["terra2>luna",{"native":"uluna"}]]}"#,
+        )
+        .unwrap();
+
+        write_ans_files("pisco-1", &out_dir, &golden_output()).unwrap();
+
+        for file in [ASSETS_FILE, CONTRACTS_FILE, CHANNELS_FILE, POOLS_FILE] {
+            assert_golden(&format!("ans/{}", file), &out_dir.join(file));
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn ans_files_replace_the_entries_with_the_same_key() {
+        let out_dir = temp_out_dir("ans-merge");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let terraswap_pool = json!([
+            ["contract", "terra1other"],
+            { "dex": "terraswap", "pool_type": "ConstantProduct", "assets": ["terra2>luna", "terra2>usdc"] }
+        ]);
+        std::fs::write(
+            out_dir.join(POOLS_FILE),
+            serde_json::to_vec(&json!({
+                "pisco-1": [
+                    [["contract", "terra1pair"], { "dex": "astroport", "pool_type": "Stable", "assets": [] }],
+                    terraswap_pool,
+                ]
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        let output = golden_output();
+        write_ans_files("pisco-1", &out_dir, &output).unwrap();
+        // a second run of the same scrape changes nothing
+        write_ans_files("pisco-1", &out_dir, &output).unwrap();
+
+        let pools: Value =
+            serde_json::from_slice(&std::fs::read(out_dir.join(POOLS_FILE)).unwrap()).unwrap();
+        let pools = pools["pisco-1"].as_array().unwrap();
+        assert_eq!(pools.len(), 3);
+        assert_eq!(pools[0][0], json!(["contract", "terra1pair"]));
+        assert_eq!(pools[0][1]["pool_type"], "ConstantProduct");
+        assert_eq!(pools[1], terraswap_pool);
+        assert_eq!(pools[2][0], json!(["id", 7]));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn golden_csv_export() {
+        let out_dir = temp_out_dir("csv");
+
+        let paths = ScrapeExport::from_output(&golden_output())
+            .write(&out_dir, ExportFormat::Csv)
+            .unwrap();
+
+        for path in paths {
+            let file = path.file_name().unwrap().to_str().unwrap();
+            assert_golden(&format!("csv/{}", file), &path);
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
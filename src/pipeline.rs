@@ -0,0 +1,1155 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{
+    AssetEntry, DexAssetPairing, PoolMetadata, UncheckedChannelEntry, UncheckedContractEntry,
+};
+use cosmwasm_std::Addr;
+use cw_asset::{AssetInfo, AssetInfoUnchecked};
+use log::Level;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::checkpoint::{DexCheckpoint, ScrapeCheckpoint, ScrapePhase};
+use crate::config::ScraperConfig;
+use crate::helpers::channels::{ChannelConflict, ChannelResolver};
+use crate::helpers::decimals::{AssetMetadata, DecimalsMismatch, DecimalsResolver};
+use crate::helpers::names::{
+    sanitize_asset_entry, sanitize_name_segment, validate_asset_entry, validate_name, InvalidName,
+    LP_TOKEN_SEPARATOR,
+};
+use crate::helpers::retry::RetryPolicy;
+use crate::logging::log_event;
+use crate::report::{DexReport, OverridesReport, SkippedPool};
+use crate::traits::dex::{AnsAsset, DexScraper};
+use crate::{
+    consolidate_assets, AssetConflict, ChainClients, Cw20Verifier, Overrides, ResolutionCache,
+    ScraperError,
+};
+
+/// Version of the scraper, stamped into its outputs.
+pub const ABSTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Prefix of the ANS contract entries of staking contracts, `staking/{lp token}`.
+const STAKING_CONTRACT_PREFIX: &str = "staking";
+
+/// Asset names that more than one scraper (or pair) used for different assets.
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "{} asset names map to different assets: {}",
+    conflicts.len(),
+    describe_conflicts(conflicts)
+)]
+pub struct AssetConflictsError {
+    pub conflicts: Vec<AssetConflict>,
+}
+
+/// `name (info, info)` of every conflict, so the assets can be told apart by a human.
+fn describe_conflicts(conflicts: &[AssetConflict]) -> String {
+    conflicts
+        .iter()
+        .map(|c| {
+            let infos = c.infos.iter().map(ToString::to_string).collect::<Vec<_>>();
+            format!("{} ({})", c.name, infos.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What a pipeline does with asset names that map to different assets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fail the run with an [`AssetConflictsError`]
+    #[default]
+    Fail,
+    /// Drop the conflicting names and the pools that use them, they are reported in
+    /// [`ScrapeOutput::asset_conflicts`]
+    Drop,
+}
+
+/// Where and when a scrape was taken, written under the `meta` key of its output files.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeMeta {
+    pub chain_id: String,
+    /// Latest block height when the scrape started, `None` if the chain wasn't queried
+    pub block_height: Option<u64>,
+    /// [`ABSTRACT_VERSION`] of the scraper
+    pub scraper_version: String,
+}
+
+impl Default for ScrapeMeta {
+    fn default() -> Self {
+        Self::new("", None)
+    }
+}
+
+impl ScrapeMeta {
+    pub fn new(chain_id: impl Into<String>, block_height: Option<u64>) -> Self {
+        Self {
+            chain_id: chain_id.into(),
+            block_height,
+            scraper_version: ABSTRACT_VERSION.to_string(),
+        }
+    }
+
+    /// The meta of a scrape of the chain of `clients` starting now, offline clients don't know
+    /// the height.
+    pub async fn query(clients: &ChainClients) -> anyhow::Result<Self> {
+        let block_height = if clients.offline {
+            None
+        } else {
+            Some(clients.node.block_height().await?)
+        };
+        Ok(Self::new(&clients.chain_id, block_height))
+    }
+}
+
+/// Everything the scrapers of a pipeline found, ready to be written out or registered.
+#[derive(Debug, Clone, Default)]
+pub struct ScrapeOutput {
+    pub meta: ScrapeMeta,
+    /// Unique asset names
+    pub assets: Vec<(String, AssetInfo)>,
+    /// Staking contracts and the contracts of the protocols, keyed by dex
+    pub contracts: Vec<(UncheckedContractEntry, String)>,
+    pub pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    /// Every pairing of two assets of the pools, with the pools that serve it
+    pub pairings: Vec<(DexAssetPairing, Vec<UncheckedPoolAddress>)>,
+    /// Names that were dropped because they map to different assets
+    pub asset_conflicts: Vec<AssetConflict>,
+    /// cw20s that aren't verified, never registered
+    pub unverified_assets: Vec<(String, AssetInfo)>,
+    /// What each scraper left out
+    pub reports: Vec<DexReport>,
+    /// What the overrides of the chain changed
+    pub overrides: OverridesReport,
+    /// Original name -> sanitized name of the names that weren't valid ANS names
+    pub sanitized_names: BTreeMap<String, String>,
+    /// Name -> denom of the names made up because no asset list has the asset
+    pub low_confidence_names: BTreeMap<String, String>,
+    /// The assets with their decimals, empty without a [`DecimalsResolver`]
+    pub asset_metadata: Vec<AssetMetadata>,
+    /// Assets whose decimals differ between the chain registry and the chain
+    pub decimals_mismatches: Vec<DecimalsMismatch>,
+    /// Chains whose chain registry asset list couldn't be fetched
+    pub failed_registry_chains: Vec<String>,
+    /// Transfer channels the IBC assets came over, empty without a [`ChannelResolver`]
+    pub channels: Vec<(UncheckedChannelEntry, String)>,
+    /// Counterparty chains the IBC assets came from over more than one channel, left out of
+    /// the channels
+    pub channel_conflicts: Vec<ChannelConflict>,
+}
+
+/// Runs a set of scrapers and merges their results into one [`ScrapeOutput`].
+pub struct ScrapePipeline<'a> {
+    scrapers: Vec<Box<dyn DexScraper + 'a>>,
+    conflict_policy: ConflictPolicy,
+    cw20_verifier: Option<Cw20Verifier>,
+    /// Chain the scrapers run on, for the [`ScrapeMeta`] of the output
+    clients: Option<Arc<ChainClients>>,
+    overrides: Option<Overrides>,
+    decimals_resolver: Option<DecimalsResolver>,
+    channel_resolver: Option<ChannelResolver>,
+    /// Where the scrapers recorded the names they sanitized or made up
+    resolution_cache: Option<Arc<ResolutionCache>>,
+    /// Where the progress is checkpointed
+    checkpoint_path: Option<PathBuf>,
+    /// Continue from the checkpoint instead of starting over
+    resume: bool,
+    /// How the failed queries of the run are retried
+    retry_policy: RetryPolicy,
+}
+
+impl<'a> ScrapePipeline<'a> {
+    /// A pipeline of `scrapers` that retries failed queries with the retry policy of `config`.
+    pub fn new(scrapers: Vec<Box<dyn DexScraper + 'a>>, config: &ScraperConfig) -> Self {
+        Self {
+            scrapers,
+            conflict_policy: ConflictPolicy::default(),
+            cw20_verifier: None,
+            clients: None,
+            overrides: None,
+            decimals_resolver: None,
+            channel_resolver: None,
+            resolution_cache: None,
+            checkpoint_path: None,
+            resume: false,
+            retry_policy: config.retry_policy(),
+        }
+    }
+
+    /// Stamp the output with the chain id and latest block height of the chain of `clients`.
+    pub fn with_clients(mut self, clients: Arc<ChainClients>) -> Self {
+        self.clients = Some(clients);
+        self
+    }
+
+    /// Set what happens to asset names that map to different assets.
+    pub fn with_conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.conflict_policy = conflict_policy;
+        self
+    }
+
+    /// Only keep the cw20s `verifier` trusts, the others (and their pools) are moved to
+    /// [`ScrapeOutput::unverified_assets`].
+    pub fn with_cw20_verifier(mut self, verifier: Cw20Verifier) -> Self {
+        self.cw20_verifier = Some(verifier);
+        self
+    }
+
+    /// Drop the assets and pools denied by `overrides`, and fail on override entries that match
+    /// nothing that was scraped.
+    ///
+    /// The forced names are applied by the scrapers, through the resolution cache.
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// Look the decimals of the output assets up, they end up in
+    /// [`ScrapeOutput::asset_metadata`]. Needs the clients of the chain.
+    pub fn with_decimals_resolver(mut self, resolver: DecimalsResolver) -> Self {
+        self.decimals_resolver = Some(resolver);
+        self
+    }
+
+    /// Look the transfer channels of the IBC assets of the output up, they end up in
+    /// [`ScrapeOutput::channels`]. Needs the clients of the chain.
+    pub fn with_channel_resolver(mut self, resolver: ChannelResolver) -> Self {
+        self.channel_resolver = Some(resolver);
+        self
+    }
+
+    /// Report the names the scrapers sanitized or made up while naming the assets of the chain,
+    /// as recorded in `resolution_cache`.
+    pub fn with_resolution_cache(mut self, resolution_cache: Arc<ResolutionCache>) -> Self {
+        self.resolution_cache = Some(resolution_cache);
+        self
+    }
+
+    /// Write the progress to the [`ScrapeCheckpoint`] at `path` after every phase, and with
+    /// `resume` continue from the phases it records as completed. The checkpoint is removed once
+    /// the run completes.
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>, resume: bool) -> Self {
+        self.checkpoint_path = Some(path.into());
+        self.resume = resume;
+        self
+    }
+
+    /// Run the scrapers one after the other.
+    ///
+    /// Assets are fetched before the pools and staking contracts, as the scrapers name those
+    /// after the resolved assets. Identical asset entries are merged, a name used for different
+    /// assets is handled according to the [`ConflictPolicy`], after the denied assets and pools
+    /// are dropped and invalid names are sanitized. Pools of the same assets are deduplicated
+    /// once the names are known to be unique, and unverified cw20s are set apart last.
+    pub async fn run(&mut self) -> Result<ScrapeOutput, ScraperError> {
+        let retry_policy = self.retry_policy;
+        Ok(retry_policy.scope(self.run_scrapers()).await?)
+    }
+
+    async fn run_scrapers(&mut self) -> anyhow::Result<ScrapeOutput> {
+        let mut assets = vec![];
+        let mut output = ScrapeOutput::default();
+        if let Some(clients) = &self.clients {
+            output.meta = ScrapeMeta::query(clients).await?;
+            log::info!(
+                "{}: scraping at height {:?}",
+                output.meta.chain_id,
+                output.meta.block_height
+            );
+        }
+
+        let dexes: Vec<String> = self
+            .scrapers
+            .iter()
+            .map(|scraper| scraper.dex_id().to_string())
+            .collect();
+        let mut checkpoint = match self.resumed_checkpoint(&output.meta.chain_id, &dexes)? {
+            Some(checkpoint) => {
+                log::info!(
+                    "{}: resuming the scrape started at height {:?}",
+                    checkpoint.meta.chain_id,
+                    checkpoint.meta.block_height
+                );
+                output.meta = checkpoint.meta.clone();
+                checkpoint
+            }
+            None => ScrapeCheckpoint::new(output.meta.clone(), dexes),
+        };
+        let checkpoint_path = self.checkpoint_path.clone();
+
+        for scraper in &mut self.scrapers {
+            let dex = scraper.dex_id();
+            let mut progress = checkpoint.dex(dex);
+            if progress.completed(ScrapePhase::Done) {
+                log::info!("{} was scraped before the interruption", dex);
+            } else {
+                log::info!("Scraping {}", dex);
+
+                // Always run again, the scrapers name the pools and staking contracts after the
+                // assets they resolved. The names come from the resolution cache.
+                progress.assets = scraper
+                    .fetch_asset_infos()
+                    .await?
+                    .into_iter()
+                    .map(|AnsAsset { entry, info }| Ok((entry, checked_asset_info(info)?)))
+                    .collect::<anyhow::Result<_>>()?;
+                progress.phase = progress.phase.max(Some(ScrapePhase::AssetsResolved));
+                record_phase(checkpoint_path.as_deref(), &mut checkpoint, &progress)?;
+
+                if !progress.completed(ScrapePhase::PoolsMapped) {
+                    progress.pools = scraper.fetch_dex_pools().await?;
+                    progress.report = Some(scraper.report());
+                    progress.phase = Some(ScrapePhase::PoolsMapped);
+                    record_phase(checkpoint_path.as_deref(), &mut checkpoint, &progress)?;
+                }
+
+                if !progress.completed(ScrapePhase::StakingFetched) {
+                    progress.contracts = scraper
+                        .fetch_staking_contracts()
+                        .await?
+                        .into_iter()
+                        .map(|(name, addr)| staking_contract_entry(dex, &name, addr))
+                        .collect();
+                    progress.phase = Some(ScrapePhase::StakingFetched);
+                    record_phase(checkpoint_path.as_deref(), &mut checkpoint, &progress)?;
+                }
+
+                progress
+                    .contracts
+                    .extend(scraper.fetch_contract_entries().await?.into_iter().map(
+                        |(entry, addr)| {
+                            (
+                                UncheckedContractEntry::new(entry.protocol, entry.contract),
+                                addr.to_string(),
+                            )
+                        },
+                    ));
+                progress.phase = Some(ScrapePhase::Done);
+                record_phase(checkpoint_path.as_deref(), &mut checkpoint, &progress)?;
+            }
+
+            assets.extend(progress.assets);
+            output.pools.extend(progress.pools);
+            output.contracts.extend(progress.contracts);
+            // Taken when the pools were mapped, the later phases skip nothing
+            output
+                .reports
+                .push(progress.report.unwrap_or_else(|| scraper.report()));
+        }
+
+        if let Some(overrides) = &self.overrides {
+            apply_overrides(overrides, &mut assets, &mut output)?;
+        }
+        let resolution_cache = self.resolution_cache.clone();
+        let chain_id = output.meta.chain_id.clone();
+        let recorded = |name: &str, record: fn(&ResolutionCache, &str, &str) -> Option<String>| {
+            record(resolution_cache.as_deref()?, &chain_id, name)
+        };
+        sanitize_names(&mut assets, &mut output, |name| {
+            recorded(name, ResolutionCache::unsanitized_name)
+        });
+        // Before the pools are deduplicated, a name shared by different assets would make their
+        // pools look like the same pair
+        let (assets, conflicts) = consolidate_assets(assets);
+        output.assets = assets;
+        self.handle_conflicts(&mut output, conflicts)?;
+        self.dedup_pools(&mut output).await;
+
+        if let Some(verifier) = &self.cw20_verifier {
+            let assets = std::mem::take(&mut output.assets);
+            let (verified, unverified) = verifier.partition(assets).await?;
+            for (name, info) in &unverified {
+                log::warn!("Not registering unverified cw20 {} ({})", name, info);
+            }
+            let unverified_names = unverified.iter().map(|(name, _)| name.as_str()).collect();
+            drop_pools_using(&mut output.pools, &unverified_names, "an unverified cw20");
+            output.assets = verified;
+            output.unverified_assets = unverified;
+        }
+
+        output.pairings = pool_pairings(&output.pools);
+        output.low_confidence_names = output
+            .assets
+            .iter()
+            .filter_map(|(name, _)| {
+                let denom = recorded(name, ResolutionCache::low_confidence_denom)?;
+                Some((name.clone(), denom))
+            })
+            .collect();
+        for (name, denom) in &output.low_confidence_names {
+            log::warn!(
+                "Made up name {} for {}, check it before registering",
+                name,
+                denom
+            );
+        }
+
+        if let (Some(resolver), Some(clients)) = (&self.decimals_resolver, &self.clients) {
+            let (asset_metadata, decimals_mismatches) =
+                resolver.resolve(clients, &output.assets).await?;
+            output.asset_metadata = asset_metadata;
+            output.decimals_mismatches = decimals_mismatches;
+        }
+        if let (Some(resolver), Some(clients)) = (&self.channel_resolver, &self.clients) {
+            let (channels, channel_conflicts) = resolver.resolve(clients, &output.assets).await?;
+            output.channels = channels;
+            output.channel_conflicts = channel_conflicts;
+        }
+
+        if let Some(path) = &self.checkpoint_path {
+            ScrapeCheckpoint::remove(path)?;
+        }
+        Ok(output)
+    }
+
+    /// The checkpoint to resume from, `None` without one or if it is of another scrape.
+    fn resumed_checkpoint(
+        &self,
+        chain_id: &str,
+        dexes: &[String],
+    ) -> anyhow::Result<Option<ScrapeCheckpoint>> {
+        match &self.checkpoint_path {
+            Some(path) if self.resume => ScrapeCheckpoint::load(path, chain_id, dexes),
+            _ => Ok(None),
+        }
+    }
+
+    /// Sort the assets of every pool by name, like ANS pairs them, and drop the pools that have
+    /// the same dex, assets and type as another pool (a pair that was created again).
+    ///
+    /// The most recently instantiated pool is kept, the others are reported as skipped by their
+    /// dex. Without clients to ask (or offline), the last one found is kept. Scrapers should drop
+    /// such pools themselves, with their LP tokens, which otherwise end up as conflicting names.
+    async fn dedup_pools(&self, output: &mut ScrapeOutput) {
+        for (_, metadata) in &mut output.pools {
+            metadata.assets.sort_by_key(|asset| asset.to_string());
+        }
+
+        // dex, type and sorted assets -> indices of the pools
+        let mut groups = BTreeMap::<String, Vec<usize>>::new();
+        for (idx, (_, metadata)) in output.pools.iter().enumerate() {
+            let key = serde_json::to_string(metadata).expect("pool metadata serializes");
+            groups.entry(key).or_default().push(idx);
+        }
+
+        let mut dropped = BTreeSet::new();
+        for indices in groups.into_values().filter(|indices| indices.len() > 1) {
+            let mut newest = *indices.last().expect("groups are not empty");
+            let mut newest_created = None;
+            for &idx in &indices {
+                let created = self.created(&output.pools[idx].0).await;
+                if created.is_some() && created >= newest_created {
+                    newest = idx;
+                    newest_created = created;
+                }
+            }
+
+            let (kept, metadata) = output.pools[newest].clone();
+            for idx in indices.into_iter().filter(|idx| *idx != newest) {
+                let pool = output.pools[idx].0.clone();
+                log::warn!(
+                    "Dropping pool {:?}, {:?} is a newer {} pool of the same assets",
+                    pool,
+                    kept,
+                    metadata.dex
+                );
+                if let Some(report) = output.reports.iter_mut().find(|r| r.dex == metadata.dex) {
+                    report
+                        .skipped_pools
+                        .push(SkippedPool::duplicate_of(pool, kept.clone()));
+                }
+                dropped.insert(idx);
+            }
+        }
+
+        let mut idx = 0;
+        output.pools.retain(|_| {
+            idx += 1;
+            !dropped.contains(&(idx - 1))
+        });
+    }
+
+    /// `(block height, tx index)` of the instantiation of a pool contract, the id of a pool
+    /// without contract. `None` if it can't be queried.
+    async fn created(&self, pool: &UncheckedPoolAddress) -> Option<(u64, u64)> {
+        match pool {
+            UncheckedPoolAddress::Id(id) => Some((*id, 0)),
+            UncheckedPoolAddress::Contract(address) => {
+                self.clients.as_ref()?.contract_created(address).await
+            }
+        }
+    }
+
+    /// Fail the run on conflicting asset names or drop them, depending on the policy.
+    fn handle_conflicts(
+        &self,
+        output: &mut ScrapeOutput,
+        conflicts: Vec<AssetConflict>,
+    ) -> anyhow::Result<()> {
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+
+        for conflict in &conflicts {
+            for (name, info) in conflict.suggested_names() {
+                log::warn!(
+                    "Asset name {} conflicts, suggested name for {}: {}",
+                    conflict.name,
+                    info,
+                    name
+                );
+            }
+        }
+        match self.conflict_policy {
+            ConflictPolicy::Fail => Err(AssetConflictsError { conflicts }.into()),
+            ConflictPolicy::Drop => {
+                // The pools of a dropped name can't be registered either, their dex reports them
+                let names: BTreeSet<&str> = conflicts.iter().map(|c| c.name.as_str()).collect();
+                let mut skipped = vec![];
+                output.pools.retain(|(pool, metadata)| {
+                    let conflicting: Vec<String> = metadata
+                        .assets
+                        .iter()
+                        .map(ToString::to_string)
+                        .filter(|asset| names.contains(asset.as_str()))
+                        .collect();
+                    if conflicting.is_empty() {
+                        return true;
+                    }
+                    log::warn!("Dropping pool {:?}, it uses a conflicting asset name", pool);
+                    skipped.push((
+                        metadata.dex.clone(),
+                        SkippedPool::conflicting_names(pool.clone(), conflicting),
+                    ));
+                    false
+                });
+                for (dex, pool) in skipped {
+                    if let Some(report) = output.reports.iter_mut().find(|r| r.dex == dex) {
+                        report.skipped_pools.push(pool);
+                    }
+                }
+                output.asset_conflicts = conflicts;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Log the completed phase of a dex, record it and write the checkpoint, if the pipeline has one.
+fn record_phase(
+    path: Option<&Path>,
+    checkpoint: &mut ScrapeCheckpoint,
+    progress: &DexCheckpoint,
+) -> anyhow::Result<()> {
+    log_event(
+        Level::Info,
+        "phase completed",
+        json!({
+            "chain_id": checkpoint.meta.chain_id,
+            "dex": progress.dex,
+            "phase": progress.phase,
+            "assets": progress.assets.len(),
+            "pools": progress.pools.len(),
+            "contracts": progress.contracts.len(),
+        }),
+    );
+    let Some(path) = path else {
+        return Ok(());
+    };
+    checkpoint.update(progress.clone());
+    checkpoint.save(path)
+}
+
+/// Check the overrides against the scrape, then drop the denied assets (with the LP tokens and
+/// staking contracts of their pools) and the denied pools. The changes are noted in
+/// [`ScrapeOutput::overrides`].
+fn apply_overrides(
+    overrides: &Overrides,
+    assets: &mut Vec<(String, AssetInfo)>,
+    output: &mut ScrapeOutput,
+) -> anyhow::Result<()> {
+    let unresolved_assets = output
+        .reports
+        .iter()
+        .flat_map(|report| &report.unresolved_assets)
+        .map(|asset| &asset.info);
+    let skipped_pools = output
+        .reports
+        .iter()
+        .flat_map(|report| &report.skipped_pools)
+        .map(|pool| &pool.pool);
+    overrides.check_known(
+        assets.iter().map(|(_, info)| info).chain(unresolved_assets),
+        output
+            .pools
+            .iter()
+            .map(|(pool, _)| pool)
+            .chain(skipped_pools),
+    )?;
+
+    let mut report = OverridesReport::default();
+    for (name, info) in assets.iter() {
+        if overrides.asset_name(info).is_some()
+            && !report
+                .renamed_assets
+                .contains(&(name.clone(), info.clone()))
+        {
+            report.renamed_assets.push((name.clone(), info.clone()));
+        }
+    }
+
+    let denied_names: BTreeSet<String> = assets
+        .iter()
+        .filter(|(_, info)| overrides.is_denied_asset(info))
+        .map(|(name, _)| name.clone())
+        .collect();
+    let denied_names: BTreeSet<&str> = denied_names.iter().map(String::as_str).collect();
+    assets.retain(|(name, info)| {
+        let denied = overrides.is_denied_asset(info) || lp_token_uses(name, &denied_names);
+        if denied {
+            log::warn!("Dropping denied asset {} ({})", name, info);
+            if !report.denied_assets.contains(&(name.clone(), info.clone())) {
+                report.denied_assets.push((name.clone(), info.clone()));
+            }
+        }
+        !denied
+    });
+
+    report.denied_pools = drop_pools_using(&mut output.pools, &denied_names, "a denied asset");
+    output.pools.retain(|(pool_id, _)| {
+        let denied = overrides.is_denied_pool(pool_id);
+        if denied {
+            log::warn!("Dropping denied pool {:?}", pool_id);
+            report.denied_pools.push(pool_id.clone());
+        }
+        !denied
+    });
+
+    output.contracts.retain(|(entry, _)| {
+        entry
+            .contract
+            .strip_prefix(&format!("{}/", STAKING_CONTRACT_PREFIX))
+            .map_or(true, |lp_token_name| {
+                !lp_token_uses(lp_token_name, &denied_names)
+            })
+    });
+
+    output.overrides = report;
+    Ok(())
+}
+
+/// Validate the names like ANS does and replace the invalid ones by their sanitized form: the
+/// asset names in the assets, the pools and the staking contracts, the dex names of the pools
+/// (and so of their pairings) and the names of the contracts. Asset names that can't be
+/// sanitized are dropped with their pools, dex and contract names with their pools and contracts.
+///
+/// Every sanitization is recorded in [`ScrapeOutput::sanitized_names`], the ones done while naming
+/// the assets included, which `unsanitized_name` looks up.
+fn sanitize_names(
+    assets: &mut Vec<(String, AssetInfo)>,
+    output: &mut ScrapeOutput,
+    unsanitized_name: impl Fn(&str) -> Option<String>,
+) {
+    // invalid name -> sanitized name, `None` if it can't be sanitized
+    let mut renames = BTreeMap::<String, Option<String>>::new();
+    for (name, _) in assets.iter() {
+        sanitized_name(
+            &mut renames,
+            name,
+            validate_asset_entry,
+            sanitize_asset_entry,
+        );
+    }
+
+    assets.retain_mut(|(name, _)| match renames.get(name) {
+        Some(Some(sanitized)) => {
+            *name = sanitized.clone();
+            true
+        }
+        Some(None) => false,
+        None => true,
+    });
+
+    let invalid_names = renames
+        .iter()
+        .filter(|(_, sanitized)| sanitized.is_none())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    drop_pools_using(&mut output.pools, &invalid_names, "an invalid name");
+    for (_, metadata) in &mut output.pools {
+        for asset in &mut metadata.assets {
+            if let Some(Some(sanitized)) = renames.get(&asset.to_string()) {
+                *asset = AssetEntry::from(sanitized.clone());
+            }
+        }
+    }
+
+    // Dex, protocol and contract names are single segments
+    let mut segment_renames = BTreeMap::<String, Option<String>>::new();
+    let mut sanitized_segment = |name: &str| {
+        sanitized_name(
+            &mut segment_renames,
+            name,
+            validate_name,
+            sanitize_name_segment,
+        )
+    };
+    output.pools.retain_mut(|(pool_id, metadata)| {
+        let Some(dex) = sanitized_segment(&metadata.dex) else {
+            log::warn!("Dropping pool {:?} of an invalid dex name", pool_id);
+            return false;
+        };
+        metadata.dex = dex;
+        true
+    });
+
+    let staking_prefix = format!("{}/", STAKING_CONTRACT_PREFIX);
+    output.contracts.retain_mut(|(entry, _)| {
+        let Some(protocol) = sanitized_segment(&entry.protocol) else {
+            return false;
+        };
+        entry.protocol = protocol;
+        let contract = match entry.contract.strip_prefix(&staking_prefix) {
+            Some(lp_token_name) => sanitized_name(
+                &mut renames,
+                lp_token_name,
+                validate_asset_entry,
+                sanitize_asset_entry,
+            )
+            .map(|sanitized| format!("{}{}", staking_prefix, sanitized)),
+            None => sanitized_segment(&entry.contract),
+        };
+        match contract {
+            Some(contract) => {
+                entry.contract = contract;
+                true
+            }
+            None => false,
+        }
+    });
+
+    output.sanitized_names = assets
+        .iter()
+        .filter_map(|(name, _)| Some((unsanitized_name(name)?, name.clone())))
+        .chain(
+            renames
+                .into_iter()
+                .chain(segment_renames)
+                .filter_map(|(name, sanitized)| Some((name, sanitized?))),
+        )
+        .collect();
+}
+
+/// The valid form of `name`: `name` itself if `validate` accepts it, else its sanitized form,
+/// `None` if it can't be sanitized. Invalid names are looked up in and recorded to `renames`.
+fn sanitized_name(
+    renames: &mut BTreeMap<String, Option<String>>,
+    name: &str,
+    validate: impl Fn(&str) -> Result<(), InvalidName>,
+    sanitize: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    if let Some(sanitized) = renames.get(name) {
+        return sanitized.clone();
+    }
+    let Err(err) = validate(name) else {
+        return Some(name.to_string());
+    };
+    let sanitized = sanitize(name);
+    match &sanitized {
+        Some(sanitized) => log::warn!("{}, sanitized to {}", err, sanitized),
+        None => log::warn!("{}, dropping it", err),
+    }
+    renames.insert(name.to_string(), sanitized.clone());
+    sanitized
+}
+
+/// Whether `name` is the LP token entry of a pool with an asset in `names`.
+pub(crate) fn lp_token_uses(name: &str, names: &BTreeSet<&str>) -> bool {
+    name.split_once(LP_TOKEN_SEPARATOR)
+        .map_or(false, |(_, assets)| {
+            assets.split(',').any(|asset| names.contains(asset))
+        })
+}
+
+/// Drop the pools that have an asset in `names`, `reason` describes those names in the log.
+///
+/// Returns the dropped pools.
+fn drop_pools_using(
+    pools: &mut Vec<(UncheckedPoolAddress, PoolMetadata)>,
+    names: &BTreeSet<&str>,
+    reason: &str,
+) -> Vec<UncheckedPoolAddress> {
+    let mut dropped_pools = vec![];
+    pools.retain(|(pool_id, metadata)| {
+        let dropped = metadata
+            .assets
+            .iter()
+            .any(|asset| names.contains(asset.to_string().as_str()));
+        if dropped {
+            log::warn!("Dropping pool {:?}, it uses {}", pool_id, reason);
+            dropped_pools.push(pool_id.clone());
+        }
+        !dropped
+    });
+    dropped_pools
+}
+
+/// The asset pairings of the pools, as AnsHost indexes them: one for every two assets of a pool,
+/// so a pool of three assets has three pairings.
+///
+/// The assets of a pairing are ordered by name. A pairing served by several pools keeps all of
+/// them, AnsHost references every pool of a pairing.
+pub fn pool_pairings(
+    pools: &[(UncheckedPoolAddress, PoolMetadata)],
+) -> Vec<(DexAssetPairing, Vec<UncheckedPoolAddress>)> {
+    // (dex, asset, asset) -> pools
+    let mut pairings = BTreeMap::<(String, String, String), Vec<UncheckedPoolAddress>>::new();
+    for (pool, metadata) in pools {
+        for (idx, asset_x) in metadata.assets.iter().enumerate() {
+            for asset_y in &metadata.assets[idx + 1..] {
+                let (asset_x, asset_y) = (asset_x.to_string(), asset_y.to_string());
+                let (asset_x, asset_y) = if asset_x <= asset_y {
+                    (asset_x, asset_y)
+                } else {
+                    (asset_y, asset_x)
+                };
+                pairings
+                    .entry((metadata.dex.clone(), asset_x, asset_y))
+                    .or_default()
+                    .push(pool.clone());
+            }
+        }
+    }
+
+    pairings
+        .into_iter()
+        .map(|((dex, asset_x, asset_y), pools)| {
+            let pairing =
+                DexAssetPairing::new(AssetEntry::new(&asset_x), AssetEntry::new(&asset_y), &dex);
+            (pairing, pools)
+        })
+        .collect()
+}
+
+/// The ANS contract entry of the staking contract of an LP token.
+pub(crate) fn staking_contract_entry(
+    dex: &str,
+    lp_token_name: &str,
+    addr: Addr,
+) -> (UncheckedContractEntry, String) {
+    (
+        UncheckedContractEntry::new(
+            dex,
+            format!("{}/{}", STAKING_CONTRACT_PREFIX, lp_token_name),
+        ),
+        addr.to_string(),
+    )
+}
+
+/// Scrapers only return on-chain addresses, so they don't need to be validated again.
+fn checked_asset_info(info: AssetInfoUnchecked) -> anyhow::Result<AssetInfo> {
+    match info {
+        AssetInfoUnchecked::Native(denom) => Ok(AssetInfo::native(denom)),
+        AssetInfoUnchecked::Cw20(contract_addr) => {
+            Ok(AssetInfo::cw20(Addr::unchecked(contract_addr)))
+        }
+        _ => anyhow::bail!("AssetInfo not supported: {:?}", info),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abstract_core::objects::PoolType;
+    use async_trait::async_trait;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::error::ScraperError;
+    use crate::helpers::names::lp_token_name;
+    use crate::traits::dex::{AssetSource, DexId};
+
+    /// A scraper that returns canned data.
+    struct CannedDex {
+        dex: &'static str,
+        assets: Vec<AnsAsset>,
+        pools: Vec<(UncheckedPoolAddress, PoolMetadata)>,
+        staking: Vec<(String, Addr)>,
+    }
+
+    impl DexId for CannedDex {
+        fn dex_id(&self) -> &'static str {
+            self.dex
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl AssetSource for CannedDex {
+        async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+            Ok(self.assets.clone())
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl DexScraper for CannedDex {
+        async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+            Ok(self.staking.clone())
+        }
+
+        async fn fetch_dex_pools(
+            &mut self,
+        ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+            Ok(self.pools.clone())
+        }
+
+        fn report(&self) -> DexReport {
+            DexReport::new(self.dex, vec![], vec![])
+        }
+    }
+
+    fn pool(
+        dex: &str,
+        address: &str,
+        pool_type: PoolType,
+        assets: [&str; 2],
+    ) -> (UncheckedPoolAddress, PoolMetadata) {
+        (
+            UncheckedPoolAddress::contract(address),
+            PoolMetadata {
+                dex: dex.to_string(),
+                pool_type,
+                assets: assets.into_iter().map(AssetEntry::new).collect(),
+            },
+        )
+    }
+
+    fn astroport() -> CannedDex {
+        CannedDex {
+            dex: "astroport",
+            assets: vec![
+                AnsAsset::new("terra2>luna", AssetInfo::native("uluna")),
+                AnsAsset::new(
+                    "terra2>astro",
+                    AssetInfo::cw20(Addr::unchecked("terra1astro")),
+                ),
+            ],
+            pools: vec![pool(
+                "astroport",
+                "terra1pair",
+                PoolType::ConstantProduct,
+                ["terra2>luna", "terra2>astro"],
+            )],
+            staking: vec![(
+                "astroport/terra2>astro,terra2>luna".to_string(),
+                Addr::unchecked("terra1staking"),
+            )],
+        }
+    }
+
+    /// Shares LUNA with [`astroport`], and names `second_asset` after its symbol.
+    fn terraswap(second_asset: AnsAsset) -> CannedDex {
+        let second_name = second_asset.entry.clone();
+        CannedDex {
+            dex: "terraswap",
+            assets: vec![
+                AnsAsset::new("terra2>luna", AssetInfo::native("uluna")),
+                second_asset,
+            ],
+            pools: vec![pool(
+                "terraswap",
+                "terra1swap",
+                PoolType::Stable,
+                ["terra2>luna", &second_name],
+            )],
+            staking: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn canned_scrapers_are_merged() {
+        let usdc = AnsAsset::new("terra2>usdc", AssetInfo::native("ibc/usdc"));
+        let mut pipeline = ScrapePipeline::new(
+            vec![Box::new(astroport()), Box::new(terraswap(usdc))],
+            &ScraperConfig::default(),
+        );
+
+        let output = pipeline.run().await.unwrap();
+
+        assert_eq!(
+            output.assets,
+            [
+                ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+                (
+                    "terra2>astro".to_string(),
+                    AssetInfo::cw20(Addr::unchecked("terra1astro"))
+                ),
+                ("terra2>usdc".to_string(), AssetInfo::native("ibc/usdc")),
+            ]
+        );
+        assert_eq!(
+            output.pools,
+            [
+                pool(
+                    "astroport",
+                    "terra1pair",
+                    PoolType::ConstantProduct,
+                    ["terra2>astro", "terra2>luna"],
+                ),
+                pool(
+                    "terraswap",
+                    "terra1swap",
+                    PoolType::Stable,
+                    ["terra2>luna", "terra2>usdc"],
+                ),
+            ]
+        );
+        assert_eq!(
+            output.contracts,
+            [(
+                UncheckedContractEntry::new(
+                    "astroport",
+                    "staking/astroport/terra2>astro,terra2>luna"
+                ),
+                "terra1staking".to_string()
+            )]
+        );
+        assert_eq!(output.pairings.len(), 2);
+        let dexes: Vec<&str> = output.reports.iter().map(|r| r.dex.as_str()).collect();
+        assert_eq!(dexes, ["astroport", "terraswap"]);
+        assert!(output.asset_conflicts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn conflicting_names_fail_the_run_or_are_dropped() {
+        let fake_astro = || {
+            AnsAsset::new(
+                "terra2>astro",
+                AssetInfo::cw20(Addr::unchecked("terra1fake")),
+            )
+        };
+
+        let err = ScrapePipeline::new(
+            vec![Box::new(astroport()), Box::new(terraswap(fake_astro()))],
+            &ScraperConfig::default(),
+        )
+        .run()
+        .await
+        .unwrap_err();
+        let ScraperError::AssetConflicts(AssetConflictsError { conflicts }) = &err else {
+            panic!("expected asset conflicts, got {:?}", err);
+        };
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].name, "terra2>astro");
+
+        let output = ScrapePipeline::new(
+            vec![Box::new(astroport()), Box::new(terraswap(fake_astro()))],
+            &ScraperConfig::default(),
+        )
+        .with_conflict_policy(ConflictPolicy::Drop)
+        .run()
+        .await
+        .unwrap();
+        assert_eq!(
+            output.assets,
+            [("terra2>luna".to_string(), AssetInfo::native("uluna"))]
+        );
+        // both pools use the dropped name, and are reported by their dex
+        assert!(output.pools.is_empty());
+        let skipped: Vec<&[SkippedPool]> = output
+            .reports
+            .iter()
+            .map(|report| report.skipped_pools.as_slice())
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                [SkippedPool::conflicting_names(
+                    UncheckedPoolAddress::contract("terra1pair"),
+                    vec!["terra2>astro".to_string()]
+                )],
+                [SkippedPool::conflicting_names(
+                    UncheckedPoolAddress::contract("terra1swap"),
+                    vec!["terra2>astro".to_string()]
+                )],
+            ]
+        );
+        assert_eq!(
+            output.asset_conflicts,
+            [AssetConflict {
+                name: "terra2>astro".to_string(),
+                infos: vec![
+                    AssetInfo::cw20(Addr::unchecked("terra1astro")),
+                    AssetInfo::cw20(Addr::unchecked("terra1fake")),
+                ],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn dex_and_contract_names_are_sanitized() {
+        let mut fork = astroport();
+        fork.dex = "Astro Fork";
+        fork.pools[0].1.dex = "Astro Fork".to_string();
+        fork.staking[0].0 = "Astro Fork/terra2>astro,terra2>luna".to_string();
+
+        let output = ScrapePipeline::new(vec![Box::new(fork)], &ScraperConfig::default())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(output.pools[0].1.dex, "astro_fork");
+        let dexes: Vec<&str> = output
+            .pairings
+            .iter()
+            .map(|(pairing, _)| pairing.dex())
+            .collect();
+        assert_eq!(dexes, ["astro_fork"]);
+        assert_eq!(
+            output.contracts,
+            [(
+                UncheckedContractEntry::new(
+                    "astro_fork",
+                    "staking/astro_fork/terra2>astro,terra2>luna"
+                ),
+                "terra1staking".to_string()
+            )]
+        );
+        assert_eq!(
+            output.sanitized_names,
+            BTreeMap::from([
+                ("Astro Fork".to_string(), "astro_fork".to_string()),
+                (
+                    "Astro Fork/terra2>astro,terra2>luna".to_string(),
+                    "astro_fork/terra2>astro,terra2>luna".to_string()
+                ),
+            ])
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn staking_keys_ignore_the_asset_order(
+            assets in prop::collection::vec("[a-z0-9]{1,8}>[a-z0-9]{1,8}", 2..4).prop_shuffle(),
+        ) {
+            let mut sorted = assets.clone();
+            sorted.sort();
+            let addr = Addr::unchecked("terra1staking");
+
+            let (entry, address) =
+                staking_contract_entry("astroport", &lp_token_name("astroport", &assets), addr.clone());
+
+            prop_assert_eq!(
+                (entry.clone(), address),
+                staking_contract_entry("astroport", &lp_token_name("astroport", &sorted), addr)
+            );
+            prop_assert_eq!(entry.protocol, "astroport");
+            prop_assert_eq!(
+                entry.contract,
+                format!("staking/astroport/{}", sorted.join(","))
+            );
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use abstract_core::ans_host::ExecuteMsg;
+use abstract_core::objects::UniquePoolId;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfoUnchecked;
+use cw_orch::{Contract, Daemon};
+use log::Level;
+use serde_json::json;
+use tokio::runtime::Handle;
+
+use crate::ans_version::TargetVersion;
+use crate::diff::{AnsDiff, AnsHostEntries, DEFAULT_MAX_HEIGHT_GAP};
+use crate::error::ScraperError;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::retry::RetryPolicy;
+use crate::logging::log_event;
+use crate::pipeline::{pool_pairings, ScrapeOutput};
+
+/// Default number of entries per `Update*` message, bigger messages run out of gas.
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// A batch of a registration that failed to execute.
+#[derive(Debug, thiserror::Error)]
+#[error("registering {failed} failed after {} successful batches: {error:#}", succeeded.len())]
+pub struct RegistrationError {
+    /// Batches that were executed before the failure
+    pub succeeded: Vec<String>,
+    pub failed: String,
+    pub error: anyhow::Error,
+}
+
+/// An `Update*` message of a registration with a description of its entries.
+#[derive(Debug, Clone)]
+pub struct RegistrationBatch {
+    pub description: String,
+    pub msg: ExecuteMsg,
+}
+
+/// Registers the output of a scrape on the AnsHost contract of the chain.
+///
+/// The messages are planned with the queriers of the chain only, a wallet is needed to broadcast
+/// them, see [`Self::with_sender`]. Without one they are logged (a dry run).
+pub struct AnsRegistrar {
+    rt_handle: Handle,
+    clients: Arc<ChainClients>,
+    ans_host: Addr,
+    /// Contract the messages are executed on, `None` to log them instead
+    sender: Option<Contract<Daemon>>,
+    batch_size: usize,
+    /// Also remove the entries of the scraped dexes that the scrape no longer finds
+    prune: bool,
+    /// Version the messages are written for, the one of the contract if `None`
+    target_version: Option<TargetVersion>,
+    /// How the failed queries of AnsHost are retried
+    retry_policy: RetryPolicy,
+}
+
+impl AnsRegistrar {
+    /// A registrar that queries `ans_host` with `clients` on the runtime of `rt_handle`.
+    pub fn new(rt_handle: Handle, clients: Arc<ChainClients>, ans_host: Addr) -> Self {
+        Self {
+            rt_handle,
+            clients,
+            ans_host,
+            sender: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            prune: false,
+            target_version: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Broadcast the messages with the wallet of `chain` instead of logging them.
+    pub fn with_sender(mut self, chain: Daemon) -> Self {
+        self.sender =
+            Some(Contract::new("abstract:ans_host", chain).with_address(Some(&self.ans_host)));
+        self
+    }
+
+    /// Set the number of entries per `Update*` message.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Also remove the registered entries of the scraped dexes that the scrape didn't find.
+    pub fn with_prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    /// Write the messages for AnsHost `version` instead of the version of the contract.
+    pub fn with_target_version(mut self, version: Option<TargetVersion>) -> Self {
+        self.target_version = version;
+        self
+    }
+
+    /// Retry the failed queries of AnsHost with `retry_policy` instead of the default one.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// The AnsHost version the messages are written for, read from the contract unless set.
+    pub fn target_version(&self) -> anyhow::Result<TargetVersion> {
+        if let Some(version) = self.target_version {
+            return Ok(version);
+        }
+        self.rt_handle.block_on(
+            self.retry_policy
+                .scope(TargetVersion::detect(&self.clients, &self.ans_host)),
+        )
+    }
+
+    /// Register the entries of `output` that AnsHost doesn't have yet (or has with another
+    /// value), and remove the ones it no longer finds when pruning. Returns the executed (or
+    /// logged) batches.
+    pub fn register(&self, output: &ScrapeOutput) -> Result<Vec<RegistrationBatch>, ScraperError> {
+        let version = self.target_version()?;
+        let batches = self.plan_for(output, version)?;
+        if batches.is_empty() {
+            log::info!("{}: AnsHost is up to date", self.clients.chain_id);
+            return Ok(batches);
+        }
+
+        let mut succeeded = vec![];
+        for batch in &batches {
+            let msg = version.adapt_msg(&batch.msg)?;
+            let Some(sender) = &self.sender else {
+                log_event(
+                    Level::Info,
+                    "dry run message",
+                    json!({
+                        "chain_id": self.clients.chain_id,
+                        "batch": batch.description,
+                        "msg": msg,
+                    }),
+                );
+                continue;
+            };
+
+            log::info!(
+                "{}: registering {}",
+                self.clients.chain_id,
+                batch.description
+            );
+            if let Err(err) = sender.execute(&msg, None) {
+                return Err(ScraperError::Registration(RegistrationError {
+                    succeeded,
+                    failed: batch.description.clone(),
+                    error: err.into(),
+                }));
+            }
+            succeeded.push(batch.description.clone());
+        }
+
+        Ok(batches)
+    }
+
+    /// The messages that bring AnsHost up to date with `output`, in execution order.
+    ///
+    /// New dexes are registered before the pools, AnsHost only accepts pools of registered dexes.
+    pub fn plan(&self, output: &ScrapeOutput) -> Result<Vec<RegistrationBatch>, ScraperError> {
+        Ok(self.plan_for(output, self.target_version()?)?)
+    }
+
+    /// [`Self::plan`] for AnsHost `version`, which leaves out the pools it has no pool type for.
+    fn plan_for(
+        &self,
+        output: &ScrapeOutput,
+        version: TargetVersion,
+    ) -> anyhow::Result<Vec<RegistrationBatch>> {
+        let registered = self.rt_handle.block_on(
+            self.retry_policy
+                .scope(AnsHostEntries::query(&self.clients, &self.ans_host)),
+        )?;
+        plan_batches(&registered, output, version, self.batch_size, self.prune)
+    }
+}
+
+/// The messages that bring the `registered` entries up to date with `output` for AnsHost
+/// `version`, in execution order, with at most `batch_size` entries each.
+///
+/// With `prune`, the entries of the scraped dexes that `output` doesn't have anymore are removed
+/// first, the pools before the assets they use. New dexes are registered before the pools,
+/// AnsHost only accepts pools of registered dexes. Pools `version` has no pool type for are left
+/// out.
+pub fn plan_batches(
+    registered: &AnsHostEntries,
+    output: &ScrapeOutput,
+    version: TargetVersion,
+    batch_size: usize,
+    prune: bool,
+) -> anyhow::Result<Vec<RegistrationBatch>> {
+    let batch_size = batch_size.max(1);
+    let diff = AnsDiff::new(registered, output, DEFAULT_MAX_HEIGHT_GAP)?;
+    diff.log_summary();
+    let mut batches = vec![];
+    if prune {
+        batches.extend(removal_batches(registered, &diff, batch_size));
+    }
+
+    // Entries that are new or point at something else now
+    let assets = [diff.assets.to_add, diff.assets.to_update]
+        .concat()
+        .into_iter()
+        .map(|(name, info)| (name, AssetInfoUnchecked::from(info)))
+        .collect::<Vec<_>>();
+    for chunk in assets.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!("{} assets from {}", chunk.len(), chunk[0].0),
+            msg: ExecuteMsg::UpdateAssetAddresses {
+                to_add: chunk.to_vec(),
+                to_remove: vec![],
+            },
+        });
+    }
+
+    let contracts = [diff.contracts.to_add, diff.contracts.to_update].concat();
+    for chunk in contracts.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!("{} contracts from {}", chunk.len(), chunk[0].0.contract),
+            msg: ExecuteMsg::UpdateContractAddresses {
+                to_add: chunk.to_vec(),
+                to_remove: vec![],
+            },
+        });
+    }
+
+    // A registered channel may have been picked by hand among several, it is not replaced
+    for (entry, channel) in &diff.channels.to_update {
+        log::warn!(
+            "Not replacing the {} channel to {}, the scrape found {}",
+            entry.protocol,
+            entry.connected_chain,
+            channel
+        );
+    }
+    for chunk in diff.channels.to_add.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!(
+                "{} channels from {}",
+                chunk.len(),
+                chunk[0].0.connected_chain
+            ),
+            msg: ExecuteMsg::UpdateChannels {
+                to_add: chunk.to_vec(),
+                to_remove: vec![],
+            },
+        });
+    }
+
+    // Registering a pool again would give it a second id, only new pools are added
+    let (pools, unsupported): (Vec<_>, Vec<_>) = diff
+        .pools
+        .to_add
+        .into_iter()
+        .partition(|(_, metadata)| version.supports_pool_type(&metadata.pool_type));
+    for (pool, metadata) in &unsupported {
+        log::warn!(
+            "Not registering pool {:?}, AnsHost {} has no pool type {:?}",
+            pool,
+            version,
+            metadata.pool_type
+        );
+    }
+    let new_dexes = pools
+        .iter()
+        .map(|(_, metadata)| metadata.dex.clone())
+        .filter(|dex| !registered.dexes.contains(dex))
+        .collect::<BTreeSet<_>>();
+    if !new_dexes.is_empty() {
+        batches.push(RegistrationBatch {
+            description: format!(
+                "dexes {}",
+                new_dexes.iter().cloned().collect::<Vec<_>>().join(", ")
+            ),
+            msg: ExecuteMsg::UpdateDexes {
+                to_add: new_dexes.into_iter().collect(),
+                to_remove: vec![],
+            },
+        });
+    }
+    for chunk in pools.chunks(batch_size) {
+        // AnsHost indexes the pools by these pairings when it registers them
+        let pairings = pool_pairings(chunk);
+        for (pairing, pools) in &pairings {
+            log::debug!("Pairing {:?} of pools {:?}", pairing, pools);
+        }
+        batches.push(RegistrationBatch {
+            description: format!(
+                "{} pools from {:?} ({} pairings)",
+                chunk.len(),
+                chunk[0].0,
+                pairings.len()
+            ),
+            msg: ExecuteMsg::UpdatePools {
+                to_add: chunk.to_vec(),
+                to_remove: vec![],
+            },
+        });
+    }
+
+    Ok(batches)
+}
+
+/// The messages that remove the entries of `diff` the scrape no longer finds, pools first.
+///
+/// Pools are removed by their unique id, the ones `registered` has no id for are left.
+fn removal_batches(
+    registered: &AnsHostEntries,
+    diff: &AnsDiff,
+    batch_size: usize,
+) -> Vec<RegistrationBatch> {
+    let mut batches = vec![];
+
+    let mut pool_ids = vec![];
+    for (pool, _) in &diff.pools.to_remove {
+        match registered
+            .pool_ids
+            .iter()
+            .find(|(_, address)| address == pool)
+        {
+            Some((id, _)) => pool_ids.push(*id),
+            None => log::warn!("Not removing pool {:?}, its unique id is unknown", pool),
+        }
+    }
+    for chunk in pool_ids.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!("removal of {} pools from id {}", chunk.len(), chunk[0]),
+            msg: ExecuteMsg::UpdatePools {
+                to_add: vec![],
+                to_remove: chunk.iter().copied().map(UniquePoolId::new).collect(),
+            },
+        });
+    }
+
+    let assets = diff
+        .assets
+        .to_remove
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    for chunk in assets.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!("removal of {} assets from {}", chunk.len(), chunk[0]),
+            msg: ExecuteMsg::UpdateAssetAddresses {
+                to_add: vec![],
+                to_remove: chunk.to_vec(),
+            },
+        });
+    }
+
+    let contracts = diff
+        .contracts
+        .to_remove
+        .iter()
+        .map(|(entry, _)| entry.clone())
+        .collect::<Vec<_>>();
+    for chunk in contracts.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!(
+                "removal of {} contracts from {}",
+                chunk.len(),
+                chunk[0].contract
+            ),
+            msg: ExecuteMsg::UpdateContractAddresses {
+                to_add: vec![],
+                to_remove: chunk.to_vec(),
+            },
+        });
+    }
+
+    let channels = diff
+        .channels
+        .to_remove
+        .iter()
+        .map(|(entry, _)| entry.clone())
+        .collect::<Vec<_>>();
+    for chunk in channels.chunks(batch_size) {
+        batches.push(RegistrationBatch {
+            description: format!(
+                "removal of {} channels from {}",
+                chunk.len(),
+                chunk[0].connected_chain
+            ),
+            msg: ExecuteMsg::UpdateChannels {
+                to_add: vec![],
+                to_remove: chunk.to_vec(),
+            },
+        });
+    }
+
+    batches
+}
@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use cosmwasm_std::Uint128;
+use cw_asset::AssetInfo;
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::cache::write_json_atomic;
+use crate::helpers::channels::ChannelConflict;
+use crate::helpers::decimals::DecimalsMismatch;
+use crate::pipeline::ScrapeMeta;
+
+pub const REPORT_FILE: &str = "report.json";
+
+/// Why an asset could not be named.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", content = "details", rename_all = "snake_case")]
+pub enum UnresolvedReason {
+    /// The cw20 `TokenInfo` query failed or returned a token info that can't name the asset
+    Cw20Query(String),
+    /// The IBC denom trace could not be queried
+    DenomTrace(String),
+    /// The IBC denom came over another port than transfer
+    NonTransferPort(String),
+    /// The IBC denom travelled through more than one channel
+    MultiHop(String),
+    /// The chain registry has no asset for the denom
+    NoRegistryMatch(String),
+    /// Neither a native nor a cw20 asset
+    Unsupported(String),
+    /// The LP share of a pool (`gamm/pool/<id>`), not an asset of its own
+    PoolShare(String),
+    /// The token factory denom has no asset in the registry and its subdenom can't name it
+    FactoryDenom(String),
+}
+
+impl fmt::Display for UnresolvedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnresolvedReason::Cw20Query(details) => write!(f, "cw20 query failed: {}", details),
+            UnresolvedReason::DenomTrace(details) => write!(f, "denom trace failed: {}", details),
+            UnresolvedReason::NonTransferPort(port) => write!(f, "non-transfer port {}", port),
+            UnresolvedReason::MultiHop(path) => write!(f, "multi-hop denom {}", path),
+            UnresolvedReason::NoRegistryMatch(details) => {
+                write!(f, "no registry match: {}", details)
+            }
+            UnresolvedReason::Unsupported(details) => write!(f, "unsupported: {}", details),
+            UnresolvedReason::PoolShare(pool_id) => write!(f, "share of pool {}", pool_id),
+            UnresolvedReason::FactoryDenom(details) => {
+                write!(f, "token factory denom: {}", details)
+            }
+        }
+    }
+}
+
+/// An asset of a dex that has no ANS name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedAsset {
+    pub info: AssetInfo,
+    #[serde(flatten)]
+    pub reason: UnresolvedReason,
+}
+
+impl UnresolvedAsset {
+    pub fn new(info: AssetInfo, reason: UnresolvedReason) -> Self {
+        Self { info, reason }
+    }
+}
+
+/// Why a pool of a dex is not in the output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", content = "details", rename_all = "snake_case")]
+pub enum SkipReason {
+    /// These assets of the pool have no ANS name
+    MissingAssets(Vec<AssetInfo>),
+    /// ANS has no pool type for this pair (or pool) type
+    UnsupportedPairType(String),
+    /// A reserve of the pool is below the minimum liquidity
+    LowLiquidity(Vec<PoolReserve>),
+    /// A newer pool of the dex has the same assets and type
+    DuplicateOf(UncheckedPoolAddress),
+    /// The pair has a number of assets its pool type can't have
+    MalformedPair(String),
+    /// These asset names of the pool are used for different assets and were dropped
+    ConflictingNames(Vec<String>),
+}
+
+/// Amount of an asset held by a pool.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolReserve {
+    pub info: AssetInfo,
+    pub amount: Uint128,
+}
+
+/// A pool of a dex that was left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedPool {
+    pub pool: UncheckedPoolAddress,
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+impl SkippedPool {
+    pub fn missing_assets(pool: UncheckedPoolAddress, assets: Vec<AssetInfo>) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::MissingAssets(assets),
+        }
+    }
+
+    pub fn unsupported_pair_type(pool: UncheckedPoolAddress, pair_type: impl Into<String>) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::UnsupportedPairType(pair_type.into()),
+        }
+    }
+
+    pub fn low_liquidity(pool: UncheckedPoolAddress, reserves: Vec<PoolReserve>) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::LowLiquidity(reserves),
+        }
+    }
+
+    pub fn duplicate_of(pool: UncheckedPoolAddress, kept: UncheckedPoolAddress) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::DuplicateOf(kept),
+        }
+    }
+
+    pub fn malformed_pair(pool: UncheckedPoolAddress, details: impl Into<String>) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::MalformedPair(details.into()),
+        }
+    }
+
+    pub fn conflicting_names(pool: UncheckedPoolAddress, names: Vec<String>) -> Self {
+        Self {
+            pool,
+            reason: SkipReason::ConflictingNames(names),
+        }
+    }
+}
+
+/// What a scraper couldn't turn into ANS entries, and why.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DexReport {
+    pub dex: String,
+    pub unresolved_assets: Vec<UnresolvedAsset>,
+    pub skipped_pools: Vec<SkippedPool>,
+    /// Pair types without an ANS pool type, with the number of pools that have them
+    pub unsupported_pair_types: BTreeMap<String, usize>,
+}
+
+impl DexReport {
+    pub fn new(
+        dex: impl Into<String>,
+        unresolved_assets: Vec<UnresolvedAsset>,
+        skipped_pools: Vec<SkippedPool>,
+    ) -> Self {
+        let mut unsupported_pair_types = BTreeMap::<String, usize>::new();
+        for pool in &skipped_pools {
+            if let SkipReason::UnsupportedPairType(pair_type) = &pool.reason {
+                *unsupported_pair_types.entry(pair_type.clone()).or_default() += 1;
+            }
+        }
+        Self {
+            dex: dex.into(),
+            unresolved_assets,
+            skipped_pools,
+            unsupported_pair_types,
+        }
+    }
+}
+
+/// What the overrides of a chain changed in the scrape.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OverridesReport {
+    /// Assets named by an asset override
+    pub renamed_assets: Vec<(String, AssetInfo)>,
+    /// Denied assets, and the LP tokens of their pools
+    pub denied_assets: Vec<(String, AssetInfo)>,
+    /// Denied pools, and the pools of denied assets
+    pub denied_pools: Vec<UncheckedPoolAddress>,
+}
+
+/// Everything the scrapers of a chain left out, written to `<out_dir>/<chain_id>/report.json`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScrapeReport {
+    pub chain_id: String,
+    pub meta: ScrapeMeta,
+    pub dexes: Vec<DexReport>,
+    pub overrides: OverridesReport,
+    /// Original name -> sanitized name of the names that weren't valid ANS names
+    pub sanitized_names: BTreeMap<String, String>,
+    /// Name -> denom of the names made up because no asset list has the asset, to be checked
+    pub low_confidence_names: BTreeMap<String, String>,
+    /// Assets whose decimals differ between the chain registry and the chain, written without
+    /// decimals
+    pub decimals_mismatches: Vec<DecimalsMismatch>,
+    /// Chains whose chain registry asset list was unavailable, their assets couldn't be named
+    pub failed_registry_chains: Vec<String>,
+    /// Counterparty chains the IBC assets came from over more than one channel, no channel is
+    /// written for them
+    pub channel_conflicts: Vec<ChannelConflict>,
+}
+
+impl ScrapeReport {
+    pub fn new(meta: ScrapeMeta, dexes: Vec<DexReport>) -> Self {
+        Self {
+            chain_id: meta.chain_id.clone(),
+            meta,
+            dexes,
+            overrides: OverridesReport::default(),
+            sanitized_names: BTreeMap::new(),
+            low_confidence_names: BTreeMap::new(),
+            decimals_mismatches: vec![],
+            failed_registry_chains: vec![],
+            channel_conflicts: vec![],
+        }
+    }
+
+    /// Note the names that were sanitized.
+    pub fn with_sanitized_names(mut self, sanitized_names: BTreeMap<String, String>) -> Self {
+        self.sanitized_names = sanitized_names;
+        self
+    }
+
+    /// Note the names that were made up for their denom.
+    pub fn with_low_confidence_names(
+        mut self,
+        low_confidence_names: BTreeMap<String, String>,
+    ) -> Self {
+        self.low_confidence_names = low_confidence_names;
+        self
+    }
+
+    /// Note the assets whose decimals disagree.
+    pub fn with_decimals_mismatches(mut self, decimals_mismatches: Vec<DecimalsMismatch>) -> Self {
+        self.decimals_mismatches = decimals_mismatches;
+        self
+    }
+
+    /// Note the chains whose asset list was unavailable.
+    pub fn with_failed_registry_chains(mut self, failed_registry_chains: Vec<String>) -> Self {
+        self.failed_registry_chains = failed_registry_chains;
+        self
+    }
+
+    /// Note the counterparty chains that more than one channel leads to.
+    pub fn with_channel_conflicts(mut self, channel_conflicts: Vec<ChannelConflict>) -> Self {
+        self.channel_conflicts = channel_conflicts;
+        self
+    }
+
+    /// Note what the overrides of the chain changed.
+    pub fn with_overrides(mut self, overrides: OverridesReport) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Write the report to `<out_dir>/<chain_id>/report.json`.
+    pub fn write(&self, out_dir: &Path) -> anyhow::Result<PathBuf> {
+        let chain_dir = out_dir.join(&self.chain_id);
+        std::fs::create_dir_all(&chain_dir)?;
+        let path = chain_dir.join(REPORT_FILE);
+        write_json_atomic(&path, self)?;
+        Ok(path)
+    }
+}
+
+/// A table with one row per chain and dex: the block height of the scrape, the counts of
+/// unresolved assets (by reason), skipped pools and the unsupported pair types.
+pub fn summary_table(reports: &[ScrapeReport]) -> String {
+    let header = [
+        "chain",
+        "height",
+        "dex",
+        "cw20 query",
+        "denom trace",
+        "ibc path",
+        "no registry match",
+        "unsupported",
+        "skipped pools",
+        "pair types",
+    ];
+    let mut rows = vec![header.map(str::to_string).to_vec()];
+    for report in reports {
+        for dex in &report.dexes {
+            let mut reasons = [0usize; 5];
+            for asset in &dex.unresolved_assets {
+                reasons[match asset.reason {
+                    UnresolvedReason::Cw20Query(_) => 0,
+                    UnresolvedReason::DenomTrace(_) => 1,
+                    UnresolvedReason::NonTransferPort(_) | UnresolvedReason::MultiHop(_) => 2,
+                    UnresolvedReason::NoRegistryMatch(_) | UnresolvedReason::FactoryDenom(_) => 3,
+                    UnresolvedReason::Unsupported(_) | UnresolvedReason::PoolShare(_) => 4,
+                }] += 1;
+            }
+            let height = report
+                .meta
+                .block_height
+                .map_or_else(|| "-".to_string(), |height| height.to_string());
+            let mut row = vec![report.chain_id.clone(), height, dex.dex.clone()];
+            row.extend(reasons.iter().map(ToString::to_string));
+            row.push(dex.skipped_pools.len().to_string());
+            row.push(
+                dex.unsupported_pair_types
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            );
+            rows.push(row);
+        }
+    }
+
+    let widths = (0..header.len())
+        .map(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
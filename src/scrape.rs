@@ -0,0 +1,848 @@
+//! Scrape the dexes of a network through a [`ScrapePipeline`], shared by the binaries and the
+//! library callers.
+
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cw_asset::AssetInfo;
+use futures::stream::{self, StreamExt};
+
+use crate::checkpoint::ScrapeCheckpoint;
+use crate::config::ScraperConfig;
+use crate::dexes::astroport::{AstroportScraper, PairSourceKind};
+use crate::dexes::kujira::KujiraScraper;
+use crate::dexes::osmosis::OsmosisScraper;
+use crate::dexes::whitewhale::WhiteWhaleScraper;
+use crate::dexes::wyndex::WyndexScraper;
+use crate::helpers::chain_registry::ChainRegistryOptions;
+use crate::helpers::channels::ChannelResolver;
+use crate::helpers::clients::ChainClients;
+use crate::helpers::decimals::DecimalsResolver;
+use crate::helpers::http::{fetch_stats, is_offline};
+use crate::helpers::liquidity::LiquidityFilter;
+use crate::helpers::networks::{grpc_url, parse_network, NetworkError, GRPC_URL_ENV};
+use crate::helpers::overrides::Overrides;
+use crate::helpers::verify::Cw20Verifier;
+use crate::output::{
+    write_ans_files, write_asset_metadata, write_channels, write_contracts, write_output,
+    write_pairings, write_unverified_assets, FailedNetwork, RunSummary, ScrapeSummary,
+    StreamingOutput,
+};
+use crate::pipeline::{lp_token_uses, ConflictPolicy, ScrapeMeta, ScrapeOutput, ScrapePipeline};
+use crate::report::{summary_table, ScrapeReport, SkippedPool};
+use crate::traits::dex::DexScraper;
+use crate::{AssetConflict, LazyChainRegistry, ResolutionCache, ScraperError};
+
+/// The dexes that can be scraped by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Dex {
+    Astroport,
+    Wyndex,
+    #[value(name = "whitewhale")]
+    WhiteWhale,
+    /// Kujira's FIN orderbooks and BOW vaults
+    Fin,
+    Osmosis,
+}
+
+impl Dex {
+    /// ANS prefix of the assets native to the chain, an error if the dex isn't on the chain.
+    pub fn ans_prefix(self, chain_id: &str) -> Result<&'static str, NetworkError> {
+        match self {
+            Dex::Astroport => AstroportScraper::ans_prefix(chain_id),
+            Dex::Wyndex => WyndexScraper::ans_prefix(chain_id),
+            Dex::WhiteWhale => WhiteWhaleScraper::ans_prefix(chain_id),
+            Dex::Fin => KujiraScraper::ans_prefix(chain_id),
+            Dex::Osmosis => OsmosisScraper::ans_prefix(chain_id),
+        }
+    }
+}
+
+/// What and how to scrape on a network, where to is in the [`ScraperConfig`].
+#[derive(Debug, Clone)]
+pub struct ScrapeOptions {
+    pub dexes: Vec<Dex>,
+    /// Fetch everything again instead of starting from the caches, the names, token infos and
+    /// denom traces included. The fetched entries are merged into the caches.
+    pub refresh: bool,
+    pub conflict_policy: ConflictPolicy,
+    /// Also register cw20s that are neither allow-listed nor in the chain registry
+    pub include_unverified: bool,
+    /// Drop the pools with a reserve below this amount, or below the minimum of the asset in
+    /// `<config_dir>/<chain_id>/min_reserves.json` (astroport only)
+    pub min_liquidity: Option<u128>,
+    /// Where the astroport pairs are listed from
+    pub pair_source: PairSourceKind,
+    /// Continue from the checkpoint of an interrupted scrape of the same dexes
+    pub resume: bool,
+    /// Scrape astroport in pages of this many pairs, streaming the assets and pools of every page
+    /// to the output files instead of keeping them all in memory, see [`scrape_network_paged`]
+    pub page_limit: Option<u32>,
+}
+
+impl Default for ScrapeOptions {
+    fn default() -> Self {
+        Self {
+            dexes: vec![Dex::Astroport],
+            refresh: false,
+            conflict_policy: ConflictPolicy::default(),
+            include_unverified: false,
+            min_liquidity: None,
+            pair_source: PairSourceKind::default(),
+            resume: false,
+            page_limit: None,
+        }
+    }
+}
+
+/// Scrape the dexes of `options` on `chain_id`, from the caller's own Tokio runtime.
+///
+/// Connects query-only clients to the gRPC endpoint of `GRPC_URL` or the first one cw-orch knows
+/// for the chain, and only fetches the chain registry asset lists of the chain (and of the source
+/// chains of its IBC denoms). Nothing is written to the output directory, see [`write_scrape`].
+/// Failed queries and downloads are retried with the retry policy of `config`.
+pub async fn scrape(
+    chain_id: &str,
+    config: &ScraperConfig,
+    options: &ScrapeOptions,
+) -> Result<ScrapeOutput, ScraperError> {
+    let clients = config.retry_policy().scope(connect(chain_id, None)).await?;
+    let chain_registry = LazyChainRegistry::for_chains(
+        &[config.ans_prefix(chain_id)?],
+        config.chain_registry_options(),
+    );
+    scrape_network(
+        clients,
+        chain_registry,
+        Arc::new(ResolutionCache::new()),
+        config,
+        options,
+    )
+    .await
+}
+
+/// Query-only clients of `chain_id` on `grpc_url`, `GRPC_URL` or the first endpoint cw-orch knows
+/// for the chain. When [offline](crate::set_offline), the clients only use the query cache.
+pub async fn connect(
+    chain_id: &str,
+    grpc_url: Option<&str>,
+) -> Result<Arc<ChainClients>, ScraperError> {
+    let network = parse_network(chain_id)?;
+    if is_offline() {
+        return Ok(ChainClients::offline(&network.chain_id));
+    }
+    let grpc_url = self::grpc_url(&network, grpc_url)?;
+    Ok(ChainClients::connect(&network.chain_id, &grpc_url).await?)
+}
+
+/// Scrape the dexes of `options` on the chain of `clients` with one pipeline.
+///
+/// Only queries are made, so `clients` don't need a wallet. The scrapers share the gRPC clients,
+/// the chain registry and the resolved names, which are loaded from and saved to the cache
+/// directory of `config`, as are the token infos and denom traces in the query cache of
+/// `clients`. The overrides of `<config_dir>/<chain_id>/overrides.toml` are applied. Offline
+/// clients can only scrape astroport.
+///
+/// The progress is checkpointed to `<cache_dir>/<chain_id>/checkpoint.json`, the caches are
+/// saved even when the scrape fails, so a resumed one doesn't query them again. Failed queries
+/// and downloads are retried with the retry policy of `config`.
+pub async fn scrape_network(
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    config: &ScraperConfig,
+    options: &ScrapeOptions,
+) -> Result<ScrapeOutput, ScraperError> {
+    let scrape = scrape_chain(clients, chain_registry, resolution_cache, config, options);
+    Ok(config.retry_policy().scope(scrape).await?)
+}
+
+async fn scrape_chain(
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    config: &ScraperConfig,
+    options: &ScrapeOptions,
+) -> anyhow::Result<ScrapeOutput> {
+    let chain_id = clients.chain_id.clone();
+    if clients.offline {
+        if let Some(dex) = options.dexes.iter().find(|dex| **dex != Dex::Astroport) {
+            anyhow::bail!("{:?} can't be scraped offline, only astroport can", dex);
+        }
+    }
+    // A refresh names every asset again from fresh queries, the caches are only written to
+    if !options.refresh {
+        resolution_cache.load(&config.cache_dir, &chain_id)?;
+        clients.query_cache.load(&config.cache_dir, &chain_id)?;
+    }
+    let overrides = Overrides::load(&config.config_dir, &chain_id)?;
+    resolution_cache.set_overrides(&chain_id, &overrides);
+
+    let mut scrapers: Vec<Box<dyn DexScraper>> = vec![];
+    for dex in &options.dexes {
+        let scraper: Box<dyn DexScraper> = match dex {
+            Dex::Astroport => {
+                let mut astroport =
+                    AstroportScraper::new(clients.clone(), chain_registry.clone(), config)
+                        .await?
+                        .with_resolution_cache(resolution_cache.clone())
+                        .with_refresh(options.refresh);
+                if options.pair_source == PairSourceKind::Api {
+                    let api_pairs = astroport.api_pairs(config)?;
+                    astroport = astroport.with_pair_source(api_pairs);
+                }
+                if let Some(min_liquidity) = options.min_liquidity {
+                    astroport = astroport.with_liquidity_filter(LiquidityFilter::load(
+                        &config.config_dir,
+                        &chain_id,
+                        min_liquidity,
+                    )?);
+                }
+                Box::new(astroport)
+            }
+            Dex::Wyndex => Box::new(
+                WyndexScraper::new(clients.clone(), chain_registry.clone(), config)?
+                    .with_resolution_cache(resolution_cache.clone()),
+            ),
+            Dex::WhiteWhale => Box::new(
+                WhiteWhaleScraper::new(clients.clone(), chain_registry.clone(), config)?
+                    .with_resolution_cache(resolution_cache.clone()),
+            ),
+            Dex::Fin => Box::new(
+                KujiraScraper::new(clients.clone(), chain_registry.clone(), config)?
+                    .with_resolution_cache(resolution_cache.clone()),
+            ),
+            Dex::Osmosis => Box::new(
+                OsmosisScraper::new(clients.clone(), chain_registry.clone(), config)?
+                    .with_resolution_cache(resolution_cache.clone()),
+            ),
+        };
+        scrapers.push(scraper);
+    }
+
+    let mut pipeline = ScrapePipeline::new(scrapers, config)
+        .with_conflict_policy(options.conflict_policy)
+        .with_clients(clients.clone())
+        .with_overrides(overrides)
+        .with_resolution_cache(resolution_cache.clone())
+        .with_checkpoint(
+            ScrapeCheckpoint::path(&config.cache_dir, &chain_id),
+            options.resume,
+        );
+    // The registry is only fetched if an asset needs it, a cw20 that isn't allow-listed or a
+    // native denom
+    if !options.dexes.is_empty() {
+        let chain_name = config.ans_prefix(&chain_id)?;
+        if !options.include_unverified {
+            let verifier = Cw20Verifier::load(&config.config_dir, &chain_id)?
+                .with_registry_cw20s(chain_registry.clone(), chain_name);
+            pipeline = pipeline.with_cw20_verifier(verifier);
+        }
+        pipeline = pipeline
+            .with_decimals_resolver(DecimalsResolver::new(
+                chain_registry.clone(),
+                chain_name,
+                config.query_concurrency,
+            ))
+            .with_channel_resolver(ChannelResolver::new(
+                chain_registry.clone(),
+                config.query_concurrency,
+            ));
+    }
+    let output = pipeline.run().await;
+
+    resolution_cache.save(&config.cache_dir, &chain_id)?;
+    clients.query_cache.save(&config.cache_dir, &chain_id)?;
+    let mut output = output?;
+    output.failed_registry_chains = chain_registry.failed_chains();
+    Ok(output)
+}
+
+/// Scrape astroport on the chain of `clients` in pages of `page_limit` pairs and write the output
+/// to the output directory of `config`.
+///
+/// The assets and pools of every page are streamed to the output files, only the resolved names
+/// are kept in memory, see [`write_paged_scrape`]. Paged scrapes don't go through the
+/// [`ScrapePipeline`]: the cw20s aren't verified, the decimals, channels and pairings aren't
+/// resolved and the chain can't have overrides. That's why they need
+/// [`ConflictPolicy::Drop`] and `include_unverified`.
+pub async fn scrape_network_paged(
+    clients: Arc<ChainClients>,
+    chain_registry: LazyChainRegistry,
+    resolution_cache: Arc<ResolutionCache>,
+    config: &ScraperConfig,
+    options: &ScrapeOptions,
+    page_limit: u32,
+) -> anyhow::Result<(ScrapeSummary, ScrapeReport)> {
+    let chain_id = clients.chain_id.clone();
+    // Denied entries are dropped by the pipeline, which paged scrapes don't go through
+    if !Overrides::load(&config.config_dir, &chain_id)?.is_empty() {
+        anyhow::bail!("{}: overrides are not supported by paged scrapes", chain_id);
+    }
+    if !options.refresh {
+        resolution_cache.load(&config.cache_dir, &chain_id)?;
+        clients.query_cache.load(&config.cache_dir, &chain_id)?;
+    }
+
+    let scrape = async {
+        let mut astroport = AstroportScraper::new(clients.clone(), chain_registry, config)
+            .await?
+            .with_resolution_cache(resolution_cache.clone())
+            .with_refresh(options.refresh);
+        if let Some(min_liquidity) = options.min_liquidity {
+            astroport = astroport.with_liquidity_filter(LiquidityFilter::load(
+                &config.config_dir,
+                &chain_id,
+                min_liquidity,
+            )?);
+        }
+        let meta = ScrapeMeta::query(&clients).await?;
+        let (written_to, summary) =
+            write_paged_scrape(&mut astroport, &config.out_dir, &meta, page_limit).await?;
+        let report = ScrapeReport::new(meta, vec![astroport.report()]);
+        report.write(&config.out_dir)?;
+        log::info!("{}: output written to {}", chain_id, written_to.display());
+        anyhow::Ok((summary, report))
+    };
+    let result = config.retry_policy().scope(scrape).await;
+
+    resolution_cache.save(&config.cache_dir, &chain_id)?;
+    clients.query_cache.save(&config.cache_dir, &chain_id)?;
+    result
+}
+
+/// Scrape `astroport` page by page, streaming the assets and pools of every page to the asset and
+/// pool files of `<out_dir>/<chain_id>/`, and write the summary.
+///
+/// Earlier pages are already written, so conflicts are handled like [`ConflictPolicy::Drop`] as
+/// far as that's possible: the first asset to claim a name keeps it, the others are reported as
+/// conflicts and the pools and LP tokens of the page that use the name are dropped and reported
+/// as skipped pools.
+pub async fn write_paged_scrape(
+    astroport: &mut AstroportScraper,
+    out_dir: &Path,
+    meta: &ScrapeMeta,
+    page_limit: u32,
+) -> anyhow::Result<(PathBuf, ScrapeSummary)> {
+    let mut output = StreamingOutput::create(out_dir, meta)?;
+    let mut emitted_names = HashMap::<String, AssetInfo>::new();
+    let mut asset_conflicts = Vec::<AssetConflict>::new();
+    let mut dropped_pools = Vec::<SkippedPool>::new();
+
+    astroport
+        .scrape_paged(page_limit, |assets, pools| {
+            let mut page_names = HashMap::<&str, &AssetInfo>::new();
+            let mut conflicting = BTreeSet::<&str>::new();
+            let mut new_assets = vec![];
+            for (name, info) in assets {
+                let claimed = emitted_names
+                    .get(name)
+                    .or_else(|| page_names.get(name.as_str()).copied());
+                match claimed {
+                    None => {
+                        page_names.insert(name, info);
+                        new_assets.push((name.clone(), info.clone()));
+                    }
+                    Some(existing) if existing == info => {}
+                    Some(existing) => {
+                        log::warn!("Asset name {} maps to {} and {}", name, existing, info);
+                        asset_conflicts.push(AssetConflict {
+                            name: name.clone(),
+                            infos: vec![existing.clone(), info.clone()],
+                        });
+                        conflicting.insert(name);
+                    }
+                }
+            }
+            new_assets.retain(|(name, _)| !lp_token_uses(name, &conflicting));
+            let mut kept_pools = Vec::with_capacity(pools.len());
+            for (pool, metadata) in pools {
+                let names: Vec<String> = metadata
+                    .assets
+                    .iter()
+                    .map(ToString::to_string)
+                    .filter(|asset| conflicting.contains(asset.as_str()))
+                    .collect();
+                if names.is_empty() {
+                    kept_pools.push((pool.clone(), metadata.clone()));
+                } else {
+                    log::warn!("Dropping pool {:?}, it uses a conflicting asset name", pool);
+                    dropped_pools.push(SkippedPool::conflicting_names(pool.clone(), names));
+                }
+            }
+
+            for (name, info) in &new_assets {
+                emitted_names.insert(name.clone(), info.clone());
+            }
+            output.append_assets(&new_assets)?;
+            output.append_pools(&kept_pools)
+        })
+        .await?;
+    astroport.skip_pools(dropped_pools);
+
+    output.finish(ScrapeSummary {
+        chain_id: meta.chain_id.clone(),
+        meta: meta.clone(),
+        not_found_assets: astroport
+            .not_found_assets()
+            .iter()
+            .map(|asset| asset.info.clone())
+            .collect(),
+        skipped_pools: astroport
+            .skipped_pools()
+            .iter()
+            .map(|pool| pool.pool.clone())
+            .collect(),
+        asset_conflicts,
+        ..Default::default()
+    })
+}
+
+/// Fail on the options a paged scrape doesn't support.
+fn check_paged(options: &ScrapeOptions, ans_dir: Option<&Path>) -> anyhow::Result<()> {
+    if options.dexes != [Dex::Astroport] {
+        anyhow::bail!("only astroport can be scraped page by page");
+    }
+    if is_offline() {
+        anyhow::bail!("paged scrapes query the factory, they can't run offline");
+    }
+    if options.resume || options.pair_source != PairSourceKind::Onchain {
+        anyhow::bail!("paged scrapes paginate the factory from the start, without checkpoints");
+    }
+    if ans_dir.is_some() {
+        anyhow::bail!("the output of paged scrapes can't be merged into the ANS files");
+    }
+    // Pages are written as they come, so conflicting names can only be dropped and the cw20s of
+    // a page can't wait for the verification of the whole scrape
+    if options.conflict_policy != ConflictPolicy::Drop {
+        anyhow::bail!("paged scrapes can't fail on asset conflicts, use --drop-asset-conflicts");
+    }
+    if !options.include_unverified {
+        anyhow::bail!("paged scrapes don't verify the cw20s, use --include-unverified");
+    }
+    Ok(())
+}
+
+/// Scrape the dexes of `options` on every network of `network_ids`, at most `parallel_networks`
+/// at the same time, and write the output of each with [`write_scrape`], then the [`RunSummary`]
+/// of all of them to the output directory.
+///
+/// The networks are queried through [`connect`], on `grpc_url` if there's only one. They share the
+/// resolved names and the chain registry, which only fetches the asset lists of their chains. With
+/// `ans_dir` the outputs are also merged into the ANS files of abstract. A failing network doesn't
+/// stop the others, it ends up in the failed networks of the summary. Unknown network ids fail
+/// before anything is scraped. Failed queries and downloads are retried with the retry policy of
+/// `config`.
+pub async fn scrape_networks(
+    network_ids: &[String],
+    parallel_networks: usize,
+    grpc_url: Option<&str>,
+    registry_options: ChainRegistryOptions,
+    config: &ScraperConfig,
+    options: &ScrapeOptions,
+    ans_dir: Option<&Path>,
+) -> anyhow::Result<RunSummary> {
+    if network_ids.is_empty() {
+        anyhow::bail!("no network to scrape");
+    }
+    for network_id in network_ids {
+        parse_network(network_id)?;
+    }
+    if options.page_limit.is_some() {
+        check_paged(options, ans_dir)?;
+    }
+    // A gRPC endpoint belongs to a single chain
+    let grpc_override = grpc_url.is_some() || std::env::var(GRPC_URL_ENV).is_ok();
+    if grpc_override && network_ids.len() > 1 {
+        anyhow::bail!(
+            "--grpc-url and {} can only be used when scraping a single network",
+            GRPC_URL_ENV
+        );
+    }
+
+    let registry_chains: BTreeSet<&str> = network_ids
+        .iter()
+        .filter_map(|network_id| config.ans_prefix(network_id).ok())
+        .collect();
+    let chain_registry = LazyChainRegistry::for_chains(
+        &registry_chains.into_iter().collect::<Vec<_>>(),
+        registry_options,
+    );
+    let resolution_cache = Arc::new(ResolutionCache::new());
+
+    let (shared_registry, shared_cache) = (&chain_registry, &resolution_cache);
+    let scrapes = for_each_network(
+        network_ids,
+        parallel_networks,
+        move |network_id| async move {
+            log::info!("{}: scraping", network_id);
+            let clients = connect(&network_id, grpc_url).await?;
+            if let Some(page_limit) = options.page_limit {
+                return scrape_network_paged(
+                    clients,
+                    shared_registry.clone(),
+                    shared_cache.clone(),
+                    config,
+                    options,
+                    page_limit,
+                )
+                .await;
+            }
+            let output = scrape_network(
+                clients,
+                shared_registry.clone(),
+                shared_cache.clone(),
+                config,
+                options,
+            )
+            .await?;
+            if let Some(ans_dir) = ans_dir {
+                write_ans_files(&network_id, ans_dir, &output)?;
+            }
+            write_scrape(&config.out_dir, &network_id, &output)
+        },
+    );
+    let results = config.retry_policy().scope(scrapes).await;
+
+    let mut run_summary = RunSummary::default();
+    let mut reports = vec![];
+    for (network_id, result) in results {
+        match result {
+            Ok((summary, report)) => {
+                run_summary.networks.push(summary);
+                reports.push(report);
+            }
+            Err(err) => {
+                log::error!("{}: failed: {:#}", network_id, err);
+                run_summary.failed_networks.push(FailedNetwork {
+                    network_id,
+                    error: format!("{:#}", err),
+                });
+            }
+        }
+    }
+
+    if !chain_registry.is_initialized() {
+        log::info!("No native denoms to resolve, the chain registry was not fetched");
+    }
+    let (hits, misses) = resolution_cache.stats();
+    log::info!("Asset name cache: {} hits, {} misses", hits, misses);
+    let fetches = fetch_stats();
+    log::info!(
+        "HTTP: {} requests, {} deduplicated, {} throttled for {:?}",
+        fetches.requests,
+        fetches.deduplicated,
+        fetches.throttle_waits,
+        fetches.throttled_for
+    );
+    if !reports.is_empty() {
+        log::info!(
+            "Unresolved assets and skipped pools:\n{}",
+            summary_table(&reports)
+        );
+    }
+    run_summary.log();
+    let path = run_summary.write(&config.out_dir)?;
+    log::info!("Run summary written to {}", path.display());
+    Ok(run_summary)
+}
+
+/// Run `scrape` on every network, at most `parallel_networks` at the same time, the results in
+/// the order of `network_ids`.
+async fn for_each_network<T, F, Fut>(
+    network_ids: &[String],
+    parallel_networks: usize,
+    scrape: F,
+) -> Vec<(String, anyhow::Result<T>)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    stream::iter(network_ids)
+        .map(|network_id| {
+            let result = scrape(network_id.clone());
+            async move { (network_id.clone(), result.await) }
+        })
+        .buffered(parallel_networks.max(1))
+        .collect()
+        .await
+}
+
+/// Write the assets, their decimals, pools, pairings, summary, unverified cw20s and report of a
+/// scrape to `<out_dir>/<chain_id>/`.
+pub fn write_scrape(
+    out_dir: &Path,
+    chain_id: &str,
+    output: &ScrapeOutput,
+) -> anyhow::Result<(ScrapeSummary, ScrapeReport)> {
+    let summary = ScrapeSummary::new(chain_id, output);
+    let written_to = write_output(out_dir, &output.assets, &output.pools, &summary)?;
+    write_contracts(out_dir, &output.meta, &output.contracts)?;
+    write_channels(out_dir, &output.meta, &output.channels)?;
+    write_pairings(out_dir, &output.meta, &output.pairings)?;
+    write_asset_metadata(out_dir, &output.meta, &output.asset_metadata)?;
+    if !output.unverified_assets.is_empty() {
+        write_unverified_assets(out_dir, &output.meta, &output.unverified_assets)?;
+    }
+    let report = ScrapeReport::new(output.meta.clone(), output.reports.clone())
+        .with_overrides(output.overrides.clone())
+        .with_sanitized_names(output.sanitized_names.clone())
+        .with_low_confidence_names(output.low_confidence_names.clone())
+        .with_decimals_mismatches(output.decimals_mismatches.clone())
+        .with_failed_registry_chains(output.failed_registry_chains.clone())
+        .with_channel_conflicts(output.channel_conflicts.clone());
+    report.write(out_dir)?;
+
+    log::info!("{}: output written to {}", chain_id, written_to.display());
+    Ok((summary, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use astroport::asset::{AssetInfo as AstroportAssetInfo, PairInfo};
+    use astroport::factory::PairType;
+    use async_trait::async_trait;
+    use cosmwasm_std::Addr;
+
+    use super::*;
+    use crate::output::{ASSETS_FILE, POOLS_FILE};
+    use crate::report::SkipReason;
+    use crate::traits::deployment::StaticDeployment;
+    use crate::traits::pairs::PairSource;
+    use crate::traits::resolve::KnownNames;
+
+    /// A factory with a pair of every two of `denoms`, generated page by page so the pairs are
+    /// never all in memory. Counts the pages and the largest one it served.
+    struct GeneratedPairs {
+        denoms: usize,
+        pages: Arc<AtomicUsize>,
+        largest_page: Arc<AtomicUsize>,
+    }
+
+    impl GeneratedPairs {
+        fn denom(idx: usize) -> String {
+            format!("udenom{:03}", idx)
+        }
+
+        /// Index of the denom of the native asset info.
+        fn denom_idx(info: &AstroportAssetInfo) -> usize {
+            match info {
+                AstroportAssetInfo::NativeToken { denom } => {
+                    denom["udenom".len()..].parse().unwrap()
+                }
+                AstroportAssetInfo::Token { .. } => unreachable!("only natives are generated"),
+            }
+        }
+
+        fn pair(&self, first: usize, second: usize) -> PairInfo {
+            let native = |idx| AstroportAssetInfo::NativeToken {
+                denom: Self::denom(idx),
+            };
+            PairInfo {
+                asset_infos: vec![native(first), native(second)],
+                contract_addr: Addr::unchecked(format!("pair{}_{}", first, second)),
+                liquidity_token: Addr::unchecked(format!("lp{}_{}", first, second)),
+                pair_type: PairType::Xyk {},
+            }
+        }
+    }
+
+    #[async_trait]
+    impl PairSource for GeneratedPairs {
+        async fn pairs_after(
+            &self,
+            _start_after: Option<Vec<AstroportAssetInfo>>,
+        ) -> anyhow::Result<Vec<PairInfo>> {
+            anyhow::bail!("a paged scrape only asks for pages")
+        }
+
+        async fn page_after(
+            &self,
+            start_after: Option<Vec<AstroportAssetInfo>>,
+            limit: u32,
+        ) -> anyhow::Result<Vec<PairInfo>> {
+            let (mut first, mut second) = match start_after {
+                Some(assets) => (Self::denom_idx(&assets[0]), Self::denom_idx(&assets[1]) + 1),
+                None => (0, 1),
+            };
+            let mut page = vec![];
+            while page.len() < limit as usize && first + 1 < self.denoms {
+                if second == self.denoms {
+                    first += 1;
+                    second = first + 1;
+                    continue;
+                }
+                page.push(self.pair(first, second));
+                second += 1;
+            }
+            self.pages.fetch_add(1, Ordering::SeqCst);
+            self.largest_page.fetch_max(page.len(), Ordering::SeqCst);
+            Ok(page)
+        }
+    }
+
+    fn network_ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn a_failing_network_does_not_stop_the_others() {
+        let networks = network_ids(&["phoenix-1", "juno-1", "osmosis-1"]);
+        let results = for_each_network(&networks, 1, |network_id| async move {
+            if network_id == "juno-1" {
+                anyhow::bail!("node unavailable");
+            }
+            Ok(network_id.len())
+        })
+        .await;
+
+        let outcomes: Vec<(&str, bool)> = results
+            .iter()
+            .map(|(network_id, result)| (network_id.as_str(), result.is_ok()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [("phoenix-1", true), ("juno-1", false), ("osmosis-1", true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn networks_run_in_parallel_up_to_the_limit_in_order() {
+        let networks = network_ids(&["phoenix-1", "neutron-1", "juno-1", "osmosis-1", "kaiyo-1"]);
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let (running, max_running) = (&running, &max_running);
+
+        let results = for_each_network(&networks, 2, |network_id| async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now, Ordering::SeqCst);
+            // The first network finishes last, the results keep the order of the ids anyway
+            let delay = if network_id == "phoenix-1" { 30 } else { 10 };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok(network_id)
+        })
+        .await;
+
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+        let scraped: Vec<String> = results
+            .into_iter()
+            .map(|(_, result)| result.unwrap())
+            .collect();
+        assert_eq!(scraped, networks);
+    }
+
+    #[tokio::test]
+    async fn zero_parallel_networks_still_scrapes_them_one_by_one() {
+        let networks = network_ids(&["phoenix-1", "juno-1"]);
+        let results =
+            for_each_network(&networks, 0, |network_id| async move { Ok(network_id) }).await;
+        assert_eq!(results.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn a_large_factory_is_streamed_page_by_page() {
+        // 317 denoms have 50_086 pairs
+        let denoms = 317;
+        let pair_count = denoms * (denoms - 1) / 2;
+        let page_limit = 1_000;
+        let pages = Arc::new(AtomicUsize::new(0));
+        let largest_page = Arc::new(AtomicUsize::new(0));
+        let pair_source = GeneratedPairs {
+            denoms,
+            pages: pages.clone(),
+            largest_page: largest_page.clone(),
+        };
+        let names = KnownNames::new((0..denoms).map(|idx| {
+            let denom = GeneratedPairs::denom(idx);
+            (format!("terra2>{}", &denom[1..]), AssetInfo::native(denom))
+        }));
+        let mut astroport = AstroportScraper::from_sources(
+            "phoenix-1",
+            &StaticDeployment::new([("factory_address", "factory")]),
+            pair_source,
+            names,
+            &ScraperConfig::default_for("phoenix-1"),
+        )
+        .await
+        .unwrap();
+
+        let out_dir =
+            std::env::temp_dir().join(format!("ans-scraper-paged-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let meta = ScrapeMeta::new("phoenix-1", Some(1));
+        let (chain_dir, summary) = write_paged_scrape(&mut astroport, &out_dir, &meta, page_limit)
+            .await
+            .unwrap();
+
+        // every page was resolved and written before the next one was asked for
+        assert_eq!(largest_page.load(Ordering::SeqCst), page_limit as usize);
+        assert!(pages.load(Ordering::SeqCst) > pair_count / page_limit as usize);
+        // the assets once, plus the LP token of every pair
+        assert_eq!(summary.assets, denoms + pair_count);
+        assert_eq!(summary.pools, pair_count);
+        assert!(summary.not_found_assets.is_empty());
+        assert!(summary.asset_conflicts.is_empty());
+
+        let written = |file: &str| -> usize {
+            let entries: serde_json::Value =
+                serde_json::from_slice(&std::fs::read(chain_dir.join(file)).unwrap()).unwrap();
+            entries["entries"].as_array().unwrap().len()
+        };
+        assert_eq!(written(ASSETS_FILE), denoms + pair_count);
+        assert_eq!(written(POOLS_FILE), pair_count);
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pools_of_a_conflicting_name_are_dropped_from_the_page() {
+        let denoms = 4;
+        let pair_source = GeneratedPairs {
+            denoms,
+            pages: Arc::default(),
+            largest_page: Arc::default(),
+        };
+        // the last denom claims the name of the first one
+        let names = KnownNames::new((0..denoms).map(|idx| {
+            let name_idx = if idx == denoms - 1 { 0 } else { idx };
+            let name = format!("terra2>{}", &GeneratedPairs::denom(name_idx)[1..]);
+            (name, AssetInfo::native(GeneratedPairs::denom(idx)))
+        }));
+        let mut astroport = AstroportScraper::from_sources(
+            "phoenix-1",
+            &StaticDeployment::new([("factory_address", "factory")]),
+            pair_source,
+            names,
+            &ScraperConfig::default_for("phoenix-1"),
+        )
+        .await
+        .unwrap();
+
+        let out_dir =
+            std::env::temp_dir().join(format!("ans-scraper-paged-conflict-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let meta = ScrapeMeta::new("phoenix-1", Some(1));
+        let (_, summary) = write_paged_scrape(&mut astroport, &out_dir, &meta, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.asset_conflicts.len(), 1);
+        assert_eq!(summary.asset_conflicts[0].name, "terra2>denom000");
+        // only the pair of the second and third denom doesn't use the conflicting name
+        assert_eq!(summary.pools, 1);
+        assert_eq!(summary.skipped_pools.len(), 5);
+        assert!(astroport
+            .report()
+            .skipped_pools
+            .iter()
+            .all(|pool| matches!(pool.reason, SkipReason::ConflictingNames(_))));
+        // the three names and the LP token of the kept pool
+        assert_eq!(summary.assets, 4);
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! Builders for chain registry fixtures, for scraper authors writing resolution tests.
+//!
+//! They are always available to the tests of this crate, other crates enable the `test-utils`
+//! feature to use them:
+//!
+//! ```ignore
+//! let osmosis = AssetListBuilder::chain("osmosis")
+//!     .asset("OSMO", "uosmo", 6)
+//!     .build();
+//! let atom = DenomTraceFixture::transfer("channel-0", "uatom");
+//! let terra = AssetListBuilder::chain("terra2")
+//!     .ibc_asset("ATOM", &atom, "cosmoshub", 6)
+//!     .build();
+//! let registry = registry_from_lists(vec![osmosis, terra]);
+//! ```
+//!
+//! Nothing here touches the disk or the network.
+//!
+//! The submodules hold the pairs, configs and scrapers the tests of each dex share. They build
+//! the scrapers' internal state, so they are only available to the tests of this crate.
+
+use ibc_chain_registry::asset_list::AssetList as ChainRegistryAssetList;
+use serde_json::{json, Value};
+
+use crate::helpers::denoms::{DenomTrace, IBC_DENOM_PREFIX};
+use crate::ChainRegistry;
+
+#[cfg(test)]
+pub(crate) mod astroport;
+#[cfg(test)]
+pub(crate) mod kujira;
+#[cfg(test)]
+pub(crate) mod osmosis;
+#[cfg(test)]
+pub(crate) mod terraswap;
+#[cfg(test)]
+pub(crate) mod whitewhale;
+#[cfg(test)]
+pub(crate) mod wyndex;
+
+/// Builds an asset list the way it appears in the chain registry.
+pub struct AssetListBuilder {
+    chain_name: String,
+    assets: Vec<Value>,
+}
+
+impl AssetListBuilder {
+    pub fn chain(chain_name: &str) -> Self {
+        Self {
+            chain_name: chain_name.to_string(),
+            assets: vec![],
+        }
+    }
+
+    /// Add a native asset with its base denom and the display denom at `exponent`.
+    pub fn asset(mut self, symbol: &str, base: &str, exponent: u32) -> Self {
+        self.assets.push(asset_json(symbol, base, exponent, None));
+        self
+    }
+
+    /// Add an asset that was transferred over IBC from `counterparty_chain`.
+    pub fn ibc_asset(
+        mut self,
+        symbol: &str,
+        trace: &DenomTraceFixture,
+        counterparty_chain: &str,
+        exponent: u32,
+    ) -> Self {
+        let ibc = json!({
+            "source_channel": trace.channel,
+            "dst_channel": trace.channel,
+            "source_denom": trace.base_denom,
+        });
+        let mut asset = asset_json(symbol, &trace.ibc_denom(), exponent, Some(ibc));
+        asset["traces"] = json!([{
+            "type": "ibc",
+            "counterparty": {
+                "chain_name": counterparty_chain,
+                "base_denom": trace.base_denom,
+                "channel_id": trace.channel,
+            },
+            "chain": { "channel_id": trace.channel },
+        }]);
+        self.assets.push(asset);
+        self
+    }
+
+    /// Add an already built raw asset, e.g. to test schema drift.
+    pub fn raw_asset(mut self, asset: Value) -> Self {
+        self.assets.push(asset);
+        self
+    }
+
+    /// The asset list as raw registry JSON.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "$schema": "../assetlist.schema.json",
+            "chain_name": self.chain_name,
+            "assets": self.assets,
+        })
+    }
+
+    /// Parse the list like a fetched one. Panics if none of the assets deserialize.
+    pub fn build(self) -> ChainRegistryAssetList {
+        ChainRegistry::parse_asset_list(&self.chain_name, self.to_json())
+            .expect("valid asset list fixture")
+            .0
+    }
+}
+
+fn asset_json(symbol: &str, base: &str, exponent: u32, ibc: Option<Value>) -> Value {
+    let display = symbol.to_lowercase();
+    let mut asset = json!({
+        "description": format!("{} fixture", symbol),
+        "denom_units": [
+            { "denom": base, "exponent": 0 },
+            { "denom": display, "exponent": exponent },
+        ],
+        "base": base,
+        "name": symbol,
+        "display": display,
+        "symbol": symbol,
+    });
+    if let Some(ibc) = ibc {
+        asset["ibc"] = ibc;
+    }
+    asset
+}
+
+/// A single hop `transfer` denom trace and the IBC denom it hashes to.
+pub struct DenomTraceFixture {
+    pub channel: String,
+    pub base_denom: String,
+}
+
+impl DenomTraceFixture {
+    pub fn transfer(channel: &str, base_denom: &str) -> Self {
+        Self {
+            channel: channel.to_string(),
+            base_denom: base_denom.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> String {
+        format!("transfer/{}", self.channel)
+    }
+
+    /// The denom trace as returned by the node.
+    pub fn trace(&self) -> DenomTrace {
+        DenomTrace::new(&self.path(), &self.base_denom)
+    }
+
+    /// Upper case hex sha256 of `path/base_denom`, as used in IBC voucher denoms.
+    pub fn hash(&self) -> String {
+        sha256::digest(format!("{}/{}", self.path(), self.base_denom)).to_uppercase()
+    }
+
+    /// The `ibc/<HASH>` voucher denom.
+    pub fn ibc_denom(&self) -> String {
+        format!("{}{}", IBC_DENOM_PREFIX, self.hash())
+    }
+}
+
+/// Assemble a registry from in-memory asset lists.
+pub fn registry_from_lists(asset_lists: Vec<ChainRegistryAssetList>) -> ChainRegistry {
+    ChainRegistry::from_asset_lists(asset_lists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denom_trace_fixtures_hash_like_ibc() {
+        let atom = DenomTraceFixture::transfer("channel-0", "uatom");
+
+        // ATOM on osmosis
+        assert_eq!(
+            atom.ibc_denom(),
+            "ibc/27394FB092D2ECCD56123C74F36E4C1F926001CEADA9CA97EA622B25F41E5EB2"
+        );
+        assert_eq!(atom.trace(), DenomTrace::new("transfer/channel-0", "uatom"));
+    }
+
+    #[test]
+    fn built_asset_lists_are_found_by_denom() {
+        let atom = DenomTraceFixture::transfer("channel-0", "uatom");
+        let osmosis = AssetListBuilder::chain("osmosis")
+            .asset("OSMO", "uosmo", 6)
+            .ibc_asset("ATOM", &atom, "cosmoshub", 6)
+            .build();
+        assert_eq!(osmosis.assets.len(), 2);
+
+        let registry = registry_from_lists(vec![osmosis]);
+
+        let (asset_list, asset) = registry.find_by_denom("uosmo").unwrap();
+        assert_eq!(asset_list.chain_name, "osmosis");
+        assert_eq!(asset.symbol, "OSMO");
+        assert_eq!(
+            registry.asset_by_denom(&atom.ibc_denom()).unwrap().symbol,
+            "ATOM"
+        );
+        assert!(registry.find_by_denom("uatom").is_none());
+    }
+}
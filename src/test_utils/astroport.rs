@@ -0,0 +1,102 @@
+//! Pairs and scrapers of astroport deployments that don't query a chain.
+
+use std::collections::HashMap;
+
+use astroport::asset::{AssetInfo as AstroportAssetInfo, PairInfo};
+use astroport::factory::PairType;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+
+use crate::config::ScraperConfig;
+use crate::dexes::astroport::{
+    pairs_to_pools, AssembledPools, AstroportScraper, ASTRO_TOKEN_KEY, FACTORY_KEY,
+};
+use crate::traits::deployment::StaticDeployment;
+use crate::traits::pairs::StaticPairs;
+use crate::traits::resolve::{DenomResolver, KnownNames};
+
+pub fn native(denom: &str) -> AstroportAssetInfo {
+    AstroportAssetInfo::NativeToken {
+        denom: denom.to_string(),
+    }
+}
+
+pub fn token(contract_addr: &str) -> AstroportAssetInfo {
+    AstroportAssetInfo::Token {
+        contract_addr: Addr::unchecked(contract_addr),
+    }
+}
+
+/// A xyk pair of `asset_infos`, its LP token is `<contract_addr>_lp`.
+pub fn pair(contract_addr: &str, asset_infos: Vec<AstroportAssetInfo>) -> PairInfo {
+    PairInfo {
+        asset_infos,
+        contract_addr: Addr::unchecked(contract_addr),
+        liquidity_token: Addr::unchecked(format!("{}_lp", contract_addr)),
+        pair_type: PairType::Xyk {},
+    }
+}
+
+/// Names of LUNA and ASTRO.
+pub fn names() -> HashMap<AstroportAssetInfo, String> {
+    HashMap::from([
+        (native("uluna"), "terra2>luna".to_string()),
+        (token("astro_token"), "terra2>astro".to_string()),
+    ])
+}
+
+/// The pools of `pairs` with the [`names`] of their assets.
+pub fn assemble(pairs: Vec<PairInfo>) -> AssembledPools {
+    pairs_to_pools(pairs, &names(), &HashMap::new())
+}
+
+/// A scraper of a static deployment and static pairs, no chain is queried: a xyk, a stable and a
+/// pair with an asset that has no name.
+pub async fn static_scraper() -> AstroportScraper {
+    let mut stable = pair("stable", vec![native("uluna"), native("ibc/usdc")]);
+    stable.pair_type = PairType::Stable {};
+    scraper_of(vec![
+        pair("xyk", vec![native("uluna"), token("astro_token")]),
+        stable,
+        pair("unnamed", vec![native("uluna"), token("unknown_token")]),
+    ])
+    .await
+}
+
+/// A scraper of the static `pairs` of a static deployment, LUNA, ASTRO and USDC have names.
+pub async fn scraper_of(pairs: Vec<PairInfo>) -> AstroportScraper {
+    scraper_naming(
+        pairs,
+        KnownNames::new([
+            ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+            (
+                "terra2>astro".to_string(),
+                AssetInfo::cw20(Addr::unchecked("astro_token")),
+            ),
+            ("terra2>usdc".to_string(), AssetInfo::native("ibc/usdc")),
+        ]),
+    )
+    .await
+}
+
+/// A scraper of the static `pairs` of a static deployment, their assets are named by `names`.
+pub async fn scraper_naming(
+    pairs: Vec<PairInfo>,
+    names: impl DenomResolver + Send + Sync + 'static,
+) -> AstroportScraper {
+    let deployment = StaticDeployment::new([
+        (FACTORY_KEY, "factory"),
+        ("router_address", "router"),
+        (ASTRO_TOKEN_KEY, "astro_token"),
+    ]);
+
+    AstroportScraper::from_sources(
+        "phoenix-1",
+        &deployment,
+        StaticPairs::new(pairs),
+        names,
+        &ScraperConfig::default_for("phoenix-1"),
+    )
+    .await
+    .unwrap()
+}
@@ -0,0 +1,47 @@
+//! Markets, configs and scrapers of kujira deployments.
+
+use cosmwasm_std::Addr;
+
+use crate::config::ScraperConfig;
+use crate::dexes::kujira::{BowVault, FinMarket, KujiraDeployment, KujiraScraper};
+use crate::helpers::clients::ChainClients;
+use crate::test_utils::{registry_from_lists, AssetListBuilder};
+use crate::LazyChainRegistry;
+
+pub fn market(contract: &str, denoms: &[&str]) -> FinMarket {
+    FinMarket {
+        contract: Addr::unchecked(contract),
+        denoms: denoms.iter().map(|denom| denom.to_string()).collect(),
+    }
+}
+
+/// Config with a kujira deployment of FIN code id 1 on kaiyo-1, its markets are constant product
+/// pools.
+pub fn config() -> ScraperConfig {
+    let mut config = ScraperConfig::default_for("kaiyo-1");
+    config.kujira_deployments.insert(
+        "kaiyo-1".to_string(),
+        KujiraDeployment {
+            fin_code_ids: vec![1],
+            bow_code_ids: vec![],
+            fin_pool_type: Some("constant_product".to_string()),
+        },
+    );
+    config
+}
+
+/// A scraper of `markets` and `vaults` on an offline kaiyo-1, KUJI and USK are in the chain
+/// registry.
+pub fn scraper_of(markets: Vec<FinMarket>, vaults: Vec<BowVault>) -> KujiraScraper {
+    let clients = ChainClients::offline("kaiyo-1");
+    let chain_registry = registry_from_lists(vec![AssetListBuilder::chain("kujira")
+        .asset("KUJI", "ukuji", 6)
+        .asset("USK", "uusk", 6)
+        .build()]);
+
+    let mut scraper =
+        KujiraScraper::new(clients, LazyChainRegistry::from(chain_registry), &config()).unwrap();
+    scraper.loaded_markets = markets;
+    scraper.loaded_vaults = vaults;
+    scraper
+}
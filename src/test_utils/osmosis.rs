@@ -0,0 +1,35 @@
+//! Pools and scrapers of osmosis.
+
+use abstract_core::objects::PoolType;
+
+use crate::config::ScraperConfig;
+use crate::dexes::osmosis::{OsmosisPool, OsmosisScraper};
+use crate::helpers::clients::ChainClients;
+use crate::test_utils::{registry_from_lists, AssetListBuilder};
+use crate::LazyChainRegistry;
+
+pub fn pool(id: u64, pool_type: PoolType, denoms: &[&str]) -> OsmosisPool {
+    OsmosisPool {
+        id,
+        pool_type,
+        denoms: denoms.iter().map(|denom| denom.to_string()).collect(),
+    }
+}
+
+/// A scraper of `pools` on an offline osmosis-1, OSMO and ION are in the chain registry.
+pub fn scraper_of(pools: Vec<OsmosisPool>) -> OsmosisScraper {
+    let clients = ChainClients::offline("osmosis-1");
+    let chain_registry = registry_from_lists(vec![AssetListBuilder::chain("osmosis")
+        .asset("OSMO", "uosmo", 6)
+        .asset("ION", "uion", 6)
+        .build()]);
+
+    let mut scraper = OsmosisScraper::new(
+        clients,
+        LazyChainRegistry::from(chain_registry),
+        &ScraperConfig::default_for("osmosis-1"),
+    )
+    .unwrap();
+    scraper.loaded_pools = pools;
+    scraper
+}
@@ -0,0 +1,60 @@
+//! Pairs and scrapers of terraswap factories, the assets are also in the format of white whale.
+
+use cosmwasm_std::Addr;
+
+use crate::config::ScraperConfig;
+use crate::dexes::terraswap::{TerraswapPairInfo, TerraswapScraper};
+use crate::dexes::{FactoryAssetInfo, FactoryToken};
+use crate::helpers::clients::ChainClients;
+use crate::test_utils::{registry_from_lists, AssetListBuilder};
+use crate::LazyChainRegistry;
+
+pub fn native(denom: &str) -> FactoryAssetInfo {
+    FactoryAssetInfo::NativeToken {
+        denom: denom.to_string(),
+    }
+}
+
+pub fn token(contract_addr: &str) -> FactoryAssetInfo {
+    FactoryAssetInfo::Token(FactoryToken::Contract {
+        contract_addr: contract_addr.to_string(),
+    })
+}
+
+/// A pair of `asset_infos`, its LP token is `<contract_addr>_lp`.
+pub fn pair(contract_addr: &str, asset_infos: Vec<FactoryAssetInfo>) -> TerraswapPairInfo {
+    TerraswapPairInfo {
+        asset_infos,
+        contract_addr: Addr::unchecked(contract_addr),
+        liquidity_token: Addr::unchecked(format!("{}_lp", contract_addr)),
+    }
+}
+
+/// A scraper of `pairs` on an offline phoenix-1, LUNA is in the chain registry and ASTRO has a
+/// cached token info.
+pub fn scraper_of(dex_id: &'static str, pairs: Vec<TerraswapPairInfo>) -> TerraswapScraper {
+    let clients = ChainClients::offline("phoenix-1");
+    clients.query_cache.insert_cw20_info(
+        &Addr::unchecked("astro_token"),
+        cw20::TokenInfoResponse {
+            name: "Astroport".to_string(),
+            symbol: "ASTRO".to_string(),
+            decimals: 6,
+            total_supply: Default::default(),
+        },
+    );
+    let chain_registry = registry_from_lists(vec![AssetListBuilder::chain("terra2")
+        .asset("LUNA", "uluna", 6)
+        .build()]);
+
+    let mut scraper = TerraswapScraper::new(
+        clients,
+        LazyChainRegistry::from(chain_registry),
+        dex_id,
+        Addr::unchecked("factory"),
+        &ScraperConfig::default_for("phoenix-1"),
+    )
+    .unwrap();
+    scraper.pairs.loaded_pairs = pairs;
+    scraper
+}
@@ -0,0 +1,75 @@
+//! Pairs, configs and scrapers of white whale deployments.
+
+use std::collections::BTreeMap;
+
+use cosmwasm_std::Addr;
+use serde_json::Value;
+
+use crate::config::ScraperConfig;
+use crate::dexes::whitewhale::{
+    WhiteWhalePairInfo, WhiteWhaleScraper, POOL_FACTORY_KEY, WHITE_WHALE_DEX,
+};
+use crate::dexes::FactoryAssetInfo;
+use crate::helpers::clients::ChainClients;
+use crate::test_utils::{registry_from_lists, AssetListBuilder};
+use crate::LazyChainRegistry;
+
+// White whale lists its assets like terraswap
+pub use super::terraswap::{native, token};
+
+/// A pair of `asset_infos` and `pair_type`, its LP token is the cw20 `<contract_addr>_lp`.
+pub fn pair(
+    contract_addr: &str,
+    asset_infos: Vec<FactoryAssetInfo>,
+    pair_type: Value,
+) -> WhiteWhalePairInfo {
+    WhiteWhalePairInfo {
+        asset_infos,
+        contract_addr: Addr::unchecked(contract_addr),
+        liquidity_token: token(&format!("{}_lp", contract_addr)),
+        pair_type,
+    }
+}
+
+/// Config with the white whale deployment `addresses` on phoenix-1.
+pub fn config_with(addresses: &[(&str, &str)]) -> ScraperConfig {
+    let mut config = ScraperConfig::default_for("phoenix-1");
+    config.deployment_addresses.insert(
+        WHITE_WHALE_DEX.to_string(),
+        BTreeMap::from([(
+            "phoenix-1".to_string(),
+            addresses
+                .iter()
+                .map(|(key, addr)| (key.to_string(), addr.to_string()))
+                .collect(),
+        )]),
+    );
+    config
+}
+
+/// A scraper of `pairs` on an offline phoenix-1, LUNA is in the chain registry and WHALE has a
+/// cached token info.
+pub fn scraper_of(pairs: Vec<WhiteWhalePairInfo>) -> WhiteWhaleScraper {
+    let clients = ChainClients::offline("phoenix-1");
+    clients.query_cache.insert_cw20_info(
+        &Addr::unchecked("whale_token"),
+        cw20::TokenInfoResponse {
+            name: "Whale".to_string(),
+            symbol: "WHALE".to_string(),
+            decimals: 6,
+            total_supply: Default::default(),
+        },
+    );
+    let chain_registry = registry_from_lists(vec![AssetListBuilder::chain("terra2")
+        .asset("LUNA", "uluna", 6)
+        .build()]);
+
+    let mut scraper = WhiteWhaleScraper::new(
+        clients,
+        LazyChainRegistry::from(chain_registry),
+        &config_with(&[(POOL_FACTORY_KEY, "pool_factory")]),
+    )
+    .unwrap();
+    scraper.pairs.loaded_pairs = pairs;
+    scraper
+}
@@ -0,0 +1,76 @@
+//! Pairs, configs and scrapers of wyndex factories.
+
+use std::collections::BTreeMap;
+
+use cosmwasm_std::Addr;
+
+use crate::config::ScraperConfig;
+use crate::dexes::wyndex::{
+    WyndexPairInfo, WyndexPairType, WyndexScraper, FACTORY_KEY, WYNDEX_DEX,
+};
+use crate::dexes::{FactoryAssetInfo, FactoryToken};
+use crate::helpers::clients::ChainClients;
+use crate::test_utils::{registry_from_lists, AssetListBuilder};
+use crate::LazyChainRegistry;
+
+pub fn native(denom: &str) -> FactoryAssetInfo {
+    FactoryAssetInfo::Native(denom.to_string())
+}
+
+pub fn token(contract_addr: &str) -> FactoryAssetInfo {
+    FactoryAssetInfo::Token(FactoryToken::Address(contract_addr.to_string()))
+}
+
+/// A pair of `asset_infos` and `pair_type`, its LP token is `<contract_addr>_lp`.
+pub fn pair(
+    contract_addr: &str,
+    asset_infos: Vec<FactoryAssetInfo>,
+    pair_type: WyndexPairType,
+) -> WyndexPairInfo {
+    WyndexPairInfo {
+        asset_infos,
+        contract_addr: Addr::unchecked(contract_addr),
+        liquidity_token: Addr::unchecked(format!("{}_lp", contract_addr)),
+        pair_type,
+    }
+}
+
+/// Config with `factory_address` as the wyndex factory of `chain_id`.
+pub fn config_with_factory(chain_id: &str, factory_address: &str) -> ScraperConfig {
+    let mut config = ScraperConfig::default_for(chain_id);
+    config.deployment_addresses.insert(
+        WYNDEX_DEX.to_string(),
+        BTreeMap::from([(
+            chain_id.to_string(),
+            BTreeMap::from([(FACTORY_KEY.to_string(), factory_address.to_string())]),
+        )]),
+    );
+    config
+}
+
+/// A scraper of `pairs` on an offline juno-1, JUNO is in the chain registry and WYND has a cached
+/// token info.
+pub fn scraper_of(pairs: Vec<WyndexPairInfo>) -> WyndexScraper {
+    let clients = ChainClients::offline("juno-1");
+    clients.query_cache.insert_cw20_info(
+        &Addr::unchecked("wynd_token"),
+        cw20::TokenInfoResponse {
+            name: "Wynd".to_string(),
+            symbol: "WYND".to_string(),
+            decimals: 6,
+            total_supply: Default::default(),
+        },
+    );
+    let chain_registry = registry_from_lists(vec![AssetListBuilder::chain("juno")
+        .asset("JUNO", "ujuno", 6)
+        .build()]);
+
+    let mut scraper = WyndexScraper::new(
+        clients,
+        LazyChainRegistry::from(chain_registry),
+        &ScraperConfig::default_for("juno-1"),
+    )
+    .unwrap();
+    scraper.pairs.loaded_pairs = pairs;
+    scraper
+}
@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+
+/// Where the contract addresses of a dex deployment come from.
+#[async_trait]
+pub trait DeploymentSource: Send + Sync {
+    /// Address of the contract `key` (e.g. `factory_address`) of the deployment on `chain_id`,
+    /// `None` if the deployment doesn't have it.
+    async fn address(&self, chain_id: &str, key: &str) -> anyhow::Result<Option<String>>;
+
+    /// Keys of the deployment on `chain_id`, listed in the errors of missing contracts. None by
+    /// default.
+    async fn keys(&self, _chain_id: &str) -> anyhow::Result<Vec<String>> {
+        Ok(vec![])
+    }
+}
+
+/// A deployment with known addresses, e.g. the addresses of a test or of a local chain.
+#[derive(Debug, Clone, Default)]
+pub struct StaticDeployment {
+    addresses: BTreeMap<String, String>,
+}
+
+impl StaticDeployment {
+    pub fn new<'a>(addresses: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            addresses: addresses
+                .into_iter()
+                .map(|(key, address)| (key.to_string(), address.to_string()))
+                .collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl DeploymentSource for StaticDeployment {
+    async fn address(&self, _chain_id: &str, key: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.addresses.get(key).cloned())
+    }
+
+    async fn keys(&self, _chain_id: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self.addresses.keys().cloned().collect())
+    }
+}
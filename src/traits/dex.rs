@@ -1,17 +1,183 @@
 use abstract_core::objects::pool_id::UncheckedPoolAddress;
-use abstract_core::objects::PoolMetadata;
+use abstract_core::objects::{ContractEntry, PoolMetadata};
+use async_trait::async_trait;
 use cosmwasm_std::Addr;
-use cw_asset::AssetInfo;
+use cw_asset::AssetInfoUnchecked;
+use serde::{Deserialize, Serialize};
 
+use crate::error::ScraperError;
+use crate::report::DexReport;
+
+/// An asset together with its ANS entry name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnsAsset {
+    pub entry: String,
+    pub info: AssetInfoUnchecked,
+}
+
+impl AnsAsset {
+    pub fn new(entry: impl Into<String>, info: impl Into<AssetInfoUnchecked>) -> Self {
+        Self {
+            entry: entry.into(),
+            info: info.into(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
 pub trait AssetSource {
-    fn fetch_asset_infos(&mut self) -> anyhow::Result<Vec<AssetInfo>>;
+    /// Fetch the assets of the source with their ANS entry names.
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError>;
 }
 
 pub trait DexId {
     fn dex_id(&self) -> &'static str;
 }
 
+#[async_trait(?Send)]
 pub trait DexScraper: DexId + AssetSource {
-    fn fetch_staking_contracts(&mut self) -> anyhow::Result<Vec<(String, Addr)>>;
-    fn fetch_dex_pools(&mut self) -> anyhow::Result<Vec<(UncheckedPoolAddress, PoolMetadata)>>;
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError>;
+    /// Contracts of the protocol itself that adapters look up, e.g. `astroport:router`. None by
+    /// default.
+    async fn fetch_contract_entries(&mut self) -> Result<Vec<(ContractEntry, Addr)>, ScraperError> {
+        Ok(vec![])
+    }
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError>;
+    /// The assets and pools the last fetches left out, and why.
+    fn report(&self) -> DexReport;
+}
+
+// Lets a scraper run through a pipeline while its owner keeps access to it.
+#[async_trait(?Send)]
+impl<T: AssetSource + ?Sized> AssetSource for &mut T {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        (**self).fetch_asset_infos().await
+    }
+}
+
+impl<T: DexId + ?Sized> DexId for &mut T {
+    fn dex_id(&self) -> &'static str {
+        (**self).dex_id()
+    }
+}
+
+#[async_trait(?Send)]
+impl<T: DexScraper + ?Sized> DexScraper for &mut T {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        (**self).fetch_staking_contracts().await
+    }
+
+    async fn fetch_contract_entries(&mut self) -> Result<Vec<(ContractEntry, Addr)>, ScraperError> {
+        (**self).fetch_contract_entries().await
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        (**self).fetch_dex_pools().await
+    }
+
+    fn report(&self) -> DexReport {
+        (**self).report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cw_asset::AssetInfo;
+
+    use super::*;
+
+    /// Counts the fetches, the report tells how many there were.
+    #[derive(Default)]
+    struct CountingDex {
+        fetches: usize,
+    }
+
+    impl DexId for CountingDex {
+        fn dex_id(&self) -> &'static str {
+            "counting"
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl AssetSource for CountingDex {
+        async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+            self.fetches += 1;
+            Ok(vec![AnsAsset::new(
+                "terra2>luna",
+                AssetInfo::native("uluna"),
+            )])
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl DexScraper for CountingDex {
+        async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+            self.fetches += 1;
+            Ok(vec![])
+        }
+
+        async fn fetch_dex_pools(
+            &mut self,
+        ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+            self.fetches += 1;
+            Ok(vec![])
+        }
+
+        fn report(&self) -> DexReport {
+            DexReport::new(format!("counting-{}", self.fetches), vec![], vec![])
+        }
+    }
+
+    async fn fetch_assets(mut source: impl AssetSource) -> Vec<AnsAsset> {
+        source.fetch_asset_infos().await.unwrap()
+    }
+
+    async fn fetch_all(mut scraper: impl DexScraper) -> DexReport {
+        scraper.fetch_asset_infos().await.unwrap();
+        scraper.fetch_staking_contracts().await.unwrap();
+        assert!(scraper.fetch_contract_entries().await.unwrap().is_empty());
+        scraper.fetch_dex_pools().await.unwrap();
+        scraper.report()
+    }
+
+    #[tokio::test]
+    async fn boxed_asset_source_is_fetched_directly_and_borrowed() {
+        let mut source: Box<dyn AssetSource> = Box::<CountingDex>::default();
+
+        let assets = source.fetch_asset_infos().await.unwrap();
+        assert_eq!(
+            assets,
+            [AnsAsset::new("terra2>luna", AssetInfo::native("uluna"))]
+        );
+        assert_eq!(fetch_assets(&mut *source).await, assets);
+    }
+
+    #[tokio::test]
+    async fn borrowed_boxed_scraper_keeps_its_state() {
+        let mut scraper: Box<dyn DexScraper> = Box::<CountingDex>::default();
+
+        let report = fetch_all(&mut *scraper).await;
+        assert_eq!(report.dex, "counting-3");
+        assert_eq!(scraper.dex_id(), "counting");
+        // the fetches through the borrow went to the boxed scraper
+        assert_eq!(scraper.report().dex, "counting-3");
+        fetch_all(&mut *scraper).await;
+        assert_eq!(scraper.report().dex, "counting-6");
+    }
+
+    #[test]
+    fn ans_asset_serializes_with_its_entry_and_info() {
+        let asset = AnsAsset::new("terra2>luna", AssetInfo::native("uluna"));
+
+        let json = serde_json::to_value(&asset).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "entry": "terra2>luna", "info": { "native": "uluna" } })
+        );
+        assert_eq!(serde_json::from_value::<AnsAsset>(json).unwrap(), asset);
+    }
 }
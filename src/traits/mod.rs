@@ -1 +1,4 @@
+pub mod deployment;
 pub mod dex;
+pub mod pairs;
+pub mod resolve;
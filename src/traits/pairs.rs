@@ -0,0 +1,78 @@
+use astroport::asset::{AssetInfo as AstroportAssetInfo, PairInfo};
+use async_trait::async_trait;
+
+/// Where the pairs of an astroport factory come from.
+///
+/// Every source yields the factory's own [`PairInfo`]s, so the pairs are mapped to pools the same
+/// way whatever their source.
+#[async_trait]
+pub trait PairSource: Send + Sync {
+    /// The pairs after the pair of the assets `start_after` in the order of the factory, all the
+    /// pairs without a cursor.
+    async fn pairs_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+    ) -> anyhow::Result<Vec<PairInfo>>;
+
+    /// At most `limit` of the pairs after `start_after`, for scrapes that go page by page.
+    /// Sources that can't be paginated list all the pairs after it.
+    async fn page_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+        _limit: u32,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        self.pairs_after(start_after).await
+    }
+
+    /// Whether [`Self::pairs_after`] honours its cursor. The pairs cache is not extended from
+    /// sources that always list all the pairs.
+    fn is_incremental(&self) -> bool {
+        true
+    }
+}
+
+/// Pairs known up front, e.g. the pairs of a mock factory.
+#[derive(Debug, Clone, Default)]
+pub struct StaticPairs {
+    pairs: Vec<PairInfo>,
+}
+
+impl StaticPairs {
+    pub fn new(pairs: impl IntoIterator<Item = PairInfo>) -> Self {
+        Self {
+            pairs: pairs.into_iter().collect(),
+        }
+    }
+
+    /// Index of the pair after the one with the assets `start_after`, 0 if there is none.
+    fn start(&self, start_after: Option<Vec<AstroportAssetInfo>>) -> usize {
+        start_after
+            .and_then(|assets| {
+                self.pairs
+                    .iter()
+                    .position(|pair| pair.asset_infos == assets)
+            })
+            .map_or(0, |position| position + 1)
+    }
+}
+
+#[async_trait]
+impl PairSource for StaticPairs {
+    /// The pairs after the one with the assets `start_after`, all the pairs if there is none.
+    async fn pairs_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        Ok(self.pairs[self.start(start_after)..].to_vec())
+    }
+
+    async fn page_after(
+        &self,
+        start_after: Option<Vec<AstroportAssetInfo>>,
+        limit: u32,
+    ) -> anyhow::Result<Vec<PairInfo>> {
+        let start = self.start(start_after);
+        let end = self.pairs.len().min(start + limit as usize);
+        Ok(self.pairs[start..end].to_vec())
+    }
+}
@@ -0,0 +1,54 @@
+use async_trait::async_trait;
+use cw_asset::AssetInfo;
+
+use crate::helpers::resolve::{AssetResolver, ResolvedAssets};
+use crate::report::{UnresolvedAsset, UnresolvedReason};
+
+/// Names the assets of a chain.
+#[async_trait(?Send)]
+pub trait DenomResolver {
+    /// Name the assets, sorted by name. Assets that can't be named end up in
+    /// [`ResolvedAssets::not_found`].
+    async fn resolve(&self, asset_infos: Vec<AssetInfo>) -> anyhow::Result<ResolvedAssets>;
+}
+
+#[async_trait(?Send)]
+impl DenomResolver for AssetResolver<'_> {
+    async fn resolve(&self, asset_infos: Vec<AssetInfo>) -> anyhow::Result<ResolvedAssets> {
+        AssetResolver::resolve(self, asset_infos).await
+    }
+}
+
+/// Names known up front, without any queries. Other assets are not found.
+#[derive(Debug, Clone, Default)]
+pub struct KnownNames {
+    names: Vec<(String, AssetInfo)>,
+}
+
+impl KnownNames {
+    pub fn new(names: impl IntoIterator<Item = (String, AssetInfo)>) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl DenomResolver for KnownNames {
+    async fn resolve(&self, asset_infos: Vec<AssetInfo>) -> anyhow::Result<ResolvedAssets> {
+        let mut resolved = ResolvedAssets::default();
+        for asset_info in asset_infos {
+            match self.names.iter().find(|(_, info)| *info == asset_info) {
+                Some((name, _)) => resolved.names.push((name.clone(), asset_info)),
+                None => {
+                    let reason = UnresolvedReason::NoRegistryMatch("no known name".to_string());
+                    resolved
+                        .not_found
+                        .push(UnresolvedAsset::new(asset_info, reason));
+                }
+            }
+        }
+        resolved.names.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(resolved)
+    }
+}
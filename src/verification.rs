@@ -0,0 +1,319 @@
+//! Checks of the entries registered on AnsHost against the contracts they point at.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::PoolMetadata;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::diff::AnsHostEntries;
+use crate::helpers::cache::write_json_atomic;
+use crate::helpers::names::LP_TOKEN_SEPARATOR;
+use crate::helpers::wasm_batch::WasmQueryBatcher;
+use crate::pipeline::ScrapeMeta;
+use crate::ChainClients;
+
+pub const VERIFICATION_FILE: &str = "verification.json";
+
+/// A registered pool whose contract answers neither a `pair` nor a `pool` query.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadPool {
+    pub unique_id: Option<u64>,
+    pub pool: UncheckedPoolAddress,
+    pub metadata: PoolMetadata,
+    pub error: String,
+}
+
+/// A registered pool whose contract holds other assets than its metadata says.
+#[derive(Debug, Clone, Serialize)]
+pub struct MismatchedPool {
+    pub unique_id: Option<u64>,
+    pub pool: UncheckedPoolAddress,
+    pub metadata: PoolMetadata,
+    /// Assets of the metadata, `None` for names that aren't registered assets
+    pub registered: Vec<Option<AssetInfo>>,
+    /// Assets the contract reports
+    pub actual: Vec<AssetInfo>,
+}
+
+/// A registered cw20 whose contract doesn't answer a `token_info` query.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadAsset {
+    pub name: String,
+    pub info: AssetInfo,
+    pub error: String,
+}
+
+/// The registered entries of an AnsHost that don't match the chain anymore, written to
+/// `<out_dir>/<chain_id>/verification.json`.
+///
+/// The dead and mismatched entries carry what the `to_remove` of the AnsHost update messages
+/// needs: the unique ids of the pools and the names of the assets.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub chain_id: String,
+    pub meta: ScrapeMeta,
+    pub ans_host: String,
+    /// Dex whose entries were checked, all of them if `None`
+    pub dex: Option<String>,
+    pub checked_pools: usize,
+    pub checked_assets: usize,
+    /// Pools that are not contracts (pool ids) and can't be queried
+    pub unchecked_pools: usize,
+    pub dead_pools: Vec<DeadPool>,
+    pub mismatched_pools: Vec<MismatchedPool>,
+    pub dead_assets: Vec<DeadAsset>,
+}
+
+impl VerificationReport {
+    /// Whether every checked entry matches the chain.
+    pub fn is_clean(&self) -> bool {
+        self.dead_pools.is_empty()
+            && self.mismatched_pools.is_empty()
+            && self.dead_assets.is_empty()
+    }
+
+    pub fn log_summary(&self) {
+        log::info!(
+            "{}: {} pools and {} cw20s checked, {} pool ids not checked",
+            self.chain_id,
+            self.checked_pools,
+            self.checked_assets,
+            self.unchecked_pools
+        );
+        for pool in &self.dead_pools {
+            log::warn!("Dead pool {:?}: {}", pool.pool, pool.error);
+        }
+        for pool in &self.mismatched_pools {
+            log::warn!(
+                "Pool {:?} holds {:?}, registered with {:?}",
+                pool.pool,
+                pool.actual,
+                pool.metadata.assets
+            );
+        }
+        for asset in &self.dead_assets {
+            log::warn!("Dead cw20 {} ({}): {}", asset.name, asset.info, asset.error);
+        }
+        log::info!(
+            "{}: {} dead pools, {} mismatched pools, {} dead cw20s",
+            self.chain_id,
+            self.dead_pools.len(),
+            self.mismatched_pools.len(),
+            self.dead_assets.len()
+        );
+    }
+
+    /// Write the report to `<out_dir>/<chain_id>/verification.json`.
+    pub fn write(&self, out_dir: &Path) -> anyhow::Result<PathBuf> {
+        let chain_dir = out_dir.join(&self.chain_id);
+        std::fs::create_dir_all(&chain_dir)?;
+        let path = chain_dir.join(VERIFICATION_FILE);
+        write_json_atomic(&path, self)?;
+        Ok(path)
+    }
+}
+
+/// Check the pools (of `dex`, or all of them) and cw20s registered on `ans_host`.
+///
+/// Every pool contract is asked for its `pair`, or its `pool` if it has no pair query, and the
+/// assets it answers with are compared to the registered metadata. Every cw20 is asked for its
+/// `token_info`. With a dex only the cw20s its pools use (and its LP tokens) are checked.
+pub async fn verify_entries(
+    clients: &ChainClients,
+    ans_host: &Addr,
+    dex: Option<&str>,
+    concurrency: usize,
+) -> anyhow::Result<VerificationReport> {
+    let entries = AnsHostEntries::query(clients, ans_host).await?;
+    let batcher = WasmQueryBatcher::new(&clients.wasm, concurrency);
+
+    let unique_id = |pool: &UncheckedPoolAddress| {
+        entries
+            .pool_ids
+            .iter()
+            .find(|(_, registered)| registered == pool)
+            .map(|(unique_id, _)| *unique_id)
+    };
+    let asset_infos: BTreeMap<&str, &AssetInfo> = entries
+        .assets
+        .iter()
+        .map(|(name, info)| (name.as_str(), info))
+        .collect();
+
+    let pools: Vec<&(UncheckedPoolAddress, PoolMetadata)> = entries
+        .pools
+        .iter()
+        .filter(|(_, metadata)| dex.map_or(true, |dex| metadata.dex == dex))
+        .collect();
+    let contracts: BTreeMap<Addr, &(UncheckedPoolAddress, PoolMetadata)> = pools
+        .iter()
+        .filter_map(|pool| match &pool.0 {
+            UncheckedPoolAddress::Contract(addr) => Some((Addr::unchecked(addr), *pool)),
+            UncheckedPoolAddress::Id(_) => None,
+        })
+        .collect();
+
+    let mut dead_pools = vec![];
+    let mut mismatched_pools = vec![];
+    let pair_responses = batcher
+        .query_each(contracts.keys().cloned(), &json!({"pair": {}}))
+        .await;
+    // Pools without a pair query may still have a pool query
+    let pool_responses = batcher
+        .query_each(
+            pair_responses
+                .iter()
+                .filter(|(_, response)| response.is_err())
+                .map(|(contract, _)| contract.clone()),
+            &json!({"pool": {}}),
+        )
+        .await;
+    for (contract, (pool, metadata)) in &contracts {
+        let response = match pair_responses.get(contract) {
+            Some(Ok(response)) => Ok(response),
+            _ => match pool_responses.get(contract) {
+                Some(Ok(response)) => Ok(response),
+                Some(Err(err)) => Err(format!("{:#}", err)),
+                None => Err("not queried".to_string()),
+            },
+        };
+        let actual = response.and_then(|response| {
+            let response: Value = serde_json::from_slice(response).map_err(|e| e.to_string())?;
+            response_asset_infos(&response)
+                .ok_or_else(|| "response without asset infos".to_string())
+        });
+        let actual = match actual {
+            Ok(actual) => actual,
+            Err(error) => {
+                dead_pools.push(DeadPool {
+                    unique_id: unique_id(pool),
+                    pool: pool.clone(),
+                    metadata: metadata.clone(),
+                    error,
+                });
+                continue;
+            }
+        };
+
+        let registered: Vec<Option<AssetInfo>> = metadata
+            .assets
+            .iter()
+            .map(|asset| {
+                asset_infos
+                    .get(asset.to_string().as_str())
+                    .map(|info| (*info).clone())
+            })
+            .collect();
+        let registered_set: BTreeSet<String> = registered
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect();
+        let actual_set: BTreeSet<String> = actual.iter().map(ToString::to_string).collect();
+        if registered.iter().any(Option::is_none) || registered_set != actual_set {
+            mismatched_pools.push(MismatchedPool {
+                unique_id: unique_id(pool),
+                pool: pool.clone(),
+                metadata: metadata.clone(),
+                registered,
+                actual,
+            });
+        }
+    }
+
+    // With a dex, the cw20s of its pools and its LP tokens
+    let dex_assets: Option<BTreeSet<String>> = dex.map(|_| {
+        pools
+            .iter()
+            .flat_map(|(_, metadata)| metadata.assets.iter().map(ToString::to_string))
+            .collect()
+    });
+    let cw20s: BTreeMap<Addr, &str> = entries
+        .assets
+        .iter()
+        .filter(|(name, _)| match (&dex_assets, dex) {
+            (Some(dex_assets), Some(dex)) => {
+                dex_assets.contains(name)
+                    || name
+                        .split_once(LP_TOKEN_SEPARATOR)
+                        .map_or(false, |(lp_dex, _)| lp_dex == dex)
+            }
+            _ => true,
+        })
+        .filter_map(|(name, info)| match info {
+            AssetInfo::Cw20(addr) => Some((addr.clone(), name.as_str())),
+            _ => None,
+        })
+        .collect();
+    let token_infos = batcher
+        .query_each(cw20s.keys().cloned(), &cw20::Cw20QueryMsg::TokenInfo {})
+        .await;
+    let dead_assets = token_infos
+        .into_iter()
+        .filter_map(|(addr, response)| {
+            let error = match response.and_then(|response| {
+                serde_json::from_slice::<cw20::TokenInfoResponse>(&response)
+                    .map_err(anyhow::Error::from)
+            }) {
+                Ok(_) => return None,
+                Err(err) => format!("{:#}", err),
+            };
+            Some(DeadAsset {
+                name: cw20s[&addr].to_string(),
+                info: AssetInfo::Cw20(addr),
+                error,
+            })
+        })
+        .collect();
+
+    Ok(VerificationReport {
+        chain_id: clients.chain_id.clone(),
+        meta: entries.meta.clone(),
+        ans_host: ans_host.to_string(),
+        dex: dex.map(str::to_string),
+        checked_pools: contracts.len(),
+        checked_assets: cw20s.len(),
+        unchecked_pools: pools.len() - contracts.len(),
+        dead_pools,
+        mismatched_pools,
+        dead_assets,
+    })
+}
+
+/// Asset infos of a `pair` (`asset_infos`) or `pool` (`assets[].info`) response, in the
+/// astroport/terraswap or the cw-asset format.
+fn response_asset_infos(response: &Value) -> Option<Vec<AssetInfo>> {
+    let infos: Vec<&Value> = match response.get("asset_infos") {
+        Some(Value::Array(infos)) => infos.iter().collect(),
+        _ => response
+            .get("assets")?
+            .as_array()?
+            .iter()
+            .map(|asset| asset.get("info"))
+            .collect::<Option<_>>()?,
+    };
+    infos.into_iter().map(asset_info).collect()
+}
+
+fn asset_info(info: &Value) -> Option<AssetInfo> {
+    if let Some(token) = info.get("token") {
+        return Some(AssetInfo::cw20(Addr::unchecked(
+            token.get("contract_addr")?.as_str()?,
+        )));
+    }
+    if let Some(native) = info.get("native_token") {
+        return Some(AssetInfo::native(native.get("denom")?.as_str()?));
+    }
+    if let Some(addr) = info.get("cw20") {
+        return Some(AssetInfo::cw20(Addr::unchecked(addr.as_str()?)));
+    }
+    if let Some(denom) = info.get("native") {
+        return Some(AssetInfo::native(denom.as_str()?));
+    }
+    None
+}
@@ -0,0 +1,440 @@
+//! Replays the recorded gRPC queries of a phoenix-1 scrape through the whole pipeline, offline.
+//!
+//! `tests/fixtures/cassettes/phoenix-1.json` has the shape of an astroport scrape of phoenix-1
+//! stopped after two pages of the factory (`max_pair_pages = 2`), 20 pairs, with the addresses
+//! replaced by placeholders so the output is readable. It has what live chains throw at the
+//! scraper: IBC denoms from a known and an unknown counterparty chain, a denom trace the node
+//! doesn't have, a token factory denom no asset list has, a cw20 posing as USDC, a contract that
+//! isn't a cw20, a custom pair type and a generator with an LP token of a pair after the pages.
+//! Recorded again with:
+//!
+//! ```text
+//! ans-scraper --network phoenix-1 --refresh --config <config with max_pair_pages = 2> \
+//!     scrape --record-cassette tests/fixtures/cassettes/phoenix-1.json
+//! ```
+//!
+//! The asset lists and the deployment file are served from the fixtures, the test never reaches
+//! the network.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{
+    AssetEntry, DexAssetPairing, PoolMetadata, PoolType, UncheckedChannelEntry,
+};
+use ans_scraper_rs::cassette::{Cassette, CassettePlayer};
+use ans_scraper_rs::pipeline::ScrapeMeta;
+use ans_scraper_rs::report::{SkipReason, SkippedPool, UnresolvedReason};
+use ans_scraper_rs::{
+    scrape_network, AssetMetadata, ChainClients, ChainRegistryOptions, LazyChainRegistry,
+    ResolutionCache, ScrapeOptions, ScraperConfig, ICS20_PROTOCOL,
+};
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const CHAIN_ID: &str = "phoenix-1";
+const BLOCK_HEIGHT: u64 = 5_512_345;
+
+const CASSETTE: &str = "tests/fixtures/cassettes/phoenix-1.json";
+const DEPLOYMENT: &str = include_str!("fixtures/deployments/core_phoenix.json");
+const ASSET_LISTS: [(&str, &str); 3] = [
+    (
+        "terra2",
+        include_str!("fixtures/cassettes/asset_lists/terra2.json"),
+    ),
+    (
+        "cosmoshub",
+        include_str!("fixtures/cassettes/asset_lists/cosmoshub.json"),
+    ),
+    (
+        "axelar",
+        include_str!("fixtures/cassettes/asset_lists/axelar.json"),
+    ),
+];
+
+/// `transfer/channel-1/uatom`, its channel leads to cosmoshub-4
+const ATOM: &str = "ibc/C4CFF46FD6DE35CA4CF4CE031E643C8FDC9BA4B99AE598E9B0ED98FE3A2319F9";
+/// `transfer/channel-6/uusdc`, its channel leads to axelar-dojo-1, which has no registry name
+const USDC: &str = "ibc/B3504E092456BA618CC28AC671A71FB08C6CA0FD0BE7C8A5B5A3E2DD933CC9E4";
+/// `transfer/channel-99/ufoo`, the node has no denom trace for it
+const FOO: &str = "ibc/3ACD366F2CFCD991B83E302FA9DD59A5F39F6D33F5BBAAC07F5EFE11DAB237AC";
+const AMPROAR: &str = "factory/terra1creatoraddress/ampROAR";
+
+const ASTRO: &str = "terra1astrotokenaddress";
+const XASTRO: &str = "terra1xastroaddress";
+const AMPLUNA: &str = "terra1amplunaaddress";
+/// A cw20 with the USDC symbol, neither allow-listed nor in the asset list
+const FAKE_USDC: &str = "terra1fakeusdcaddress";
+/// A contract that doesn't answer the cw20 `TokenInfo` query
+const NOT_A_TOKEN: &str = "terra1nottokenaddress";
+const GENERATOR: &str = "terra1generatoraddress";
+
+/// The asset lists and the deployment file of the fixtures.
+async fn serve_fixtures() -> MockServer {
+    let server = MockServer::start().await;
+    for (chain, asset_list) in ASSET_LISTS {
+        Mock::given(method("GET"))
+            .and(path(format!("/{}/assetlist.json", chain)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(asset_list))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/core_phoenix.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(DEPLOYMENT))
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Configuration of the recorded scrape in a fresh directory: two pages of pairs, the deployment
+/// file of the fixtures and the allow-listed xASTRO and ampLUNA cw20s.
+fn scraper_config(server: &MockServer) -> ScraperConfig {
+    let dir = std::env::temp_dir().join(format!("ans-scraper-cassette-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let chain_config_dir = dir.join("config").join(CHAIN_ID);
+    std::fs::create_dir_all(&chain_config_dir).unwrap();
+    std::fs::write(
+        chain_config_dir.join("verified_cw20s.json"),
+        json!([AMPLUNA, XASTRO]).to_string(),
+    )
+    .unwrap();
+
+    ScraperConfig {
+        cache_dir: dir.join("cache"),
+        config_dir: dir.join("config"),
+        out_dir: dir.join("out"),
+        deployment_urls: BTreeMap::from([(
+            "astroport".to_string(),
+            BTreeMap::from([(
+                CHAIN_ID.to_string(),
+                format!("{}/core_phoenix.json", server.uri()),
+            )]),
+        )]),
+        max_pair_pages: 2,
+        ..Default::default()
+    }
+}
+
+fn cw20(addr: &str) -> AssetInfo {
+    AssetInfo::cw20(Addr::unchecked(addr))
+}
+
+fn asset(name: &str, info: AssetInfo) -> (String, AssetInfo) {
+    (name.to_string(), info)
+}
+
+fn lp(pair: u8, assets: &str) -> (String, AssetInfo) {
+    (
+        format!("astroport/{}", assets),
+        cw20(&format!("terra1pair{:02}lpaddress", pair)),
+    )
+}
+
+fn pair(pair: u8) -> UncheckedPoolAddress {
+    UncheckedPoolAddress::contract(format!("terra1pair{:02}address", pair))
+}
+
+fn pool(
+    pair_idx: u8,
+    pool_type: PoolType,
+    assets: [&str; 2],
+) -> (UncheckedPoolAddress, PoolMetadata) {
+    (
+        pair(pair_idx),
+        PoolMetadata {
+            dex: "astroport".to_string(),
+            pool_type,
+            assets: assets.into_iter().map(AssetEntry::new).collect(),
+        },
+    )
+}
+
+#[tokio::test]
+async fn recorded_phoenix_scrape_is_replayed() {
+    let server = serve_fixtures().await;
+    let config = scraper_config(&server);
+    let chain_registry = LazyChainRegistry::for_chains(
+        &["terra2"],
+        ChainRegistryOptions {
+            cache_dir: config.cache_dir.join("asset_lists"),
+            registry_url: server.uri(),
+            ..Default::default()
+        },
+    );
+    let cassette = Cassette::load(CASSETTE).unwrap();
+    assert_eq!(cassette.chain_id, CHAIN_ID);
+    let player = CassettePlayer::start(&cassette).unwrap();
+
+    let clients = ChainClients::connect(CHAIN_ID, &player.url())
+        .await
+        .unwrap();
+    let output = scrape_network(
+        clients,
+        chain_registry,
+        Arc::new(ResolutionCache::new()),
+        &config,
+        // recorded with --refresh, nothing comes from the caches
+        &ScrapeOptions {
+            refresh: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(player.misses(), Vec::<String>::new());
+
+    assert_eq!(output.meta, ScrapeMeta::new(CHAIN_ID, Some(BLOCK_HEIGHT)));
+
+    let mut assets = output.assets.clone();
+    assets.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        assets,
+        [
+            lp(2, "axelar>usdc,terra2>luna"),
+            lp(9, "axelar>usdc,cosmoshub>atom"),
+            lp(14, "axelar>usdc,terra2>ampluna"),
+            lp(20, "axelar>usdc,terra2>amproar"),
+            lp(3, "axelar>usdc,terra2>astro"),
+            lp(18, "axelar>usdc,terra2>xastro"),
+            lp(17, "cosmoshub>atom,terra2>ampluna"),
+            lp(12, "cosmoshub>atom,terra2>astro"),
+            lp(4, "cosmoshub>atom,terra2>luna"),
+            lp(15, "cosmoshub>atom,terra2>xastro"),
+            lp(13, "terra2>ampluna,terra2>astro"),
+            lp(5, "terra2>ampluna,terra2>luna"),
+            lp(19, "terra2>ampluna,terra2>xastro"),
+            lp(11, "terra2>amproar,terra2>luna"),
+            lp(1, "terra2>astro,terra2>luna"),
+            lp(6, "terra2>astro,terra2>xastro"),
+            // the pool of the unverified USDC is dropped, its LP token is still named after it
+            lp(7, "terra2>luna,terra2>usdc"),
+            lp(16, "terra2>luna,terra2>xastro"),
+            asset("axelar>usdc", AssetInfo::native(USDC)),
+            asset("cosmoshub>atom", AssetInfo::native(ATOM)),
+            asset("terra2>ampluna", cw20(AMPLUNA)),
+            asset("terra2>amproar", AssetInfo::native(AMPROAR)),
+            asset("terra2>astro", cw20(ASTRO)),
+            asset("terra2>luna", AssetInfo::native("uluna")),
+            asset("terra2>xastro", cw20(XASTRO)),
+        ]
+    );
+    assert_eq!(
+        output.unverified_assets,
+        [asset("terra2>usdc", cw20(FAKE_USDC))]
+    );
+    assert!(output.asset_conflicts.is_empty());
+    assert!(output.sanitized_names.is_empty());
+    assert_eq!(
+        output.low_confidence_names,
+        BTreeMap::from([("terra2>amproar".to_string(), AMPROAR.to_string())])
+    );
+
+    let mut pools = output.pools.clone();
+    pools.sort_by_key(|(_, metadata)| {
+        metadata
+            .assets
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    });
+    let expected_pools = [
+        pool(2, PoolType::ConstantProduct, ["axelar>usdc", "terra2>luna"]),
+        pool(
+            9,
+            PoolType::ConstantProduct,
+            ["axelar>usdc", "cosmoshub>atom"],
+        ),
+        pool(
+            14,
+            PoolType::ConstantProduct,
+            ["axelar>usdc", "terra2>ampluna"],
+        ),
+        pool(
+            20,
+            PoolType::ConstantProduct,
+            ["axelar>usdc", "terra2>amproar"],
+        ),
+        pool(
+            3,
+            PoolType::ConstantProduct,
+            ["axelar>usdc", "terra2>astro"],
+        ),
+        pool(
+            18,
+            PoolType::ConstantProduct,
+            ["axelar>usdc", "terra2>xastro"],
+        ),
+        pool(
+            17,
+            PoolType::ConstantProduct,
+            ["cosmoshub>atom", "terra2>ampluna"],
+        ),
+        pool(
+            12,
+            PoolType::ConstantProduct,
+            ["cosmoshub>atom", "terra2>astro"],
+        ),
+        pool(
+            4,
+            PoolType::ConstantProduct,
+            ["cosmoshub>atom", "terra2>luna"],
+        ),
+        pool(15, PoolType::Stable, ["cosmoshub>atom", "terra2>xastro"]),
+        pool(
+            13,
+            PoolType::ConstantProduct,
+            ["terra2>ampluna", "terra2>astro"],
+        ),
+        pool(5, PoolType::Stable, ["terra2>ampluna", "terra2>luna"]),
+        pool(
+            11,
+            PoolType::ConstantProduct,
+            ["terra2>amproar", "terra2>luna"],
+        ),
+        pool(
+            1,
+            PoolType::ConstantProduct,
+            ["terra2>astro", "terra2>luna"],
+        ),
+        pool(
+            6,
+            PoolType::ConstantProduct,
+            ["terra2>astro", "terra2>xastro"],
+        ),
+        pool(
+            16,
+            PoolType::ConstantProduct,
+            ["terra2>luna", "terra2>xastro"],
+        ),
+    ];
+    assert_eq!(pools, expected_pools);
+    // every pool has assets no other pool has
+    let expected_pairings: Vec<(DexAssetPairing, Vec<UncheckedPoolAddress>)> = expected_pools
+        .iter()
+        .map(|(pool, metadata)| {
+            let pairing = DexAssetPairing::new(
+                metadata.assets[0].clone(),
+                metadata.assets[1].clone(),
+                "astroport",
+            );
+            (pairing, vec![pool.clone()])
+        })
+        .collect();
+    assert_eq!(output.pairings, expected_pairings);
+
+    // the staking contract of the LP token of a pair after the pages, and of the pair of the cw20
+    // that isn't one, are left out
+    let mut contracts: Vec<(String, String, String)> = output
+        .contracts
+        .iter()
+        .map(|(entry, address)| {
+            (
+                entry.protocol.clone(),
+                entry.contract.clone(),
+                address.clone(),
+            )
+        })
+        .collect();
+    contracts.sort();
+    let contract = |name: &str, address: &str| {
+        (
+            "astroport".to_string(),
+            name.to_string(),
+            address.to_string(),
+        )
+    };
+    assert_eq!(
+        contracts,
+        [
+            contract("generator", GENERATOR),
+            contract("maker", "terra1makeraddress"),
+            contract("router", "terra1routeraddress"),
+            contract("staking", "terra1stakingaddress"),
+            contract("staking/astroport/axelar>usdc,terra2>luna", GENERATOR),
+            contract("staking/astroport/cosmoshub>atom,terra2>luna", GENERATOR),
+            contract("staking/astroport/terra2>astro,terra2>luna", GENERATOR),
+            contract("staking/astroport/terra2>astro,terra2>xastro", GENERATOR),
+        ]
+    );
+
+    let [report] = &output.reports[..] else {
+        panic!("one report per dex: {:?}", output.reports);
+    };
+    assert_eq!(report.dex, "astroport");
+    let mut unresolved_assets = report.unresolved_assets.clone();
+    unresolved_assets.sort_by_key(|asset| asset.info.to_string());
+    let [not_a_token, foo] = &unresolved_assets[..] else {
+        panic!("two unresolved assets: {:?}", unresolved_assets);
+    };
+    assert_eq!(not_a_token.info, cw20(NOT_A_TOKEN));
+    assert!(
+        matches!(not_a_token.reason, UnresolvedReason::Cw20Query(_)),
+        "{:?}",
+        not_a_token
+    );
+    assert_eq!(foo.info, AssetInfo::native(FOO));
+    assert!(
+        matches!(foo.reason, UnresolvedReason::DenomTrace(_)),
+        "{:?}",
+        foo
+    );
+    let mut skipped_pools = report.skipped_pools.clone();
+    skipped_pools.sort_by_key(|skipped| format!("{:?}", skipped.pool));
+    assert_eq!(
+        skipped_pools,
+        [
+            SkippedPool::missing_assets(pair(8), vec![cw20(NOT_A_TOKEN)]),
+            SkippedPool::missing_assets(pair(10), vec![AssetInfo::native(FOO)]),
+            SkippedPool::unsupported_pair_type(pair(19), "transmuter"),
+        ]
+    );
+    assert!(matches!(
+        skipped_pools[2].reason,
+        SkipReason::UnsupportedPairType(_)
+    ));
+    assert_eq!(
+        report.unsupported_pair_types,
+        BTreeMap::from([("transmuter".to_string(), 1)])
+    );
+    assert!(output.overrides.renamed_assets.is_empty());
+    assert!(output.overrides.denied_assets.is_empty());
+    assert!(output.overrides.denied_pools.is_empty());
+
+    // everything has 6 decimals, but the token factory denom no asset list has
+    let mut asset_metadata = output.asset_metadata.clone();
+    asset_metadata.sort_by(|a, b| a.name.cmp(&b.name));
+    let expected_metadata: Vec<AssetMetadata> = assets
+        .iter()
+        .map(|(name, info)| AssetMetadata {
+            name: name.clone(),
+            info: info.clone(),
+            decimals: (name != "terra2>amproar").then_some(6),
+        })
+        .collect();
+    assert_eq!(asset_metadata, expected_metadata);
+    assert!(output.decimals_mismatches.is_empty());
+    assert!(output.failed_registry_chains.is_empty());
+
+    // axelar-dojo-1 has no registry name, its channel is named after the chain id
+    assert_eq!(
+        output.channels,
+        [
+            (
+                UncheckedChannelEntry::new("axelar-dojo", ICS20_PROTOCOL),
+                "channel-6".to_string()
+            ),
+            (
+                UncheckedChannelEntry::new("cosmoshub", ICS20_PROTOCOL),
+                "channel-1".to_string()
+            ),
+        ]
+    );
+    assert!(output.channel_conflicts.is_empty());
+
+    std::fs::remove_dir_all(config.cache_dir.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,106 @@
+//! Runs a scrape through the public API only, the way the deploy tooling calls the library.
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use ans_scraper_rs::output::{ASSETS_FILE, POOLS_FILE, SUMMARY_FILE};
+use ans_scraper_rs::pipeline::ScrapeMeta;
+use ans_scraper_rs::report::DexReport;
+use ans_scraper_rs::{
+    scrape, write_scrape, AnsAsset, AssetSource, DexId, DexScraper, ScrapeOptions, ScrapePipeline,
+    ScraperConfig, ScraperError,
+};
+use async_trait::async_trait;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+
+/// A dex of one pool, the way a scraper outside the crate implements the traits.
+struct MockDex;
+
+impl DexId for MockDex {
+    fn dex_id(&self) -> &'static str {
+        "mockdex"
+    }
+}
+
+#[async_trait(?Send)]
+impl AssetSource for MockDex {
+    async fn fetch_asset_infos(&mut self) -> Result<Vec<AnsAsset>, ScraperError> {
+        Ok(vec![
+            AnsAsset::new("terra2>luna", AssetInfo::native("uluna")),
+            AnsAsset::new(
+                "terra2>astro",
+                AssetInfo::cw20(Addr::unchecked("terra1astro")),
+            ),
+        ])
+    }
+}
+
+#[async_trait(?Send)]
+impl DexScraper for MockDex {
+    async fn fetch_staking_contracts(&mut self) -> Result<Vec<(String, Addr)>, ScraperError> {
+        Ok(vec![(
+            "mockdex/terra2>astro,terra2>luna".to_string(),
+            Addr::unchecked("terra1staking"),
+        )])
+    }
+
+    async fn fetch_dex_pools(
+        &mut self,
+    ) -> Result<Vec<(UncheckedPoolAddress, PoolMetadata)>, ScraperError> {
+        Ok(vec![(
+            UncheckedPoolAddress::contract("terra1pair"),
+            PoolMetadata {
+                dex: "mockdex".to_string(),
+                pool_type: PoolType::ConstantProduct,
+                assets: vec![
+                    AssetEntry::new("terra2>luna"),
+                    AssetEntry::new("terra2>astro"),
+                ],
+            },
+        )])
+    }
+
+    fn report(&self) -> DexReport {
+        DexReport::new("mockdex", vec![], vec![])
+    }
+}
+
+#[tokio::test]
+async fn mock_scraper_is_scraped_and_written_through_the_library() {
+    let out_dir = std::env::temp_dir().join(format!("ans-scraper-library-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&out_dir);
+
+    let mut output = ScrapePipeline::new(vec![Box::new(MockDex)], &ScraperConfig::default())
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(output.assets.len(), 2);
+    assert_eq!(output.pools.len(), 1);
+    assert_eq!(output.contracts.len(), 1);
+    // without the clients of a chain the pipeline doesn't know which chain it scraped
+    output.meta = ScrapeMeta::new("pisco-1", None);
+
+    let (summary, report) = write_scrape(&out_dir, "pisco-1", &output).unwrap();
+    assert_eq!((summary.assets, summary.pools), (2, 1));
+    assert_eq!(report.dexes.len(), 1);
+    for file in [ASSETS_FILE, POOLS_FILE, SUMMARY_FILE] {
+        assert!(out_dir.join("pisco-1").join(file).exists(), "{}", file);
+    }
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[tokio::test]
+async fn unknown_chain_is_an_unsupported_network() {
+    let err = scrape(
+        "unknown-chain",
+        &ScraperConfig::default(),
+        &ScrapeOptions::default(),
+    )
+    .await
+    .unwrap_err();
+    assert!(
+        matches!(err, ScraperError::NetworkUnsupported(_)),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,509 @@
+//! Scrapes an astroport deployment on a LocalTerra node, through the public API only.
+//!
+//! Ignored by default, it needs docker and the wasm artifacts of astroport-core. `just integration`
+//! builds the artifacts and runs it. The node is started in a container of its own and removed
+//! at the end of the test.
+//!
+//! - `LOCALTERRA_IMAGE`: image of the node, defaults to [`DEFAULT_IMAGE`]
+//! - `ASTROPORT_ARTIFACTS`: directory of the astroport wasm files, defaults to
+//!   `../astroport-core/artifacts`
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::Arc;
+use std::time::Duration;
+
+use abstract_core::objects::pool_id::UncheckedPoolAddress;
+use abstract_core::objects::{AssetEntry, PoolMetadata, PoolType};
+use ans_scraper_rs::{
+    scrape_network, ChainClients, ChainRegistryOptions, Dex, LazyChainRegistry, ResolutionCache,
+    ScrapeOptions, ScraperConfig,
+};
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+use serde_json::{json, Value};
+use wiremock::MockServer;
+
+const DEFAULT_IMAGE: &str = "ghcr.io/terra-money/localterra:latest";
+const CHAIN_ID: &str = "localterra";
+const GRPC_URL: &str = "http://localhost:9090";
+
+/// `test1` of LocalTerra, funded in the genesis of the node.
+const MNEMONIC: &str = "notice oak worry limit wrap speak medal online prefer cluster roof addict wrist behave treat actual wasp year salad speed social layer crew genius";
+const ADDRESS: &str = "terra1x46rqay4d3cssq8gxxvqz8xt6nwlz4td20k38v";
+/// Name of the mnemonic's key in the keyring of the container.
+const KEY: &str = "ans-scraper";
+/// Subdenom of the token factory denom of the native/native pair.
+const SUBDENOM: &str = "test";
+
+/// A LocalTerra node in a docker container, removed on drop.
+struct LocalTerra {
+    container: String,
+}
+
+impl LocalTerra {
+    /// Start the node and wait for its first blocks.
+    fn start() -> Self {
+        let image = std::env::var("LOCALTERRA_IMAGE").unwrap_or_else(|_| DEFAULT_IMAGE.into());
+        let container = format!("ans-scraper-localterra-{}", std::process::id());
+        run(Command::new("docker").args([
+            "run",
+            "--detach",
+            "--name",
+            &container,
+            "--publish",
+            "9090:9090",
+            "--publish",
+            "26657:26657",
+            &image,
+        ]));
+        let node = Self { container };
+
+        for _ in 0..60 {
+            if node.block_height().map_or(false, |height| height > 1) {
+                node.add_key();
+                return node;
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        panic!("{} produced no blocks within a minute", node.container);
+    }
+
+    fn block_height(&self) -> Option<u64> {
+        let output = Command::new("docker")
+            .args(["exec", &self.container, "terrad", "status"])
+            .output()
+            .ok()?;
+        // older versions of the SDK print the status to stderr
+        let status: Value = serde_json::from_slice(&output.stdout)
+            .or_else(|_| serde_json::from_slice(&output.stderr))
+            .ok()?;
+        let sync_info = status.get("SyncInfo").or_else(|| status.get("sync_info"))?;
+        sync_info["latest_block_height"].as_str()?.parse().ok()
+    }
+
+    fn add_key(&self) {
+        let add_key = format!(
+            "echo '{}' | terrad keys add {} --recover --keyring-backend test",
+            MNEMONIC, KEY
+        );
+        run(Command::new("docker").args(["exec", &self.container, "sh", "-c", &add_key]));
+    }
+
+    fn terrad(&self, args: &[&str]) -> Value {
+        let output = run(Command::new("docker")
+            .args(["exec", &self.container, "terrad"])
+            .args(args)
+            .args(["--output", "json"]));
+        serde_json::from_slice(&output.stdout)
+            .unwrap_or_else(|err| panic!("parsing the output of terrad {:?}: {}", args, err))
+    }
+
+    /// Broadcast a transaction signed by [`KEY`] and wait for it to be included.
+    fn tx(&self, args: &[&str]) {
+        let mut tx_args = vec!["tx"];
+        tx_args.extend_from_slice(args);
+        tx_args.extend_from_slice(&[
+            "--from",
+            KEY,
+            "--keyring-backend",
+            "test",
+            "--chain-id",
+            CHAIN_ID,
+            "--gas",
+            "auto",
+            "--gas-adjustment",
+            "1.5",
+            "--gas-prices",
+            "0.15uluna",
+            "--broadcast-mode",
+            "sync",
+            "--yes",
+        ]);
+        let response = self.terrad(&tx_args);
+        assert_eq!(response["code"], 0, "tx {:?} failed: {}", args, response);
+        let hash = response["txhash"].as_str().expect("tx hash").to_string();
+
+        for _ in 0..30 {
+            std::thread::sleep(Duration::from_secs(1));
+            let output = Command::new("docker")
+                .args(["exec", &self.container, "terrad", "query", "tx", &hash])
+                .args(["--output", "json"])
+                .output()
+                .unwrap();
+            if !output.status.success() {
+                continue;
+            }
+            let tx: Value = serde_json::from_slice(&output.stdout).unwrap();
+            assert_eq!(tx["code"], 0, "tx {:?} failed: {}", args, tx["raw_log"]);
+            return;
+        }
+        panic!("tx {} of {:?} was not included", hash, args);
+    }
+
+    /// Store the contract of `artifact` and return its code id.
+    fn store(&self, artifact: &str) -> u64 {
+        let path = artifacts_dir().join(artifact);
+        assert!(path.exists(), "no artifact at {}", path.display());
+        let in_container = format!("/tmp/{}", artifact);
+        run(Command::new("docker").args([
+            "cp",
+            path.to_str().unwrap(),
+            &format!("{}:{}", self.container, in_container),
+        ]));
+        self.tx(&["wasm", "store", &in_container]);
+
+        let codes = self.terrad(&["query", "wasm", "list-code", "--reverse"]);
+        codes["code_infos"][0]["code_id"]
+            .as_str()
+            .and_then(|code_id| code_id.parse().ok())
+            .expect("code id of the stored contract")
+    }
+
+    /// Instantiate a contract of `code_id` and return its address.
+    fn instantiate(&self, code_id: u64, msg: Value, label: &str) -> String {
+        let code_id = code_id.to_string();
+        self.tx(&[
+            "wasm",
+            "instantiate",
+            &code_id,
+            &msg.to_string(),
+            "--label",
+            label,
+            "--admin",
+            ADDRESS,
+        ]);
+        let contracts = self.terrad(&["query", "wasm", "list-contract-by-code", &code_id]);
+        contracts["contracts"]
+            .as_array()
+            .and_then(|contracts| contracts.last())
+            .and_then(Value::as_str)
+            .expect("address of the instantiated contract")
+            .to_string()
+    }
+
+    fn execute(&self, contract: &str, msg: Value) {
+        self.tx(&["wasm", "execute", contract, &msg.to_string()]);
+    }
+
+    fn query(&self, contract: &str, msg: Value) -> Value {
+        let response = self.terrad(&[
+            "query",
+            "wasm",
+            "contract-state",
+            "smart",
+            contract,
+            &msg.to_string(),
+        ]);
+        response["data"].clone()
+    }
+}
+
+impl Drop for LocalTerra {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .args(["rm", "--force", &self.container])
+            .output();
+    }
+}
+
+fn run(command: &mut Command) -> Output {
+    let output = command.output().expect("running docker");
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        command,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+fn artifacts_dir() -> PathBuf {
+    std::env::var("ASTROPORT_ARTIFACTS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../astroport-core/artifacts")
+        })
+}
+
+/// The contracts of the deployment on the node.
+struct Deployment {
+    astro_token: String,
+    factory: String,
+    generator: String,
+    /// (pair, LP token) of the uluna/token factory denom pair
+    native_pair: (String, String),
+    /// (pair, LP token) of the ASTRO/uluna pair
+    astro_pair: (String, String),
+}
+
+fn native(denom: &str) -> Value {
+    json!({ "native_token": { "denom": denom } })
+}
+
+fn token(contract_addr: &str) -> Value {
+    json!({ "token": { "contract_addr": contract_addr } })
+}
+
+fn factory_denom() -> String {
+    format!("factory/{}/{}", ADDRESS, SUBDENOM)
+}
+
+/// Deploy a factory with an uluna/token factory denom pair and an ASTRO/uluna pair, and a
+/// generator that only has the ASTRO/uluna pair set up.
+fn deploy(node: &LocalTerra) -> Deployment {
+    let token_code = node.store("astroport_token.wasm");
+    let pair_code = node.store("astroport_pair.wasm");
+    let whitelist_code = node.store("astroport_whitelist.wasm");
+    let factory_code = node.store("astroport_factory.wasm");
+    let generator_code = node.store("astroport_generator.wasm");
+
+    let astro_token = node.instantiate(
+        token_code,
+        json!({
+            "name": "Astroport",
+            "symbol": "ASTRO",
+            "decimals": 6,
+            "initial_balances": [{ "address": ADDRESS, "amount": "1000000000000" }],
+            "mint": { "minter": ADDRESS },
+        }),
+        "ASTRO",
+    );
+    let factory = node.instantiate(
+        factory_code,
+        json!({
+            "pair_configs": [{
+                "code_id": pair_code,
+                "pair_type": { "xyk": {} },
+                "total_fee_bps": 30,
+                "maker_fee_bps": 3333,
+                "is_disabled": false,
+                "is_generator_disabled": false,
+            }],
+            "token_code_id": token_code,
+            "owner": ADDRESS,
+            "whitelist_code_id": whitelist_code,
+        }),
+        "astroport factory",
+    );
+
+    node.tx(&["tokenfactory", "create-denom", SUBDENOM]);
+    for asset_infos in [
+        [native("uluna"), native(&factory_denom())],
+        [token(&astro_token), native("uluna")],
+    ] {
+        node.execute(
+            &factory,
+            json!({ "create_pair": { "pair_type": { "xyk": {} }, "asset_infos": asset_infos } }),
+        );
+    }
+    let pairs = node.query(&factory, json!({ "pairs": {} }));
+    let pair_with = |asset_info: Value| -> (String, String) {
+        let pair = pairs["pairs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|pair| {
+                pair["asset_infos"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&asset_info)
+            })
+            .expect("created pair");
+        (
+            pair["contract_addr"].as_str().unwrap().to_string(),
+            pair["liquidity_token"].as_str().unwrap().to_string(),
+        )
+    };
+    let native_pair = pair_with(native(&factory_denom()));
+    let astro_pair = pair_with(token(&astro_token));
+
+    let generator = node.instantiate(
+        generator_code,
+        json!({
+            "owner": ADDRESS,
+            "factory": factory,
+            "astro_token": token(&astro_token),
+            "tokens_per_block": "10000000",
+            "start_block": "1",
+            // only called when rewards are claimed, which the scrape never does
+            "vesting_contract": ADDRESS,
+            "whitelist_code_id": whitelist_code,
+        }),
+        "astroport generator",
+    );
+    node.execute(
+        &generator,
+        json!({ "setup_pools": { "pools": [[astro_pair.1, "1"]] } }),
+    );
+
+    Deployment {
+        astro_token,
+        factory,
+        generator,
+        native_pair,
+        astro_pair,
+    }
+}
+
+/// Configuration of the scrape in a fresh directory: the deployment, the names of the natives
+/// (LocalTerra is in no asset list) and the allow-listed ASTRO cw20.
+fn scraper_config(deployment: &Deployment) -> ScraperConfig {
+    let dir = std::env::temp_dir().join(format!("ans-scraper-localterra-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let chain_config_dir = dir.join("config").join(CHAIN_ID);
+    std::fs::create_dir_all(&chain_config_dir).unwrap();
+    std::fs::write(
+        chain_config_dir.join("overrides.toml"),
+        format!(
+            "[asset_overrides]\n\"uluna\" = \"terra2>luna\"\n\"{}\" = \"terra2>test\"\n",
+            factory_denom()
+        ),
+    )
+    .unwrap();
+    std::fs::write(
+        chain_config_dir.join("verified_cw20s.json"),
+        json!([deployment.astro_token]).to_string(),
+    )
+    .unwrap();
+
+    let addresses = BTreeMap::from([
+        ("factory_address".to_string(), deployment.factory.clone()),
+        (
+            "generator_address".to_string(),
+            deployment.generator.clone(),
+        ),
+        (
+            "astro_token_address".to_string(),
+            deployment.astro_token.clone(),
+        ),
+    ]);
+    ScraperConfig {
+        cache_dir: dir.join("cache"),
+        config_dir: dir.join("config"),
+        out_dir: dir.join("out"),
+        ans_prefixes: BTreeMap::from([(CHAIN_ID.to_string(), "terra2".to_string())]),
+        deployment_addresses: BTreeMap::from([(
+            "astroport".to_string(),
+            BTreeMap::from([(CHAIN_ID.to_string(), addresses)]),
+        )]),
+        ..Default::default()
+    }
+}
+
+fn pool(pair: &str, assets: [&str; 2]) -> (UncheckedPoolAddress, PoolMetadata) {
+    (
+        UncheckedPoolAddress::contract(pair),
+        PoolMetadata {
+            dex: "astroport".to_string(),
+            pool_type: PoolType::ConstantProduct,
+            assets: assets.into_iter().map(AssetEntry::new).collect(),
+        },
+    )
+}
+
+#[tokio::test]
+#[ignore = "needs docker and the astroport artifacts, run with `just integration`"]
+async fn local_astroport_deployment_is_scraped() {
+    let node = LocalTerra::start();
+    let deployment = deploy(&node);
+    let config = scraper_config(&deployment);
+    // an empty registry: nothing is fetched from GitHub, the natives are named by the overrides
+    let registry = MockServer::start().await;
+    let chain_registry = LazyChainRegistry::for_chains(
+        &[],
+        ChainRegistryOptions {
+            cache_dir: config.cache_dir.join("asset_lists"),
+            registry_url: registry.uri(),
+            ..Default::default()
+        },
+    );
+
+    let clients = ChainClients::connect(CHAIN_ID, GRPC_URL).await.unwrap();
+    let output = scrape_network(
+        clients,
+        chain_registry,
+        Arc::new(ResolutionCache::new()),
+        &config,
+        &ScrapeOptions {
+            dexes: vec![Dex::Astroport],
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (native_pair, native_lp) = &deployment.native_pair;
+    let (astro_pair, astro_lp) = &deployment.astro_pair;
+    let mut assets = output.assets.clone();
+    assets.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        assets,
+        [
+            (
+                "astroport/terra2>astro,terra2>luna".to_string(),
+                AssetInfo::cw20(Addr::unchecked(astro_lp))
+            ),
+            (
+                "astroport/terra2>luna,terra2>test".to_string(),
+                AssetInfo::cw20(Addr::unchecked(native_lp))
+            ),
+            (
+                "terra2>astro".to_string(),
+                AssetInfo::cw20(Addr::unchecked(&deployment.astro_token))
+            ),
+            ("terra2>luna".to_string(), AssetInfo::native("uluna")),
+            (
+                "terra2>test".to_string(),
+                AssetInfo::native(factory_denom())
+            ),
+        ]
+    );
+
+    let mut pools = output.pools.clone();
+    pools.sort_by_key(|(_, metadata)| metadata.assets[0].to_string());
+    assert_eq!(
+        pools,
+        [
+            pool(astro_pair, ["terra2>astro", "terra2>luna"]),
+            pool(native_pair, ["terra2>luna", "terra2>test"]),
+        ]
+    );
+
+    // only the pair set up in the generator has a staking contract
+    let mut contracts: Vec<(String, String, String)> = output
+        .contracts
+        .iter()
+        .map(|(entry, address)| {
+            (
+                entry.protocol.clone(),
+                entry.contract.clone(),
+                address.clone(),
+            )
+        })
+        .collect();
+    contracts.sort();
+    assert_eq!(
+        contracts,
+        [
+            (
+                "astroport".to_string(),
+                "generator".to_string(),
+                deployment.generator.clone()
+            ),
+            (
+                "astroport".to_string(),
+                "staking/astroport/terra2>astro,terra2>luna".to_string(),
+                deployment.generator.clone()
+            ),
+        ]
+    );
+
+    assert!(output.asset_conflicts.is_empty());
+    for report in &output.reports {
+        assert!(report.unresolved_assets.is_empty(), "{:?}", report);
+        assert!(report.skipped_pools.is_empty(), "{:?}", report);
+    }
+    std::fs::remove_dir_all(config.cache_dir.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,88 @@
+//! Peak memory of writing large output files. A test binary of its own, the counting allocator
+//! sees every allocation of the process.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ans_scraper_rs::output::{write_entries, write_json_array, STREAMING_THRESHOLD};
+use ans_scraper_rs::pipeline::ScrapeMeta;
+use cosmwasm_std::Addr;
+use cw_asset::AssetInfo;
+
+/// The system allocator, keeping track of the allocated bytes and their peak.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Bytes allocated by `f` on top of what was allocated before, at its peak.
+fn peak_allocation_of(f: impl FnOnce()) -> usize {
+    let before = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    f();
+    PEAK.load(Ordering::SeqCst) - before
+}
+
+/// Large files are written entry by entry, without ever holding the serialized file (~10 MB for
+/// these entries) in memory.
+#[test]
+fn large_entry_files_are_streamed_in_bounded_memory() {
+    const MAX_ALLOCATION: usize = 1024 * 1024;
+
+    let out_dir = std::env::temp_dir().join(format!(
+        "ans-scraper-streaming-memory-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&out_dir);
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let meta = ScrapeMeta::new("phoenix-1", Some(1));
+    let entries: Vec<(String, AssetInfo)> = (0..200_000)
+        .map(|i| {
+            (
+                format!("terra2>token{:06}", i),
+                AssetInfo::Cw20(Addr::unchecked(format!("terra1token{:06}", i))),
+            )
+        })
+        .collect();
+    assert!(entries.len() > STREAMING_THRESHOLD);
+
+    let entries_path = out_dir.join("assets.json");
+    let peak = peak_allocation_of(|| write_entries(entries_path.clone(), &meta, &entries).unwrap());
+    assert!(
+        peak < MAX_ALLOCATION,
+        "writing the entries allocated {} bytes",
+        peak
+    );
+
+    let array_path = out_dir.join("unverified_assets.json");
+    let peak = peak_allocation_of(|| write_json_array(array_path.clone(), &entries).unwrap());
+    assert!(
+        peak < MAX_ALLOCATION,
+        "writing the array allocated {} bytes",
+        peak
+    );
+
+    // the files were written in full all the same
+    let written = std::fs::metadata(&entries_path).unwrap().len() as usize;
+    assert!(written > 4 * MAX_ALLOCATION);
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}